@@ -0,0 +1,228 @@
+use crate::arbiter::ActuationRequest;
+use crate::control::{MAX_BRAKING, MIN_THROTTLE};
+use serde::Deserialize;
+
+/// Thresholds of the automatic emergency braking function.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AebConfig {
+    /// Time-to-collision (s) below which a forward-collision warning is raised.
+    pub warning_ttc: f64,
+    /// Time-to-collision (s) below which throttle is cut and partial braking applied.
+    pub partial_braking_ttc: f64,
+    /// Time-to-collision (s) below which full braking is applied.
+    pub full_braking_ttc: f64,
+    /// Brake applied during partial braking (0.0 to 1.0).
+    pub partial_brake: f32,
+    /// Simulation time (s) after which an obstacle without new detections is forgotten.
+    pub obstacle_timeout: f64,
+    /// Ego speed (km/h) below which no warning or braking is staged.
+    pub min_speed_kmh: f64,
+    /// Ego speed (km/h) at which a latched full braking is released.
+    pub standstill_kmh: f64,
+}
+
+impl Default for AebConfig {
+    fn default() -> Self {
+        Self {
+            warning_ttc: 2.6,
+            partial_braking_ttc: 1.6,
+            full_braking_ttc: 0.9,
+            partial_brake: 0.4,
+            obstacle_timeout: 0.5,
+            min_speed_kmh: 5.0,
+            standstill_kmh: 0.5,
+        }
+    }
+}
+
+/// Escalation stages, published as their numeric value on the warning topic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AebStage {
+    #[default]
+    Inactive = 0,
+    Warning = 1,
+    PartialBraking = 2,
+    FullBraking = 3,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AebStatus {
+    pub stage: AebStage,
+    /// Time-to-collision (s) with the tracked obstacle, if any.
+    pub time_to_collision: Option<f64>,
+    /// Distance (m) to the tracked obstacle, if any.
+    pub distance: Option<f64>,
+}
+
+/// Automatic emergency braking driven by obstacle detection and collision events.
+///
+/// Time-to-collision is computed from the latest obstacle distance and the ego speed,
+/// assuming a stationary obstacle. Once full braking is reached (or a collision is
+/// reported) it is held until the vehicle comes to a standstill.
+#[derive(Debug)]
+pub struct EmergencyBraking {
+    config: AebConfig,
+    obstacle: Option<(f64, f64)>,
+    latched: bool,
+    status: AebStatus,
+}
+
+impl EmergencyBraking {
+    pub fn new(config: AebConfig) -> Self {
+        Self {
+            config,
+            obstacle: None,
+            latched: false,
+            status: AebStatus::default(),
+        }
+    }
+
+    /// Records an obstacle `distance` (m) detected at simulation time `now` (s).
+    pub fn on_obstacle(&mut self, now: f64, distance: f64) {
+        self.obstacle = Some((now, distance));
+    }
+
+    pub fn on_collision(&mut self) {
+        log::warn!("[aeb] collision reported, braking to a standstill");
+        self.latched = true;
+    }
+
+    /// Re-evaluates the stage at simulation time `now` (s) for the ego speed in km/h.
+    pub fn update(&mut self, now: f64, speed_kmh: f64) -> AebStatus {
+        let timeout = self.config.obstacle_timeout;
+        self.obstacle = self.obstacle.filter(|(seen, _)| now - seen <= timeout);
+
+        let distance = self.obstacle.map(|(_, d)| d);
+        let speed = speed_kmh / 3.6;
+        let time_to_collision = distance.filter(|_| speed > 0.0).map(|d| d.max(0.0) / speed);
+
+        let mut stage = match time_to_collision {
+            _ if speed_kmh < self.config.min_speed_kmh => AebStage::Inactive,
+            Some(ttc) if ttc <= self.config.full_braking_ttc => AebStage::FullBraking,
+            Some(ttc) if ttc <= self.config.partial_braking_ttc => AebStage::PartialBraking,
+            Some(ttc) if ttc <= self.config.warning_ttc => AebStage::Warning,
+            _ => AebStage::Inactive,
+        };
+
+        if stage == AebStage::FullBraking {
+            self.latched = true;
+        }
+        if self.latched {
+            if speed_kmh <= self.config.standstill_kmh {
+                self.latched = false;
+            } else {
+                stage = AebStage::FullBraking;
+            }
+        }
+
+        if stage != self.status.stage {
+            log::warn!(
+                "[aeb] stage {:?} -> {:?} (distance: {:?} m, ttc: {:?} s)",
+                self.status.stage,
+                stage,
+                distance,
+                time_to_collision
+            );
+        }
+
+        self.status = AebStatus {
+            stage,
+            time_to_collision,
+            distance,
+        };
+        self.status
+    }
+
+    pub fn status(&self) -> AebStatus {
+        self.status
+    }
+
+    /// Actuation requested for the current stage; `None` leaves control to other sources.
+    pub fn request(&self) -> Option<ActuationRequest> {
+        let brake = match self.status.stage {
+            AebStage::Inactive | AebStage::Warning => return None,
+            AebStage::PartialBraking => self.config.partial_brake,
+            AebStage::FullBraking => MAX_BRAKING,
+        };
+
+        Some(ActuationRequest {
+            throttle: Some(MIN_THROTTLE),
+            brake: Some(brake),
+            steer: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.1;
+
+    /// Drives towards a stationary obstacle at constant speed, reporting a detection
+    /// every tick, and returns the stage reached at each tick.
+    fn approach(
+        aeb: &mut EmergencyBraking,
+        start: f64,
+        speed_kmh: f64,
+        ticks: usize,
+    ) -> Vec<AebStage> {
+        let speed = speed_kmh / 3.6;
+        (0..ticks)
+            .map(|i| {
+                let now = i as f64 * DT;
+                aeb.on_obstacle(now, start - speed * now);
+                aeb.update(now, speed_kmh).stage
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stages_escalate_while_closing_in() {
+        let mut aeb = EmergencyBraking::new(AebConfig::default());
+        // 50 km/h towards an obstacle 40 m ahead: TTC starts at ~2.9 s
+        let stages = approach(&mut aeb, 40.0, 50.0, 25);
+
+        assert_eq!(stages[0], AebStage::Inactive);
+        assert!(stages.contains(&AebStage::Warning));
+        assert!(stages.contains(&AebStage::PartialBraking));
+        assert_eq!(*stages.last().unwrap(), AebStage::FullBraking);
+        assert!(stages.windows(2).all(|w| w[0] <= w[1]));
+
+        let request = aeb.request().unwrap();
+        assert_eq!(request.brake, Some(MAX_BRAKING));
+        assert_eq!(request.throttle, Some(MIN_THROTTLE));
+    }
+
+    #[test]
+    fn test_full_braking_is_held_until_standstill() {
+        let mut aeb = EmergencyBraking::new(AebConfig::default());
+        aeb.on_obstacle(0.0, 5.0);
+        assert_eq!(aeb.update(0.0, 36.0).stage, AebStage::FullBraking);
+
+        // The obstacle disappears, but the vehicle is still moving
+        assert_eq!(aeb.update(1.0, 20.0).stage, AebStage::FullBraking);
+        assert_eq!(aeb.update(2.0, 0.0).stage, AebStage::Inactive);
+        assert!(aeb.request().is_none());
+    }
+
+    #[test]
+    fn test_stale_obstacle_and_slow_speed_do_not_brake() {
+        let mut aeb = EmergencyBraking::new(AebConfig::default());
+        aeb.on_obstacle(0.0, 10.0);
+        assert_eq!(aeb.update(1.0, 30.0).stage, AebStage::Inactive);
+        assert_eq!(aeb.status().distance, None);
+
+        aeb.on_obstacle(2.0, 0.5);
+        assert_eq!(aeb.update(2.0, 3.0).stage, AebStage::Inactive);
+    }
+
+    #[test]
+    fn test_collision_latches_full_braking() {
+        let mut aeb = EmergencyBraking::new(AebConfig::default());
+        aeb.on_collision();
+        assert_eq!(aeb.update(0.0, 15.0).stage, AebStage::FullBraking);
+        assert_eq!(aeb.update(0.1, 0.2).stage, AebStage::Inactive);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::control::{
+    MAX_BRAKING, MAX_STEERING, MAX_THROTTLE, MIN_BRAKING, MIN_STEERING, MIN_THROTTLE,
+    VehicleCommand,
+};
+use std::collections::BTreeMap;
+
+/// Sources that may request actuation, in increasing order of priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActuationSource {
+    /// Manual throttle, brake and steering inputs.
+    Manual,
+    /// Cruise control actuation command.
+    CruiseControl,
+    /// Lane keeping assist steering.
+    LaneKeeping,
+    /// Automatic emergency braking.
+    EmergencyBraking,
+}
+
+/// Actuator values requested by one source; `None` leaves the actuator to lower priorities.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActuationRequest {
+    pub throttle: Option<f32>,
+    pub brake: Option<f32>,
+    pub steer: Option<f32>,
+}
+
+/// Decides, per actuator, which source drives the vehicle.
+///
+/// Every source keeps its latest request until released; for each actuator the
+/// highest-priority source that requests it wins. Emergency braking only ever adds
+/// brake: a stronger brake requested by another source is kept.
+#[derive(Debug, Default)]
+pub struct ActuationArbiter {
+    requests: BTreeMap<ActuationSource, ActuationRequest>,
+}
+
+impl ActuationArbiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&mut self, source: ActuationSource, request: ActuationRequest) {
+        self.requests.insert(source, request);
+    }
+
+    pub fn release(&mut self, source: ActuationSource) {
+        self.requests.remove(&source);
+    }
+
+    /// Requests `request` when `Some`, releases the source otherwise.
+    pub fn update(&mut self, source: ActuationSource, request: Option<ActuationRequest>) {
+        match request {
+            Some(request) => self.request(source, request),
+            None => self.release(source),
+        }
+    }
+
+    /// Highest-priority source currently holding a request.
+    pub fn active_source(&self) -> Option<ActuationSource> {
+        self.requests.keys().next_back().copied()
+    }
+
+    pub fn command(&self) -> VehicleCommand {
+        let mut command = VehicleCommand::default();
+
+        let winner = |pick: fn(&ActuationRequest) -> Option<f32>| {
+            self.requests.values().rev().find_map(pick)
+        };
+
+        if let Some(throttle) = winner(|r| r.throttle) {
+            command.throttle = throttle.clamp(MIN_THROTTLE, MAX_THROTTLE);
+        }
+        let emergency_brake = self
+            .requests
+            .get(&ActuationSource::EmergencyBraking)
+            .and_then(|r| r.brake);
+        let brake = match emergency_brake {
+            Some(emergency) => Some(
+                self.requests
+                    .iter()
+                    .filter(|(source, _)| **source != ActuationSource::EmergencyBraking)
+                    .rev()
+                    .find_map(|(_, r)| r.brake)
+                    .map_or(emergency, |other| other.max(emergency)),
+            ),
+            None => winner(|r| r.brake),
+        };
+        if let Some(brake) = brake {
+            command.brake = brake.clamp(MIN_BRAKING, MAX_BRAKING);
+        }
+        if let Some(steer) = winner(|r| r.steer) {
+            command.steer = steer.clamp(MIN_STEERING, MAX_STEERING);
+        }
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_higher_priority_wins_per_actuator() {
+        let mut arbiter = ActuationArbiter::new();
+        arbiter.request(
+            ActuationSource::Manual,
+            ActuationRequest {
+                throttle: Some(0.8),
+                brake: Some(0.0),
+                steer: Some(0.2),
+            },
+        );
+        arbiter.request(
+            ActuationSource::CruiseControl,
+            ActuationRequest {
+                throttle: Some(0.3),
+                brake: Some(0.0),
+                steer: None,
+            },
+        );
+
+        let command = arbiter.command();
+        assert_eq!(command.throttle, 0.3);
+        assert_eq!(command.steer, 0.2);
+
+        arbiter.request(
+            ActuationSource::EmergencyBraking,
+            ActuationRequest {
+                throttle: Some(0.0),
+                brake: Some(1.0),
+                steer: None,
+            },
+        );
+
+        let command = arbiter.command();
+        assert_eq!(command.throttle, 0.0);
+        assert_eq!(command.brake, 1.0);
+        assert_eq!(command.steer, 0.2);
+        assert_eq!(
+            arbiter.active_source(),
+            Some(ActuationSource::EmergencyBraking)
+        );
+
+        arbiter.release(ActuationSource::EmergencyBraking);
+        assert_eq!(arbiter.command().throttle, 0.3);
+    }
+
+    #[test]
+    fn test_emergency_braking_keeps_stronger_brake() {
+        let mut arbiter = ActuationArbiter::new();
+        arbiter.request(
+            ActuationSource::Manual,
+            ActuationRequest {
+                throttle: Some(0.0),
+                brake: Some(0.9),
+                steer: None,
+            },
+        );
+        arbiter.request(
+            ActuationSource::EmergencyBraking,
+            ActuationRequest {
+                throttle: Some(0.0),
+                brake: Some(0.4),
+                steer: None,
+            },
+        );
+        assert_eq!(arbiter.command().brake, 0.9);
+    }
+}
@@ -0,0 +1,53 @@
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+use up_transport_zenoh::zenoh_config;
+use zenoh::Config;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(long, default_value = "127.0.0.1")]
+    pub host: String,
+    #[clap(long, default_value_t = 2000)]
+    pub port: u16,
+    #[clap(long, default_value = "ego_vehicle")]
+    pub role: String,
+    #[clap(long, default_value_t = 0.100)]
+    pub delta: f64,
+    #[clap(long, default_value = None)]
+    pub router: Option<String>,
+    /// Signal mapping file (YAML). The built-in uProtocol mapping is used when omitted.
+    #[clap(long, default_value = None)]
+    pub config: Option<PathBuf>,
+    /// Actors to spawn in CARLA (YAML) instead of waiting for an externally spawned vehicle.
+    #[clap(long, default_value = None)]
+    pub spawn: Option<PathBuf>,
+    /// Simulator driving the ego vehicle.
+    #[clap(long, value_enum, default_value_t = Backend::Carla)]
+    pub backend: Backend,
+    /// Road curvature (1/m) of the headless backend; positive turns right, 0 is straight.
+    #[clap(long, default_value_t = 0.0)]
+    pub road_curvature: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// A running CARLA server.
+    Carla,
+    /// Built-in vehicle model, no simulator required.
+    Headless,
+}
+
+/// Builds the Zenoh configuration shared by the raw Zenoh session and the uProtocol transport.
+pub fn get_zenoh_config(router: &Option<String>) -> zenoh_config::Config {
+    let zenoh_string = if let Some(router) = router {
+        format!(
+            "{{ mode: 'peer', connect: {{ endpoints: [ 'tcp/{}:7447' ] }} }}",
+            router
+        )
+    } else {
+        "{ mode: 'peer' }".to_string()
+    };
+
+    Config::from_json5(&zenoh_string).expect("Failed to load Zenoh config")
+}
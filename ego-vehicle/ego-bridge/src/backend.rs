@@ -0,0 +1,104 @@
+mod carla_backend;
+mod headless;
+
+pub use carla_backend::*;
+pub use headless::*;
+
+use crate::control::VehicleCommand;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Simulation step reported by a backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SimTick {
+    pub frame: u64,
+    pub elapsed_seconds: f64,
+}
+
+/// Planar pose in the simulator frame (x forward, y right, yaw in radians towards +y).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    pub yaw: f64,
+}
+
+impl Pose {
+    /// Expresses the world point (`x`, `y`) in the vehicle frame (forward, right).
+    pub fn to_local(&self, x: f64, y: f64) -> (f64, f64) {
+        let (dx, dy) = (x - self.x, y - self.y);
+        let (sin, cos) = self.yaw.sin_cos();
+        (dx * cos + dy * sin, dy * cos - dx * sin)
+    }
+}
+
+/// Ego vehicle state read from the backend on every tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VehicleState {
+    pub speed_kmh: f64,
+    pub pose: Pose,
+}
+
+/// Centre line of the ego lane ahead of the vehicle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LaneGeometry {
+    /// Points along the lane centre, starting next to the vehicle.
+    pub centerline: Vec<(f64, f64)>,
+    /// Lane width in meters.
+    pub lane_width: f64,
+}
+
+/// Weather presets, named after CARLA's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherPreset {
+    ClearNoon,
+    CloudyNoon,
+    WetNoon,
+    MidRainyNoon,
+    HardRainNoon,
+    ClearSunset,
+    ClearNight,
+}
+
+/// Simulator the bridge drives: CARLA, or a headless model for runs without a simulator.
+///
+/// Vehicles are addressed by their index in the bridge configuration; weather, NPCs
+/// and pausing apply to the whole world.
+#[async_trait(?Send)]
+pub trait VehicleBackend {
+    /// Waits for the next simulation step.
+    async fn tick(&mut self) -> SimTick;
+
+    /// Number of vehicles driven through the backend.
+    fn vehicle_count(&self) -> usize;
+
+    /// Current state of `vehicle`, `None` if the vehicle is gone.
+    fn vehicle_state(&self, vehicle: usize) -> Option<VehicleState>;
+
+    /// Lane centre line up to `distance` meters ahead of `vehicle`, where the backend
+    /// knows the road.
+    fn lane_ahead(&self, vehicle: usize, distance: f64) -> Option<LaneGeometry>;
+
+    fn apply_control(&mut self, vehicle: usize, command: &VehicleCommand) -> Result<(), String>;
+
+    /// Moves `vehicle` to `pose`, keeping its speed.
+    fn teleport(&mut self, vehicle: usize, pose: Pose) -> Result<(), String>;
+
+    /// Moves `vehicle` back to where it started, at standstill.
+    fn reset(&mut self, vehicle: usize) -> Result<(), String>;
+
+    /// Sets the speed of `vehicle` along its heading.
+    fn set_speed(&mut self, vehicle: usize, speed_kmh: f64) -> Result<(), String>;
+
+    fn set_weather(&mut self, preset: WeatherPreset) -> Result<(), String>;
+
+    /// Spawns up to `count` NPC vehicles driven by the traffic manager; returns their ids.
+    fn spawn_npcs(&mut self, count: usize) -> Result<Vec<u32>, String>;
+
+    /// Destroys the NPCs with the given ids, or all of them; returns how many were destroyed.
+    fn destroy_npcs(&mut self, ids: Option<&[u32]>) -> Result<usize, String>;
+
+    /// Freezes or resumes the simulation; `tick` keeps returning while paused.
+    fn set_paused(&mut self, paused: bool) -> Result<(), String>;
+}
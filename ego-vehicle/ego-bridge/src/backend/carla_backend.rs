@@ -0,0 +1,526 @@
+use super::{LaneGeometry, Pose, SimTick, VehicleBackend, VehicleState, WeatherPreset};
+use crate::args::Args;
+use crate::config::VehicleConfig;
+use crate::control::VehicleCommand;
+use crate::spawn::{ActorConfig, MountTransform, SpawnConfig, SpawnPoint};
+use async_trait::async_trait;
+use carla::client::{Actor, ActorBase, ActorBlueprint, Client, Map, Vehicle, World};
+use carla::rpc::AttachmentType;
+use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// General constants
+const CLIENT_TIME_MS: u64 = 5_000;
+const POLLING_EGO_MS: u64 = 1_000;
+// Spacing of the lane centre line points
+const LANE_STEP_M: f64 = 2.0;
+// Traffic manager driving the NPC vehicles
+const TRAFFIC_MANAGER_PORT: u16 = 8000;
+const NPC_ROLE: &str = "npc";
+
+/// Actors spawned by the bridge; destroyed, sensors first, when dropped.
+struct SpawnedActors {
+    actors: Vec<Actor>,
+}
+
+impl Drop for SpawnedActors {
+    fn drop(&mut self) {
+        for actor in self.actors.drain(..).rev() {
+            let id = actor.id();
+            if actor.destroy() {
+                log::info!("Destroyed actor id={id}");
+            } else {
+                log::warn!("Unable to destroy actor id={id}");
+            }
+        }
+    }
+}
+
+fn to_isometry(transform: &MountTransform) -> Isometry3<f32> {
+    Isometry3::from_parts(
+        Translation3::new(transform.x, transform.y, transform.z),
+        UnitQuaternion::from_euler_angles(
+            transform.roll.to_radians(),
+            transform.pitch.to_radians(),
+            transform.yaw.to_radians(),
+        ),
+    )
+}
+
+/// Cloudiness, precipitation, precipitation deposits, wind intensity, sun altitude angle
+/// and fog density of a preset.
+fn weather_values(preset: WeatherPreset) -> [f32; 6] {
+    match preset {
+        WeatherPreset::ClearNoon => [5.0, 0.0, 0.0, 10.0, 45.0, 2.0],
+        WeatherPreset::CloudyNoon => [60.0, 0.0, 0.0, 10.0, 45.0, 3.0],
+        WeatherPreset::WetNoon => [5.0, 0.0, 50.0, 10.0, 45.0, 3.0],
+        WeatherPreset::MidRainyNoon => [60.0, 60.0, 60.0, 60.0, 45.0, 3.0],
+        WeatherPreset::HardRainNoon => [100.0, 100.0, 90.0, 100.0, 45.0, 7.0],
+        WeatherPreset::ClearSunset => [5.0, 0.0, 0.0, 10.0, 15.0, 2.0],
+        WeatherPreset::ClearNight => [5.0, 0.0, 0.0, 10.0, -90.0, 60.0],
+    }
+}
+
+/// Spawns the vehicle with `role_name` = `role` at `spawn_point` and attaches the
+/// configured sensors, their roles followed by `suffix`.
+fn spawn_actors(
+    world: &mut World,
+    config: &SpawnConfig,
+    spawn_point: &SpawnPoint,
+    role: &str,
+    suffix: &str,
+) -> Result<SpawnedActors, Box<dyn Error>> {
+    let library = world.blueprint_library();
+    let blueprint = |actor: &ActorConfig, role: &str| -> Result<ActorBlueprint, String> {
+        let mut blueprint = library
+            .find(&actor.blueprint)
+            .ok_or_else(|| format!("Unknown blueprint '{}'", actor.blueprint))?;
+        for (id, value) in &actor.attributes {
+            if !blueprint.set_attribute(id, &value.to_string()) {
+                return Err(format!(
+                    "Blueprint '{}' has no attribute '{id}'",
+                    actor.blueprint
+                ));
+            }
+        }
+        blueprint.set_attribute("role_name", role);
+        Ok(blueprint)
+    };
+
+    let spawn_point = match spawn_point {
+        SpawnPoint::Index(index) => world
+            .map()
+            .recommended_spawn_points()
+            .get(*index)
+            .ok_or_else(|| format!("The map has no spawn point {index}"))?,
+        SpawnPoint::Transform(transform) => to_isometry(transform),
+    };
+
+    // Already spawned actors are destroyed again if a later one fails
+    let mut spawned = SpawnedActors { actors: Vec::new() };
+
+    let vehicle = world.spawn_actor(&blueprint(&config.vehicle, role)?, &spawn_point)?;
+    log::info!(
+        "Spawned '{}' actor ({}) with id: {}",
+        role,
+        config.vehicle.blueprint,
+        vehicle.id()
+    );
+    spawned.actors.push(vehicle.clone());
+
+    for sensor in &config.sensors {
+        let sensor_role = format!("{}{suffix}", sensor.role);
+        let actor = world.spawn_actor_opt(
+            &blueprint(&sensor.actor, &sensor_role)?,
+            &to_isometry(&sensor.transform),
+            Some(&vehicle),
+            AttachmentType::Rigid,
+        )?;
+        log::info!(
+            "Spawned '{}' sensor ({}) with id: {}",
+            sensor_role,
+            sensor.actor.blueprint,
+            actor.id()
+        );
+        spawned.actors.push(actor);
+    }
+
+    Ok(spawned)
+}
+
+/// Spawn point of the `index`-th vehicle: its own, or the one after the configured one.
+fn vehicle_spawn_point(
+    config: &SpawnConfig,
+    vehicle: &VehicleConfig,
+    index: usize,
+) -> Result<SpawnPoint, String> {
+    match (&vehicle.spawn_point, &config.spawn_point) {
+        (Some(spawn_point), _) => Ok(spawn_point.clone()),
+        (None, SpawnPoint::Index(first)) => Ok(SpawnPoint::Index(first + index)),
+        (None, spawn_point) if index == 0 => Ok(spawn_point.clone()),
+        (None, _) => Err(format!(
+            "Vehicle '{}' needs its own spawn_point",
+            vehicle.role
+        )),
+    }
+}
+
+/// Drives the vehicles of a running CARLA server.
+pub struct CarlaBackend {
+    _spawned: Option<SpawnedActors>,
+    npcs: Vec<Actor>,
+    client: Client,
+    world: World,
+    map: Map,
+    roles: Vec<String>,
+    vehicle_ids: Vec<u32>,
+    starts: Vec<Option<Isometry3<f32>>>,
+    delta: f64,
+    last_time: f64,
+    last_tick: SimTick,
+    paused: bool,
+}
+
+impl CarlaBackend {
+    /// Connects to CARLA and spawns the actors of `--spawn` for every vehicle, or
+    /// otherwise waits for the actors with the vehicles' roles. Spawned actors are
+    /// destroyed with the backend.
+    ///
+    /// Returns `None` if `running` is cleared before all actors appear.
+    pub async fn connect(
+        args: &Args,
+        vehicles: &[VehicleConfig],
+        running: &AtomicBool,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        // Connect to the Carla Server
+        log::info!(
+            "Connecting to the Carla Server at {}:{}...",
+            args.host,
+            args.port
+        );
+
+        let mut carla_client = Client::connect(&args.host, args.port, None);
+
+        carla_client.set_timeout(Duration::from_millis(CLIENT_TIME_MS));
+
+        // Configure Carla's World
+        let mut carla_world = carla_client.world();
+        let mut carla_settings = carla_world.settings();
+
+        carla_settings.synchronous_mode = false;
+        carla_settings.fixed_delta_seconds = Some(args.delta);
+
+        carla_world.apply_settings(&carla_settings, Duration::from_millis(CLIENT_TIME_MS));
+
+        log::info!(
+            "World Settings: Synchronous mode: {}, Fixed delta seconds: {:?}",
+            carla_settings.synchronous_mode,
+            carla_settings.fixed_delta_seconds
+        );
+
+        // Spawn the vehicles and their sensors, if requested
+        let mut vehicle_ids: Vec<Option<u32>> = vec![None; vehicles.len()];
+        let spawned = match &args.spawn {
+            Some(path) => {
+                let config = SpawnConfig::from_file(path)?;
+                let mut spawned = SpawnedActors { actors: Vec::new() };
+                for (index, vehicle) in vehicles.iter().enumerate() {
+                    let spawn_point = vehicle_spawn_point(&config, vehicle, index)?;
+                    let mut actors = spawn_actors(
+                        &mut carla_world,
+                        &config,
+                        &spawn_point,
+                        &vehicle.role,
+                        &vehicle.suffix,
+                    )?;
+                    vehicle_ids[index] = actors.actors.first().map(|vehicle| vehicle.id());
+                    spawned.actors.append(&mut actors.actors);
+                }
+                Some(spawned)
+            }
+            None => None,
+        };
+
+        // Wait for the vehicle actors
+        while running.load(Ordering::SeqCst) && vehicle_ids.contains(&None) {
+            log::info!("Waiting for the vehicle actors...");
+
+            // Synchronize Carla's world
+            let _ = carla_world.wait_for_tick();
+
+            // Check if the vehicle actors exist in the world
+            for actor in carla_world.actors().iter() {
+                for attribute in actor.attributes().iter() {
+                    if attribute.id() != "role_name" {
+                        continue;
+                    }
+                    let role = attribute.value_string();
+                    if let Some(index) = vehicles.iter().position(|v| v.role == role) {
+                        if vehicle_ids[index].is_none() {
+                            log::info!("Found '{}' actor with id: {}", role, actor.id());
+                            vehicle_ids[index] = Some(actor.id());
+                        }
+                    }
+                }
+            }
+
+            // Sleep to avoid busy-waiting
+            tokio::time::sleep(Duration::from_millis(POLLING_EGO_MS)).await;
+        }
+
+        let Some(vehicle_ids) = vehicle_ids.into_iter().collect::<Option<Vec<u32>>>() else {
+            return Ok(None);
+        };
+
+        let mut backend = Self {
+            _spawned: spawned,
+            npcs: Vec::new(),
+            client: carla_client,
+            map: carla_world.map(),
+            world: carla_world,
+            roles: vehicles.iter().map(|v| v.role.clone()).collect(),
+            vehicle_ids,
+            starts: Vec::new(),
+            delta: args.delta,
+            last_time: 0.0,
+            last_tick: SimTick::default(),
+            paused: false,
+        };
+        backend.starts = (0..backend.vehicle_ids.len())
+            .map(|index| backend.vehicle(index).map(|vehicle| vehicle.transform()))
+            .collect();
+
+        Ok(Some(backend))
+    }
+
+    fn vehicle(&self, index: usize) -> Option<Vehicle> {
+        self.world
+            .actor(*self.vehicle_ids.get(index)?)?
+            .into_kinds()
+            .try_into_vehicle()
+            .ok()
+    }
+
+    fn ego_vehicle(&self, index: usize) -> Result<Vehicle, String> {
+        let role = self
+            .roles
+            .get(index)
+            .ok_or_else(|| format!("Unknown vehicle {index}"))?;
+        self.vehicle(index)
+            .ok_or_else(|| format!("'{role}' actor not found in the world anymore!"))
+    }
+}
+
+impl Drop for CarlaBackend {
+    fn drop(&mut self) {
+        let _ = self.destroy_npcs(None);
+    }
+}
+
+#[async_trait(?Send)]
+impl VehicleBackend for CarlaBackend {
+    async fn tick(&mut self) -> SimTick {
+        // Nobody ticks the world in synchronous mode: just keep the pace
+        if self.paused {
+            tokio::time::sleep(Duration::from_secs_f64(self.delta)).await;
+            return self.last_tick;
+        }
+
+        // Synchronize Carla's world and take a snapshot of the current frame
+        let snapshot = self.world.wait_for_tick();
+        let timestamp = snapshot.timestamp();
+        let delta_time = timestamp.platform_timestamp - self.last_time;
+
+        if delta_time < self.delta {
+            let secs = self.delta - delta_time;
+            log::debug!("[to_sleep] secs : {}", secs);
+            tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+        }
+
+        self.last_time = timestamp.platform_timestamp;
+
+        self.last_tick = SimTick {
+            frame: timestamp.frame as u64,
+            elapsed_seconds: timestamp.elapsed_seconds,
+        };
+        self.last_tick
+    }
+
+    fn vehicle_count(&self) -> usize {
+        self.vehicle_ids.len()
+    }
+
+    fn vehicle_state(&self, vehicle: usize) -> Option<VehicleState> {
+        let vehicle = self.vehicle(vehicle)?;
+        let transform = vehicle.transform();
+        let (_, _, yaw) = transform.rotation.euler_angles();
+
+        Some(VehicleState {
+            speed_kmh: 3.6 * vehicle.velocity().norm() as f64,
+            pose: Pose {
+                x: transform.translation.x as f64,
+                y: transform.translation.y as f64,
+                yaw: yaw as f64,
+            },
+        })
+    }
+
+    fn lane_ahead(&self, vehicle: usize, distance: f64) -> Option<LaneGeometry> {
+        let location = self.vehicle(vehicle)?.transform().translation;
+        let mut waypoint = self.map.waypoint_at(&location)?;
+        let lane_width = waypoint.lane_width();
+
+        let mut centerline = Vec::new();
+        let mut travelled = 0.0;
+        loop {
+            let point = waypoint.transform().translation;
+            centerline.push((point.x as f64, point.y as f64));
+
+            if travelled >= distance {
+                break;
+            }
+            // Follows the first successor, i.e. keeps straight through junctions
+            let Some(next) = waypoint.next(LANE_STEP_M).iter().next() else {
+                break;
+            };
+            waypoint = next;
+            travelled += LANE_STEP_M;
+        }
+
+        Some(LaneGeometry {
+            centerline,
+            lane_width,
+        })
+    }
+
+    fn apply_control(&mut self, vehicle: usize, command: &VehicleCommand) -> Result<(), String> {
+        let ego_vehicle = self.ego_vehicle(vehicle)?;
+
+        let mut control = ego_vehicle.control();
+
+        control.throttle = command.throttle;
+        control.steer = command.steer;
+        control.brake = command.brake;
+
+        log::debug!(
+            "[to_carla] vehicle={vehicle}, throttle={}, steer={}, brake={}",
+            control.throttle,
+            control.steer,
+            control.brake
+        );
+
+        ego_vehicle.apply_control(&control);
+
+        Ok(())
+    }
+
+    fn teleport(&mut self, vehicle: usize, pose: Pose) -> Result<(), String> {
+        let vehicle = self.ego_vehicle(vehicle)?;
+        let current = vehicle.transform();
+        let (roll, pitch, _) = current.rotation.euler_angles();
+
+        vehicle.set_transform(&Isometry3::from_parts(
+            Translation3::new(pose.x as f32, pose.y as f32, current.translation.z),
+            UnitQuaternion::from_euler_angles(roll, pitch, pose.yaw as f32),
+        ));
+        Ok(())
+    }
+
+    fn reset(&mut self, vehicle: usize) -> Result<(), String> {
+        let start = self
+            .starts
+            .get(vehicle)
+            .copied()
+            .flatten()
+            .ok_or("Start transform of the vehicle is unknown")?;
+        let vehicle = self.ego_vehicle(vehicle)?;
+
+        vehicle.set_transform(&start);
+        vehicle.set_target_velocity(&Vector3::zeros());
+        vehicle.set_target_angular_velocity(&Vector3::zeros());
+        Ok(())
+    }
+
+    fn set_speed(&mut self, vehicle: usize, speed_kmh: f64) -> Result<(), String> {
+        let vehicle = self.ego_vehicle(vehicle)?;
+        let forward = vehicle.transform().rotation * Vector3::x();
+
+        vehicle.set_target_velocity(&(forward * (speed_kmh / 3.6) as f32));
+        Ok(())
+    }
+
+    fn set_weather(&mut self, preset: WeatherPreset) -> Result<(), String> {
+        let [cloudiness, precipitation, deposits, wind, sun_altitude, fog] = weather_values(preset);
+
+        let mut weather = self.world.weather();
+        weather.cloudiness = cloudiness;
+        weather.precipitation = precipitation;
+        weather.precipitation_deposits = deposits;
+        weather.wind_intensity = wind;
+        weather.sun_altitude_angle = sun_altitude;
+        weather.fog_density = fog;
+
+        self.world.set_weather(&weather);
+        log::info!("Weather set to {preset:?}");
+        Ok(())
+    }
+
+    fn spawn_npcs(&mut self, count: usize) -> Result<Vec<u32>, String> {
+        // Make sure the traffic manager is running before handing vehicles over
+        let traffic_manager = self.client.instance_tm(Some(TRAFFIC_MANAGER_PORT));
+
+        let blueprints: Vec<ActorBlueprint> = self
+            .world
+            .blueprint_library()
+            .filter("vehicle.*")
+            .iter()
+            .collect();
+        if blueprints.is_empty() {
+            return Err("No vehicle blueprints available".to_string());
+        }
+
+        let bridged: Vec<_> = (0..self.vehicle_ids.len())
+            .filter_map(|index| self.vehicle(index))
+            .map(|vehicle| vehicle.transform().translation.vector)
+            .collect();
+        let mut ids = Vec::new();
+
+        for spawn_point in self.map.recommended_spawn_points().iter() {
+            if ids.len() == count {
+                break;
+            }
+            // Leave some room around the bridged vehicles
+            let location = spawn_point.translation.vector;
+            if bridged.iter().any(|v| (location - v).norm() < 10.0) {
+                continue;
+            }
+
+            let mut blueprint = blueprints[self.npcs.len() % blueprints.len()].clone();
+            blueprint.set_attribute("role_name", NPC_ROLE);
+
+            // Occupied spawn points fail; try the next one
+            let Ok(actor) = self.world.spawn_actor(&blueprint, &spawn_point) else {
+                continue;
+            };
+            if let Ok(vehicle) = actor.clone().into_kinds().try_into_vehicle() {
+                vehicle.set_autopilot_opt(true, traffic_manager.port());
+            }
+
+            ids.push(actor.id());
+            self.npcs.push(actor);
+        }
+
+        log::info!("Spawned {} of {count} NPC vehicles", ids.len());
+        Ok(ids)
+    }
+
+    fn destroy_npcs(&mut self, ids: Option<&[u32]>) -> Result<usize, String> {
+        let (destroy, keep): (Vec<Actor>, Vec<Actor>) = self
+            .npcs
+            .drain(..)
+            .partition(|actor| ids.is_none_or(|ids| ids.contains(&actor.id())));
+        self.npcs = keep;
+
+        let count = destroy.len();
+        for actor in destroy {
+            actor.destroy();
+        }
+
+        log::info!("Destroyed {count} NPC vehicles");
+        Ok(count)
+    }
+
+    fn set_paused(&mut self, paused: bool) -> Result<(), String> {
+        // In synchronous mode the world only advances when a client ticks it
+        let mut settings = self.world.settings();
+        settings.synchronous_mode = paused;
+        self.world
+            .apply_settings(&settings, Duration::from_millis(CLIENT_TIME_MS));
+
+        self.paused = paused;
+        log::info!("Simulation {}", if paused { "paused" } else { "resumed" });
+        Ok(())
+    }
+}
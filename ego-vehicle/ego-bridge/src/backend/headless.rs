@@ -0,0 +1,262 @@
+use super::{LaneGeometry, Pose, SimTick, VehicleBackend, VehicleState, WeatherPreset};
+use crate::control::VehicleCommand;
+use async_trait::async_trait;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+// Vehicle model constants
+const WHEELBASE_M: f64 = 2.9;
+const MAX_STEER_ANGLE_RAD: f64 = 70.0 * PI / 180.0;
+const MAX_ACCELERATION: f64 = 3.5; // m/s² at full throttle
+const MAX_DECELERATION: f64 = 9.0; // m/s² at full brake
+const DRAG: f64 = 0.02; // 1/s, speed-proportional losses
+// Road constants
+const LANE_WIDTH_M: f64 = 3.5;
+const LANE_STEP_M: f64 = 2.0;
+// Gap between the start positions of consecutive vehicles
+const VEHICLE_SPACING_M: f64 = 20.0;
+
+/// State of one vehicle of the model.
+#[derive(Clone, Copy, Debug, Default)]
+struct Model {
+    pose: Pose,
+    speed: f64,
+    command: VehicleCommand,
+    start: (Pose, f64),
+}
+
+/// Simulator-free backend: a kinematic bicycle model on a road of constant curvature.
+///
+/// The road starts at the origin heading along +x; the lane centre line is known
+/// exactly, so lane geometry is always available. Further vehicles start on the lane
+/// centre, each 20 m behind the previous one. Steps are paced in real time.
+/// NPCs and weather are only bookkept: they do not affect the model.
+#[derive(Debug)]
+pub struct HeadlessBackend {
+    delta: f64,
+    frame: u64,
+    elapsed_seconds: f64,
+    /// Road curvature in 1/m (positive turns right, 0 is straight).
+    curvature: f64,
+    vehicles: Vec<Model>,
+    paused: bool,
+    weather: WeatherPreset,
+    npcs: Vec<u32>,
+    next_npc_id: u32,
+}
+
+impl HeadlessBackend {
+    pub fn new(delta: f64, curvature: f64) -> Self {
+        Self {
+            delta,
+            frame: 0,
+            elapsed_seconds: 0.0,
+            curvature,
+            vehicles: vec![Model::default()],
+            paused: false,
+            weather: WeatherPreset::ClearNoon,
+            npcs: Vec::new(),
+            next_npc_id: 1,
+        }
+    }
+
+    /// Drives `count` vehicles (at least one), lined up behind the first one.
+    pub fn with_vehicles(mut self, count: usize) -> Self {
+        self.vehicles = (0..count.max(1))
+            .map(|i| {
+                let s = -(i as f64) * VEHICLE_SPACING_M;
+                let (x, y) = self.road_point(s);
+                let pose = Pose {
+                    x,
+                    y,
+                    yaw: s * self.curvature,
+                };
+                Model {
+                    pose,
+                    start: (pose, 0.0),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        self
+    }
+
+    /// Places the first vehicle; `speed_kmh` sets its initial speed.
+    pub fn with_state(mut self, pose: Pose, speed_kmh: f64) -> Self {
+        let speed = speed_kmh / 3.6;
+        self.vehicles[0] = Model {
+            pose,
+            speed,
+            start: (pose, speed),
+            ..Default::default()
+        };
+        self
+    }
+
+    pub fn weather(&self) -> WeatherPreset {
+        self.weather
+    }
+
+    pub fn npcs(&self) -> &[u32] {
+        &self.npcs
+    }
+
+    /// Advances the model by one step without waiting; a paused model does not move.
+    pub fn step(&mut self) -> SimTick {
+        if self.paused {
+            return SimTick {
+                frame: self.frame,
+                elapsed_seconds: self.elapsed_seconds,
+            };
+        }
+
+        let dt = self.delta;
+        for model in &mut self.vehicles {
+            let command = model.command;
+
+            let acceleration = command.throttle as f64 * MAX_ACCELERATION
+                - command.brake as f64 * MAX_DECELERATION
+                - DRAG * model.speed;
+            model.speed = (model.speed + acceleration * dt).max(0.0);
+
+            let steer_angle = command.steer as f64 * MAX_STEER_ANGLE_RAD;
+            model.pose.x += model.speed * model.pose.yaw.cos() * dt;
+            model.pose.y += model.speed * model.pose.yaw.sin() * dt;
+            model.pose.yaw += model.speed / WHEELBASE_M * steer_angle.tan() * dt;
+        }
+
+        self.frame += 1;
+        self.elapsed_seconds += dt;
+
+        SimTick {
+            frame: self.frame,
+            elapsed_seconds: self.elapsed_seconds,
+        }
+    }
+
+    /// Point of the lane centre at arc length `s`.
+    fn road_point(&self, s: f64) -> (f64, f64) {
+        if self.curvature.abs() < 1e-9 {
+            (s, 0.0)
+        } else {
+            let radius = 1.0 / self.curvature;
+            let heading = s * self.curvature;
+            (radius * heading.sin(), radius * (1.0 - heading.cos()))
+        }
+    }
+
+    /// Arc length of the lane centre point closest to (`x`, `y`).
+    fn road_position(&self, x: f64, y: f64) -> f64 {
+        if self.curvature.abs() < 1e-9 {
+            x
+        } else {
+            let radius = 1.0 / self.curvature;
+            // Heading of the closest point, seen from the centre of curvature at (0, radius)
+            let sign = radius.signum();
+            let heading = (x * sign).atan2((radius - y) * sign);
+            heading * radius
+        }
+    }
+
+    /// Signed distance (m) of `vehicle` from the lane centre, positive to the right.
+    pub fn lateral_offset(&self, vehicle: usize) -> f64 {
+        let pose = self.vehicles[vehicle].pose;
+        let s = self.road_position(pose.x, pose.y);
+        let (cx, cy) = self.road_point(s);
+        let heading = s * self.curvature;
+        (pose.y - cy) * heading.cos() - (pose.x - cx) * heading.sin()
+    }
+
+    fn model(&mut self, vehicle: usize) -> Result<&mut Model, String> {
+        self.vehicles
+            .get_mut(vehicle)
+            .ok_or_else(|| format!("Unknown vehicle {vehicle}"))
+    }
+}
+
+#[async_trait(?Send)]
+impl VehicleBackend for HeadlessBackend {
+    async fn tick(&mut self) -> SimTick {
+        tokio::time::sleep(Duration::from_secs_f64(self.delta)).await;
+        self.step()
+    }
+
+    fn vehicle_count(&self) -> usize {
+        self.vehicles.len()
+    }
+
+    fn vehicle_state(&self, vehicle: usize) -> Option<VehicleState> {
+        let model = self.vehicles.get(vehicle)?;
+        Some(VehicleState {
+            speed_kmh: model.speed * 3.6,
+            pose: model.pose,
+        })
+    }
+
+    fn lane_ahead(&self, vehicle: usize, distance: f64) -> Option<LaneGeometry> {
+        let pose = self.vehicles.get(vehicle)?.pose;
+        let start = self.road_position(pose.x, pose.y);
+        let points = (distance / LANE_STEP_M).ceil() as usize;
+
+        Some(LaneGeometry {
+            centerline: (0..=points)
+                .map(|i| self.road_point(start + i as f64 * LANE_STEP_M))
+                .collect(),
+            lane_width: LANE_WIDTH_M,
+        })
+    }
+
+    fn apply_control(&mut self, vehicle: usize, command: &VehicleCommand) -> Result<(), String> {
+        log::debug!(
+            "[to_headless] vehicle={vehicle}, throttle={}, steer={}, brake={}",
+            command.throttle,
+            command.steer,
+            command.brake
+        );
+        self.model(vehicle)?.command = *command;
+        Ok(())
+    }
+
+    fn teleport(&mut self, vehicle: usize, pose: Pose) -> Result<(), String> {
+        self.model(vehicle)?.pose = pose;
+        Ok(())
+    }
+
+    fn reset(&mut self, vehicle: usize) -> Result<(), String> {
+        let model = self.model(vehicle)?;
+        (model.pose, model.speed) = (model.start.0, 0.0);
+        model.command = VehicleCommand::default();
+        Ok(())
+    }
+
+    fn set_speed(&mut self, vehicle: usize, speed_kmh: f64) -> Result<(), String> {
+        self.model(vehicle)?.speed = speed_kmh.max(0.0) / 3.6;
+        Ok(())
+    }
+
+    fn set_weather(&mut self, preset: WeatherPreset) -> Result<(), String> {
+        self.weather = preset;
+        Ok(())
+    }
+
+    fn spawn_npcs(&mut self, count: usize) -> Result<Vec<u32>, String> {
+        let ids: Vec<u32> = (self.next_npc_id..).take(count).collect();
+        self.next_npc_id += count as u32;
+        self.npcs.extend(&ids);
+        Ok(ids)
+    }
+
+    fn destroy_npcs(&mut self, ids: Option<&[u32]>) -> Result<usize, String> {
+        let before = self.npcs.len();
+        match ids {
+            Some(ids) => self.npcs.retain(|id| !ids.contains(id)),
+            None => self.npcs.clear(),
+        }
+        Ok(before - self.npcs.len())
+    }
+
+    fn set_paused(&mut self, paused: bool) -> Result<(), String> {
+        self.paused = paused;
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::stamp::SampleStamp;
+use serde::Deserialize;
+use up_rust::UPayloadFormat;
+use zenoh::bytes::Encoding;
+
+/// Wire encoding of a scalar signal, selectable per signal in the bridge configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEncoding {
+    /// Decimal number as UTF-8 text, e.g. `45.2`.
+    #[default]
+    Text,
+    /// JSON object carrying the value under the configured field, and the simulation time
+    /// when published, e.g. `{"velocity": 45.2, "frame": 250, "elapsed_seconds": 12.5}`.
+    /// A bare JSON number is accepted when decoding.
+    Json,
+    /// 4-byte little-endian IEEE 754 float.
+    F32Le,
+    /// 8-byte little-endian IEEE 754 float.
+    F64Le,
+    /// `<field>:<value>` text as parsed by the AAOS cluster, followed by the simulation
+    /// time when published, e.g. `fcw:2,frame:250,elapsed_seconds:12.5`. A bare pair is
+    /// accepted when decoding.
+    KeyValue,
+    /// Event notification; any payload is received as `1`, e.g. collision sensor events.
+    Event,
+    /// Switch state as text, as the former controllers read it: `0`, `false`, `off`, `no`
+    /// and an empty payload are received as `0`, any other payload as `1` (e.g. `true`,
+    /// `on`). Encoded as `1` or `0`.
+    Bool,
+}
+
+impl PayloadEncoding {
+    /// Encodes `value`; `json` and `key_value` payloads carry the simulation time of
+    /// `stamp`, see [`PayloadEncoding::is_stamped`].
+    pub fn encode(&self, value: f64, field: &str, stamp: &SampleStamp) -> Vec<u8> {
+        match self {
+            PayloadEncoding::Text => value.to_string().into_bytes(),
+            PayloadEncoding::Json => {
+                let mut object = serde_json::Map::new();
+                object.insert("frame".to_string(), serde_json::json!(stamp.frame));
+                object.insert(
+                    "elapsed_seconds".to_string(),
+                    serde_json::json!(stamp.elapsed_seconds),
+                );
+                object.insert(field.to_string(), serde_json::json!(value));
+                serde_json::to_vec(&object).unwrap_or_default()
+            }
+            PayloadEncoding::F32Le => (value as f32).to_le_bytes().to_vec(),
+            PayloadEncoding::F64Le => value.to_le_bytes().to_vec(),
+            PayloadEncoding::KeyValue => format!(
+                "{field}:{value},frame:{},elapsed_seconds:{}",
+                stamp.frame, stamp.elapsed_seconds
+            )
+            .into_bytes(),
+            PayloadEncoding::Event => Vec::new(),
+            PayloadEncoding::Bool => if value != 0.0 { "1" } else { "0" }.into(),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8], field: &str) -> Result<f64, String> {
+        match self {
+            PayloadEncoding::Text => std::str::from_utf8(bytes)
+                .map_err(|e| e.to_string())?
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| e.to_string()),
+            PayloadEncoding::Json => {
+                let value: serde_json::Value =
+                    serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+                value
+                    .as_f64()
+                    .or_else(|| value.get(field).and_then(|v| v.as_f64()))
+                    .ok_or_else(|| format!("no numeric '{field}' field in {value}"))
+            }
+            PayloadEncoding::F32Le => bytes
+                .try_into()
+                .map(|b: [u8; 4]| f32::from_le_bytes(b) as f64)
+                .map_err(|_| format!("expected 4 bytes, got {}", bytes.len())),
+            PayloadEncoding::F64Le => bytes
+                .try_into()
+                .map(f64::from_le_bytes)
+                .map_err(|_| format!("expected 8 bytes, got {}", bytes.len())),
+            PayloadEncoding::KeyValue => {
+                let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+                key_value_pairs(text)
+                    .find(|(key, _)| *key == field)
+                    .ok_or_else(|| format!("expected '{field}:<value>', got '{text}'"))?
+                    .1
+                    .parse::<f64>()
+                    .map_err(|e| e.to_string())
+            }
+            PayloadEncoding::Event => Ok(1.0),
+            PayloadEncoding::Bool => {
+                let text = String::from_utf8_lossy(bytes).trim().to_lowercase();
+                let off = match text.parse::<f64>() {
+                    Ok(value) => value == 0.0,
+                    Err(_) => matches!(text.as_str(), "" | "false" | "off" | "no"),
+                };
+                Ok(if off { 0.0 } else { 1.0 })
+            }
+        }
+    }
+
+    /// Whether payloads carry the simulation time themselves; raw Zenoh samples always
+    /// carry it in their attachment.
+    pub fn is_stamped(&self) -> bool {
+        matches!(self, PayloadEncoding::Json | PayloadEncoding::KeyValue)
+    }
+
+    pub fn upayload_format(&self) -> UPayloadFormat {
+        match self {
+            PayloadEncoding::Text | PayloadEncoding::KeyValue | PayloadEncoding::Bool => {
+                UPayloadFormat::UPAYLOAD_FORMAT_TEXT
+            }
+            PayloadEncoding::Json => UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            PayloadEncoding::F32Le | PayloadEncoding::F64Le | PayloadEncoding::Event => {
+                UPayloadFormat::UPAYLOAD_FORMAT_RAW
+            }
+        }
+    }
+
+    pub fn zenoh_encoding(&self) -> Encoding {
+        match self {
+            PayloadEncoding::Text | PayloadEncoding::KeyValue | PayloadEncoding::Bool => {
+                Encoding::TEXT_PLAIN
+            }
+            PayloadEncoding::Json => Encoding::APPLICATION_JSON,
+            PayloadEncoding::F32Le | PayloadEncoding::F64Le | PayloadEncoding::Event => {
+                Encoding::ZENOH_BYTES
+            }
+        }
+    }
+}
+
+/// `<key>:<value>` pairs of a `key_value` payload, separated by `,`, `;` or new lines as
+/// the AAOS cluster splits them.
+pub fn key_value_pairs(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.split(['\n', ',', ';'])
+        .filter_map(|pair| pair.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_all_encodings() {
+        for encoding in [
+            PayloadEncoding::Text,
+            PayloadEncoding::Json,
+            PayloadEncoding::F32Le,
+            PayloadEncoding::F64Le,
+            PayloadEncoding::KeyValue,
+        ] {
+            let bytes = encoding.encode(42.5, "velocity", &SampleStamp::default());
+            assert_eq!(
+                encoding.decode(&bytes, "velocity"),
+                Ok(42.5),
+                "{encoding:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy_payloads() {
+        assert_eq!(PayloadEncoding::Text.decode(b" 1\n", "value"), Ok(1.0));
+        assert_eq!(PayloadEncoding::Json.decode(b"0.7", "value"), Ok(0.7));
+        assert_eq!(
+            PayloadEncoding::Json.decode(br#"{"engaged": 1}"#, "engaged"),
+            Ok(1.0)
+        );
+        assert!(
+            PayloadEncoding::Text
+                .decode(b"Invalid UTF-8", "value")
+                .is_err()
+        );
+        assert!(PayloadEncoding::F32Le.decode(&[0, 1], "value").is_err());
+        assert_eq!(PayloadEncoding::KeyValue.decode(b"fcw: 2", "fcw"), Ok(2.0));
+        assert!(PayloadEncoding::KeyValue.decode(b"ldw:1", "fcw").is_err());
+        assert_eq!(PayloadEncoding::Event.decode(b"{}", "value"), Ok(1.0));
+        for (payload, engaged) in [
+            (&b"1"[..], 1.0),
+            (b"0", 0.0),
+            (b"0.0", 0.0),
+            (b"True", 1.0),
+            (b"on", 1.0),
+            (b" false\n", 0.0),
+            (b"off", 0.0),
+            (b"engaged", 1.0),
+        ] {
+            assert_eq!(
+                PayloadEncoding::Bool.decode(payload, "value"),
+                Ok(engaged),
+                "{payload:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::aeb::AebConfig;
+use crate::codec::PayloadEncoding;
+use crate::lka::LkaConfig;
+use crate::spawn::SpawnPoint;
+use crate::state::alive_key;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use up_rust::UUri;
+use zenoh::key_expr::KeyExpr;
+
+/// Mapping used when no `--config` is given; matches the former `uprotocol-control` binary.
+pub const DEFAULT_CONFIG: &str = include_str!("../config/uprotocol.yaml");
+
+fn default_authority() -> String {
+    "EGOVehicle".to_string()
+}
+
+fn default_ue_version() -> u8 {
+    2
+}
+
+fn default_field() -> String {
+    "value".to_string()
+}
+
+fn default_state_prefix() -> String {
+    "vehicle{suffix}/bridge".to_string()
+}
+
+/// Role validated against when no `vehicles` are configured.
+const DEFAULT_ROLE: &str = "ego_vehicle";
+
+/// Top-level bridge configuration.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BridgeConfig {
+    #[serde(default)]
+    pub entity: EntityConfig,
+    pub signals: BTreeMap<String, SignalConfig>,
+    /// Automatic emergency braking; disabled when absent.
+    #[serde(default)]
+    pub aeb: Option<AebConfig>,
+    /// Lane keeping assist; disabled when absent.
+    #[serde(default)]
+    pub lka: Option<LkaConfig>,
+    /// Serves the simulation control methods (reset, teleport, weather, NPCs, pause)
+    /// over uProtocol RPC, as the bridge's entity.
+    #[serde(default)]
+    pub sim_control: bool,
+    /// Liveliness token and state queryables over Zenoh; disabled when absent.
+    #[serde(default)]
+    pub state: Option<StateConfig>,
+    /// Vehicles driven by the bridge. When empty, a single vehicle with the `--role`
+    /// of the bridge and the entity's authority.
+    #[serde(default)]
+    pub vehicles: Vec<VehicleConfig>,
+}
+
+/// A vehicle driven by the bridge, with its own endpoints and arbitration.
+///
+/// Signal endpoints may contain `{authority}`, `{role}` and `{suffix}`, which are
+/// replaced by the values of each vehicle.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct VehicleConfig {
+    /// `role_name` of the vehicle actor.
+    pub role: String,
+    /// uProtocol authority of the vehicle, e.g. `EGOVehicle-2`.
+    pub authority: String,
+    /// Distinguishes the vehicle's endpoints, e.g. `-2`.
+    #[serde(default)]
+    pub suffix: String,
+    /// Spawn point in spawn mode; defaults to the next one after the previous vehicle's.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub spawn_point: Option<SpawnPoint>,
+}
+
+impl VehicleConfig {
+    fn expand(&self, endpoint: &str) -> String {
+        endpoint
+            .replace("{authority}", &self.authority)
+            .replace("{role}", &self.role)
+            .replace("{suffix}", &self.suffix)
+    }
+}
+
+/// Zenoh keys at which the bridge announces itself and answers state queries.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateConfig {
+    /// Key prefix of the liveliness token (`<prefix>/alive`) and of the queryables
+    /// (`<prefix>/speed`, `clock`, `control` and `engage`).
+    #[serde(default = "default_state_prefix")]
+    pub key_prefix: String,
+}
+
+/// uProtocol identity of the bridge.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntityConfig {
+    #[serde(default = "default_authority")]
+    pub authority: String,
+    #[serde(default)]
+    pub ue_id: u32,
+    #[serde(default = "default_ue_version")]
+    pub ue_version: u8,
+}
+
+impl Default for EntityConfig {
+    fn default() -> Self {
+        Self {
+            authority: default_authority(),
+            ue_id: 0,
+            ue_version: default_ue_version(),
+        }
+    }
+}
+
+/// One signal moved by the bridge, over uProtocol, raw Zenoh, or both.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SignalConfig {
+    #[serde(flatten)]
+    pub role: SignalRole,
+    /// uProtocol topic URI, e.g. `//EGOVehicle/0/2/8001`.
+    #[serde(default)]
+    pub uprotocol: Option<String>,
+    /// Raw Zenoh key expression, e.g. `vehicle/status/velocity_status`.
+    #[serde(default)]
+    pub zenoh: Option<String>,
+    #[serde(default)]
+    pub encoding: PayloadEncoding,
+    /// Object field used by the `json` encoding, key used by the `key_value` encoding.
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Value assumed for a subscribed signal until the first sample arrives.
+    #[serde(default)]
+    pub initial: Option<f64>,
+}
+
+/// Whether the bridge publishes a vehicle quantity or feeds a control input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalRole {
+    Publish(VehicleQuantity),
+    Subscribe(ControlInput),
+}
+
+/// Quantities the bridge can read from the simulation and publish.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleQuantity {
+    /// Vehicle speed in km/h.
+    SpeedKmh,
+    /// Simulation clock in seconds.
+    ElapsedSeconds,
+    /// Simulation frame number.
+    Frame,
+    /// Throttle applied in the last tick.
+    Throttle,
+    /// Brake applied in the last tick.
+    Brake,
+    /// Steering applied in the last tick.
+    Steer,
+    /// Automatic emergency braking stage (0 = inactive, 1 = warning, 2 = partial, 3 = full).
+    AebStage,
+    /// Time-to-collision in seconds with the obstacle ahead, while one is tracked.
+    TimeToCollision,
+    /// Lane keeping assist state (0 = off, 1 = standby, 2 = steering).
+    LaneAssistState,
+    /// Lane-departure warning (0 or 1).
+    LaneDepartureWarning,
+}
+
+/// Inputs consumed by the control logic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlInput {
+    /// Manual throttle (0.0 to 1.0).
+    Throttle,
+    /// Manual brake (0.0 to 1.0).
+    Brake,
+    /// Manual steering (-1.0 to 1.0).
+    Steer,
+    /// Cruise control output; positive values throttle, negative values brake.
+    Actuation,
+    /// Cruise control engagement (0 = manual, otherwise automatic).
+    Engage,
+    /// Distance in meters to the obstacle detected ahead.
+    ObstacleDistance,
+    /// Collision event; any sample counts.
+    Collision,
+    /// Lateral assist engagement (0 = off, otherwise lane keeping assist engaged).
+    LaneAssist,
+    /// Lane invasion event; any sample counts.
+    LaneInvasion,
+}
+
+impl BridgeConfig {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        Self::from_yaml(&text)
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let config: BridgeConfig = serde_yaml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that vehicles are distinct, that every signal has at least one valid
+    /// endpoint for each of them, and that no two vehicles share an endpoint.
+    pub fn validate(&self) -> Result<(), String> {
+        let vehicles = self.vehicles(DEFAULT_ROLE);
+        let roles: BTreeSet<&str> = vehicles.iter().map(|v| v.role.as_str()).collect();
+        let authorities: BTreeSet<&str> = vehicles.iter().map(|v| v.authority.as_str()).collect();
+        if roles.len() != vehicles.len() || authorities.len() != vehicles.len() {
+            return Err("Every vehicle needs its own role and authority".to_string());
+        }
+
+        let mut owners: BTreeMap<String, &str> = BTreeMap::new();
+        for vehicle in &vehicles {
+            for endpoint in self.for_vehicle(vehicle)?.endpoints() {
+                if let Some(other) = owners.insert(endpoint.clone(), &vehicle.role) {
+                    return Err(format!(
+                        "Vehicles '{other}' and '{}' share the endpoint '{endpoint}'; \
+                         use {{authority}}, {{role}} or {{suffix}} in it",
+                        vehicle.role
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured vehicles, or a single vehicle `role` with the entity's authority.
+    pub fn vehicles(&self, role: &str) -> Vec<VehicleConfig> {
+        if !self.vehicles.is_empty() {
+            return self.vehicles.clone();
+        }
+
+        vec![VehicleConfig {
+            role: role.to_string(),
+            authority: self.entity.authority.clone(),
+            suffix: String::new(),
+            spawn_point: None,
+        }]
+    }
+
+    /// Configuration of a single vehicle: its authority, and its endpoints.
+    pub fn for_vehicle(&self, vehicle: &VehicleConfig) -> Result<BridgeConfig, String> {
+        let mut config = self.clone();
+        config.entity.authority = vehicle.authority.clone();
+        config.vehicles = vec![vehicle.clone()];
+
+        for signal in config.signals.values_mut() {
+            signal.uprotocol = signal.uprotocol.as_deref().map(|e| vehicle.expand(e));
+            signal.zenoh = signal.zenoh.as_deref().map(|e| vehicle.expand(e));
+        }
+        if let Some(state) = config.state.as_mut() {
+            state.key_prefix = vehicle.expand(&state.key_prefix);
+        }
+        config.check_endpoints()?;
+
+        Ok(config)
+    }
+
+    // uProtocol URIs and Zenoh keys of the signals, and the liveliness key of the state
+    fn endpoints(&self) -> Vec<String> {
+        let signals = self
+            .signals
+            .values()
+            .flat_map(|signal| [signal.uprotocol.clone(), signal.zenoh.clone()]);
+        let state = self.state.as_ref().map(|s| alive_key(&s.key_prefix));
+        signals.chain([state]).flatten().collect()
+    }
+
+    fn check_endpoints(&self) -> Result<(), String> {
+        for (name, signal) in &self.signals {
+            if signal.uprotocol.is_none() && signal.zenoh.is_none() {
+                return Err(format!(
+                    "Signal '{name}' needs a 'uprotocol' URI, a 'zenoh' key expression or both"
+                ));
+            }
+            if let Some(uri) = &signal.uprotocol {
+                UUri::from_str(uri)
+                    .map_err(|e| format!("Signal '{name}' has an invalid uProtocol URI: {e}"))?;
+            }
+            if let Some(key) = &signal.zenoh {
+                KeyExpr::new(key.as_str())
+                    .map_err(|e| format!("Signal '{name}' has an invalid Zenoh key: {e}"))?;
+            }
+        }
+        if let Some(state) = &self.state {
+            KeyExpr::new(alive_key(&state.key_prefix).as_str())
+                .map_err(|e| format!("Invalid state key prefix: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn uses_uprotocol(&self) -> bool {
+        self.sim_control || self.signals.values().any(|s| s.uprotocol.is_some())
+    }
+
+    pub fn uses_zenoh(&self) -> bool {
+        self.state.is_some() || self.signals.values().any(|s| s.zenoh.is_some())
+    }
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self::from_yaml(DEFAULT_CONFIG).expect("built-in bridge configuration is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_configs_are_valid() {
+        let config = BridgeConfig::default();
+        assert_eq!(config.entity.authority, "EGOVehicle");
+        assert_eq!(
+            config.signals["speed"].role,
+            SignalRole::Publish(VehicleQuantity::SpeedKmh)
+        );
+        assert_eq!(
+            config.signals["engage"].role,
+            SignalRole::Subscribe(ControlInput::Engage)
+        );
+
+        let zenoh = BridgeConfig::from_yaml(include_str!("../config/zenoh.yaml")).unwrap();
+        assert!(!zenoh.uses_uprotocol());
+        assert!(zenoh.uses_zenoh());
+        assert!(config.aeb.is_some());
+        assert!(zenoh.aeb.is_none());
+        assert!(config.lka.is_some());
+        assert_eq!(
+            zenoh.state.map(|s| s.key_prefix).as_deref(),
+            Some("vehicle{suffix}/bridge")
+        );
+    }
+
+    #[test]
+    fn test_endpoints_are_expanded_per_vehicle() {
+        let yaml = "signals:\n  speed:\n    publish: speed_kmh\n    uprotocol: //{authority}/0/2/8001\n    \
+                    zenoh: vehicle{suffix}/status/velocity_status\n\
+                    vehicles:\n  - {role: ego_vehicle, authority: EGOVehicle}\n  \
+                    - {role: ego_vehicle_2, authority: EGOVehicle-2, suffix: '-2', spawn_point: {index: 5}}\n";
+        let config = BridgeConfig::from_yaml(yaml).unwrap();
+
+        let vehicles = config.vehicles("ignored");
+        assert_eq!(vehicles.len(), 2);
+        assert_eq!(vehicles[1].spawn_point, Some(SpawnPoint::Index(5)));
+        let second = config.for_vehicle(&vehicles[1]).unwrap();
+        assert_eq!(second.entity.authority, "EGOVehicle-2");
+        assert_eq!(
+            second.signals["speed"].uprotocol.as_deref(),
+            Some("//EGOVehicle-2/0/2/8001")
+        );
+        assert_eq!(
+            second.signals["speed"].zenoh.as_deref(),
+            Some("vehicle-2/status/velocity_status")
+        );
+
+        let duplicate = yaml.replace("EGOVehicle-2", "EGOVehicle");
+        assert!(BridgeConfig::from_yaml(&duplicate).is_err());
+        let shared_key = yaml.replace("vehicle{suffix}", "vehicle");
+        let error = BridgeConfig::from_yaml(&shared_key)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("share the endpoint 'vehicle/status/velocity_status'"));
+
+        // The bundled mappings give every vehicle its own endpoints
+        let vehicles = "\nvehicles:\n  - {role: ego_vehicle, authority: EGOVehicle}\n  \
+                        - {role: ego_vehicle_2, authority: EGOVehicle-2, suffix: '-2'}\n";
+        for bundled in [DEFAULT_CONFIG, include_str!("../config/zenoh.yaml")] {
+            assert!(BridgeConfig::from_yaml(&format!("{bundled}{vehicles}")).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_signal_without_endpoint_is_rejected() {
+        let yaml = "signals:\n  speed:\n    publish: speed_kmh\n";
+        assert!(BridgeConfig::from_yaml(yaml).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::arbiter::ActuationRequest;
+
+// Vehicle control constants
+pub const MIN_THROTTLE: f32 = 0.0;
+pub const MIN_STEERING: f32 = -1.0;
+pub const MIN_BRAKING: f32 = 0.0;
+
+pub const MID_STEERING: f32 = 0.0;
+
+pub const MAX_THROTTLE: f32 = 1.0;
+pub const MAX_STEERING: f32 = 1.0;
+pub const MAX_BRAKING: f32 = 1.0;
+
+/// Latest values of the subscribed control inputs (`None` until first received).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ControlInputs {
+    pub throttle: Option<f64>,
+    pub brake: Option<f64>,
+    pub steer: Option<f64>,
+    pub actuation: Option<f64>,
+    pub engage: Option<f64>,
+    pub lane_assist: Option<f64>,
+}
+
+/// Actuator values applied to the vehicle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VehicleCommand {
+    pub throttle: f32,
+    pub steer: f32,
+    pub brake: f32,
+}
+
+impl Default for VehicleCommand {
+    fn default() -> Self {
+        Self {
+            throttle: MIN_THROTTLE,
+            steer: MID_STEERING,
+            brake: MIN_BRAKING,
+        }
+    }
+}
+
+impl ControlInputs {
+    /// Whether cruise control drives the longitudinal actuators (defaults to manual).
+    pub fn engaged(&self) -> bool {
+        self.engage.is_some_and(|v| v != 0.0)
+    }
+
+    /// Whether the lane keeping assist may steer (defaults to off).
+    pub fn lane_assist_engaged(&self) -> bool {
+        self.lane_assist.is_some_and(|v| v != 0.0)
+    }
+
+    /// Manual throttle, brake and steering, as far as they have been received.
+    pub fn manual_request(&self) -> ActuationRequest {
+        let request = ActuationRequest {
+            throttle: self.throttle.map(|v| v as f32),
+            brake: self.brake.map(|v| v as f32),
+            steer: self.steer.map(|v| v as f32),
+        };
+
+        log::debug!(
+            "[from_manual] throttle_sts: {:?}, braking_sts: {:?}, steering_sts: {:?}",
+            request.throttle,
+            request.brake,
+            request.steer
+        );
+
+        request
+    }
+
+    /// Throttle and brake derived from the cruise control actuation command while engaged;
+    /// positive values throttle, negative values brake.
+    pub fn cruise_request(&self) -> Option<ActuationRequest> {
+        if !self.engaged() {
+            return None;
+        }
+
+        let pid_output = self.actuation.unwrap_or(0.0) as f32;
+
+        log::debug!("[from_pid] actuation_cmd: {pid_output}");
+
+        let (throttle, brake) = if pid_output >= 0.0 {
+            (pid_output, MIN_BRAKING)
+        } else {
+            (MIN_THROTTLE, pid_output.abs())
+        };
+
+        Some(ActuationRequest {
+            throttle: Some(throttle),
+            brake: Some(brake),
+            steer: None,
+        })
+    }
+}
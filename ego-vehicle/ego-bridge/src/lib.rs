@@ -0,0 +1,14 @@
+pub mod aeb;
+pub mod arbiter;
+pub mod args;
+pub mod backend;
+pub mod codec;
+pub mod config;
+pub mod control;
+pub mod lka;
+pub mod signals;
+pub mod sim_control;
+pub mod stamp;
+pub mod spawn;
+pub mod state;
+pub mod vehicle;
@@ -0,0 +1,314 @@
+use crate::arbiter::ActuationRequest;
+use crate::backend::{LaneGeometry, Pose, VehicleState};
+use serde::Deserialize;
+
+/// What the lane keeping assist does once engaged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LkaMode {
+    /// Lane-departure warning only; steering is left to the driver.
+    Warning,
+    /// Lane-departure warning and active steering towards the lane centre.
+    #[default]
+    Active,
+}
+
+/// Parameters of the lane keeping assist.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LkaConfig {
+    pub mode: LkaMode,
+    /// Minimum pure pursuit look-ahead distance (m).
+    pub lookahead_min: f64,
+    /// Look-ahead time (s); the look-ahead distance grows with speed.
+    pub lookahead_gain: f64,
+    /// Vehicle wheelbase (m).
+    pub wheelbase: f64,
+    /// Road wheel angle (rad) reached at full steering input.
+    pub max_steer_angle: f64,
+    /// Largest steering input the assist may apply (0.0 to 1.0).
+    pub max_steer: f32,
+    /// Distance (m) from the lane centre beyond which a departure is reported.
+    pub departure_offset: f64,
+    /// Simulation time (s) a lane invasion event keeps the departure warning raised.
+    pub invasion_hold: f64,
+    /// Ego speed (km/h) below which the assist does not steer.
+    pub min_speed_kmh: f64,
+}
+
+impl Default for LkaConfig {
+    fn default() -> Self {
+        Self {
+            mode: LkaMode::default(),
+            lookahead_min: 5.0,
+            lookahead_gain: 0.6,
+            wheelbase: 2.9,
+            max_steer_angle: 70f64.to_radians(),
+            max_steer: 0.5,
+            departure_offset: 0.8,
+            invasion_hold: 1.0,
+            min_speed_kmh: 10.0,
+        }
+    }
+}
+
+/// Engage state, published as its numeric value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LkaState {
+    /// Lateral assist not engaged.
+    #[default]
+    Off = 0,
+    /// Engaged but not steering (warning mode, too slow, or no lane geometry).
+    Standby = 1,
+    /// Engaged and steering.
+    Active = 2,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LkaStatus {
+    pub state: LkaState,
+    pub departure_warning: bool,
+    /// Distance (m) of the vehicle from the lane centre, positive to the right.
+    pub lateral_offset: Option<f64>,
+    /// Steering input requested while active.
+    pub steer: Option<f32>,
+}
+
+/// Lane keeping assist: a pure pursuit lateral controller tracking the lane centre line.
+///
+/// Lane departures are detected from lane invasion events and, where the backend
+/// provides lane geometry, from the lateral offset to the lane centre.
+#[derive(Debug)]
+pub struct LaneKeeping {
+    config: LkaConfig,
+    last_invasion: Option<f64>,
+    status: LkaStatus,
+}
+
+impl LaneKeeping {
+    pub fn new(config: LkaConfig) -> Self {
+        Self {
+            config,
+            last_invasion: None,
+            status: LkaStatus::default(),
+        }
+    }
+
+    /// Pure pursuit look-ahead distance (m) at `speed_kmh`.
+    pub fn lookahead(&self, speed_kmh: f64) -> f64 {
+        self.config.lookahead_min + self.config.lookahead_gain * speed_kmh / 3.6
+    }
+
+    /// Records a lane invasion event at simulation time `now` (s).
+    pub fn on_lane_invasion(&mut self, now: f64) {
+        self.last_invasion = Some(now);
+    }
+
+    /// Re-evaluates the assist at simulation time `now` (s).
+    pub fn update(
+        &mut self,
+        now: f64,
+        engaged: bool,
+        vehicle: Option<VehicleState>,
+        lane: Option<&LaneGeometry>,
+    ) -> LkaStatus {
+        let invaded = self
+            .last_invasion
+            .is_some_and(|t| now - t <= self.config.invasion_hold);
+
+        let tracking = match (vehicle, lane) {
+            (Some(vehicle), Some(lane)) => {
+                let lookahead = self.lookahead(vehicle.speed_kmh);
+                pure_pursuit(&vehicle.pose, lane, lookahead, self.config.wheelbase)
+                    .map(|(angle, offset)| (vehicle.speed_kmh, angle, offset))
+            }
+            _ => None,
+        };
+        let lateral_offset = tracking.map(|(_, _, offset)| offset);
+
+        let previous = self.status.state;
+        self.status = if !engaged {
+            LkaStatus {
+                lateral_offset,
+                ..Default::default()
+            }
+        } else {
+            let departure_warning =
+                invaded || lateral_offset.is_some_and(|o| o.abs() > self.config.departure_offset);
+
+            let steer = tracking
+                .filter(|_| self.config.mode == LkaMode::Active)
+                .filter(|(speed_kmh, _, _)| *speed_kmh >= self.config.min_speed_kmh)
+                .map(|(_, angle, _)| {
+                    let max = self.config.max_steer;
+                    ((angle / self.config.max_steer_angle) as f32).clamp(-max, max)
+                });
+
+            LkaStatus {
+                state: if steer.is_some() {
+                    LkaState::Active
+                } else {
+                    LkaState::Standby
+                },
+                departure_warning,
+                lateral_offset,
+                steer,
+            }
+        };
+
+        if self.status.state != previous {
+            log::info!("[lka] state {:?} -> {:?}", previous, self.status.state);
+        }
+
+        self.status
+    }
+
+    pub fn status(&self) -> LkaStatus {
+        self.status
+    }
+
+    /// Steering requested while active; `None` leaves steering to other sources.
+    pub fn request(&self) -> Option<ActuationRequest> {
+        self.status.steer.map(|steer| ActuationRequest {
+            throttle: None,
+            brake: None,
+            steer: Some(steer),
+        })
+    }
+}
+
+/// Road wheel angle (rad) steering `pose` onto the lane centre line, and the lateral
+/// offset (m) of `pose` from the centre line, positive to the right.
+fn pure_pursuit(
+    pose: &Pose,
+    lane: &LaneGeometry,
+    lookahead: f64,
+    wheelbase: f64,
+) -> Option<(f64, f64)> {
+    let local: Vec<(f64, f64)> = lane
+        .centerline
+        .iter()
+        .map(|&(x, y)| pose.to_local(x, y))
+        .collect();
+
+    let &(_, nearest_right) = local
+        .iter()
+        .min_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)))?;
+
+    // First point ahead at the look-ahead distance, or the farthest known point
+    let &(forward, right) = local
+        .iter()
+        .find(|(f, r)| *f > 0.0 && f.hypot(*r) >= lookahead)
+        .or_else(|| local.last())?;
+
+    let distance_sq = forward * forward + right * right;
+    if distance_sq < f64::EPSILON {
+        return None;
+    }
+    let curvature = 2.0 * right / distance_sq;
+
+    Some(((wheelbase * curvature).atan(), -nearest_right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{HeadlessBackend, VehicleBackend};
+    use crate::control::VehicleCommand;
+
+    fn straight_lane() -> LaneGeometry {
+        LaneGeometry {
+            centerline: (0..20).map(|i| (i as f64 * 2.0, 0.0)).collect(),
+            lane_width: 3.5,
+        }
+    }
+
+    fn state(y: f64, speed_kmh: f64) -> VehicleState {
+        VehicleState {
+            speed_kmh,
+            pose: Pose {
+                x: 0.0,
+                y,
+                yaw: 0.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_steers_back_towards_lane_centre() {
+        let mut lka = LaneKeeping::new(LkaConfig::default());
+        let lane = straight_lane();
+
+        // Drifted to the left (negative y): steer right (positive)
+        let status = lka.update(0.0, true, Some(state(-1.0, 50.0)), Some(&lane));
+        assert_eq!(status.state, LkaState::Active);
+        assert!(status.departure_warning);
+        assert!(status.steer.unwrap() > 0.0);
+        assert!((status.lateral_offset.unwrap() + 1.0).abs() < 1e-9);
+
+        let status = lka.update(0.1, true, Some(state(0.5, 50.0)), Some(&lane));
+        assert!(!status.departure_warning);
+        assert!(status.steer.unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_warning_mode_and_disengaged_do_not_steer() {
+        let mut lka = LaneKeeping::new(LkaConfig {
+            mode: LkaMode::Warning,
+            ..Default::default()
+        });
+        let lane = straight_lane();
+
+        let status = lka.update(0.0, true, Some(state(-1.0, 50.0)), Some(&lane));
+        assert_eq!(status.state, LkaState::Standby);
+        assert!(status.departure_warning);
+        assert!(lka.request().is_none());
+
+        let status = lka.update(0.1, false, Some(state(-1.0, 50.0)), Some(&lane));
+        assert_eq!(status.state, LkaState::Off);
+        assert!(!status.departure_warning);
+    }
+
+    #[test]
+    fn test_lane_invasion_warns_without_geometry() {
+        let mut lka = LaneKeeping::new(LkaConfig::default());
+        lka.on_lane_invasion(1.0);
+
+        let status = lka.update(1.5, true, Some(state(0.0, 50.0)), None);
+        assert_eq!(status.state, LkaState::Standby);
+        assert!(status.departure_warning);
+
+        assert!(!lka.update(2.5, true, None, None).departure_warning);
+    }
+
+    #[test]
+    fn test_keeps_headless_vehicle_in_a_curve() {
+        let config = LkaConfig::default();
+        let mut lka = LaneKeeping::new(config.clone());
+        let mut backend = HeadlessBackend::new(0.05, 1.0 / 150.0).with_state(
+            Pose {
+                x: 0.0,
+                y: -1.2,
+                yaw: 0.0,
+            },
+            60.0,
+        );
+
+        for _ in 0..400 {
+            let tick = backend.step();
+            let vehicle = backend.vehicle_state(0);
+            let lane = backend.lane_ahead(0, 2.0 * lka.lookahead(60.0));
+            lka.update(tick.elapsed_seconds, true, vehicle, lane.as_ref());
+
+            let command = VehicleCommand {
+                throttle: 0.1,
+                steer: lka.status().steer.unwrap_or_default(),
+                brake: 0.0,
+            };
+            backend.apply_control(0, &command).unwrap();
+        }
+
+        assert!(backend.lateral_offset(0).abs() < 0.2);
+        assert!(!lka.status().departure_warning);
+    }
+}
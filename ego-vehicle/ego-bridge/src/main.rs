@@ -0,0 +1,103 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use clap::Parser;
+use log;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ego_bridge::args::{Args, Backend};
+use ego_bridge::backend::{CarlaBackend, HeadlessBackend, VehicleBackend};
+use ego_bridge::config::BridgeConfig;
+use ego_bridge::vehicle::BridgedVehicle;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Parse command line arguments
+    let args = Args::parse();
+
+    // Initiate logging
+    pretty_env_logger::init();
+
+    // Load the signal mapping
+    let bridge_config = match &args.config {
+        Some(path) => BridgeConfig::from_file(path)?,
+        None => BridgeConfig::default(),
+    };
+    let vehicles = bridge_config.vehicles(&args.role);
+
+    log::info!(
+        "Bridging {} signals for {} vehicle(s)",
+        bridge_config.signals.len(),
+        vehicles.len()
+    );
+
+    // Stop the program gracefully on Ctrl-C
+    let running = Arc::new(AtomicBool::new(true));
+    let running_clone = running.clone();
+
+    ctrlc::set_handler(move || {
+        log::warn!("Cancelled by user. Bye!");
+        running_clone.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    // Connect to the simulator driving the vehicles
+    let mut backend: Box<dyn VehicleBackend> = match args.backend {
+        Backend::Carla => match CarlaBackend::connect(&args, &vehicles, &running).await? {
+            Some(backend) => Box::new(backend),
+            None => {
+                log::info!("Exiting before the vehicle actors appeared. Bye!");
+                return Ok(());
+            }
+        },
+        Backend::Headless => {
+            log::info!(
+                "Running headless (road curvature: {} 1/m)",
+                args.road_curvature
+            );
+            Box::new(
+                HeadlessBackend::new(args.delta, args.road_curvature).with_vehicles(vehicles.len()),
+            )
+        }
+    };
+
+    // Every vehicle has its own signals, simulation control and arbitration
+    let mut bridged = Vec::new();
+    for (index, vehicle) in vehicles.iter().enumerate() {
+        bridged.push(BridgedVehicle::connect(index, vehicle, &bridge_config, &args.router).await?);
+    }
+
+    // Main loop
+    while running.load(Ordering::SeqCst) {
+        // Wait for the next simulation step
+        let tick = backend.tick().await;
+
+        // Control the vehicles
+        for vehicle in bridged.iter_mut() {
+            if let Err(e) = vehicle.step(backend.as_mut(), tick).await {
+                log::error!("[{}] {e}", vehicle.role());
+                running.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
+    log::info!("Exiting the main loop. Bye!");
+
+    // Return success when the program exits
+    Ok(())
+}
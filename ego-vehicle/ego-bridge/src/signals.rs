@@ -0,0 +1,312 @@
+use crate::aeb::AebStatus;
+use crate::args::get_zenoh_config;
+use crate::codec::PayloadEncoding;
+use crate::config::{BridgeConfig, ControlInput, SignalRole, VehicleQuantity};
+use crate::control::{ControlInputs, VehicleCommand};
+use crate::lka::LkaStatus;
+use crate::stamp::SampleStamp;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use up_rust::{UListener, UMessage, UMessageBuilder, UTransport, UUri};
+use up_transport_zenoh::UPTransportZenoh;
+use zenoh::Session;
+use zenoh::key_expr::KeyExpr;
+use zenoh::pubsub::Publisher;
+
+/// Shared slot holding the latest decoded value of a subscribed signal.
+pub type SignalSlot = Arc<Mutex<Option<f64>>>;
+
+/// Values published by the bridge for one simulation tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VehicleSample {
+    pub elapsed_seconds: f64,
+    pub frame: u64,
+    pub speed_kmh: Option<f64>,
+    pub command: Option<VehicleCommand>,
+    pub aeb: Option<AebStatus>,
+    pub lka: Option<LkaStatus>,
+}
+
+impl VehicleSample {
+    pub fn stamp(&self) -> SampleStamp {
+        SampleStamp {
+            frame: self.frame,
+            elapsed_seconds: self.elapsed_seconds,
+        }
+    }
+
+    pub fn value(&self, quantity: VehicleQuantity) -> Option<f64> {
+        match quantity {
+            VehicleQuantity::SpeedKmh => self.speed_kmh,
+            VehicleQuantity::ElapsedSeconds => Some(self.elapsed_seconds),
+            VehicleQuantity::Frame => Some(self.frame as f64),
+            VehicleQuantity::Throttle => self.command.map(|c| c.throttle as f64),
+            VehicleQuantity::Brake => self.command.map(|c| c.brake as f64),
+            VehicleQuantity::Steer => self.command.map(|c| c.steer as f64),
+            VehicleQuantity::AebStage => self.aeb.map(|a| a.stage as u8 as f64),
+            VehicleQuantity::TimeToCollision => self.aeb.and_then(|a| a.time_to_collision),
+            VehicleQuantity::LaneAssistState => self.lka.map(|l| l.state as u8 as f64),
+            VehicleQuantity::LaneDepartureWarning => {
+                self.lka.map(|l| f64::from(u8::from(l.departure_warning)))
+            }
+        }
+    }
+}
+
+// Listener for a subscribed signal - implements the UListener trait for uProtocol
+struct SignalListener {
+    name: String,
+    encoding: PayloadEncoding,
+    field: String,
+    data: SignalSlot,
+}
+
+#[async_trait]
+impl UListener for SignalListener {
+    async fn on_receive(&self, msg: UMessage) {
+        if let Some(payload) = msg.payload {
+            store_sample(&self.name, self.encoding, &self.field, &payload, &self.data);
+        }
+    }
+}
+
+fn store_sample(
+    name: &str,
+    encoding: PayloadEncoding,
+    field: &str,
+    bytes: &[u8],
+    data: &SignalSlot,
+) {
+    match encoding.decode(bytes, field) {
+        Ok(value) => {
+            log::trace!("[from_bus] {name} : {value}");
+            *data.lock().unwrap() = Some(value);
+        }
+        Err(e) => log::warn!("Dropping malformed '{name}' payload: {e}"),
+    }
+}
+
+struct OutputSignal {
+    name: String,
+    quantity: VehicleQuantity,
+    encoding: PayloadEncoding,
+    field: String,
+    uprotocol: Option<UUri>,
+    zenoh: Option<Publisher<'static>>,
+}
+
+/// Moves the configured signals between the bridge and uProtocol / Zenoh.
+pub struct SignalBus {
+    inputs: HashMap<ControlInput, SignalSlot>,
+    outputs: Vec<OutputSignal>,
+    transport: Option<Arc<dyn UTransport>>,
+    session: Option<Session>,
+}
+
+impl SignalBus {
+    /// Opens only the transports referenced by `config`, registers a listener per
+    /// subscribed signal and declares a publisher per published signal.
+    pub async fn connect(
+        config: &BridgeConfig,
+        router: &Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let transport: Option<Arc<dyn UTransport>> = if config.uses_uprotocol() {
+            // Initialize uProtocol logging
+            UPTransportZenoh::try_init_log_from_env();
+
+            let transport = UPTransportZenoh::builder(config.entity.authority.clone())
+                .expect("invalid authority name")
+                .with_config(get_zenoh_config(router))
+                .build()
+                .await?;
+            Some(Arc::new(transport))
+        } else {
+            None
+        };
+
+        let session = if config.uses_zenoh() {
+            log::info!("Opening the Zenoh session...");
+            Some(zenoh::open(get_zenoh_config(router)).await?)
+        } else {
+            None
+        };
+
+        let mut bus = SignalBus {
+            inputs: HashMap::new(),
+            outputs: Vec::new(),
+            transport,
+            session,
+        };
+
+        for (name, signal) in &config.signals {
+            match signal.role {
+                SignalRole::Subscribe(input) => {
+                    let data = bus
+                        .inputs
+                        .entry(input)
+                        .or_insert_with(|| Arc::new(Mutex::new(None)))
+                        .clone();
+                    if signal.initial.is_some() {
+                        *data.lock().unwrap() = signal.initial;
+                    }
+
+                    if let (Some(uri), Some(transport)) = (&signal.uprotocol, &bus.transport) {
+                        let filter = UUri::from_str(uri)?;
+                        log::info!(
+                            "Registering '{name}' listener [filter: {}]",
+                            filter.to_uri(false)
+                        );
+                        transport
+                            .register_listener(
+                                &filter,
+                                None,
+                                Arc::new(SignalListener {
+                                    name: name.clone(),
+                                    encoding: signal.encoding,
+                                    field: signal.field.clone(),
+                                    data: data.clone(),
+                                }),
+                            )
+                            .await?;
+                    }
+
+                    if let (Some(key), Some(session)) = (&signal.zenoh, &bus.session) {
+                        let key_expr = KeyExpr::try_from(key.clone())?;
+                        log::info!("Declaring Subscriber on '{}'...", &key_expr);
+                        let subscriber = session.declare_subscriber(key_expr).await?;
+
+                        let name = name.clone();
+                        let encoding = signal.encoding;
+                        let field = signal.field.clone();
+                        tokio::spawn(async move {
+                            while let Ok(sample) = subscriber.recv_async().await {
+                                let bytes = sample.payload().to_bytes();
+                                store_sample(&name, encoding, &field, &bytes, &data);
+                            }
+                        });
+                    }
+                }
+                SignalRole::Publish(quantity) => {
+                    let uprotocol = signal
+                        .uprotocol
+                        .as_deref()
+                        .map(UUri::from_str)
+                        .transpose()?;
+
+                    let zenoh = match (&signal.zenoh, &bus.session) {
+                        (Some(key), Some(session)) => {
+                            let key_expr = KeyExpr::try_from(key.clone())?;
+                            log::info!("Declaring a Zenoh Publisher on '{key_expr}'...");
+                            let publisher = session.declare_publisher(key_expr).await?;
+
+                            let topic = key.clone();
+                            publisher
+                                .matching_listener()
+                                .callback(move |matching_status| {
+                                    if matching_status.matching() {
+                                        log::info!(
+                                            "Publisher has at least one subscriber for '{topic}'."
+                                        );
+                                    } else {
+                                        log::info!(
+                                            "Publisher has NO MORE subscribers for '{topic}'."
+                                        );
+                                    }
+                                })
+                                .background()
+                                .await?;
+                            Some(publisher)
+                        }
+                        _ => None,
+                    };
+
+                    if uprotocol.is_some() && !signal.encoding.is_stamped() {
+                        log::warn!(
+                            "Signal '{name}' is published over uProtocol without its simulation \
+                             time; use `encoding: json` to stamp it"
+                        );
+                    }
+
+                    bus.outputs.push(OutputSignal {
+                        name: name.clone(),
+                        quantity,
+                        encoding: signal.encoding,
+                        field: signal.field.clone(),
+                        uprotocol,
+                        zenoh,
+                    });
+                }
+            }
+        }
+
+        Ok(bus)
+    }
+
+    /// uProtocol transport, if the configuration uses uProtocol.
+    pub fn transport(&self) -> Option<Arc<dyn UTransport>> {
+        self.transport.clone()
+    }
+
+    /// Zenoh session, if the configuration uses raw Zenoh.
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Latest value of a control input, if any signal feeds it.
+    pub fn input(&self, input: ControlInput) -> Option<f64> {
+        self.inputs
+            .get(&input)
+            .and_then(|data| *data.lock().unwrap())
+    }
+
+    /// Takes the value received since the last call, for event-like inputs.
+    pub fn take_input(&self, input: ControlInput) -> Option<f64> {
+        self.inputs
+            .get(&input)
+            .and_then(|data| data.lock().unwrap().take())
+    }
+
+    pub fn control_inputs(&self) -> ControlInputs {
+        ControlInputs {
+            throttle: self.input(ControlInput::Throttle),
+            brake: self.input(ControlInput::Brake),
+            steer: self.input(ControlInput::Steer),
+            actuation: self.input(ControlInput::Actuation),
+            engage: self.input(ControlInput::Engage),
+            lane_assist: self.input(ControlInput::LaneAssist),
+        }
+    }
+
+    /// Publishes every configured output for which `sample` has a value, stamped with
+    /// the frame and simulation time of `sample`.
+    pub async fn publish(&self, sample: &VehicleSample) -> Result<(), Box<dyn Error>> {
+        let stamp = sample.stamp();
+        for output in &self.outputs {
+            let Some(value) = sample.value(output.quantity) else {
+                continue;
+            };
+            let payload = output.encoding.encode(value, &output.field, &stamp);
+
+            log::debug!("[to_bus] {} : {}", output.name, value);
+
+            if let (Some(uri), Some(transport)) = (&output.uprotocol, &self.transport) {
+                let message = UMessageBuilder::publish(uri.clone())
+                    .build_with_payload(payload.clone(), output.encoding.upayload_format())?;
+                transport.send(message).await?;
+            }
+
+            if let Some(publisher) = &output.zenoh {
+                publisher
+                    .put(payload)
+                    .encoding(output.encoding.zenoh_encoding())
+                    .attachment(stamp.to_json())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+use crate::backend::{Pose, VehicleBackend, WeatherPreset};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use up_rust::communication::{
+    InMemoryRpcServer, RequestHandler, RpcServer, ServiceInvocationError, UPayload,
+};
+use up_rust::{LocalUriProvider, UAttributes, UPayloadFormat, UTransport};
+
+// uProtocol resource IDs of the simulation control methods
+pub const RESOURCE_TELEPORT: u16 = 0x0001;
+pub const RESOURCE_RESET: u16 = 0x0002;
+pub const RESOURCE_SET_SPEED: u16 = 0x0003;
+pub const RESOURCE_SET_WEATHER: u16 = 0x0004;
+pub const RESOURCE_SPAWN_NPCS: u16 = 0x0005;
+pub const RESOURCE_DESTROY_NPCS: u16 = 0x0006;
+pub const RESOURCE_PAUSE: u16 = 0x0007;
+pub const RESOURCE_RESUME: u16 = 0x0008;
+
+const ENDPOINTS: [u16; 8] = [
+    RESOURCE_TELEPORT,
+    RESOURCE_RESET,
+    RESOURCE_SET_SPEED,
+    RESOURCE_SET_WEATHER,
+    RESOURCE_SPAWN_NPCS,
+    RESOURCE_DESTROY_NPCS,
+    RESOURCE_PAUSE,
+    RESOURCE_RESUME,
+];
+
+const MAX_SPEED_KMH: f64 = 250.0;
+const MAX_NPCS: usize = 100;
+
+/// A simulation control request, decoded from its method and JSON payload.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimRequest {
+    /// `{"x": 10.0, "y": -2.0, "yaw": 90.0}`, yaw in degrees (default 0).
+    Teleport(Pose),
+    Reset,
+    /// `{"speed_kmh": 50.0}`
+    SetSpeed(f64),
+    /// `{"preset": "hard_rain_noon"}`
+    SetWeather(WeatherPreset),
+    /// `{"count": 20}`
+    SpawnNpcs(usize),
+    /// `{"ids": [42, 43]}`, or no payload for every NPC.
+    DestroyNpcs(Option<Vec<u32>>),
+    /// Pause (`true`) or resume (`false`); no payload.
+    SetPaused(bool),
+}
+
+#[derive(Deserialize)]
+struct TeleportArgs {
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    yaw: f64,
+}
+
+#[derive(Deserialize)]
+struct SpeedArgs {
+    speed_kmh: f64,
+}
+
+#[derive(Deserialize)]
+struct WeatherArgs {
+    preset: WeatherPreset,
+}
+
+#[derive(Deserialize)]
+struct SpawnArgs {
+    count: usize,
+}
+
+#[derive(Deserialize)]
+struct DestroyArgs {
+    ids: Option<Vec<u32>>,
+}
+
+fn args<T: DeserializeOwned>(payload: Option<&[u8]>) -> Result<T, String> {
+    let bytes = payload.ok_or("Payload cannot be empty")?;
+    serde_json::from_slice(bytes).map_err(|e| format!("Invalid payload: {e}"))
+}
+
+impl SimRequest {
+    pub fn parse(resource_id: u16, payload: Option<&[u8]>) -> Result<Self, String> {
+        match resource_id {
+            RESOURCE_TELEPORT => {
+                let TeleportArgs { x, y, yaw } = args(payload)?;
+                Ok(SimRequest::Teleport(Pose {
+                    x,
+                    y,
+                    yaw: yaw.to_radians(),
+                }))
+            }
+            RESOURCE_RESET => Ok(SimRequest::Reset),
+            RESOURCE_SET_SPEED => {
+                let SpeedArgs { speed_kmh } = args(payload)?;
+                if !(0.0..=MAX_SPEED_KMH).contains(&speed_kmh) {
+                    return Err(format!("Speed must be between 0 and {MAX_SPEED_KMH}"));
+                }
+                Ok(SimRequest::SetSpeed(speed_kmh))
+            }
+            RESOURCE_SET_WEATHER => {
+                let WeatherArgs { preset } = args(payload)?;
+                Ok(SimRequest::SetWeather(preset))
+            }
+            RESOURCE_SPAWN_NPCS => {
+                let SpawnArgs { count } = args(payload)?;
+                if count > MAX_NPCS {
+                    return Err(format!("At most {MAX_NPCS} NPCs can be spawned at once"));
+                }
+                Ok(SimRequest::SpawnNpcs(count))
+            }
+            RESOURCE_DESTROY_NPCS => match payload {
+                Some(bytes) if !bytes.is_empty() => {
+                    let DestroyArgs { ids } = args(Some(bytes))?;
+                    Ok(SimRequest::DestroyNpcs(ids))
+                }
+                _ => Ok(SimRequest::DestroyNpcs(None)),
+            },
+            RESOURCE_PAUSE => Ok(SimRequest::SetPaused(true)),
+            RESOURCE_RESUME => Ok(SimRequest::SetPaused(false)),
+            other => Err(format!("Unknown simulation control method 0x{other:04X}")),
+        }
+    }
+
+    /// Runs the request for `vehicle` of `backend`; the JSON result, if any, is sent back
+    /// to the caller. Weather, NPC and pause requests apply to the whole world.
+    pub fn execute(
+        self,
+        backend: &mut dyn VehicleBackend,
+        vehicle: usize,
+    ) -> Result<Option<Value>, String> {
+        match self {
+            SimRequest::Teleport(pose) => backend.teleport(vehicle, pose).map(|_| None),
+            SimRequest::Reset => backend.reset(vehicle).map(|_| None),
+            SimRequest::SetSpeed(speed_kmh) => backend.set_speed(vehicle, speed_kmh).map(|_| None),
+            SimRequest::SetWeather(preset) => backend.set_weather(preset).map(|_| None),
+            SimRequest::SpawnNpcs(count) => backend
+                .spawn_npcs(count)
+                .map(|ids| Some(json!({ "ids": ids }))),
+            SimRequest::DestroyNpcs(ids) => backend
+                .destroy_npcs(ids.as_deref())
+                .map(|count| Some(json!({ "destroyed": count }))),
+            SimRequest::SetPaused(paused) => backend.set_paused(paused).map(|_| None),
+        }
+    }
+}
+
+type SimCall = (SimRequest, oneshot::Sender<Result<Option<Value>, String>>);
+
+/// Requests received by the service, waiting to be executed by the main loop.
+///
+/// The backend belongs to the main loop, so the RPC handlers only queue requests and
+/// wait for the main loop to run them between two ticks.
+pub struct SimControlQueue {
+    rx: mpsc::UnboundedReceiver<SimCall>,
+    _server: Option<InMemoryRpcServer>,
+}
+
+impl SimControlQueue {
+    /// Executes every pending request for `vehicle` of `backend` and answers its caller.
+    pub fn process(&mut self, backend: &mut dyn VehicleBackend, vehicle: usize) {
+        while let Ok((request, reply)) = self.rx.try_recv() {
+            log::info!("[sim_control] vehicle {vehicle}: {request:?}");
+            let result = request.execute(backend, vehicle);
+            if let Err(e) = &result {
+                log::warn!("[sim_control] request failed: {e}");
+            }
+            let _ = reply.send(result);
+        }
+    }
+}
+
+/// RPC handler shared by all simulation control methods.
+pub struct SimControlEndpoint(mpsc::UnboundedSender<SimCall>);
+
+impl SimControlEndpoint {
+    /// An endpoint with its queue, not registered on any transport.
+    pub fn new() -> (Self, SimControlQueue) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), SimControlQueue { rx, _server: None })
+    }
+}
+
+#[async_trait]
+impl RequestHandler for SimControlEndpoint {
+    async fn handle_request(
+        &self,
+        resource_id: u16,
+        _message_attributes: &UAttributes,
+        request_payload: Option<UPayload>,
+    ) -> Result<Option<UPayload>, ServiceInvocationError> {
+        let payload = request_payload.map(|p| p.payload());
+        let request = SimRequest::parse(resource_id, payload.as_deref())
+            .map_err(ServiceInvocationError::InvalidArgument)?;
+
+        let stopped = || ServiceInvocationError::Unavailable("Simulation loop stopped".into());
+        let (reply, result) = oneshot::channel();
+        self.0.send((request, reply)).map_err(|_| stopped())?;
+
+        match result.await.map_err(|_| stopped())? {
+            Ok(Some(value)) => Ok(Some(UPayload::new(
+                serde_json::to_vec(&value).unwrap_or_default(),
+                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            ))),
+            Ok(None) => Ok(None),
+            Err(e) => Err(ServiceInvocationError::FailedPrecondition(e)),
+        }
+    }
+}
+
+/// Registers the simulation control methods of the entity given by `uri_provider`.
+pub async fn serve(
+    transport: Arc<dyn UTransport>,
+    uri_provider: Arc<dyn LocalUriProvider>,
+) -> Result<SimControlQueue, Box<dyn Error>> {
+    let (endpoint, mut queue) = SimControlEndpoint::new();
+    let endpoint = Arc::new(endpoint);
+    let server = InMemoryRpcServer::new(transport, uri_provider.clone());
+
+    for resource_id in ENDPOINTS {
+        server
+            .register_endpoint(None, resource_id, endpoint.clone())
+            .await?;
+        log::info!(
+            "Registered simulation control endpoint {}",
+            uri_provider.get_resource_uri(resource_id).to_uri(true)
+        );
+    }
+
+    queue._server = Some(server);
+    Ok(queue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::HeadlessBackend;
+
+    #[test]
+    fn test_parse_requests() {
+        assert_eq!(
+            SimRequest::parse(RESOURCE_SET_WEATHER, Some(br#"{"preset": "wet_noon"}"#)),
+            Ok(SimRequest::SetWeather(WeatherPreset::WetNoon))
+        );
+        assert_eq!(
+            SimRequest::parse(RESOURCE_DESTROY_NPCS, None),
+            Ok(SimRequest::DestroyNpcs(None))
+        );
+        assert!(SimRequest::parse(RESOURCE_SET_SPEED, Some(br#"{"speed_kmh": -5}"#)).is_err());
+        assert!(SimRequest::parse(RESOURCE_TELEPORT, None).is_err());
+        assert!(SimRequest::parse(0x0042, None).is_err());
+    }
+
+    #[test]
+    fn test_requests_drive_headless_backend() {
+        let mut backend = HeadlessBackend::new(0.1, 0.0);
+
+        let teleport = br#"{"x": 100.0, "y": 1.5, "yaw": 90.0}"#;
+        SimRequest::parse(RESOURCE_TELEPORT, Some(teleport))
+            .unwrap()
+            .execute(&mut backend, 0)
+            .unwrap();
+        SimRequest::SetSpeed(36.0).execute(&mut backend, 0).unwrap();
+        let state = backend.vehicle_state(0).unwrap();
+        assert_eq!((state.pose.x, state.pose.y), (100.0, 1.5));
+        assert!((state.speed_kmh - 36.0).abs() < 1e-9);
+
+        // Paused: the model does not move
+        SimRequest::SetPaused(true)
+            .execute(&mut backend, 0)
+            .unwrap();
+        backend.step();
+        assert_eq!(backend.vehicle_state(0).unwrap().pose, state.pose);
+        SimRequest::SetPaused(false)
+            .execute(&mut backend, 0)
+            .unwrap();
+        backend.step();
+        assert!(backend.vehicle_state(0).unwrap().pose.y > 1.5);
+
+        let spawned = SimRequest::SpawnNpcs(3).execute(&mut backend, 0).unwrap();
+        assert_eq!(spawned, Some(json!({ "ids": [1, 2, 3] })));
+        let destroyed = SimRequest::DestroyNpcs(Some(vec![2]))
+            .execute(&mut backend, 0)
+            .unwrap();
+        assert_eq!(destroyed, Some(json!({ "destroyed": 1 })));
+        assert_eq!(backend.npcs(), &[1, 3]);
+
+        SimRequest::Reset.execute(&mut backend, 0).unwrap();
+        let state = backend.vehicle_state(0).unwrap();
+        assert_eq!(state.pose, Pose::default());
+        assert_eq!(state.speed_kmh, 0.0);
+    }
+
+    #[test]
+    fn test_requests_address_one_vehicle() {
+        let mut backend = HeadlessBackend::new(0.1, 0.0).with_vehicles(2);
+        assert_eq!(backend.vehicle_count(), 2);
+        let follower = backend.vehicle_state(1).unwrap();
+        assert_eq!(follower.pose.x, -20.0);
+
+        SimRequest::SetSpeed(50.0).execute(&mut backend, 1).unwrap();
+        assert_eq!(backend.vehicle_state(0).unwrap().speed_kmh, 0.0);
+        assert!((backend.vehicle_state(1).unwrap().speed_kmh - 50.0).abs() < 1e-9);
+
+        SimRequest::Reset.execute(&mut backend, 1).unwrap();
+        assert_eq!(backend.vehicle_state(1).unwrap(), follower);
+        assert!(SimRequest::Reset.execute(&mut backend, 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_answers_through_queue() {
+        let (endpoint, mut queue) = SimControlEndpoint::new();
+        let mut backend = HeadlessBackend::new(0.1, 0.0);
+
+        let call = tokio::spawn(async move {
+            let payload = UPayload::new(
+                br#"{"count": 2}"#.to_vec(),
+                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            );
+            endpoint
+                .handle_request(RESOURCE_SPAWN_NPCS, &UAttributes::default(), Some(payload))
+                .await
+        });
+
+        // The main loop serves the queue between two ticks
+        while !call.is_finished() {
+            queue.process(&mut backend, 0);
+            tokio::task::yield_now().await;
+        }
+
+        let response = call.await.unwrap().unwrap().unwrap();
+        let value: Value = serde_json::from_slice(&response.payload()).unwrap();
+        assert_eq!(value, json!({ "ids": [1, 2] }));
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// Actors the bridge spawns itself (`--spawn`), instead of waiting for an external client.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SpawnConfig {
+    pub vehicle: ActorConfig,
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub spawn_point: SpawnPoint,
+    /// Sensors attached to the vehicle.
+    #[serde(default)]
+    pub sensors: Vec<SensorConfig>,
+}
+
+/// Blueprint and attributes of an actor to spawn.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActorConfig {
+    /// Blueprint id, e.g. `vehicle.tesla.model3`.
+    pub blueprint: String,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, AttributeValue>,
+}
+
+/// A sensor attached to the vehicle.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SensorConfig {
+    #[serde(flatten)]
+    pub actor: ActorConfig,
+    /// `role_name` of the sensor, as looked up by `uprotocol-sensors`.
+    pub role: String,
+    /// Mount transform relative to the vehicle.
+    #[serde(default)]
+    pub transform: MountTransform,
+}
+
+/// Where the vehicle is spawned.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnPoint {
+    /// Index into the recommended spawn points of the map.
+    Index(usize),
+    /// Explicit world transform.
+    Transform(MountTransform),
+}
+
+impl Default for SpawnPoint {
+    fn default() -> Self {
+        SpawnPoint::Index(0)
+    }
+}
+
+/// Location in meters and rotation in degrees, in CARLA's conventions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MountTransform {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+/// Blueprint attribute value; CARLA takes every attribute as a string.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeValue::Bool(value) => write!(f, "{value}"),
+            AttributeValue::Number(value) => write!(f, "{value}"),
+            AttributeValue::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+impl SpawnConfig {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        Self::from_yaml(&text)
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let config: SpawnConfig = serde_yaml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that sensor roles are unique, so each can be found by its `role_name`.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut roles = std::collections::BTreeSet::new();
+        for sensor in &self.sensors {
+            if !roles.insert(sensor.role.as_str()) {
+                return Err(format!("Sensor role '{}' is used twice", sensor.role));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_spawn_config_is_valid() {
+        let config = SpawnConfig::from_yaml(include_str!("../config/spawn.yaml")).unwrap();
+        assert_eq!(config.spawn_point, SpawnPoint::Index(0));
+
+        let obstacle = config
+            .sensors
+            .iter()
+            .find(|s| s.actor.blueprint == "sensor.other.obstacle")
+            .unwrap();
+        assert_eq!(obstacle.role, "obstacle_detection_1");
+        assert_eq!(obstacle.actor.attributes["distance"].to_string(), "50");
+        assert_eq!(obstacle.transform.x, 2.0);
+    }
+
+    #[test]
+    fn test_explicit_spawn_point_and_duplicate_roles() {
+        let yaml = |second_role: &str| {
+            format!(
+                "vehicle:\n  blueprint: vehicle.tesla.model3\n\
+                 spawn_point:\n  transform: {{x: 10.0, y: -2.0, z: 0.5, yaw: 90.0}}\n\
+                 sensors:\n  - {{blueprint: sensor.other.imu, role: imu}}\n  \
+                 - {{blueprint: sensor.other.gnss, role: {second_role}}}\n"
+            )
+        };
+        assert!(SpawnConfig::from_yaml(&yaml("imu")).is_err());
+
+        let config = SpawnConfig::from_yaml(&yaml("gnss")).unwrap();
+        let SpawnPoint::Transform(transform) = config.spawn_point else {
+            panic!("expected an explicit transform");
+        };
+        assert_eq!(transform.yaw, 90.0);
+        assert_eq!(transform.roll, 0.0);
+    }
+}
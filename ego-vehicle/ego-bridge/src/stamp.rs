@@ -0,0 +1,71 @@
+use crate::codec::key_value_pairs;
+use serde::{Deserialize, Serialize};
+
+/// Simulation time of a published sample: the CARLA frame and the simulation clock.
+///
+/// Carried as the attachment of every raw Zenoh sample, and next to the value in `json`
+/// and `key_value` payloads, e.g. `{"velocity": 45.2, "frame": 250, "elapsed_seconds":
+/// 12.5}` or `fcw:2,frame:250,elapsed_seconds:12.5`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SampleStamp {
+    pub frame: u64,
+    pub elapsed_seconds: f64,
+}
+
+impl SampleStamp {
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    /// Stamp of a Zenoh attachment or of a stamped `json` or `key_value` payload.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if let Ok(stamp) = serde_json::from_slice(bytes) {
+            return Some(stamp);
+        }
+        let text = std::str::from_utf8(bytes).ok()?;
+        let value = |name: &str| {
+            key_value_pairs(text)
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value)
+        };
+        Some(Self {
+            frame: value("frame")?.parse().ok()?,
+            elapsed_seconds: value("elapsed_seconds")?.parse().ok()?,
+        })
+    }
+
+    /// Simulation time elapsed since `previous`.
+    pub fn dt(&self, previous: &SampleStamp) -> f64 {
+        self.elapsed_seconds - previous.elapsed_seconds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::PayloadEncoding;
+
+    fn stamp(frame: u64) -> SampleStamp {
+        SampleStamp {
+            frame,
+            elapsed_seconds: frame as f64 * 0.05,
+        }
+    }
+
+    #[test]
+    fn test_stamp_is_read_from_payloads() {
+        let payload = PayloadEncoding::Json.encode(45.2, "velocity", &stamp(250));
+        assert_eq!(SampleStamp::decode(&payload), Some(stamp(250)));
+        assert_eq!(PayloadEncoding::Json.decode(&payload, "velocity"), Ok(45.2));
+        assert_eq!(SampleStamp::decode(&stamp(7).to_json()), Some(stamp(7)));
+
+        let pair = PayloadEncoding::KeyValue.encode(2.0, "fcw", &stamp(250));
+        assert_eq!(pair, b"fcw:2,frame:250,elapsed_seconds:12.5");
+        assert_eq!(SampleStamp::decode(&pair), Some(stamp(250)));
+        assert_eq!(PayloadEncoding::KeyValue.decode(&pair, "fcw"), Ok(2.0));
+
+        let text = PayloadEncoding::Text.encode(45.2, "velocity", &stamp(250));
+        assert_eq!(SampleStamp::decode(&text), None);
+        assert_eq!(SampleStamp::decode(b"fcw:2"), None);
+    }
+}
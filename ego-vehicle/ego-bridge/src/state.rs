@@ -0,0 +1,319 @@
+use crate::control::ControlInputs;
+use crate::signals::VehicleSample;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zenoh::Session;
+use zenoh::bytes::Encoding;
+use zenoh::key_expr::KeyExpr;
+use zenoh::liveliness::LivelinessToken;
+use zenoh::sample::SampleKind;
+
+/// How long the client waits for the replies of the bridge.
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Parts of the vehicle state answered by the bridge, each at `<prefix>/<name>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateTopic {
+    Speed,
+    Clock,
+    Control,
+    Engage,
+}
+
+impl StateTopic {
+    pub const ALL: [StateTopic; 4] = [
+        StateTopic::Speed,
+        StateTopic::Clock,
+        StateTopic::Control,
+        StateTopic::Engage,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StateTopic::Speed => "speed",
+            StateTopic::Clock => "clock",
+            StateTopic::Control => "control",
+            StateTopic::Engage => "engage",
+        }
+    }
+
+    pub fn key(self, prefix: &str) -> String {
+        format!("{prefix}/{}", self.name())
+    }
+
+    /// Topic of a reply key below `prefix`.
+    pub fn from_key(prefix: &str, key: &str) -> Option<Self> {
+        let name = key.strip_prefix(prefix)?.strip_prefix('/')?;
+        Self::ALL.into_iter().find(|topic| topic.name() == name)
+    }
+}
+
+/// Key of the liveliness token declared by the bridge below `prefix`.
+pub fn alive_key(prefix: &str) -> String {
+    format!("{prefix}/alive")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeedState {
+    pub speed_kmh: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClockState {
+    pub elapsed_seconds: f64,
+    pub frame: u64,
+}
+
+/// Actuator values applied in the last tick.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ControlState {
+    pub throttle: f32,
+    pub brake: f32,
+    pub steer: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EngageState {
+    pub cruise_control: bool,
+    pub lane_assist: bool,
+}
+
+/// Latest vehicle state; every part is `None` until the bridge has known it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StateSnapshot {
+    pub speed: Option<SpeedState>,
+    pub clock: Option<ClockState>,
+    pub control: Option<ControlState>,
+    pub engage: Option<EngageState>,
+}
+
+impl StateSnapshot {
+    /// State after a tick, from the published sample and the control inputs.
+    pub fn new(sample: &VehicleSample, inputs: &ControlInputs) -> Self {
+        Self {
+            speed: sample.speed_kmh.map(|speed_kmh| SpeedState { speed_kmh }),
+            clock: Some(ClockState {
+                elapsed_seconds: sample.elapsed_seconds,
+                frame: sample.frame,
+            }),
+            control: sample.command.map(|c| ControlState {
+                throttle: c.throttle,
+                brake: c.brake,
+                steer: c.steer,
+            }),
+            engage: Some(EngageState {
+                cruise_control: inputs.engaged(),
+                lane_assist: inputs.lane_assist_engaged(),
+            }),
+        }
+    }
+
+    /// JSON reply for `topic`, if known.
+    pub fn encode(&self, topic: StateTopic) -> Option<Vec<u8>> {
+        let json = match topic {
+            StateTopic::Speed => serde_json::to_vec(&self.speed?),
+            StateTopic::Clock => serde_json::to_vec(&self.clock?),
+            StateTopic::Control => serde_json::to_vec(&self.control?),
+            StateTopic::Engage => serde_json::to_vec(&self.engage?),
+        };
+        json.ok()
+    }
+
+    /// Stores a JSON reply for `topic`.
+    pub fn decode(&mut self, topic: StateTopic, bytes: &[u8]) -> Result<(), serde_json::Error> {
+        match topic {
+            StateTopic::Speed => self.speed = Some(serde_json::from_slice(bytes)?),
+            StateTopic::Clock => self.clock = Some(serde_json::from_slice(bytes)?),
+            StateTopic::Control => self.control = Some(serde_json::from_slice(bytes)?),
+            StateTopic::Engage => self.engage = Some(serde_json::from_slice(bytes)?),
+        }
+        Ok(())
+    }
+}
+
+/// Announces the bridge with a liveliness token and answers `get` requests for the
+/// latest state of one vehicle; both are undeclared when dropped.
+pub struct StateServer {
+    snapshot: Arc<Mutex<StateSnapshot>>,
+    _token: LivelinessToken,
+}
+
+impl StateServer {
+    pub async fn declare(session: &Session, prefix: &str) -> Result<Self, Box<dyn Error>> {
+        let snapshot = Arc::new(Mutex::new(StateSnapshot::default()));
+
+        log::info!("Declaring the liveliness token '{}'...", alive_key(prefix));
+        let token = session
+            .liveliness()
+            .declare_token(alive_key(prefix))
+            .await?;
+
+        let topics = StateTopic::ALL
+            .into_iter()
+            .map(|topic| Ok((topic, KeyExpr::try_from(topic.key(prefix))?)))
+            .collect::<Result<Vec<_>, zenoh::Error>>()?;
+
+        log::info!("Declaring the state Queryable on '{prefix}/*'...");
+        let queryable = session.declare_queryable(format!("{prefix}/*")).await?;
+
+        let state = snapshot.clone();
+        tokio::spawn(async move {
+            while let Ok(query) = queryable.recv_async().await {
+                let current = *state.lock().unwrap();
+                for (topic, key) in &topics {
+                    if !query.key_expr().intersects(key) {
+                        continue;
+                    }
+                    let Some(payload) = current.encode(*topic) else {
+                        continue;
+                    };
+                    if let Err(e) = query
+                        .reply(key.clone(), payload)
+                        .encoding(Encoding::APPLICATION_JSON)
+                        .await
+                    {
+                        log::warn!("Unable to answer the query on '{key}': {e}");
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            snapshot,
+            _token: token,
+        })
+    }
+
+    /// Replaces the state answered to the next queries.
+    pub fn update(&self, snapshot: StateSnapshot) {
+        *self.snapshot.lock().unwrap() = snapshot;
+    }
+}
+
+/// Lets other nodes check that the bridge is alive and bootstrap the vehicle state
+/// at startup, before the first published samples arrive.
+#[derive(Clone)]
+pub struct StateClient {
+    session: Session,
+    prefix: String,
+}
+
+impl StateClient {
+    /// Client of the bridge serving its state below `prefix`, e.g. `vehicle/bridge`.
+    pub fn new(session: Session, prefix: impl Into<String>) -> Self {
+        Self {
+            session,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Whether the bridge currently holds its liveliness token.
+    pub async fn is_alive(&self) -> Result<bool, Box<dyn Error>> {
+        let replies = self
+            .session
+            .liveliness()
+            .get(alive_key(&self.prefix))
+            .timeout(QUERY_TIMEOUT)
+            .await?;
+
+        while let Ok(reply) = replies.recv_async().await {
+            if reply.result().is_ok() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Waits until the bridge is alive.
+    pub async fn wait_alive(&self) -> Result<(), Box<dyn Error>> {
+        let subscriber = self
+            .session
+            .liveliness()
+            .declare_subscriber(alive_key(&self.prefix))
+            .history(true)
+            .await?;
+
+        while let Ok(sample) = subscriber.recv_async().await {
+            if sample.kind() == SampleKind::Put {
+                return Ok(());
+            }
+        }
+        Err("Liveliness subscriber closed".into())
+    }
+
+    /// Fetches the latest state of the vehicle from the bridge.
+    pub async fn fetch(&self) -> Result<StateSnapshot, Box<dyn Error>> {
+        let replies = self
+            .session
+            .get(format!("{}/*", self.prefix))
+            .timeout(QUERY_TIMEOUT)
+            .await?;
+
+        let mut snapshot = StateSnapshot::default();
+        while let Ok(reply) = replies.recv_async().await {
+            let sample = match reply.result() {
+                Ok(sample) => sample,
+                Err(e) => {
+                    log::warn!("State query failed: {e:?}");
+                    continue;
+                }
+            };
+            let key = sample.key_expr().as_str();
+            let Some(topic) = StateTopic::from_key(&self.prefix, key) else {
+                continue;
+            };
+            if let Err(e) = snapshot.decode(topic, &sample.payload().to_bytes()) {
+                log::warn!("Dropping malformed '{key}' state: {e}");
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::VehicleCommand;
+
+    #[test]
+    fn test_snapshot_round_trips_through_topics() {
+        let sample = VehicleSample {
+            elapsed_seconds: 12.5,
+            frame: 250,
+            speed_kmh: Some(42.0),
+            command: Some(VehicleCommand {
+                throttle: 0.4,
+                steer: -0.1,
+                brake: 0.0,
+            }),
+            ..Default::default()
+        };
+        let inputs = ControlInputs {
+            engage: Some(1.0),
+            ..Default::default()
+        };
+        let snapshot = StateSnapshot::new(&sample, &inputs);
+
+        let mut fetched = StateSnapshot::default();
+        for topic in StateTopic::ALL {
+            let key = topic.key("vehicle/bridge");
+            let topic = StateTopic::from_key("vehicle/bridge", &key).unwrap();
+            fetched
+                .decode(topic, &snapshot.encode(topic).unwrap())
+                .unwrap();
+        }
+        assert_eq!(fetched, snapshot);
+        assert!(fetched.engage.unwrap().cruise_control);
+
+        // Unknown parts are not answered
+        let idle = StateSnapshot::new(&VehicleSample::default(), &ControlInputs::default());
+        assert_eq!(idle.encode(StateTopic::Speed), None);
+        assert_eq!(
+            StateTopic::from_key("vehicle/bridge", "vehicle/bridge/alive"),
+            None
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::aeb::EmergencyBraking;
+use crate::arbiter::{ActuationArbiter, ActuationSource};
+use crate::backend::{SimTick, VehicleBackend};
+use crate::config::{BridgeConfig, ControlInput, VehicleConfig};
+use crate::lka::LaneKeeping;
+use crate::signals::{SignalBus, VehicleSample};
+use crate::sim_control::{self, SimControlQueue};
+use crate::state::{StateServer, StateSnapshot};
+use std::error::Error;
+use std::sync::Arc;
+use up_rust::StaticUriProvider;
+
+/// One vehicle driven by the bridge: its signals, simulation control service and
+/// actuation arbitration, independent of the other vehicles.
+pub struct BridgedVehicle {
+    index: usize,
+    role: String,
+    bus: SignalBus,
+    sim_control: Option<SimControlQueue>,
+    state: Option<StateServer>,
+    arbiter: ActuationArbiter,
+    aeb: Option<EmergencyBraking>,
+    lka: Option<LaneKeeping>,
+}
+
+impl BridgedVehicle {
+    /// Sets up the signals of `vehicle`, the `index`-th vehicle of the backend.
+    pub async fn connect(
+        index: usize,
+        vehicle: &VehicleConfig,
+        config: &BridgeConfig,
+        router: &Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let config = config.for_vehicle(vehicle)?;
+
+        log::info!(
+            "Bridging {} signals of '{}' as '{}'",
+            config.signals.len(),
+            vehicle.role,
+            config.entity.authority
+        );
+
+        // Set up uProtocol and/or Zenoh according to the signal mapping
+        let bus = SignalBus::connect(&config, router).await?;
+
+        // Serve the simulation control methods, executed between two ticks
+        let sim_control = match bus.transport() {
+            Some(transport) if config.sim_control => {
+                let uri_provider = Arc::new(StaticUriProvider::new(
+                    config.entity.authority.clone(),
+                    config.entity.ue_id,
+                    config.entity.ue_version,
+                ));
+                Some(sim_control::serve(transport, uri_provider).await?)
+            }
+            _ => None,
+        };
+
+        // Announce the vehicle and serve its latest state to late joiners
+        let state = match (&config.state, bus.session()) {
+            (Some(state), Some(session)) => {
+                Some(StateServer::declare(session, &state.key_prefix).await?)
+            }
+            _ => None,
+        };
+
+        if config.aeb.is_some() {
+            log::info!("Automatic emergency braking enabled for '{}'", vehicle.role);
+        }
+        if config.lka.is_some() {
+            log::info!("Lane keeping assist enabled for '{}'", vehicle.role);
+        }
+
+        Ok(Self {
+            index,
+            role: vehicle.role.clone(),
+            bus,
+            sim_control,
+            state,
+            arbiter: ActuationArbiter::new(),
+            aeb: config.aeb.map(EmergencyBraking::new),
+            lka: config.lka.map(LaneKeeping::new),
+        })
+    }
+
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// Runs pending simulation control requests, controls the vehicle for `tick` and
+    /// publishes its quantities.
+    pub async fn step(
+        &mut self,
+        backend: &mut dyn VehicleBackend,
+        tick: SimTick,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(sim_control) = self.sim_control.as_mut() {
+            sim_control.process(backend, self.index);
+        }
+
+        let mut sample = VehicleSample {
+            elapsed_seconds: tick.elapsed_seconds,
+            frame: tick.frame,
+            ..Default::default()
+        };
+        let inputs = self.bus.control_inputs();
+
+        // Actuation is arbitrated between manual driving, cruise control, LKA and AEB
+        if let Some(vehicle) = backend.vehicle_state(self.index) {
+            sample.speed_kmh = Some(vehicle.speed_kmh);
+
+            let arbiter = &mut self.arbiter;
+            arbiter.request(ActuationSource::Manual, inputs.manual_request());
+            arbiter.update(ActuationSource::CruiseControl, inputs.cruise_request());
+
+            if let Some(lka) = self.lka.as_mut() {
+                if self.bus.take_input(ControlInput::LaneInvasion).is_some() {
+                    lka.on_lane_invasion(tick.elapsed_seconds);
+                }
+                let lane = backend.lane_ahead(self.index, 2.0 * lka.lookahead(vehicle.speed_kmh));
+                sample.lka = Some(lka.update(
+                    tick.elapsed_seconds,
+                    inputs.lane_assist_engaged(),
+                    Some(vehicle),
+                    lane.as_ref(),
+                ));
+                arbiter.update(ActuationSource::LaneKeeping, lka.request());
+            }
+
+            if let Some(aeb) = self.aeb.as_mut() {
+                if let Some(distance) = self.bus.take_input(ControlInput::ObstacleDistance) {
+                    aeb.on_obstacle(tick.elapsed_seconds, distance);
+                }
+                if self.bus.take_input(ControlInput::Collision).is_some() {
+                    aeb.on_collision();
+                }
+                sample.aeb = Some(aeb.update(tick.elapsed_seconds, vehicle.speed_kmh));
+                arbiter.update(ActuationSource::EmergencyBraking, aeb.request());
+            }
+
+            let command = arbiter.command();
+            backend.apply_control(self.index, &command)?;
+
+            sample.command = Some(command);
+        } else {
+            log::warn!("'{}' actor not found in the world anymore!", self.role);
+        }
+
+        // Publish the mapped vehicle quantities
+        self.bus.publish(&sample).await?;
+        if let Some(state) = &self.state {
+            state.update(StateSnapshot::new(&sample, &inputs));
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,13 @@
+use crate::codec::{ImageEncoder, ImageEncoding, PointCloudEncoding};
+use crate::discovery::{
+    DEFAULT_RESOURCE_RANGE, DiscoveredSensor, ResourceAllocator, ResourceRange, SensorKind,
+    parse_resource_id,
+};
+use crate::sensors::{PublishConfigs, QueueConfig, QueuePolicy};
+use crate::shm::{DEFAULT_SHM_KEY_PREFIX, DEFAULT_SHM_POOL_MB};
+use crate::synthetic::default_rate;
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -23,8 +32,246 @@ pub struct Args {
     pub ego_vehicle_sensor_lidar_measurement_role: Option<String>,
     #[clap(long)]
     pub ego_vehicle_sensor_imu_measurement_role: Option<String>,
+    #[clap(long)]
+    pub ego_vehicle_sensor_gnss_measurement_role: Option<String>,
+    #[clap(long)]
+    pub ego_vehicle_sensor_semantic_lidar_measurement_role: Option<String>,
+    #[clap(long)]
+    pub ego_vehicle_sensor_depth_image_role: Option<String>,
+    #[clap(long)]
+    pub ego_vehicle_sensor_semantic_segmentation_image_role: Option<String>,
     #[clap(long, default_value_t = 0.100)]
     pub delta: f64,
     #[clap(long, default_value = None)]
     pub router: Option<String>,
+    /// uProtocol authority of the bridge; one per bridged vehicle, e.g. `EGOVehicle-2`
+    #[clap(long, default_value = "EGOVehicle")]
+    pub authority: String,
+    /// Events a sensor may queue while the transport is busy
+    #[clap(long, default_value_t = 8)]
+    pub queue_capacity: usize,
+    /// What to do when a sensor queue is full: drop_oldest, drop_newest, keep_latest or block
+    #[clap(long, default_value = "drop_oldest")]
+    pub queue_policy: QueuePolicy,
+    /// Queue of one sensor, e.g. `image=keep_latest` or `lidar_measurement=drop_oldest:2`
+    #[clap(long = "sensor-queue", value_name = "SENSOR=POLICY[:CAPACITY]", value_parser = parse_sensor_queue)]
+    pub sensor_queues: Vec<(String, QueueConfig)>,
+    /// YAML file with the publish settings of the sensors (max_rate_hz, decimation, on_change)
+    #[clap(long)]
+    pub sensor_config: Option<PathBuf>,
+    /// Maximum publish rate of one sensor, e.g. `image=10`
+    #[clap(long = "sensor-rate", value_name = "SENSOR=HZ", value_parser = parse_sensor_rate)]
+    pub sensor_rates: Vec<(String, f64)>,
+    /// Only publish every Nth event of one sensor, e.g. `lidar_measurement=2`
+    #[clap(long = "sensor-decimation", value_name = "SENSOR=N", value_parser = parse_sensor_decimation)]
+    pub sensor_decimations: Vec<(String, u32)>,
+    /// Only publish the events of one sensor that differ from the last published one, e.g. `collision`
+    #[clap(long = "on-change", value_name = "SENSOR")]
+    pub on_change: Vec<String>,
+    /// Payload of the image sensor: json, raw, jpeg[:QUALITY] or png[:fast|default|best]
+    #[clap(long, default_value = "raw")]
+    pub image_encoding: ImageEncoding,
+    /// Size factor applied to binary images before encoding, e.g. 0.5 for half the resolution
+    #[clap(long, default_value_t = 1.0, value_parser = parse_image_scale)]
+    pub image_scale: f64,
+    /// Payload of the lidar sensor: json, packed or packed:f16
+    #[clap(long, default_value = "packed")]
+    pub lidar_encoding: PointCloudEncoding,
+    /// Payload of the radar sensor: json, packed or packed:f16
+    #[clap(long, default_value = "packed")]
+    pub radar_encoding: PointCloudEncoding,
+    /// Payload of the semantic segmentation cameras: raw or png[:fast|default|best]
+    #[clap(long, default_value = "png:fast", value_parser = parse_segmentation_encoding)]
+    pub segmentation_encoding: ImageEncoding,
+    /// Split sensor payloads larger than this many bytes into chunks (default: no chunking)
+    #[clap(long)]
+    pub chunk_size: Option<usize>,
+    /// Publish the camera and lidar payloads through Zenoh shared memory instead of uProtocol
+    #[clap(long)]
+    pub shm: bool,
+    /// With `--shm`, publish the camera and lidar payloads on their uProtocol topic as well
+    #[clap(long)]
+    pub shm_uprotocol: bool,
+    /// Size of the shared-memory pool, in MiB
+    #[clap(long, default_value_t = DEFAULT_SHM_POOL_MB)]
+    pub shm_pool_mb: usize,
+    /// Prefix of the Zenoh keys of the shared-memory sensors, e.g. `<prefix>/image/<role name>`
+    #[clap(long, default_value = DEFAULT_SHM_KEY_PREFIX)]
+    pub shm_key_prefix: String,
+    /// Bridge every sensor attached to the ego vehicle, along with the `--ego-vehicle-sensor-*` ones
+    #[clap(long)]
+    pub discover_sensors: bool,
+    /// Resource ID of one sensor by role name, e.g. `rear_camera=0x8030`
+    #[clap(long = "sensor-resource", value_name = "ROLE=ID", value_parser = parse_sensor_resource)]
+    pub sensor_resources: Vec<(String, u16)>,
+    /// Resource IDs of the sensors beyond the first of each type
+    #[clap(long, value_name = "START-END", default_value_t = DEFAULT_RESOURCE_RANGE)]
+    pub sensor_resource_range: ResourceRange,
+    /// Publish synthetic sensors driven by a scripted world instead of connecting to CARLA
+    #[clap(long)]
+    pub synthetic: bool,
+    /// Rate of one synthetic sensor in Hz (events per second for lane_invasion and collision), e.g. `lidar_measurement=20`
+    #[clap(long = "synthetic-rate", value_name = "SENSOR=HZ", value_parser = parse_sensor_rate)]
+    pub synthetic_rates: Vec<(String, f64)>,
+    /// Seed of the noise and events of the synthetic sensors
+    #[clap(long, default_value_t = 0)]
+    pub synthetic_seed: u64,
+    /// Seconds between two reports on the sensor diagnostics topic
+    #[clap(long, default_value_t = 1.0, value_parser = parse_diagnostics_period)]
+    pub diagnostics_period: f64,
+}
+
+/// Zenoh configuration of a peer, connected to the router on `router` (host or IP address,
+/// port 7447) if any. Shared by the bridge and the other binaries (`--router`).
+pub fn zenoh_config(router: Option<&str>) -> zenoh::Result<zenoh::Config> {
+    let zenoh_string = match router {
+        Some(router) => {
+            format!("{{ mode: 'peer', connect: {{ endpoints: [ 'tcp/{router}:7447' ] }} }}")
+        }
+        None => "{ mode: 'peer' }".to_string(),
+    };
+    zenoh::Config::from_json5(&zenoh_string)
+}
+
+fn parse_sensor_queue(s: &str) -> Result<(String, QueueConfig), String> {
+    let (sensor, config) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected SENSOR=POLICY[:CAPACITY], got '{s}'"))?;
+    Ok((sensor.replace('-', "_"), config.parse()?))
+}
+
+fn parse_sensor_rate(s: &str) -> Result<(String, f64), String> {
+    let (sensor, rate) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected SENSOR=HZ, got '{s}'"))?;
+    let rate: f64 = rate
+        .parse()
+        .map_err(|e| format!("invalid rate '{rate}': {e}"))?;
+    if !(rate.is_finite() && rate > 0.0) {
+        return Err(format!("rate must be positive, got {rate}"));
+    }
+    Ok((sensor.replace('-', "_"), rate))
+}
+
+fn parse_sensor_decimation(s: &str) -> Result<(String, u32), String> {
+    let (sensor, decimation) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected SENSOR=N, got '{s}'"))?;
+    let decimation: u32 = decimation
+        .parse()
+        .map_err(|e| format!("invalid decimation '{decimation}': {e}"))?;
+    if decimation == 0 {
+        return Err("decimation must be at least 1".to_string());
+    }
+    Ok((sensor.replace('-', "_"), decimation))
+}
+
+fn parse_sensor_resource(s: &str) -> Result<(String, u16), String> {
+    let (role, id) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected ROLE=ID, got '{s}'"))?;
+    Ok((role.to_string(), parse_resource_id(id)?))
+}
+
+fn parse_image_scale(s: &str) -> Result<f64, String> {
+    let scale: f64 = s.parse().map_err(|e| format!("invalid scale '{s}': {e}"))?;
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(format!("image scale must be in (0, 1], got {scale}"));
+    }
+    Ok(scale)
+}
+
+fn parse_diagnostics_period(s: &str) -> Result<f64, String> {
+    let period: f64 = s
+        .parse()
+        .map_err(|e| format!("invalid period '{s}': {e}"))?;
+    if !(period.is_finite() && period > 0.0) {
+        return Err(format!("diagnostics period must be positive, got {period}"));
+    }
+    Ok(period)
+}
+
+fn parse_segmentation_encoding(s: &str) -> Result<ImageEncoding, String> {
+    match s.parse()? {
+        encoding @ (ImageEncoding::Raw | ImageEncoding::Png(_)) => Ok(encoding),
+        _ => Err(format!(
+            "semantic tags must be encoded losslessly (raw or png), got '{s}'"
+        )),
+    }
+}
+
+impl Args {
+    /// Queue of `sensor` (e.g. `image`): its `--sensor-queue`, or the global queue options.
+    pub fn queue_config(&self, sensor: &str) -> QueueConfig {
+        self.sensor_queues
+            .iter()
+            .rev()
+            .find(|(name, _)| name == sensor)
+            .map(|(_, config)| *config)
+            .unwrap_or(QueueConfig {
+                capacity: self.queue_capacity.max(1),
+                policy: self.queue_policy,
+            })
+    }
+
+    /// Queue of a discovered sensor: the `--sensor-queue` of its role name, else the queue
+    /// of its type.
+    pub fn sensor_queue_config(&self, sensor: &DiscoveredSensor) -> QueueConfig {
+        let role = sensor.config_name();
+        if self.sensor_queues.iter().any(|(name, _)| *name == role) {
+            self.queue_config(&role)
+        } else {
+            self.queue_config(sensor.kind.name())
+        }
+    }
+
+    /// Rate of a synthetic sensor: the `--synthetic-rate` of its role name or of its type,
+    /// else the default rate of its type.
+    pub fn synthetic_rate(&self, kind: SensorKind, role_name: &str) -> f64 {
+        let role = role_name.replace('-', "_");
+        self.synthetic_rates
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == role)
+            .or_else(|| {
+                self.synthetic_rates
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| name == kind.name())
+            })
+            .map_or_else(|| default_rate(kind), |(_, rate)| *rate)
+    }
+
+    pub fn resource_allocator(&self) -> ResourceAllocator {
+        ResourceAllocator::new(
+            self.sensor_resources.iter().cloned().collect(),
+            self.sensor_resource_range,
+        )
+    }
+
+    /// Publish settings of every sensor: the `--sensor-config` file, overridden by the
+    /// `--sensor-rate`, `--sensor-decimation` and `--on-change` options.
+    pub fn publish_configs(&self) -> Result<PublishConfigs, String> {
+        let mut configs = match &self.sensor_config {
+            Some(path) => PublishConfigs::from_file(path)?,
+            None => PublishConfigs::default(),
+        };
+        for (sensor, rate) in &self.sensor_rates {
+            configs.update(sensor, |config| config.max_rate_hz = Some(*rate));
+        }
+        for (sensor, decimation) in &self.sensor_decimations {
+            configs.update(sensor, |config| config.decimation = *decimation);
+        }
+        for sensor in &self.on_change {
+            configs.update(&sensor.replace('-', "_"), |config| config.on_change = true);
+        }
+        Ok(configs)
+    }
+
+    pub fn image_encoder(&self) -> ImageEncoder {
+        ImageEncoder {
+            encoding: self.image_encoding,
+            scale: self.image_scale,
+        }
+    }
 }
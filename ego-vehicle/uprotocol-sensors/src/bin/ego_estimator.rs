@@ -0,0 +1,173 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Estimates the pose of the ego vehicle from the IMU, speed and GNSS topics of the
+//! bridge, and publishes it as fused odometry; or, with `--synthetic`, runs the estimator
+//! offline on the synthetic world and prints its errors against the ground truth.
+
+use clap::Parser;
+use ego_vehicle::args::zenoh_config;
+use ego_vehicle::client::{
+    BRIDGE_AUTHORITY, DEFAULT_STREAM_CAPACITY, subscribe, subscribe_gnss, subscribe_speed,
+    vehicle_sensor_topic,
+};
+use ego_vehicle::discovery::SensorKind;
+use ego_vehicle::estimation::{
+    ESTIMATOR_AUTHORITY, EstimatorConfig, ImuSample, OdometryEstimator, RESOURCE_FUSED_ODOMETRY,
+    SyntheticRun, evaluate_synthetic,
+};
+use ego_vehicle::synthetic::SyntheticWorld;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use up_rust::{LocalUriProvider, StaticUriProvider, UMessageBuilder, UPayloadFormat, UTransport};
+use up_transport_zenoh::UPTransportZenoh;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Parser, Debug)]
+#[clap(about = "EKF estimator of the ego vehicle pose")]
+struct EstimatorArgs {
+    /// IP address of the Zenoh router (default: peer-to-peer)
+    #[clap(long)]
+    router: Option<String>,
+    /// uProtocol authority of this estimator; one per vehicle, e.g. `FollowerEstimator`
+    #[clap(long, default_value = ESTIMATOR_AUTHORITY)]
+    authority: String,
+    /// uProtocol authority of the sensor bridge of the estimated vehicle
+    #[clap(long, default_value = BRIDGE_AUTHORITY)]
+    vehicle_authority: String,
+    /// Latitude and longitude of the origin of the CARLA map, e.g. `49.0,8.0` (default:
+    /// the first GNSS fix)
+    #[clap(long, value_parser = parse_geo_origin)]
+    geo_origin: Option<(f64, f64)>,
+    /// Rate of the fused odometry, in Hz
+    #[clap(long, default_value_t = 20.0)]
+    rate: f64,
+    /// Runs the estimator on that many seconds of the synthetic world instead, and prints
+    /// its errors
+    #[clap(long)]
+    synthetic: Option<f64>,
+    /// Seed of the synthetic noise
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+}
+
+fn parse_geo_origin(value: &str) -> std::result::Result<(f64, f64), String> {
+    let (latitude, longitude) = value
+        .split_once(',')
+        .ok_or_else(|| format!("invalid geo origin '{value}', expected LAT,LON"))?;
+    let parse = |angle: &str| {
+        angle
+            .trim()
+            .parse::<f64>()
+            .map_err(|e| format!("invalid geo origin '{value}': {e}"))
+    };
+    Ok((parse(latitude)?, parse(longitude)?))
+}
+
+async fn run(args: &EstimatorArgs) -> Result<()> {
+    UPTransportZenoh::try_init_log_from_env();
+    let uri_provider = StaticUriProvider::new(&args.authority, 0, 2);
+    let transport: Arc<dyn UTransport> = Arc::new(
+        UPTransportZenoh::builder(uri_provider.get_authority())
+            .expect("invalid authority name")
+            .with_config(zenoh_config(args.router.as_deref())?)
+            .build()
+            .await?,
+    );
+
+    let mut imu = subscribe::<ImuSample>(
+        Arc::clone(&transport),
+        vehicle_sensor_topic(&args.vehicle_authority, SensorKind::ImuMeasurement),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await?;
+    let mut gnss = subscribe_gnss(Arc::clone(&transport), &args.vehicle_authority).await?;
+    let mut speed = subscribe_speed(Arc::clone(&transport), &args.vehicle_authority).await?;
+
+    let odometry_topic = uri_provider.get_resource_uri(RESOURCE_FUSED_ODOMETRY);
+    log::info!(
+        "Publishing the fused odometry on {} at {} Hz",
+        odometry_topic.to_uri(false),
+        args.rate
+    );
+    let mut estimator = OdometryEstimator::new(EstimatorConfig {
+        geo_origin: args.geo_origin,
+        ..EstimatorConfig::default()
+    });
+    let mut publish = tokio::time::interval(Duration::from_secs_f64(1.0 / args.rate));
+    let mut last_published = None;
+    let mut last_dropouts = Vec::new();
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            Some(sample) = imu.next() => estimator.on_imu(&sample),
+            Some(fix) = gnss.next() => estimator.on_gnss(&fix),
+            Some(reading) = speed.next() => estimator.on_speed(reading.meters_per_second()),
+            _ = publish.tick() => {
+                let Some(odometry) = estimator.odometry() else {
+                    continue;
+                };
+                if odometry.dropouts != last_dropouts {
+                    log::warn!("Dropped out sources: {:?}", odometry.dropouts);
+                    last_dropouts = odometry.dropouts.clone();
+                }
+                // Nothing new while the simulation is paused
+                if last_published == Some(odometry.timestamp) {
+                    continue;
+                }
+                last_published = Some(odometry.timestamp);
+                let message = UMessageBuilder::publish(odometry_topic.clone())
+                    .build_with_payload(
+                        serde_json::to_string(&odometry)?,
+                        UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                    )?;
+                transport.send(message).await?;
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    pretty_env_logger::init();
+    let args = EstimatorArgs::parse();
+
+    if let Some(duration) = args.synthetic {
+        let run = SyntheticRun {
+            duration,
+            seed: args.seed,
+            ..SyntheticRun::default()
+        };
+        let errors =
+            evaluate_synthetic(&SyntheticWorld::default(), &run, EstimatorConfig::default());
+        println!(
+            "{} estimates: position rms {:.3} m, max {:.3} m, yaw rms {:.3}°, speed rms {:.3} m/s",
+            errors.samples,
+            errors.position_rms,
+            errors.position_max,
+            errors.yaw_rms,
+            errors.speed_rms
+        );
+        return Ok(());
+    }
+    run(&args).await
+}
@@ -0,0 +1,201 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tracks the objects around the ego vehicle from the lidar and radar topics of the
+//! bridge, and publishes the track list after every measurement.
+
+use clap::{Parser, ValueEnum};
+use ego_vehicle::args::zenoh_config;
+use ego_vehicle::client::{
+    BRIDGE_AUTHORITY, DEFAULT_STREAM_CAPACITY, subscribe, subscribe_lidar, subscribe_radar,
+    subscribe_speed, subscribe_transform_tree, vehicle_sensor_topic,
+};
+use ego_vehicle::discovery::SensorKind;
+use ego_vehicle::estimation::ImuSample;
+use ego_vehicle::tracking::{
+    Association, DetectionSource, EgoMotion, RESOURCE_TRACK_LIST, SensorMount, TRACKER_AUTHORITY,
+    Tracker, TrackerConfig,
+};
+use ego_vehicle::transforms::TransformTree;
+use std::error::Error;
+use std::sync::Arc;
+use up_rust::{LocalUriProvider, StaticUriProvider, UMessageBuilder, UPayloadFormat, UTransport};
+use up_transport_zenoh::UPTransportZenoh;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum AssociationArg {
+    /// Nearest neighbour
+    Nn,
+    /// Global nearest neighbour
+    Gnn,
+}
+
+#[derive(Parser, Debug)]
+#[clap(about = "Lidar and radar object tracker")]
+struct TrackerArgs {
+    /// IP address of the Zenoh router (default: peer-to-peer)
+    #[clap(long)]
+    router: Option<String>,
+    /// uProtocol authority of this tracker; one per vehicle, e.g. `FollowerTracker`
+    #[clap(long, default_value = TRACKER_AUTHORITY)]
+    authority: String,
+    /// uProtocol authority of the sensor bridge of the tracking vehicle
+    #[clap(long, default_value = BRIDGE_AUTHORITY)]
+    vehicle_authority: String,
+    /// Position of the lidar on the vehicle, in meters (default: the synthetic lidar)
+    #[clap(long, value_parser = parse_mount, default_value = "0,0,2.4")]
+    lidar_mount: SensorMount,
+    /// Position of the radar on the vehicle, in meters (default: the synthetic radar)
+    #[clap(long, value_parser = parse_mount, default_value = "0,0,0")]
+    radar_mount: SensorMount,
+    /// Role name of the lidar, whose mount is then read from the transform tree of the
+    /// bridge instead
+    #[clap(long)]
+    lidar_frame: Option<String>,
+    /// Role name of the radar, whose mount is then read from the transform tree of the
+    /// bridge instead
+    #[clap(long)]
+    radar_frame: Option<String>,
+    #[clap(long, value_enum, default_value_t = AssociationArg::Gnn)]
+    association: AssociationArg,
+    /// Width of the lane of the lead object, in meters
+    #[clap(long, default_value_t = 3.5)]
+    lane_width: f64,
+}
+
+fn parse_mount(value: &str) -> std::result::Result<SensorMount, String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| coordinate.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid sensor mount '{value}': {e}"))?;
+    let [x, y, z] = coordinates[..] else {
+        return Err(format!("invalid sensor mount '{value}', expected X,Y,Z"));
+    };
+    Ok(SensorMount { x, y, z })
+}
+
+// Sets the mounts of the sensors `frames` from `tree`
+fn set_mounts(tracker: &mut Tracker, tree: &TransformTree, frames: &[(DetectionSource, &str)]) {
+    for (source, frame) in frames {
+        let Some(sensor) = tree.sensor(frame) else {
+            log::warn!("No frame '{frame}' in the transform tree");
+            continue;
+        };
+        let transform = sensor.transform;
+        if transform.roll.abs() > 1.0 || transform.pitch.abs() > 1.0 || transform.yaw.abs() > 1.0 {
+            log::warn!("Sensor '{frame}' is rotated on the vehicle, ignoring its rotation");
+        }
+        tracker.set_mount(*source, SensorMount::from(&transform));
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    pretty_env_logger::init();
+    let args = TrackerArgs::parse();
+
+    UPTransportZenoh::try_init_log_from_env();
+    let uri_provider = StaticUriProvider::new(&args.authority, 0, 2);
+    let transport: Arc<dyn UTransport> = Arc::new(
+        UPTransportZenoh::builder(uri_provider.get_authority())
+            .expect("invalid authority name")
+            .with_config(zenoh_config(args.router.as_deref())?)
+            .build()
+            .await?,
+    );
+
+    let vehicle = args.vehicle_authority.as_str();
+    let mut lidar = subscribe_lidar(Arc::clone(&transport), vehicle).await?;
+    let mut radar = subscribe_radar(Arc::clone(&transport), vehicle).await?;
+    let mut imu = subscribe::<ImuSample>(
+        Arc::clone(&transport),
+        vehicle_sensor_topic(vehicle, SensorKind::ImuMeasurement),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await?;
+    let mut speed = subscribe_speed(Arc::clone(&transport), vehicle).await?;
+    let mut transform_tree = subscribe_transform_tree(Arc::clone(&transport), vehicle).await?;
+    let frames: Vec<(DetectionSource, &str)> = [
+        (DetectionSource::Lidar, args.lidar_frame.as_deref()),
+        (DetectionSource::Radar, args.radar_frame.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(source, frame)| Some((source, frame?)))
+    .collect();
+
+    let mut config = TrackerConfig {
+        association: match args.association {
+            AssociationArg::Nn => Association::NearestNeighbour,
+            AssociationArg::Gnn => Association::GlobalNearestNeighbour,
+        },
+        lane_width: args.lane_width,
+        ..TrackerConfig::default()
+    };
+    config.detection.lidar_mount = args.lidar_mount;
+    config.detection.radar_mount = args.radar_mount;
+    let mut tracker = Tracker::new(config);
+    let mut ego = EgoMotion::default();
+
+    let track_topic = uri_provider.get_resource_uri(RESOURCE_TRACK_LIST);
+    log::info!("Publishing the track list on {}", track_topic.to_uri(false));
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        let updated = tokio::select! {
+            Some(cloud) = lidar.next() => tracker.on_lidar(&cloud),
+            Some(cloud) = radar.next() => tracker.on_radar(&cloud),
+            Some(sample) = imu.next() => {
+                ego.yaw_rate = sample.gyroscope.z;
+                tracker.set_ego_motion(ego);
+                continue;
+            }
+            Some(reading) = speed.next() => {
+                ego.speed = reading.meters_per_second();
+                tracker.set_ego_motion(ego);
+                continue;
+            }
+            Some(tree) = transform_tree.next(), if !frames.is_empty() => {
+                set_mounts(&mut tracker, &tree, &frames);
+                continue;
+            }
+            _ = &mut shutdown => break,
+        };
+        if let Err(e) = updated {
+            log::warn!("Skipping a point cloud: {e}");
+            continue;
+        }
+
+        let tracks = tracker.track_list();
+        if let Some(lead) = tracks.lead_object() {
+            log::debug!(
+                "Lead object {} at {:.1} m, closing at {:.1} m/s",
+                lead.id,
+                lead.x,
+                -lead.vx
+            );
+        }
+        let message = UMessageBuilder::publish(track_topic.clone()).build_with_payload(
+            serde_json::to_string(&tracks)?,
+            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+        )?;
+        transport.send(message).await?;
+    }
+    Ok(())
+}
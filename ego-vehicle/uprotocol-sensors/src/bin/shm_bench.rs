@@ -0,0 +1,211 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Compares the throughput and latency of sensor-sized payloads published through Zenoh
+//! shared memory and as regular payloads, between two sessions of this process.
+
+use clap::{Parser, ValueEnum};
+use ego_vehicle::shm::{ShmPublisher, enable_shared_memory, shm_pool};
+use std::error::Error;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zenoh::Config;
+use zenoh::qos::CongestionControl;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+const KEY: &str = "ego_vehicle/bench/shm";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Shm,
+    Regular,
+    Both,
+}
+
+#[derive(Parser, Debug)]
+#[clap(about = "Zenoh shared-memory vs regular payload benchmark")]
+struct BenchArgs {
+    /// Payload size in bytes (default: a 1920x1080 BGRA image)
+    #[clap(long, default_value_t = 1920 * 1080 * 4)]
+    size: usize,
+    /// Payloads published per mode
+    #[clap(long, default_value_t = 500)]
+    count: usize,
+    /// Publish rate in Hz (default: as fast as possible)
+    #[clap(long)]
+    rate: Option<f64>,
+    /// Size of the shared-memory pool, in MiB
+    #[clap(long, default_value_t = 256)]
+    shm_pool_mb: usize,
+    /// Local port the publishing session listens on
+    #[clap(long, default_value_t = 7450)]
+    port: u16,
+    #[clap(long, value_enum, default_value_t = Mode::Both)]
+    mode: Mode,
+}
+
+struct Report {
+    received: usize,
+    elapsed: Duration,
+    latencies: Vec<Duration>,
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+fn session_config(port: u16, listen: bool, shm: bool) -> Result<Config> {
+    let endpoints = if listen { "listen" } else { "connect" };
+    let mut config = Config::from_json5(&format!(
+        "{{ mode: 'peer', {endpoints}: {{ endpoints: [ 'tcp/127.0.0.1:{port}' ] }}, \
+         scouting: {{ multicast: {{ enabled: false }} }} }}"
+    ))?;
+    if shm {
+        enable_shared_memory(&mut config)?;
+    } else {
+        config
+            .insert_json5("transport/shared_memory/enabled", "false")
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(config)
+}
+
+async fn run(args: &BenchArgs, shm: bool) -> Result<Report> {
+    let publisher_session = zenoh::open(session_config(args.port, true, shm)?).await?;
+    let subscriber_session = zenoh::open(session_config(args.port, false, shm)?).await?;
+
+    // Latency of every payload, from the send timestamp in its first 8 bytes
+    let (tx, rx) = mpsc::channel::<Duration>();
+    let _subscriber = subscriber_session
+        .declare_subscriber(KEY)
+        .callback(move |sample| {
+            let bytes = sample.payload().to_bytes();
+            if let Some(sent) = bytes.get(..8) {
+                let sent = u64::from_le_bytes(sent.try_into().unwrap());
+                let _ = tx.send(Duration::from_nanos(now_ns().saturating_sub(sent)));
+            }
+        })
+        .await?;
+
+    let shm_publisher = if shm {
+        let pool = shm_pool(args.shm_pool_mb * 1024 * 1024)?;
+        Some(ShmPublisher::declare(&publisher_session, KEY.to_string(), pool).await?)
+    } else {
+        None
+    };
+    let publisher = publisher_session
+        .declare_publisher(KEY)
+        .congestion_control(CongestionControl::Drop)
+        .await?;
+
+    // Lets the sessions connect and the subscriber be declared on the publisher side
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let period = args.rate.map(|rate| Duration::from_secs_f64(1.0 / rate));
+    let mut payload = vec![0u8; args.size.max(8)];
+    let start = Instant::now();
+    for i in 0..args.count {
+        payload[8..].fill(i as u8);
+        payload[..8].copy_from_slice(&now_ns().to_le_bytes());
+        match &shm_publisher {
+            Some(shm_publisher) => shm_publisher.put(&payload).await?,
+            None => publisher.put(payload.clone()).await?,
+        }
+        if let Some(period) = period {
+            let next = start + period * (i as u32 + 1);
+            tokio::time::sleep(next.saturating_duration_since(Instant::now())).await;
+        }
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut latencies = Vec::with_capacity(args.count);
+    let mut elapsed = Duration::ZERO;
+    while latencies.len() < args.count {
+        let Some(timeout) = deadline.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        match rx.recv_timeout(timeout) {
+            Ok(latency) => {
+                latencies.push(latency);
+                elapsed = start.elapsed();
+            }
+            Err(_) => break,
+        }
+    }
+
+    let fallbacks = shm_publisher.as_ref().map_or(0, ShmPublisher::fallbacks);
+    if fallbacks > 0 {
+        println!("  {fallbacks} payloads published without shared memory (pool exhausted)");
+    }
+
+    // Frees the port for the next mode
+    subscriber_session.close().await?;
+    publisher_session.close().await?;
+
+    Ok(Report {
+        received: latencies.len(),
+        elapsed,
+        latencies,
+    })
+}
+
+fn print_report(name: &str, args: &BenchArgs, mut report: Report) {
+    report.latencies.sort();
+    let percentile = |p: f64| {
+        report
+            .latencies
+            .get(((report.latencies.len() as f64 - 1.0) * p).round() as usize)
+            .copied()
+            .unwrap_or_default()
+    };
+    let seconds = report.elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "{name:>8}: {}/{} received, {:.1} msg/s, {:.1} MB/s, latency p50 {:?}, p99 {:?}, max {:?}",
+        report.received,
+        args.count,
+        report.received as f64 / seconds,
+        (report.received * args.size) as f64 / seconds / 1e6,
+        percentile(0.5),
+        percentile(0.99),
+        report.latencies.last().copied().unwrap_or_default()
+    );
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    pretty_env_logger::init();
+    let args = BenchArgs::parse();
+    println!(
+        "{} payloads of {} bytes{}",
+        args.count,
+        args.size,
+        args.rate
+            .map_or(String::new(), |rate| format!(" at {rate} Hz"))
+    );
+
+    if args.mode != Mode::Regular {
+        let report = run(&args, true).await?;
+        print_report("shm", &args, report);
+    }
+    if args.mode != Mode::Shm {
+        let report = run(&args, false).await?;
+        print_report("regular", &args, report);
+    }
+    Ok(())
+}
@@ -0,0 +1,465 @@
+/*!
+Fragmentation of large payloads (camera frames, point clouds) into chunks small enough for
+every transport, e.g. MQTT5 brokers with a maximum packet size.
+
+A payload larger than the chunk size is split into numbered chunks sharing a transfer id,
+each published as its own message on the same topic, with the payload format of the whole
+payload. Smaller payloads are published unchanged, so chunking only costs subscribers of
+large payloads. Every chunk starts with a 24-byte little-endian header:
+
+| Offset | Size | Field |
+|--------|------|-------|
+| 0 | 4 | magic `CCHK` |
+| 4 | 1 | version (1) |
+| 5 | 3 | reserved |
+| 8 | 4 | transfer id (u32), increasing per publisher |
+| 12 | 4 | chunk index (u32) |
+| 16 | 4 | chunk count (u32) |
+| 20 | 4 | length of the whole payload (u32) |
+
+[`ChunkedPublisher`] splits over any `UTransport`; [`ChunkedListener`] wraps any `UListener`
+and hands it the reassembled messages.
+ */
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use up_rust::{UListener, UMessage, UMessageBuilder, UPayloadFormat, UTransport, UUri};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub const CHUNK_MAGIC: [u8; 4] = *b"CCHK";
+pub const CHUNK_VERSION: u8 = 1;
+pub const CHUNK_HEADER_LEN: usize = 24;
+
+/// Smallest chunk size accepted, header included.
+pub const MIN_CHUNK_SIZE: usize = 256;
+/// How long a transfer may wait for its missing chunks.
+pub const DEFAULT_TRANSFER_TIMEOUT: Duration = Duration::from_secs(2);
+/// Transfers reassembled at the same time per listener; the oldest are given up beyond.
+pub const MAX_PENDING_TRANSFERS: usize = 16;
+/// Largest payload split into chunks and reassembled, e.g. two raw 4K camera images.
+pub const MAX_TRANSFER_LEN: u32 = 64 * 1024 * 1024;
+
+/// Header of a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub transfer_id: u32,
+    pub index: u32,
+    pub count: u32,
+    pub total_len: u32,
+}
+
+impl ChunkHeader {
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&CHUNK_MAGIC);
+        out.push(CHUNK_VERSION);
+        out.extend_from_slice(&[0; 3]);
+        out.extend_from_slice(&self.transfer_id.to_le_bytes());
+        out.extend_from_slice(&self.index.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&self.total_len.to_le_bytes());
+    }
+
+    /// Header and body of a chunk; `None` if `bytes` is not a chunk.
+    pub fn read(bytes: &[u8]) -> Option<(Self, &[u8])> {
+        if !is_chunk(bytes) || bytes[4] != CHUNK_VERSION {
+            return None;
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let header = Self {
+            transfer_id: u32_at(8),
+            index: u32_at(12),
+            count: u32_at(16),
+            total_len: u32_at(20),
+        };
+        Some((header, &bytes[CHUNK_HEADER_LEN..]))
+    }
+}
+
+/// Whether `payload` is a chunk rather than a whole payload.
+pub fn is_chunk(payload: &[u8]) -> bool {
+    payload.len() >= CHUNK_HEADER_LEN && payload[0..4] == CHUNK_MAGIC
+}
+
+/// Splits `payload` into chunks of at most `chunk_size` bytes, header included.
+pub fn split(payload: &[u8], transfer_id: u32, chunk_size: usize) -> Result<Vec<Vec<u8>>> {
+    let body_size = chunk_size.max(MIN_CHUNK_SIZE) - CHUNK_HEADER_LEN;
+    let total_len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_TRANSFER_LEN)
+        .ok_or("payload too large to chunk")?;
+    let count = payload.len().div_ceil(body_size).max(1) as u32;
+
+    Ok((0..count)
+        .map(|index| {
+            let start = index as usize * body_size;
+            let body = &payload[start..(start + body_size).min(payload.len())];
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + body.len());
+            ChunkHeader {
+                transfer_id,
+                index,
+                count,
+                total_len,
+            }
+            .write(&mut chunk);
+            chunk.extend_from_slice(body);
+            chunk
+        })
+        .collect())
+}
+
+/// Counters of a [`ChunkReassembler`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReassemblyStats {
+    /// Payloads reassembled.
+    pub completed: u64,
+    /// Transfers given up with missing chunks (timeout or too many pending transfers).
+    pub lost: u64,
+    /// Chunks received twice, or for a transfer already completed or given up.
+    pub duplicates: u64,
+    /// Chunks whose header does not match their transfer.
+    pub invalid: u64,
+}
+
+struct Transfer {
+    started: Instant,
+    total_len: u32,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+/// Rebuilds payloads from their chunks, in whatever order they arrive and interleaved
+/// with other transfers. Chunks are told apart by sender, e.g. the source URI.
+pub struct ChunkReassembler {
+    timeout: Duration,
+    transfers: HashMap<(String, u32), Transfer>,
+    // Transfers completed or given up recently, to recognise their late chunks
+    finished: HashMap<(String, u32), Instant>,
+    stats: ReassemblyStats,
+}
+
+impl Default for ChunkReassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRANSFER_TIMEOUT)
+    }
+}
+
+impl ChunkReassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            transfers: HashMap::new(),
+            finished: HashMap::new(),
+            stats: ReassemblyStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> ReassemblyStats {
+        self.stats
+    }
+
+    /// Adds a chunk received at `now` from `sender`; returns the whole payload once its
+    /// last missing chunk arrives.
+    pub fn push(
+        &mut self,
+        now: Instant,
+        sender: &str,
+        header: ChunkHeader,
+        body: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.expire(now);
+
+        let key = (sender.to_string(), header.transfer_id);
+        if self.finished.contains_key(&key) {
+            self.stats.duplicates += 1;
+            return None;
+        }
+        // Chunks are at least MIN_CHUNK_SIZE long, which bounds the allocation below
+        let max_count = (header.total_len as usize)
+            .div_ceil(MIN_CHUNK_SIZE - CHUNK_HEADER_LEN)
+            .max(1);
+        if header.index >= header.count
+            || header.count as usize > max_count
+            || header.total_len > MAX_TRANSFER_LEN
+        {
+            self.stats.invalid += 1;
+            return None;
+        }
+
+        let transfer = self
+            .transfers
+            .entry(key.clone())
+            .or_insert_with(|| Transfer {
+                started: now,
+                total_len: header.total_len,
+                chunks: vec![None; header.count as usize],
+                received: 0,
+            });
+        if transfer.chunks.len() != header.count as usize || transfer.total_len != header.total_len
+        {
+            self.stats.invalid += 1;
+            return None;
+        }
+
+        let slot = &mut transfer.chunks[header.index as usize];
+        if slot.is_some() {
+            self.stats.duplicates += 1;
+            return None;
+        }
+        *slot = Some(body.to_vec());
+        transfer.received += 1;
+
+        if transfer.received < transfer.chunks.len() {
+            self.evict();
+            return None;
+        }
+
+        let transfer = self.transfers.remove(&key).unwrap();
+        self.finished.insert(key, now);
+        let payload: Vec<u8> = transfer.chunks.into_iter().flatten().flatten().collect();
+        if payload.len() != transfer.total_len as usize {
+            self.stats.invalid += 1;
+            return None;
+        }
+        self.stats.completed += 1;
+        Some(payload)
+    }
+
+    /// Gives up the transfers older than the timeout.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let expired: Vec<_> = self
+            .transfers
+            .iter()
+            .filter(|(_, t)| now.saturating_duration_since(t.started) > timeout)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.give_up(key, now, "timed out");
+        }
+        self.finished
+            .retain(|_, finished| now.saturating_duration_since(*finished) <= timeout);
+    }
+
+    fn evict(&mut self) {
+        while self.transfers.len() > MAX_PENDING_TRANSFERS {
+            let oldest = self
+                .transfers
+                .iter()
+                .min_by_key(|(_, t)| t.started)
+                .map(|(key, t)| (key.clone(), t.started));
+            if let Some((key, started)) = oldest {
+                self.give_up(key, started, "evicted");
+            }
+        }
+    }
+
+    fn give_up(&mut self, key: (String, u32), now: Instant, reason: &str) {
+        if let Some(transfer) = self.transfers.remove(&key) {
+            log::warn!(
+                "Transfer {} from {} {reason} with {} of {} chunks",
+                key.1,
+                key.0,
+                transfer.received,
+                transfer.chunks.len()
+            );
+            self.stats.lost += 1;
+            self.finished.insert(key, now);
+        }
+    }
+}
+
+/// Publishes payloads over any `UTransport`, split into chunks when larger than the
+/// chunk size; without a chunk size every payload is published whole.
+#[derive(Clone)]
+pub struct ChunkedPublisher {
+    transport: Arc<dyn UTransport>,
+    chunk_size: Option<usize>,
+    next_transfer_id: Arc<AtomicU32>,
+}
+
+impl ChunkedPublisher {
+    pub fn new(transport: Arc<dyn UTransport>, chunk_size: Option<usize>) -> Self {
+        Self {
+            transport,
+            chunk_size: chunk_size.map(|size| size.max(MIN_CHUNK_SIZE)),
+            next_transfer_id: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    pub fn chunk_size(&self) -> Option<usize> {
+        self.chunk_size
+    }
+
+    pub async fn publish(
+        &self,
+        topic: &UUri,
+        payload: Vec<u8>,
+        payload_format: UPayloadFormat,
+    ) -> Result<()> {
+        let chunk_size = match self.chunk_size {
+            Some(chunk_size) if payload.len() > chunk_size => chunk_size,
+            _ => return self.send(topic, payload, payload_format).await,
+        };
+
+        let transfer_id = self.next_transfer_id.fetch_add(1, Ordering::Relaxed);
+        let chunks = split(&payload, transfer_id, chunk_size)?;
+        log::trace!(
+            "Publishing {} bytes in {} chunks (transfer {transfer_id})",
+            payload.len(),
+            chunks.len()
+        );
+        for chunk in chunks {
+            self.send(topic, chunk, payload_format).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, topic: &UUri, payload: Vec<u8>, format: UPayloadFormat) -> Result<()> {
+        let message =
+            UMessageBuilder::publish(topic.clone()).build_with_payload(payload, format)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| format!("Transport send failed: {e:?}").into())
+    }
+}
+
+/// Listener that reassembles chunked payloads before handing their message to `inner`;
+/// whole payloads are handed over unchanged.
+pub struct ChunkedListener {
+    inner: Arc<dyn UListener>,
+    reassembler: Mutex<ChunkReassembler>,
+}
+
+impl ChunkedListener {
+    pub fn new(inner: Arc<dyn UListener>) -> Self {
+        Self::with_timeout(inner, DEFAULT_TRANSFER_TIMEOUT)
+    }
+
+    pub fn with_timeout(inner: Arc<dyn UListener>, timeout: Duration) -> Self {
+        Self {
+            inner,
+            reassembler: Mutex::new(ChunkReassembler::new(timeout)),
+        }
+    }
+
+    pub fn stats(&self) -> ReassemblyStats {
+        self.reassembler.lock().unwrap().stats()
+    }
+}
+
+#[async_trait]
+impl UListener for ChunkedListener {
+    async fn on_receive(&self, msg: UMessage) {
+        let Some((header, body)) = msg.payload.as_deref().and_then(ChunkHeader::read) else {
+            self.inner.on_receive(msg).await;
+            return;
+        };
+
+        let sender = msg.attributes.source.to_uri(false);
+        let payload = self
+            .reassembler
+            .lock()
+            .unwrap()
+            .push(Instant::now(), &sender, header, body);
+
+        if let Some(payload) = payload {
+            let whole = UMessage {
+                attributes: msg.attributes.clone(),
+                payload: Some(payload.into()),
+                ..Default::default()
+            };
+            self.inner.on_receive(whole).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn push(reassembler: &mut ChunkReassembler, now: Instant, chunk: &[u8]) -> Option<Vec<u8>> {
+        let (header, body) = ChunkHeader::read(chunk).unwrap();
+        reassembler.push(now, "sender", header, body)
+    }
+
+    #[test]
+    fn test_out_of_order_and_interleaved() {
+        let (first, second) = (payload(1000), payload(600));
+        let a = split(&first, 1, 256).unwrap();
+        let b = split(&second, 2, 256).unwrap();
+        assert_eq!(a.len(), 5);
+        assert!(a.iter().all(|chunk| chunk.len() <= 256 && is_chunk(chunk)));
+
+        let now = Instant::now();
+        let mut reassembler = ChunkReassembler::default();
+        for chunk in [&a[4], &b[2], &a[0], &a[3], &b[0], &a[1], &a[0]] {
+            assert_eq!(push(&mut reassembler, now, chunk), None);
+        }
+        assert_eq!(push(&mut reassembler, now, &b[1]), Some(second));
+        assert_eq!(push(&mut reassembler, now, &a[2]), Some(first));
+        // Late copy of a completed transfer
+        assert_eq!(push(&mut reassembler, now, &a[2]), None);
+
+        let stats = reassembler.stats();
+        assert_eq!((stats.completed, stats.duplicates, stats.lost), (2, 2, 0));
+    }
+
+    #[test]
+    fn test_lost_chunks_time_out() {
+        let chunks = split(&payload(1000), 7, 256).unwrap();
+        let start = Instant::now();
+        let mut reassembler = ChunkReassembler::new(Duration::from_millis(100));
+        for chunk in &chunks[1..] {
+            assert_eq!(push(&mut reassembler, start, chunk), None);
+        }
+
+        // The missing chunk arrives too late: the transfer is lost, not completed
+        let late = start + Duration::from_millis(150);
+        assert_eq!(push(&mut reassembler, late, &chunks[0]), None);
+        assert_eq!(reassembler.stats().lost, 1);
+        assert_eq!(reassembler.stats().completed, 0);
+
+        // Payloads that fit in a chunk still travel as a single chunk when split
+        let small = split(&payload(10), 8, 256).unwrap();
+        assert_eq!(small.len(), 1);
+        assert_eq!(push(&mut reassembler, late, &small[0]), Some(payload(10)));
+        assert!(!is_chunk(b"{\"frame\": 1}"));
+    }
+
+    #[test]
+    fn test_oversized_headers_are_rejected() {
+        let now = Instant::now();
+        let mut reassembler = ChunkReassembler::default();
+        let header = |count, total_len| ChunkHeader {
+            transfer_id: 3,
+            index: 0,
+            count,
+            total_len,
+        };
+
+        // More chunks than the payload length allows, or a payload too large
+        for (count, total_len) in [(u32::MAX, u32::MAX), (6, 1000), (1, MAX_TRANSFER_LEN + 1)] {
+            let chunk = reassembler.push(now, "sender", header(count, total_len), &[0; 8]);
+            assert_eq!(chunk, None);
+        }
+        assert_eq!(reassembler.stats().invalid, 3);
+        assert!(reassembler.transfers.is_empty());
+
+        // The largest chunk count of a payload is accepted
+        assert_eq!(
+            reassembler.push(now, "sender", header(5, 1000), &[0; 8]),
+            None
+        );
+        assert_eq!(reassembler.transfers.len(), 1);
+    }
+}
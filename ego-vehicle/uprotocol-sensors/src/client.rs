@@ -0,0 +1,743 @@
+//! Typed subscriptions to the sensor topics of the bridge.
+//!
+//! This module is the reference for the sensor payload contracts: every payload the
+//! bridge publishes is decoded here into a typed frame, whatever its encoding, and
+//! chunked payloads (see [`crate::chunking`]) are reassembled first. A
+//! [`SensorStream`] yields the decoded frames, and counts the payloads it could not
+//! decode and the CARLA frames missing between consecutive ones.
+//!
+//! ```ignore
+//! let mut lidar = subscribe_lidar(transport, BRIDGE_AUTHORITY).await?;
+//! while let Some(cloud) = lidar.next().await {
+//!     println!("{} points in frame {}", cloud.len(), cloud.frame);
+//! }
+//! ```
+//!
+//! Consumers fusing several sensors match their frames with a [`Synchronizer`].
+
+mod synchronizer;
+
+pub use synchronizer::*;
+
+use crate::chunking::ChunkedListener;
+use crate::codec::{
+    DecodedImage, ImageHeader, PointCloud, decode_depth, decode_image, decode_labels,
+};
+use crate::diagnostics::DiagnosticsReport;
+use crate::discovery::{SensorKind, SensorManifest};
+use crate::sensors::GnssFix;
+use crate::transforms::{
+    RESOURCE_GET_TRANSFORM_TREE, RESOURCE_LOOKUP_TRANSFORM, TRANSFORM_SERVICE_ID,
+    TRANSFORM_SERVICE_VERSION, TransformLookup, TransformRequest, TransformTree,
+};
+use async_trait::async_trait;
+use carla_data_serde::{
+    CollisionEventSerDe, ImuMeasurementSerDe, LaneInvasionEventSerDe, ObstacleDetectionEventSerDe,
+};
+use futures::Stream;
+use std::error::Error;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use up_rust::communication::{CallOptions, RpcClient, UPayload};
+use up_rust::{UListener, UMessage, UPayloadFormat, UTransport, UUri};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Default authority of the bridge, e.g. in `//EGOVehicle/0/2/8015`.
+pub const BRIDGE_AUTHORITY: &str = "EGOVehicle";
+/// Resource ID of the velocity status topic.
+pub const RESOURCE_VELOCITY_STATUS: u16 = 0x8001;
+/// Resource ID of the sensor manifest topic.
+pub const RESOURCE_SENSOR_MANIFEST: u16 = 0x8020;
+/// Resource ID of the sensor diagnostics topic.
+pub const RESOURCE_SENSOR_DIAGNOSTICS: u16 = 0x8021;
+/// Resource ID of the transform tree topic.
+pub const RESOURCE_TRANSFORM_TREE: u16 = 0x8022;
+/// Milliseconds the bridge has to answer a transform request.
+pub const TRANSFORM_REQUEST_TTL_MS: u32 = 1_000;
+/// Frames a stream buffers for a slow consumer before dropping new ones.
+pub const DEFAULT_STREAM_CAPACITY: usize = 16;
+
+// A warning is logged on the first gap, then every GAP_LOG_INTERVAL gaps
+const GAP_LOG_INTERVAL: u64 = 100;
+
+/// Camera frame, as published with a binary `--image-encoding`.
+pub type CameraFrame = DecodedImage;
+/// Lidar points, with the `LIDAR_FIELDS`.
+pub type LidarFrame = PointCloud;
+/// Radar detections, with the `RADAR_FIELDS`.
+pub type RadarFrame = PointCloud;
+/// Semantic lidar points, with the `SEMANTIC_LIDAR_FIELDS`.
+pub type SemanticLidarFrame = PointCloud;
+
+/// Frame of a depth camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthFrame {
+    pub header: ImageHeader,
+    /// Depth of every pixel in meters, row by row.
+    pub depth: Vec<f32>,
+}
+
+/// Frame of a semantic segmentation camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelFrame {
+    pub header: ImageHeader,
+    /// CARLA semantic tag of every pixel, row by row.
+    pub labels: Vec<u8>,
+}
+
+/// CARLA frame and simulation time of a payload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stamp {
+    pub frame: u64,
+    /// Seconds since the start of the episode.
+    pub timestamp: f64,
+}
+
+impl Stamp {
+    pub fn new(frame: u64, timestamp: f64) -> Self {
+        Self { frame, timestamp }
+    }
+}
+
+/// A typed sensor payload.
+pub trait SensorPayload: Sized + Send + 'static {
+    fn decode(payload: &[u8], format: UPayloadFormat) -> Result<Self>;
+
+    /// CARLA frame and time of the payload, for the payloads that carry them; see
+    /// [`json_stamp`] for the others.
+    fn stamp(&self) -> Option<Stamp> {
+        None
+    }
+}
+
+/// Stamp of a JSON payload, from its top-level `frame` and `timestamp` fields as CARLA
+/// serialises its sensor events.
+pub fn json_stamp(payload: &[u8]) -> Option<Stamp> {
+    let value: serde_json::Value = serde_json::from_slice(payload).ok()?;
+    Some(Stamp::new(
+        value.get("frame")?.as_u64()?,
+        value.get("timestamp")?.as_f64()?,
+    ))
+}
+
+// Decodes the payload of `msg` with `decode`, then finds its stamp
+fn decode_stamped<T>(
+    msg: &UMessage,
+    decode: impl FnOnce(&[u8], UPayloadFormat) -> Result<T>,
+    stamp: impl FnOnce(&T) -> Option<Stamp>,
+) -> Result<(T, Option<Stamp>)> {
+    let format = msg.attributes.payload_format.enum_value_or_default();
+    let payload = msg.payload.as_deref().ok_or("message without payload")?;
+    let decoded = decode(payload, format)?;
+    let stamp = stamp(&decoded).or_else(|| {
+        (format == UPayloadFormat::UPAYLOAD_FORMAT_JSON)
+            .then(|| json_stamp(payload))
+            .flatten()
+    });
+    Ok((decoded, stamp))
+}
+
+impl SensorPayload for PointCloud {
+    fn decode(payload: &[u8], format: UPayloadFormat) -> Result<Self> {
+        if format == UPayloadFormat::UPAYLOAD_FORMAT_JSON {
+            return Err(
+                "JSON point clouds are not supported, publish them packed (the default)".into(),
+            );
+        }
+        PointCloud::decode(payload)
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.frame, self.timestamp))
+    }
+}
+
+impl SensorPayload for DecodedImage {
+    fn decode(payload: &[u8], format: UPayloadFormat) -> Result<Self> {
+        if format == UPayloadFormat::UPAYLOAD_FORMAT_JSON {
+            return Err(
+                "JSON images are not supported, publish them raw (the default), jpeg or png".into(),
+            );
+        }
+        decode_image(payload)
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.header.frame, self.header.timestamp))
+    }
+}
+
+impl SensorPayload for DepthFrame {
+    fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+        let (header, depth) = decode_depth(payload)?;
+        Ok(Self { header, depth })
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.header.frame, self.header.timestamp))
+    }
+}
+
+impl SensorPayload for LabelFrame {
+    fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+        let (header, labels) = decode_labels(payload)?;
+        Ok(Self { header, labels })
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.header.frame, self.header.timestamp))
+    }
+}
+
+impl SensorPayload for GnssFix {
+    fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+        Ok(serde_json::from_slice(payload)?)
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.frame, self.timestamp))
+    }
+}
+
+/// Speed of the ego vehicle in km/h, published on every tick as JSON stamped with the
+/// simulation time, e.g. `{"velocity": 45.2, "frame": 250, "elapsed_seconds": 12.5}`.
+/// Bare text speeds, as published by earlier bridges, have no stamp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VehicleSpeed {
+    pub kmh: f64,
+    pub stamp: Option<Stamp>,
+}
+
+impl VehicleSpeed {
+    pub fn meters_per_second(&self) -> f64 {
+        self.kmh / 3.6
+    }
+}
+
+impl SensorPayload for VehicleSpeed {
+    fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+        if let Ok(kmh) = std::str::from_utf8(payload)?.trim().parse() {
+            return Ok(Self { kmh, stamp: None });
+        }
+        let status: serde_json::Value = serde_json::from_slice(payload)?;
+        let kmh = status["velocity"]
+            .as_f64()
+            .ok_or("velocity status without a velocity")?;
+        let stamp = status["frame"]
+            .as_u64()
+            .zip(status["elapsed_seconds"].as_f64())
+            .map(|(frame, elapsed_seconds)| Stamp::new(frame, elapsed_seconds));
+        Ok(Self { kmh, stamp })
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        self.stamp
+    }
+}
+
+/// Payloads published as JSON, stamped by [`json_stamp`].
+macro_rules! json_payload {
+    ($($payload:ty),*) => {
+        $(impl SensorPayload for $payload {
+            fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+                Ok(serde_json::from_slice(payload)?)
+            }
+        })*
+    };
+}
+
+json_payload!(
+    CollisionEventSerDe,
+    LaneInvasionEventSerDe,
+    ObstacleDetectionEventSerDe,
+    ImuMeasurementSerDe,
+    SensorManifest,
+    DiagnosticsReport,
+    TransformTree
+);
+
+/// Counters of a [`SensorStream`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    /// Payloads decoded and handed to the stream.
+    pub received: u64,
+    /// Payloads that could not be decoded.
+    pub invalid: u64,
+    /// Decoded frames dropped because the consumer was too slow.
+    pub dropped: u64,
+    /// Jumps of the CARLA frame larger than the sensor period.
+    pub gaps: u64,
+    /// Frames missing in these jumps, in sensor periods.
+    pub missed: u64,
+    /// Frames older than the previous one.
+    pub out_of_order: u64,
+}
+
+/// Detects missing frames from the CARLA frame numbers of a periodic sensor.
+///
+/// The sensor period, in world frames, is the smallest step seen so far: a sensor
+/// ticking every 3 frames has no gap between frames 30 and 33, but misses one between
+/// 33 and 39.
+#[derive(Clone, Debug, Default)]
+pub struct FrameGaps {
+    last: Option<u64>,
+    period: Option<u64>,
+}
+
+/// What [`FrameGaps::observe`] found about a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameOrder {
+    /// First frame, or the next one.
+    InSequence,
+    /// Follows a gap of this many missing frames.
+    Gap(u64),
+    /// Not newer than the previous frame.
+    OutOfOrder,
+}
+
+impl FrameGaps {
+    pub fn observe(&mut self, frame: u64) -> FrameOrder {
+        let Some(last) = self.last else {
+            self.last = Some(frame);
+            return FrameOrder::InSequence;
+        };
+        if frame <= last {
+            return FrameOrder::OutOfOrder;
+        }
+        self.last = Some(frame);
+
+        let step = frame - last;
+        let period = self.period.map_or(step, |period| period.min(step));
+        self.period = Some(period);
+        match step / period {
+            0 | 1 => FrameOrder::InSequence,
+            periods => FrameOrder::Gap(periods - 1),
+        }
+    }
+}
+
+/// Typed frames of one sensor topic, as an async [`Stream`].
+pub struct SensorStream<T> {
+    receiver: mpsc::Receiver<T>,
+    stats: Arc<Mutex<StreamStats>>,
+    transport: Arc<dyn UTransport>,
+    topic: UUri,
+    listener: Arc<dyn UListener>,
+}
+
+impl<T> SensorStream<T> {
+    /// Waits for the next frame; `None` once the stream is closed.
+    pub async fn next(&mut self) -> Option<T> {
+        self.receiver.recv().await
+    }
+
+    pub fn topic(&self) -> &UUri {
+        &self.topic
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Unregisters the stream from the transport.
+    pub async fn close(self) -> Result<()> {
+        self.transport
+            .unregister_listener(&self.topic, None, self.listener)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<T> Stream for SensorStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+// Decodes the payloads of a topic into a stream
+struct DecodingListener<T> {
+    topic: String,
+    sender: mpsc::Sender<T>,
+    stats: Arc<Mutex<StreamStats>>,
+    gaps: Mutex<FrameGaps>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T: SensorPayload> DecodingListener<T> {
+    fn handle(&self, msg: &UMessage) {
+        let decoded = decode_stamped(msg, T::decode, T::stamp);
+
+        let mut stats = self.stats.lock().unwrap();
+        let (frame, stamp) = match decoded {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                stats.invalid += 1;
+                log::warn!("{}: undecodable payload: {e}", self.topic);
+                return;
+            }
+        };
+
+        if let Some(number) = stamp.map(|stamp| stamp.frame) {
+            match self.gaps.lock().unwrap().observe(number) {
+                FrameOrder::InSequence => {}
+                FrameOrder::Gap(missed) => {
+                    stats.gaps += 1;
+                    stats.missed += missed;
+                    if stats.gaps % GAP_LOG_INTERVAL == 1 {
+                        log::warn!(
+                            "{}: {missed} frames missing before frame {number} ({} gaps so far)",
+                            self.topic,
+                            stats.gaps
+                        );
+                    }
+                }
+                FrameOrder::OutOfOrder => stats.out_of_order += 1,
+            }
+        }
+
+        match self.sender.try_send(frame) {
+            Ok(()) => stats.received += 1,
+            Err(mpsc::error::TrySendError::Full(_)) => stats.dropped += 1,
+            // The stream was dropped without being closed
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+#[async_trait]
+impl<T: SensorPayload> UListener for DecodingListener<T> {
+    async fn on_receive(&self, msg: UMessage) {
+        self.handle(&msg);
+    }
+}
+
+/// Topic of the first sensor of `kind` on the bridge of [`BRIDGE_AUTHORITY`], e.g.
+/// `//EGOVehicle/0/2/8015` for the lidar; the topics of the others are listed in the
+/// [`SensorManifest`].
+pub fn sensor_topic(kind: SensorKind) -> UUri {
+    vehicle_sensor_topic(BRIDGE_AUTHORITY, kind)
+}
+
+/// Topic of the first sensor of `kind` on the bridge of `authority`, for a vehicle
+/// bridged under another authority than [`BRIDGE_AUTHORITY`].
+pub fn vehicle_sensor_topic(authority: &str, kind: SensorKind) -> UUri {
+    bridge_topic(authority, kind.default_resource_id())
+}
+
+/// Topic `resource_id` of the bridge of `authority`, e.g. `//EGOVehicle/0/2/8001`.
+pub fn bridge_topic(authority: &str, resource_id: u16) -> UUri {
+    UUri::from_str(&format!("//{authority}/0/2/{resource_id:X}")).expect("valid bridge topic")
+}
+
+/// Subscribes to `topic`, decoding its payloads as `T`.
+pub async fn subscribe<T: SensorPayload>(
+    transport: Arc<dyn UTransport>,
+    topic: UUri,
+    capacity: usize,
+) -> Result<SensorStream<T>> {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    let stats = Arc::new(Mutex::new(StreamStats::default()));
+    let decoder = DecodingListener {
+        topic: topic.to_uri(false),
+        sender,
+        stats: Arc::clone(&stats),
+        gaps: Mutex::new(FrameGaps::default()),
+        _payload: PhantomData,
+    };
+    let listener: Arc<dyn UListener> = Arc::new(ChunkedListener::new(Arc::new(decoder)));
+    transport
+        .register_listener(&topic, None, Arc::clone(&listener))
+        .await?;
+
+    Ok(SensorStream {
+        receiver,
+        stats,
+        transport,
+        topic,
+        listener,
+    })
+}
+
+async fn subscribe_default<T: SensorPayload>(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+    kind: SensorKind,
+) -> Result<SensorStream<T>> {
+    subscribe(
+        transport,
+        vehicle_sensor_topic(authority, kind),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await
+}
+
+pub async fn subscribe_lidar(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<LidarFrame>> {
+    subscribe_default(transport, authority, SensorKind::LidarMeasurement).await
+}
+
+pub async fn subscribe_radar(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<RadarFrame>> {
+    subscribe_default(transport, authority, SensorKind::RadarMeasurement).await
+}
+
+pub async fn subscribe_semantic_lidar(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<SemanticLidarFrame>> {
+    subscribe_default(transport, authority, SensorKind::SemanticLidarMeasurement).await
+}
+
+pub async fn subscribe_camera(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<CameraFrame>> {
+    subscribe_default(transport, authority, SensorKind::Image).await
+}
+
+pub async fn subscribe_depth(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<DepthFrame>> {
+    subscribe_default(transport, authority, SensorKind::DepthImage).await
+}
+
+pub async fn subscribe_segmentation(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<LabelFrame>> {
+    subscribe_default(transport, authority, SensorKind::SemanticSegmentationImage).await
+}
+
+pub async fn subscribe_gnss(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<GnssFix>> {
+    subscribe_default(transport, authority, SensorKind::GnssMeasurement).await
+}
+
+pub async fn subscribe_imu(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<ImuMeasurementSerDe>> {
+    subscribe_default(transport, authority, SensorKind::ImuMeasurement).await
+}
+
+pub async fn subscribe_collision(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<CollisionEventSerDe>> {
+    subscribe_default(transport, authority, SensorKind::Collision).await
+}
+
+pub async fn subscribe_lane_invasion(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<LaneInvasionEventSerDe>> {
+    subscribe_default(transport, authority, SensorKind::LaneInvasion).await
+}
+
+pub async fn subscribe_obstacle_detection(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<ObstacleDetectionEventSerDe>> {
+    subscribe_default(transport, authority, SensorKind::ObstacleDetection).await
+}
+
+/// Subscribes to the speed of the ego vehicle.
+pub async fn subscribe_speed(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<VehicleSpeed>> {
+    subscribe(
+        transport,
+        bridge_topic(authority, RESOURCE_VELOCITY_STATUS),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await
+}
+
+/// Subscribes to the manifest of the bridged sensors, published every few seconds.
+pub async fn subscribe_manifest(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<SensorManifest>> {
+    subscribe(
+        transport,
+        bridge_topic(authority, RESOURCE_SENSOR_MANIFEST),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await
+}
+
+/// Subscribes to the health of the bridged sensors, published every
+/// `--diagnostics-period`.
+pub async fn subscribe_diagnostics(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<DiagnosticsReport>> {
+    subscribe(
+        transport,
+        bridge_topic(authority, RESOURCE_SENSOR_DIAGNOSTICS),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await
+}
+
+/// Subscribes to the transforms of the bridged sensors relative to the ego vehicle,
+/// published with the manifest.
+pub async fn subscribe_transform_tree(
+    transport: Arc<dyn UTransport>,
+    authority: &str,
+) -> Result<SensorStream<TransformTree>> {
+    subscribe(
+        transport,
+        bridge_topic(authority, RESOURCE_TRANSFORM_TREE),
+        DEFAULT_STREAM_CAPACITY,
+    )
+    .await
+}
+
+// Invokes the transform method `resource_id` of the bridge of `authority`, decoding its
+// JSON response
+async fn invoke_transform_method<T: serde::de::DeserializeOwned>(
+    rpc_client: &dyn RpcClient,
+    authority: &str,
+    resource_id: u16,
+    request: Option<UPayload>,
+) -> Result<T> {
+    let options = CallOptions::for_rpc_request(TRANSFORM_REQUEST_TTL_MS, None, None, None);
+    let method = UUri::try_from_parts(
+        authority,
+        TRANSFORM_SERVICE_ID,
+        TRANSFORM_SERVICE_VERSION,
+        resource_id,
+    )?;
+    let response = rpc_client
+        .invoke_method(method, options, request)
+        .await?
+        .ok_or("empty transform response")?;
+    Ok(serde_json::from_slice(&response.payload())?)
+}
+
+/// Asks the bridge of `authority` for the pose of the frame `from` in the frame `to`,
+/// e.g. of a sensor role name in `vehicle` or `world`.
+pub async fn lookup_transform(
+    rpc_client: &dyn RpcClient,
+    authority: &str,
+    from: &str,
+    to: &str,
+) -> Result<TransformLookup> {
+    let request = TransformRequest {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    let payload = UPayload::new(
+        serde_json::to_vec(&request)?,
+        UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+    );
+    invoke_transform_method(
+        rpc_client,
+        authority,
+        RESOURCE_LOOKUP_TRANSFORM,
+        Some(payload),
+    )
+    .await
+}
+
+/// Asks the bridge of `authority` for the transform tree, instead of waiting for its next
+/// publication.
+pub async fn get_transform_tree(
+    rpc_client: &dyn RpcClient,
+    authority: &str,
+) -> Result<TransformTree> {
+    invoke_transform_method(rpc_client, authority, RESOURCE_GET_TRANSFORM_TREE, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{LIDAR_FIELDS, PointDatatype};
+
+    #[test]
+    fn test_frame_gaps() {
+        let mut gaps = FrameGaps::default();
+        let observed: Vec<FrameOrder> = [30, 33, 36, 42, 41, 45, 54]
+            .into_iter()
+            .map(|frame| gaps.observe(frame))
+            .collect();
+        assert_eq!(
+            observed,
+            vec![
+                FrameOrder::InSequence,
+                FrameOrder::InSequence,
+                FrameOrder::InSequence,
+                FrameOrder::Gap(1),
+                FrameOrder::OutOfOrder,
+                FrameOrder::InSequence,
+                FrameOrder::Gap(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_payloads() {
+        let mut cloud = PointCloud::new(7, 0.7, &LIDAR_FIELDS);
+        cloud.push(&[1.0, 2.0, 3.0, 0.5]);
+        let packed = cloud.encode(PointDatatype::F32).unwrap();
+        let decoded =
+            <LidarFrame as SensorPayload>::decode(&packed, UPayloadFormat::UPAYLOAD_FORMAT_RAW)
+                .unwrap();
+        assert_eq!(decoded, cloud);
+        assert_eq!(decoded.stamp(), Some(Stamp::new(7, 0.7)));
+        assert!(
+            <LidarFrame as SensorPayload>::decode(b"{}", UPayloadFormat::UPAYLOAD_FORMAT_JSON)
+                .is_err()
+        );
+
+        let fix = GnssFix::decode(
+            br#"{"frame": 3, "timestamp": 0.3, "latitude": 49.0, "longitude": 8.4, "altitude": 115.0}"#,
+            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+        )
+        .unwrap();
+        assert_eq!((fix.frame, fix.latitude), (3, 49.0));
+        assert_eq!(
+            json_stamp(br#"{"frame": 5, "timestamp": 0.25, "accelerometer": {}}"#),
+            Some(Stamp::new(5, 0.25))
+        );
+        assert_eq!(json_stamp(br#"{"timestamp": 0.25}"#), None);
+
+        let speed = VehicleSpeed::decode(b"36", UPayloadFormat::UPAYLOAD_FORMAT_TEXT).unwrap();
+        assert_eq!(speed.meters_per_second(), 10.0);
+        assert!(VehicleSpeed::decode(b"fast", UPayloadFormat::UPAYLOAD_FORMAT_TEXT).is_err());
+        let speed = VehicleSpeed::decode(
+            br#"{"velocity": 45.0, "frame": 250, "elapsed_seconds": 12.5}"#,
+            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+        )
+        .unwrap();
+        assert_eq!(
+            (speed.kmh, speed.stamp),
+            (45.0, Some(Stamp::new(250, 12.5)))
+        );
+
+        assert_eq!(
+            sensor_topic(SensorKind::LidarMeasurement).to_uri(false),
+            "//EGOVehicle/0/2/8015"
+        );
+        assert_eq!(
+            vehicle_sensor_topic("FollowerVehicle", SensorKind::LidarMeasurement).to_uri(false),
+            "//FollowerVehicle/0/2/8015"
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use super::{
+    CameraFrame, DEFAULT_STREAM_CAPACITY, DepthFrame, LabelFrame, Result, SensorPayload, Stamp,
+    decode_stamped, sensor_topic,
+};
+use crate::chunking::ChunkedListener;
+use crate::codec::PointCloud;
+use crate::discovery::SensorKind;
+use crate::sensors::GnssFix;
+use async_trait::async_trait;
+use carla_data_serde::ImuMeasurementSerDe;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use up_rust::{UListener, UMessage, UPayloadFormat, UTransport, UUri};
+
+/// How the frames of several topics are matched into tuples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncPolicy {
+    /// Frames of the same CARLA frame, as every sensor ticks with the world in
+    /// synchronous mode.
+    Exact,
+    /// Frames whose timestamps are at most `slop` seconds apart.
+    Approximate { slop: f64 },
+}
+
+/// Settings of a [`Synchronizer`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SyncConfig {
+    pub policy: SyncPolicy,
+    /// Frames buffered per topic while waiting for a match; the oldest is dropped
+    /// beyond.
+    pub queue_size: usize,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            policy: SyncPolicy::Exact,
+            queue_size: 10,
+        }
+    }
+}
+
+/// Counters of a [`Synchronizer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    /// Frames added to the queues.
+    pub received: u64,
+    /// Tuples formed.
+    pub matched: u64,
+    /// Frames dropped without being matched, too old or beyond the queue size.
+    pub dropped: u64,
+    /// Payloads that could not be decoded or carry no stamp.
+    pub invalid: u64,
+    /// Tuples dropped because the consumer of a [`SyncStream`] was too slow.
+    pub lagged: u64,
+}
+
+/// Matches the frames of N topics by their [`Stamp`], like the ROS `message_filters`
+/// synchronisers.
+///
+/// Frames are pushed as they arrive, in any order across topics; every push returns
+/// the tuples it completes, with one frame per topic in topic order. A frame that can
+/// no longer be part of a tuple is dropped.
+///
+/// With [`SyncPolicy::Approximate`], a tuple is formed around the newest of the oldest
+/// queued frames of every topic: each topic contributes its frame nearest to it, once
+/// a later frame shows that no nearer one will come.
+pub struct Synchronizer<T> {
+    config: SyncConfig,
+    queues: Vec<VecDeque<(Stamp, T)>>,
+    stats: SyncStats,
+}
+
+impl<T> Synchronizer<T> {
+    pub fn new(topics: usize, config: SyncConfig) -> Self {
+        Self {
+            config,
+            queues: (0..topics).map(|_| VecDeque::new()).collect(),
+            stats: SyncStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> SyncStats {
+        self.stats
+    }
+
+    /// Adds a frame of the topic at `index`, and returns the tuples it completes.
+    pub fn push(&mut self, index: usize, stamp: Stamp, frame: T) -> Vec<Vec<T>> {
+        let queue = &mut self.queues[index];
+        if queue.len() >= self.config.queue_size.max(1) {
+            queue.pop_front();
+            self.stats.dropped += 1;
+        }
+        // Frames arriving late are put back in order
+        let position = queue.partition_point(|(queued, _)| queued.timestamp <= stamp.timestamp);
+        queue.insert(position, (stamp, frame));
+        self.stats.received += 1;
+
+        let mut tuples = Vec::new();
+        loop {
+            let tuple = match self.config.policy {
+                SyncPolicy::Exact => self.match_exact(),
+                SyncPolicy::Approximate { slop } => self.match_approximate(slop),
+            };
+            let Some(tuple) = tuple else {
+                break;
+            };
+            self.stats.matched += 1;
+            tuples.push(tuple);
+        }
+        tuples
+    }
+
+    fn match_exact(&mut self) -> Option<Vec<T>> {
+        loop {
+            // The topic with the newest head has no older frame to match
+            let newest = self
+                .queues
+                .iter()
+                .map(|queue| queue.front().map(|(stamp, _)| stamp.frame))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .max()?;
+
+            let mut complete = true;
+            for queue in &mut self.queues {
+                while queue.front().is_some_and(|(stamp, _)| stamp.frame < newest) {
+                    queue.pop_front();
+                    self.stats.dropped += 1;
+                }
+                match queue.front() {
+                    None => return None,
+                    Some((stamp, _)) => complete &= stamp.frame == newest,
+                }
+            }
+            if complete {
+                return Some(self.take(&vec![0; self.queues.len()]));
+            }
+        }
+    }
+
+    fn match_approximate(&mut self, slop: f64) -> Option<Vec<T>> {
+        loop {
+            let heads = self
+                .queues
+                .iter()
+                .map(|queue| queue.front().map(|(stamp, _)| stamp.timestamp))
+                .collect::<Option<Vec<_>>>()?;
+            let (oldest, first) = heads
+                .iter()
+                .copied()
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))?;
+            let pivot = heads.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+            // Every frame of the pivot topic is too late for the oldest head
+            if pivot - first > slop {
+                self.queues[oldest].pop_front();
+                self.stats.dropped += 1;
+                continue;
+            }
+
+            let mut nearest = Vec::with_capacity(self.queues.len());
+            for queue in &self.queues {
+                let (index, timestamp) = queue
+                    .iter()
+                    .map(|(stamp, _)| stamp.timestamp)
+                    .enumerate()
+                    .min_by(|a, b| (a.1 - pivot).abs().total_cmp(&(b.1 - pivot).abs()))?;
+                if index + 1 == queue.len() && timestamp < pivot {
+                    // The next frame of this topic may be nearer
+                    return None;
+                }
+                nearest.push((index, timestamp));
+            }
+
+            let (min, max) = nearest
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, t)| {
+                    (min.min(*t), max.max(*t))
+                });
+            let indices: Vec<usize> = if max - min <= slop {
+                nearest.iter().map(|(index, _)| *index).collect()
+            } else {
+                vec![0; self.queues.len()]
+            };
+            return Some(self.take(&indices));
+        }
+    }
+
+    // Takes the frame at `indices[i]` of every queue i, dropping the older ones
+    fn take(&mut self, indices: &[usize]) -> Vec<T> {
+        self.queues
+            .iter_mut()
+            .zip(indices)
+            .map(|(queue, &index)| {
+                self.stats.dropped += index as u64;
+                queue.drain(..index);
+                queue.pop_front().expect("matched frame").1
+            })
+            .collect()
+    }
+}
+
+/// A frame of one of the synchronised topics.
+pub enum SensorFrame {
+    /// Lidar, radar or semantic lidar points.
+    PointCloud(PointCloud),
+    Camera(CameraFrame),
+    Depth(DepthFrame),
+    Labels(LabelFrame),
+    Gnss(GnssFix),
+    Imu(ImuMeasurementSerDe),
+}
+
+impl SensorFrame {
+    /// Decodes a payload of a sensor of `kind`; event sensors cannot be synchronised.
+    pub fn decode(kind: SensorKind, payload: &[u8], format: UPayloadFormat) -> Result<Self> {
+        Ok(match kind {
+            SensorKind::LidarMeasurement
+            | SensorKind::RadarMeasurement
+            | SensorKind::SemanticLidarMeasurement => {
+                Self::PointCloud(SensorPayload::decode(payload, format)?)
+            }
+            SensorKind::Image => Self::Camera(SensorPayload::decode(payload, format)?),
+            SensorKind::DepthImage => Self::Depth(SensorPayload::decode(payload, format)?),
+            SensorKind::SemanticSegmentationImage => {
+                Self::Labels(SensorPayload::decode(payload, format)?)
+            }
+            SensorKind::GnssMeasurement => Self::Gnss(SensorPayload::decode(payload, format)?),
+            SensorKind::ImuMeasurement => Self::Imu(SensorPayload::decode(payload, format)?),
+            other => return Err(format!("{other:?} events cannot be synchronised").into()),
+        })
+    }
+
+    /// Stamp of the frame, when its payload carries one.
+    pub fn stamp(&self) -> Option<Stamp> {
+        match self {
+            Self::PointCloud(cloud) => cloud.stamp(),
+            Self::Camera(image) => image.stamp(),
+            Self::Depth(depth) => depth.stamp(),
+            Self::Labels(labels) => labels.stamp(),
+            Self::Gnss(fix) => fix.stamp(),
+            Self::Imu(imu) => imu.stamp(),
+        }
+    }
+}
+
+/// A topic to synchronise, with the kind of sensor publishing on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncTopic {
+    pub kind: SensorKind,
+    pub topic: UUri,
+}
+
+impl SyncTopic {
+    pub fn new(kind: SensorKind, topic: UUri) -> Self {
+        Self { kind, topic }
+    }
+}
+
+/// Topic of the first sensor of the kind.
+impl From<SensorKind> for SyncTopic {
+    fn from(kind: SensorKind) -> Self {
+        Self::new(kind, sensor_topic(kind))
+    }
+}
+
+type OnMatch = Box<dyn Fn(Vec<SensorFrame>) + Send + Sync>;
+
+struct Shared {
+    synchronizer: Mutex<Synchronizer<SensorFrame>>,
+    invalid: AtomicU64,
+    on_match: OnMatch,
+}
+
+// Decodes the payloads of the topic at `index` into the shared synchronizer
+struct SyncListener {
+    index: usize,
+    kind: SensorKind,
+    topic: String,
+    shared: Arc<Shared>,
+}
+
+impl SyncListener {
+    fn handle(&self, msg: &UMessage) {
+        let decoded = decode_stamped(
+            msg,
+            |payload, format| SensorFrame::decode(self.kind, payload, format),
+            SensorFrame::stamp,
+        );
+        let (frame, stamp) = match decoded {
+            Ok((frame, Some(stamp))) => (frame, stamp),
+            Ok((_, None)) => {
+                self.shared.invalid.fetch_add(1, Ordering::Relaxed);
+                log::warn!("{}: payload without frame and timestamp", self.topic);
+                return;
+            }
+            Err(e) => {
+                self.shared.invalid.fetch_add(1, Ordering::Relaxed);
+                log::warn!("{}: undecodable payload: {e}", self.topic);
+                return;
+            }
+        };
+
+        let tuples = self
+            .shared
+            .synchronizer
+            .lock()
+            .unwrap()
+            .push(self.index, stamp, frame);
+        for tuple in tuples {
+            (self.shared.on_match)(tuple);
+        }
+    }
+}
+
+#[async_trait]
+impl UListener for SyncListener {
+    async fn on_receive(&self, msg: UMessage) {
+        self.handle(&msg);
+    }
+}
+
+/// Registration of a synchronizer on its topics.
+pub struct Synchronized {
+    transport: Arc<dyn UTransport>,
+    listeners: Vec<(UUri, Arc<dyn UListener>)>,
+    shared: Arc<Shared>,
+}
+
+impl Synchronized {
+    pub fn stats(&self) -> SyncStats {
+        SyncStats {
+            invalid: self.shared.invalid.load(Ordering::Relaxed),
+            ..self.shared.synchronizer.lock().unwrap().stats()
+        }
+    }
+
+    /// Unregisters the synchronizer from the transport.
+    pub async fn close(self) -> Result<()> {
+        for (topic, listener) in self.listeners {
+            self.transport
+                .unregister_listener(&topic, None, listener)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Subscribes to `topics` and calls `on_match` with every tuple of frames, in topic
+/// order, from the thread of the transport.
+pub async fn synchronize_with<F>(
+    transport: Arc<dyn UTransport>,
+    topics: Vec<SyncTopic>,
+    config: SyncConfig,
+    on_match: F,
+) -> Result<Synchronized>
+where
+    F: Fn(Vec<SensorFrame>) + Send + Sync + 'static,
+{
+    if topics.len() < 2 {
+        return Err("at least two topics are needed to synchronise".into());
+    }
+    if let SyncPolicy::Approximate { slop } = config.policy {
+        if !(slop.is_finite() && slop >= 0.0) {
+            return Err(format!("slop must be a non-negative duration, got {slop}").into());
+        }
+    }
+
+    let shared = Arc::new(Shared {
+        synchronizer: Mutex::new(Synchronizer::new(topics.len(), config)),
+        invalid: AtomicU64::new(0),
+        on_match: Box::new(on_match),
+    });
+    let mut synchronized = Synchronized {
+        transport: Arc::clone(&transport),
+        listeners: Vec::with_capacity(topics.len()),
+        shared: Arc::clone(&shared),
+    };
+
+    for (index, SyncTopic { kind, topic }) in topics.into_iter().enumerate() {
+        let decoder = SyncListener {
+            index,
+            kind,
+            topic: topic.to_uri(false),
+            shared: Arc::clone(&shared),
+        };
+        let listener: Arc<dyn UListener> = Arc::new(ChunkedListener::new(Arc::new(decoder)));
+        if let Err(e) = transport
+            .register_listener(&topic, None, Arc::clone(&listener))
+            .await
+        {
+            // Leave no half-registered synchronizer behind
+            let _ = synchronized.close().await;
+            return Err(e.into());
+        }
+        synchronized.listeners.push((topic, listener));
+    }
+    Ok(synchronized)
+}
+
+/// Subscribes to `topics` and yields every tuple of frames, in topic order.
+///
+/// ```ignore
+/// let topics = vec![SensorKind::Image.into(), SensorKind::LidarMeasurement.into()];
+/// let mut fused = synchronize(transport, topics, SyncConfig::default()).await?;
+/// while let Some(tuple) = fused.next().await {
+///     if let [SensorFrame::Camera(image), SensorFrame::PointCloud(cloud)] = &tuple[..] {
+///         println!("frame {}: {} points", image.header.frame, cloud.len());
+///     }
+/// }
+/// ```
+pub async fn synchronize(
+    transport: Arc<dyn UTransport>,
+    topics: Vec<SyncTopic>,
+    config: SyncConfig,
+) -> Result<SyncStream> {
+    let (sender, receiver) = mpsc::channel(DEFAULT_STREAM_CAPACITY);
+    let lagged = Arc::new(AtomicU64::new(0));
+    let synchronized = synchronize_with(transport, topics, config, {
+        let lagged = Arc::clone(&lagged);
+        move |tuple| {
+            if let Err(mpsc::error::TrySendError::Full(_)) = sender.try_send(tuple) {
+                lagged.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    })
+    .await?;
+
+    Ok(SyncStream {
+        receiver,
+        lagged,
+        synchronized,
+    })
+}
+
+/// Tuples of synchronised frames, as an async [`Stream`].
+pub struct SyncStream {
+    receiver: mpsc::Receiver<Vec<SensorFrame>>,
+    lagged: Arc<AtomicU64>,
+    synchronized: Synchronized,
+}
+
+impl SyncStream {
+    /// Waits for the next tuple; `None` once the stream is closed.
+    pub async fn next(&mut self) -> Option<Vec<SensorFrame>> {
+        self.receiver.recv().await
+    }
+
+    pub fn stats(&self) -> SyncStats {
+        SyncStats {
+            lagged: self.lagged.load(Ordering::Relaxed),
+            ..self.synchronized.stats()
+        }
+    }
+
+    /// Unregisters the stream from the transport.
+    pub async fn close(self) -> Result<()> {
+        self.synchronized.close().await
+    }
+}
+
+impl Stream for SyncStream {
+    type Item = Vec<SensorFrame>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_all(
+        synchronizer: &mut Synchronizer<&'static str>,
+        frames: &[(usize, u64, f64, &'static str)],
+    ) -> Vec<Vec<&'static str>> {
+        frames
+            .iter()
+            .flat_map(|&(index, frame, timestamp, name)| {
+                synchronizer.push(index, Stamp::new(frame, timestamp), name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_policy() {
+        // A camera every frame and a lidar every other frame, which misses frame 4
+        let mut synchronizer = Synchronizer::new(2, SyncConfig::default());
+        let tuples = push_all(
+            &mut synchronizer,
+            &[
+                (0, 1, 0.05, "camera 1"),
+                (0, 2, 0.10, "camera 2"),
+                (1, 2, 0.10, "lidar 2"),
+                (0, 3, 0.15, "camera 3"),
+                (0, 4, 0.20, "camera 4"),
+                (0, 5, 0.25, "camera 5"),
+                (0, 6, 0.30, "camera 6"),
+                (1, 6, 0.30, "lidar 6"),
+            ],
+        );
+        assert_eq!(
+            tuples,
+            vec![vec!["camera 2", "lidar 2"], vec!["camera 6", "lidar 6"]]
+        );
+        let stats = synchronizer.stats();
+        assert_eq!((stats.received, stats.matched, stats.dropped), (8, 2, 4));
+    }
+
+    #[test]
+    fn test_approximate_policy() {
+        let config = SyncConfig {
+            policy: SyncPolicy::Approximate { slop: 0.02 },
+            queue_size: 3,
+        };
+        let mut synchronizer = Synchronizer::new(2, config);
+        let tuples = push_all(
+            &mut synchronizer,
+            &[
+                // An IMU at 100 Hz and a camera at 20 Hz with a 13 ms offset
+                (0, 0, 0.000, "imu 0"),
+                (0, 0, 0.010, "imu 1"),
+                (1, 0, 0.013, "camera 0"),
+                // Waits for the next IMU frame, which might be nearer
+                (0, 0, 0.020, "imu 2"),
+                (0, 0, 0.030, "imu 3"),
+                (0, 0, 0.040, "imu 4"),
+                (0, 0, 0.050, "imu 5"),
+                (0, 0, 0.060, "imu 6"),
+                // Too late for the IMU frames before it, which overflowed the queue
+                (1, 0, 0.063, "camera 1"),
+                (0, 0, 0.070, "imu 7"),
+            ],
+        );
+        assert_eq!(
+            tuples,
+            vec![vec!["imu 1", "camera 0"], vec!["imu 6", "camera 1"]]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod image;
+mod point_cloud;
+
+pub use image::*;
+pub use point_cloud::*;
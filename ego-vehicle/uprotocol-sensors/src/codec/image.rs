@@ -0,0 +1,547 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::{self, FilterType};
+use image::{ExtendedColorType, ImageBuffer, ImageEncoder as _, ImageFormat, Rgba};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use up_rust::UPayloadFormat;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// First bytes of every binary image payload.
+pub const IMAGE_MAGIC: [u8; 4] = *b"CIMG";
+/// Version of the binary image layout.
+pub const IMAGE_VERSION: u8 = 1;
+/// Size of the header in front of the pixels (or of the compressed image).
+pub const IMAGE_HEADER_LEN: usize = 32;
+
+/// Default JPEG quality (1 to 100).
+pub const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+/// Layout of the body following the header of a binary image payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PixelFormat {
+    /// Uncompressed pixels as delivered by CARLA, 4 bytes per pixel, row by row.
+    Bgra8 = 0,
+    Jpeg = 1,
+    Png = 2,
+    /// Depth of every pixel in meters, 4 bytes (f32) per pixel, row by row.
+    DepthF32 = 3,
+    /// CARLA semantic tag of every pixel, 1 byte per pixel, row by row.
+    Label8 = 4,
+}
+
+impl TryFrom<u8> for PixelFormat {
+    type Error = String;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PixelFormat::Bgra8),
+            1 => Ok(PixelFormat::Jpeg),
+            2 => Ok(PixelFormat::Png),
+            3 => Ok(PixelFormat::DepthF32),
+            4 => Ok(PixelFormat::Label8),
+            _ => Err(format!("unknown pixel format {value}")),
+        }
+    }
+}
+
+/// Compression level of PNG images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl From<PngCompression> for CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => CompressionType::Fast,
+            PngCompression::Default => CompressionType::Default,
+            PngCompression::Best => CompressionType::Best,
+        }
+    }
+}
+
+/// How the image sensor is published.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageEncoding {
+    /// `ImageEventSerBorrowed` as JSON, pixels included (the historical format).
+    #[default]
+    Json,
+    /// Header followed by the BGRA pixels.
+    Raw,
+    /// Header followed by a JPEG image of the given quality (1 to 100).
+    Jpeg(u8),
+    /// Header followed by a PNG image.
+    Png(PngCompression),
+}
+
+impl ImageEncoding {
+    pub fn payload_format(&self) -> UPayloadFormat {
+        match self {
+            ImageEncoding::Json => UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            _ => UPayloadFormat::UPAYLOAD_FORMAT_RAW,
+        }
+    }
+}
+
+impl FromStr for ImageEncoding {
+    type Err = String;
+
+    /// Parses `json`, `raw`, `jpeg[:<quality>]` or `png[:fast|default|best]`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, option) = match s.split_once(':') {
+            Some((name, option)) => (name, Some(option)),
+            None => (s, None),
+        };
+        match (name, option) {
+            ("json", None) => Ok(ImageEncoding::Json),
+            ("raw", None) => Ok(ImageEncoding::Raw),
+            ("jpeg" | "jpg", None) => Ok(ImageEncoding::Jpeg(DEFAULT_JPEG_QUALITY)),
+            ("jpeg" | "jpg", Some(quality)) => match quality.parse::<u8>() {
+                Ok(quality @ 1..=100) => Ok(ImageEncoding::Jpeg(quality)),
+                _ => Err(format!("JPEG quality must be 1 to 100, got '{quality}'")),
+            },
+            ("png", None) => Ok(ImageEncoding::Png(PngCompression::Default)),
+            ("png", Some("fast")) => Ok(ImageEncoding::Png(PngCompression::Fast)),
+            ("png", Some("default")) => Ok(ImageEncoding::Png(PngCompression::Default)),
+            ("png", Some("best")) => Ok(ImageEncoding::Png(PngCompression::Best)),
+            ("png", Some(level)) => Err(format!(
+                "unknown PNG compression '{level}' (fast, default or best)"
+            )),
+            _ => Err(format!(
+                "unknown image encoding '{s}' (json, raw, jpeg[:QUALITY] or png[:COMPRESSION])"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ImageEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageEncoding::Json => f.write_str("json"),
+            ImageEncoding::Raw => f.write_str("raw"),
+            ImageEncoding::Jpeg(quality) => write!(f, "jpeg:{quality}"),
+            ImageEncoding::Png(PngCompression::Fast) => f.write_str("png:fast"),
+            ImageEncoding::Png(PngCompression::Default) => f.write_str("png:default"),
+            ImageEncoding::Png(PngCompression::Best) => f.write_str("png:best"),
+        }
+    }
+}
+
+/// Header of a binary image payload, little endian:
+///
+/// | Offset | Size | Field |
+/// |--------|------|-------|
+/// | 0 | 4 | magic `CIMG` |
+/// | 4 | 1 | version (1) |
+/// | 5 | 1 | pixel format (0 = BGRA8, 1 = JPEG, 2 = PNG, 3 = f32 depth, 4 = u8 labels) |
+/// | 6 | 2 | reserved |
+/// | 8 | 4 | width (u32) |
+/// | 12 | 4 | height (u32) |
+/// | 16 | 8 | frame (u64) |
+/// | 24 | 8 | timestamp in seconds (f64) |
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageHeader {
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub frame: u64,
+    pub timestamp: f64,
+}
+
+impl ImageHeader {
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&IMAGE_MAGIC);
+        out.push(IMAGE_VERSION);
+        out.push(self.format as u8);
+        out.extend_from_slice(&[0; 2]);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+
+    /// Reads the header at the start of `bytes`; the body follows [`IMAGE_HEADER_LEN`].
+    pub fn read(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < IMAGE_HEADER_LEN || bytes[0..4] != IMAGE_MAGIC {
+            return Err("not a binary image payload".into());
+        }
+        if bytes[4] != IMAGE_VERSION {
+            return Err(format!("unsupported image payload version {}", bytes[4]).into());
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Ok(Self {
+            format: PixelFormat::try_from(bytes[5])?,
+            width: u32_at(8),
+            height: u32_at(12),
+            frame: u64_at(16),
+            timestamp: f64::from_bits(u64_at(24)),
+        })
+    }
+}
+
+/// Camera frame to encode, borrowing its BGRA pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageFrame<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub frame: u64,
+    pub timestamp: f64,
+    pub bgra: &'a [u8],
+}
+
+/// Encodes camera frames into binary image payloads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageEncoder {
+    pub encoding: ImageEncoding,
+    /// Size factor applied before encoding, in (0, 1]; 1 keeps the sensor resolution.
+    pub scale: f64,
+}
+
+impl Default for ImageEncoder {
+    fn default() -> Self {
+        Self {
+            encoding: ImageEncoding::default(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl ImageEncoder {
+    /// Header and body of `frame`. The `Json` encoding has no binary form and is
+    /// encoded by the caller from the CARLA event.
+    pub fn encode(&self, frame: &ImageFrame<'_>) -> Result<Vec<u8>> {
+        check_size(frame)?;
+
+        let (width, height) = self.scaled_size(frame.width, frame.height);
+        let resized;
+        let bgra = if (width, height) == (frame.width, frame.height) {
+            frame.bgra
+        } else {
+            let source =
+                ImageBuffer::<Rgba<u8>, _>::from_raw(frame.width, frame.height, frame.bgra)
+                    .ok_or("image buffer too small")?;
+            // Channel order does not matter to the filter
+            resized = imageops::resize(&source, width, height, FilterType::Triangle);
+            resized.as_raw().as_slice()
+        };
+
+        let format = match self.encoding {
+            ImageEncoding::Json => return Err("JSON images are not binary payloads".into()),
+            ImageEncoding::Raw => PixelFormat::Bgra8,
+            ImageEncoding::Jpeg(_) => PixelFormat::Jpeg,
+            ImageEncoding::Png(_) => PixelFormat::Png,
+        };
+        let mut out = Vec::with_capacity(IMAGE_HEADER_LEN + bgra.len());
+        ImageHeader {
+            format,
+            width,
+            height,
+            frame: frame.frame,
+            timestamp: frame.timestamp,
+        }
+        .write(&mut out);
+
+        match self.encoding {
+            ImageEncoding::Json | ImageEncoding::Raw => out.extend_from_slice(bgra),
+            ImageEncoding::Jpeg(quality) => {
+                JpegEncoder::new_with_quality(&mut out, quality).write_image(
+                    &bgra_to_rgb(bgra),
+                    width,
+                    height,
+                    ExtendedColorType::Rgb8,
+                )?;
+            }
+            ImageEncoding::Png(compression) => {
+                PngEncoder::new_with_quality(&mut out, compression.into(), PngFilter::Adaptive)
+                    .write_image(&bgra_to_rgb(bgra), width, height, ExtendedColorType::Rgb8)?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if !(self.scale > 0.0 && self.scale < 1.0) {
+            return (width, height);
+        }
+        let scale = |size: u32| ((size as f64 * self.scale).round() as u32).max(1);
+        (scale(width), scale(height))
+    }
+}
+
+/// Frame reconstructed from a binary image payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedImage {
+    /// Header as published; `format` tells how the image travelled.
+    pub header: ImageHeader,
+    /// BGRA pixels, row by row (alpha is 255 for JPEG and PNG images).
+    pub bgra: Vec<u8>,
+}
+
+/// Reconstructs the frame of a binary image payload, whatever its pixel format.
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage> {
+    let header = ImageHeader::read(bytes)?;
+    let body = &bytes[IMAGE_HEADER_LEN..];
+
+    let bgra = match header.format {
+        PixelFormat::Bgra8 => {
+            let expected = header.width as usize * header.height as usize * 4;
+            if body.len() != expected {
+                return Err(format!("expected {expected} pixel bytes, got {}", body.len()).into());
+            }
+            body.to_vec()
+        }
+        PixelFormat::Jpeg | PixelFormat::Png => {
+            let format = match header.format {
+                PixelFormat::Jpeg => ImageFormat::Jpeg,
+                _ => ImageFormat::Png,
+            };
+            let rgba = image::load_from_memory_with_format(body, format)?.to_rgba8();
+            if rgba.dimensions() != (header.width, header.height) {
+                return Err("image size does not match its header".into());
+            }
+            let mut pixels = rgba.into_raw();
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+            pixels
+        }
+        PixelFormat::DepthF32 | PixelFormat::Label8 => {
+            return Err(format!("{:?} payloads are not color images", header.format).into());
+        }
+    };
+
+    Ok(DecodedImage { header, bgra })
+}
+
+/// Depth in meters of a pixel of a CARLA depth camera, encoded over its red, green and
+/// blue channels (up to 1000 m).
+pub fn carla_depth(bgra: &[u8]) -> f32 {
+    let encoded = bgra[2] as u32 + bgra[1] as u32 * 256 + bgra[0] as u32 * 256 * 256;
+    (encoded as f64 / (256.0 * 256.0 * 256.0 - 1.0) * 1000.0) as f32
+}
+
+/// Encodes a depth camera frame as a header followed by the depth of every pixel
+/// ([`PixelFormat::DepthF32`]).
+pub fn encode_depth(frame: &ImageFrame<'_>) -> Result<Vec<u8>> {
+    check_size(frame)?;
+    let mut out = Vec::with_capacity(IMAGE_HEADER_LEN + frame.bgra.len());
+    header_of(frame, PixelFormat::DepthF32).write(&mut out);
+    for pixel in frame.bgra.chunks_exact(4) {
+        out.extend_from_slice(&carla_depth(pixel).to_le_bytes());
+    }
+    Ok(out)
+}
+
+/// Encodes a semantic segmentation camera frame, whose red channel holds the CARLA
+/// semantic tag of every pixel, as a header followed by the tags: uncompressed
+/// ([`PixelFormat::Label8`]) with the `Raw` encoding, or as a grayscale PNG image.
+pub fn encode_labels(frame: &ImageFrame<'_>, encoding: ImageEncoding) -> Result<Vec<u8>> {
+    check_size(frame)?;
+    let labels: Vec<u8> = frame.bgra.chunks_exact(4).map(|p| p[2]).collect();
+    let mut out = Vec::with_capacity(IMAGE_HEADER_LEN + labels.len());
+    match encoding {
+        ImageEncoding::Raw => {
+            header_of(frame, PixelFormat::Label8).write(&mut out);
+            out.extend_from_slice(&labels);
+        }
+        ImageEncoding::Png(compression) => {
+            header_of(frame, PixelFormat::Png).write(&mut out);
+            PngEncoder::new_with_quality(&mut out, compression.into(), PngFilter::Adaptive)
+                .write_image(&labels, frame.width, frame.height, ExtendedColorType::L8)?;
+        }
+        _ => return Err(format!("labels cannot be encoded as {encoding} (raw or png)").into()),
+    }
+    Ok(out)
+}
+
+/// Reads a depth payload back: its header and the depth of every pixel in meters.
+pub fn decode_depth(bytes: &[u8]) -> Result<(ImageHeader, Vec<f32>)> {
+    let header = ImageHeader::read(bytes)?;
+    if header.format != PixelFormat::DepthF32 {
+        return Err(format!("expected a depth payload, got {:?}", header.format).into());
+    }
+    let body = &bytes[IMAGE_HEADER_LEN..];
+    if body.len() != header.width as usize * header.height as usize * 4 {
+        return Err("depth payload size does not match its header".into());
+    }
+    let depth = body
+        .chunks_exact(4)
+        .map(|d| f32::from_le_bytes(d.try_into().unwrap()))
+        .collect();
+    Ok((header, depth))
+}
+
+/// Reads a labels payload back, raw or PNG: its header and the tag of every pixel.
+pub fn decode_labels(bytes: &[u8]) -> Result<(ImageHeader, Vec<u8>)> {
+    let header = ImageHeader::read(bytes)?;
+    let body = &bytes[IMAGE_HEADER_LEN..];
+    let labels = match header.format {
+        PixelFormat::Label8 => body.to_vec(),
+        PixelFormat::Png => image::load_from_memory_with_format(body, ImageFormat::Png)?
+            .to_luma8()
+            .into_raw(),
+        format => return Err(format!("expected a labels payload, got {format:?}").into()),
+    };
+    if labels.len() != header.width as usize * header.height as usize {
+        return Err("labels payload size does not match its header".into());
+    }
+    Ok((header, labels))
+}
+
+fn check_size(frame: &ImageFrame<'_>) -> Result<()> {
+    let expected = frame.width as usize * frame.height as usize * 4;
+    if frame.bgra.len() != expected {
+        return Err(format!(
+            "{}x{} image has {} bytes, expected {expected}",
+            frame.width,
+            frame.height,
+            frame.bgra.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn header_of(frame: &ImageFrame<'_>, format: PixelFormat) -> ImageHeader {
+    ImageHeader {
+        format,
+        width: frame.width,
+        height: frame.height,
+        frame: frame.frame,
+        timestamp: frame.timestamp,
+    }
+}
+
+fn bgra_to_rgb(bgra: &[u8]) -> Vec<u8> {
+    bgra.chunks_exact(4)
+        .flat_map(|p| [p[2], p[1], p[0]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Horizontal gradient, so JPEG stays close to the original
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let x = (i % width * 255 / width) as u8;
+                [x, 128, 255 - x, 255]
+            })
+            .collect()
+    }
+
+    fn frame(bgra: &[u8]) -> ImageFrame<'_> {
+        ImageFrame {
+            width: 64,
+            height: 32,
+            frame: 1234,
+            timestamp: 61.7,
+            bgra,
+        }
+    }
+
+    #[test]
+    fn test_binary_round_trips() {
+        let bgra = gradient(64, 32);
+
+        for encoding in [ImageEncoding::Raw, ImageEncoding::Png(PngCompression::Fast)] {
+            let encoder = ImageEncoder {
+                encoding,
+                scale: 1.0,
+            };
+            let decoded = decode_image(&encoder.encode(&frame(&bgra)).unwrap()).unwrap();
+            assert_eq!(decoded.header.frame, 1234);
+            assert_eq!(decoded.header.timestamp, 61.7);
+            assert_eq!(decoded.bgra, bgra, "{encoding}");
+        }
+
+        let jpeg = ImageEncoder {
+            encoding: ImageEncoding::Jpeg(90),
+            scale: 1.0,
+        }
+        .encode(&frame(&bgra))
+        .unwrap();
+        assert!(jpeg.len() < bgra.len() / 4);
+        let decoded = decode_image(&jpeg).unwrap();
+        assert_eq!(decoded.header.format, PixelFormat::Jpeg);
+        let max_error = decoded
+            .bgra
+            .iter()
+            .zip(&bgra)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max();
+        assert!(max_error < Some(16), "{max_error:?}");
+    }
+
+    #[test]
+    fn test_downscaling() {
+        let bgra = gradient(64, 32);
+        let encoder = ImageEncoder {
+            encoding: ImageEncoding::Raw,
+            scale: 0.5,
+        };
+        let decoded = decode_image(&encoder.encode(&frame(&bgra)).unwrap()).unwrap();
+        assert_eq!((decoded.header.width, decoded.header.height), (32, 16));
+        assert_eq!(decoded.bgra.len(), 32 * 16 * 4);
+        assert!(decoded.bgra.chunks(4).all(|p| p[1] == 128 && p[3] == 255));
+    }
+
+    #[test]
+    fn test_depth_and_labels() {
+        // 1000 m at full scale, 0 m at black
+        let pixels = [
+            [255, 255, 255, 255],
+            [0, 0, 0, 255],
+            [0, 0, 7, 255],
+            [0, 1, 3, 255],
+        ];
+        let bgra: Vec<u8> = pixels.iter().flatten().copied().collect();
+        let frame = ImageFrame {
+            width: 2,
+            height: 2,
+            frame: 9,
+            timestamp: 0.5,
+            bgra: &bgra,
+        };
+
+        let (header, depth) = decode_depth(&encode_depth(&frame).unwrap()).unwrap();
+        assert_eq!((header.width, header.height, header.frame), (2, 2, 9));
+        assert_eq!(depth[0], 1000.0);
+        assert_eq!(depth[1], 0.0);
+        assert!((depth[3] - 259.0 * 1000.0 / 16_777_215.0).abs() < 1e-6);
+
+        for encoding in [ImageEncoding::Raw, ImageEncoding::Png(PngCompression::Fast)] {
+            let payload = encode_labels(&frame, encoding).unwrap();
+            assert_eq!(decode_labels(&payload).unwrap().1, vec![255, 0, 7, 3]);
+        }
+        assert!(encode_labels(&frame, ImageEncoding::Jpeg(90)).is_err());
+        assert!(decode_image(&encode_depth(&frame).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!("jpeg:60".parse(), Ok(ImageEncoding::Jpeg(60)));
+        assert_eq!(
+            "png".parse(),
+            Ok(ImageEncoding::Png(PngCompression::Default))
+        );
+        assert_eq!(
+            "raw".parse::<ImageEncoding>().map(|e| e.to_string()),
+            Ok("raw".to_string())
+        );
+        assert!("jpeg:0".parse::<ImageEncoding>().is_err());
+        assert!("webp".parse::<ImageEncoding>().is_err());
+        assert_eq!(
+            ImageEncoding::Jpeg(80).payload_format(),
+            UPayloadFormat::UPAYLOAD_FORMAT_RAW
+        );
+    }
+}
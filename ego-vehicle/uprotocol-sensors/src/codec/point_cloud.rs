@@ -0,0 +1,340 @@
+use half::f16;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use up_rust::UPayloadFormat;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// First bytes of every packed point cloud payload.
+pub const POINT_CLOUD_MAGIC: [u8; 4] = *b"CPCL";
+/// Version of the packed point cloud layout.
+pub const POINT_CLOUD_VERSION: u8 = 1;
+
+/// Fields of a lidar point: position in the sensor frame (m) and intensity.
+pub const LIDAR_FIELDS: [&str; 4] = ["x", "y", "z", "intensity"];
+/// Fields of a radar detection: velocity towards the sensor (m/s), azimuth and altitude
+/// (rad) and depth (m).
+pub const RADAR_FIELDS: [&str; 4] = ["velocity", "azimuth", "altitude", "depth"];
+/// Fields of a semantic lidar point: position in the sensor frame (m), cosine of the
+/// incidence angle, and the id and semantic tag of the object hit (exact up to 2^24 as
+/// f32).
+pub const SEMANTIC_LIDAR_FIELDS: [&str; 6] =
+    ["x", "y", "z", "cos_inc_angle", "object_idx", "object_tag"];
+
+/// Type of every field of a packed point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PointDatatype {
+    F32 = 1,
+    /// IEEE half precision: about 3 significant digits, half the size.
+    F16 = 2,
+}
+
+impl PointDatatype {
+    pub fn size(self) -> usize {
+        match self {
+            PointDatatype::F32 => 4,
+            PointDatatype::F16 => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for PointDatatype {
+    type Error = String;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            1 => Ok(PointDatatype::F32),
+            2 => Ok(PointDatatype::F16),
+            _ => Err(format!("unknown point datatype {value}")),
+        }
+    }
+}
+
+/// How a lidar or radar sensor is published.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointCloudEncoding {
+    /// The `carla-data-serde` type as JSON (the historical format).
+    #[default]
+    Json,
+    /// Packed little-endian points, with the given field type.
+    Packed(PointDatatype),
+}
+
+impl PointCloudEncoding {
+    pub fn payload_format(&self) -> UPayloadFormat {
+        match self {
+            PointCloudEncoding::Json => UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            PointCloudEncoding::Packed(_) => UPayloadFormat::UPAYLOAD_FORMAT_RAW,
+        }
+    }
+}
+
+impl FromStr for PointCloudEncoding {
+    type Err = String;
+
+    /// Parses `json`, `packed` (f32 fields) or `packed:f16`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(PointCloudEncoding::Json),
+            "packed" | "packed:f32" => Ok(PointCloudEncoding::Packed(PointDatatype::F32)),
+            "packed:f16" => Ok(PointCloudEncoding::Packed(PointDatatype::F16)),
+            _ => Err(format!(
+                "unknown point cloud encoding '{s}' (json, packed or packed:f16)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PointCloudEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointCloudEncoding::Json => f.write_str("json"),
+            PointCloudEncoding::Packed(PointDatatype::F32) => f.write_str("packed"),
+            PointCloudEncoding::Packed(PointDatatype::F16) => f.write_str("packed:f16"),
+        }
+    }
+}
+
+/// Points of one measurement, field by field for each point.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointCloud {
+    pub frame: u64,
+    pub timestamp: f64,
+    /// Names of the fields of every point, e.g. [`LIDAR_FIELDS`].
+    pub fields: Vec<String>,
+    /// `fields.len()` values per point.
+    pub values: Vec<f32>,
+}
+
+impl PointCloud {
+    pub fn new(frame: u64, timestamp: f64, fields: &[&str]) -> Self {
+        Self {
+            frame,
+            timestamp,
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, point: &[f32]) {
+        debug_assert_eq!(point.len(), self.fields.len());
+        self.values.extend_from_slice(point);
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len() / self.fields.len().max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &[f32]> {
+        self.values.chunks_exact(self.fields.len().max(1))
+    }
+
+    /// Values of the field `name`, point by point.
+    pub fn field(&self, name: &str) -> Option<impl Iterator<Item = f32> + '_> {
+        let index = self.fields.iter().position(|f| f == name)?;
+        Some(self.points().map(move |point| point[index]))
+    }
+
+    /// Packed little-endian payload:
+    ///
+    /// | Size | Field |
+    /// |------|-------|
+    /// | 4 | magic `CPCL` |
+    /// | 1 | version (1) |
+    /// | 1 | number of fields |
+    /// | 2 | point stride in bytes (u16) |
+    /// | 4 | number of points (u32) |
+    /// | 8 | frame (u64) |
+    /// | 8 | timestamp in seconds (f64) |
+    ///
+    /// then one descriptor per field (name length (u8), name, datatype (u8: 1 = f32,
+    /// 2 = f16), offset in the point (u16)) and the points.
+    pub fn encode(&self, datatype: PointDatatype) -> Result<Vec<u8>> {
+        let field_count = u8::try_from(self.fields.len()).map_err(|_| "too many fields")?;
+        let stride = u16::try_from(self.fields.len() * datatype.size())
+            .map_err(|_| "point stride too large")?;
+        let point_count = u32::try_from(self.len()).map_err(|_| "too many points")?;
+
+        let mut out = Vec::with_capacity(28 + self.fields.len() * 16 + self.values.len() * 4);
+        out.extend_from_slice(&POINT_CLOUD_MAGIC);
+        out.push(POINT_CLOUD_VERSION);
+        out.push(field_count);
+        out.extend_from_slice(&stride.to_le_bytes());
+        out.extend_from_slice(&point_count.to_le_bytes());
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+
+        for (i, name) in self.fields.iter().enumerate() {
+            let len = u8::try_from(name.len()).map_err(|_| "field name too long")?;
+            out.push(len);
+            out.extend_from_slice(name.as_bytes());
+            out.push(datatype as u8);
+            out.extend_from_slice(&((i * datatype.size()) as u16).to_le_bytes());
+        }
+
+        match datatype {
+            PointDatatype::F32 => {
+                for value in &self.values {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            PointDatatype::F16 => {
+                for value in &self.values {
+                    out.extend_from_slice(&f16::from_f32(*value).to_le_bytes());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Reads a packed payload back, widening quantised fields to f32.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != POINT_CLOUD_MAGIC {
+            return Err("not a packed point cloud payload".into());
+        }
+        let version = reader.u8()?;
+        if version != POINT_CLOUD_VERSION {
+            return Err(format!("unsupported point cloud version {version}").into());
+        }
+        let field_count = reader.u8()? as usize;
+        let stride = reader.u16()? as usize;
+        let point_count = reader.u32()? as usize;
+        let frame = reader.u64()?;
+        let timestamp = f64::from_bits(reader.u64()?);
+
+        let mut fields = Vec::with_capacity(field_count);
+        let mut layout = Vec::with_capacity(field_count);
+        for _ in 0..field_count {
+            let len = reader.u8()? as usize;
+            let name = std::str::from_utf8(reader.take(len)?)?.to_string();
+            let datatype = PointDatatype::try_from(reader.u8()?)?;
+            let offset = reader.u16()? as usize;
+            if offset + datatype.size() > stride {
+                return Err(format!("field '{name}' lies outside the point").into());
+            }
+            fields.push(name);
+            layout.push((datatype, offset));
+        }
+
+        let body = reader.take(point_count * stride)?;
+        let mut values = Vec::with_capacity(point_count * field_count);
+        for point in body.chunks_exact(stride.max(1)).take(point_count) {
+            for (datatype, offset) in &layout {
+                let value = match datatype {
+                    PointDatatype::F32 => {
+                        f32::from_le_bytes(point[*offset..*offset + 4].try_into().unwrap())
+                    }
+                    PointDatatype::F16 => {
+                        f16::from_le_bytes(point[*offset..*offset + 2].try_into().unwrap()).to_f32()
+                    }
+                };
+                values.push(value);
+            }
+        }
+
+        Ok(Self {
+            frame,
+            timestamp,
+            fields,
+            values,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err("truncated point cloud payload".into());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lidar() -> PointCloud {
+        let mut cloud = PointCloud::new(42, 3.25, &LIDAR_FIELDS);
+        for i in 0..100 {
+            let angle = i as f32 * 0.1;
+            cloud.push(&[
+                20.0 * angle.cos(),
+                20.0 * angle.sin(),
+                -1.7 + 0.01 * i as f32,
+                0.9 - 0.005 * i as f32,
+            ]);
+        }
+        cloud
+    }
+
+    #[test]
+    fn test_packed_round_trip() {
+        let cloud = lidar();
+        let packed = cloud.encode(PointDatatype::F32).unwrap();
+        assert_eq!(PointCloud::decode(&packed).unwrap(), cloud);
+        // Header, field descriptors, then 16 bytes per point
+        assert_eq!(packed.len(), 28 + (5 + 5 + 5 + 13) + 100 * 16);
+
+        let radar = PointCloud {
+            fields: RADAR_FIELDS.iter().map(|f| f.to_string()).collect(),
+            values: vec![-3.5, 0.1, -0.02, 35.0, 0.0, -0.3, 0.05, 12.5],
+            ..Default::default()
+        };
+        let decoded = PointCloud::decode(&radar.encode(PointDatatype::F32).unwrap()).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(
+            decoded.field("depth").unwrap().collect::<Vec<_>>(),
+            vec![35.0, 12.5]
+        );
+    }
+
+    #[test]
+    fn test_f16_quantisation() {
+        let cloud = lidar();
+        let packed = cloud.encode(PointDatatype::F16).unwrap();
+        let decoded = PointCloud::decode(&packed).unwrap();
+        assert_eq!(decoded.len(), cloud.len());
+        assert_eq!(decoded.fields, cloud.fields);
+
+        // Half precision keeps about 1 cm at 20 m
+        for (a, b) in decoded.values.iter().zip(&cloud.values) {
+            assert!((a - b).abs() <= 0.01 * b.abs().max(1.0), "{a} vs {b}");
+        }
+        assert!(packed.len() < cloud.encode(PointDatatype::F32).unwrap().len() / 2 + 64);
+
+        assert!(PointCloud::decode(&packed[..packed.len() - 1]).is_err());
+        assert_eq!(
+            "packed:f16".parse(),
+            Ok(PointCloudEncoding::Packed(PointDatatype::F16))
+        );
+        assert!("protobuf".parse::<PointCloudEncoding>().is_err());
+    }
+}
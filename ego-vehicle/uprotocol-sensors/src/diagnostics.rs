@@ -0,0 +1,494 @@
+//! Health of the bridged sensors, published periodically on the diagnostics topic.
+//!
+//! Every sensor has a [`SensorMonitor`], told when its events arrive and how long they
+//! take to encode and send (see [`crate::helpers::publish_source`]). Each report grades
+//! the sensor OK, WARN or ERROR from its arrival rate against the rate it should tick
+//! at, the jitter of its events, its drops and failures, and whether its actor is still
+//! in the world.
+
+use crate::discovery::{DiscoveredSensor, SensorKind};
+use crate::sensors::{QueueStats, SensorSource};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Window over which the arrival rate and jitter of a sensor are measured.
+pub const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Health of a sensor, from best to worst.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthLevel {
+    #[default]
+    Ok,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for HealthLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HealthLevel::Ok => "OK",
+            HealthLevel::Warn => "WARN",
+            HealthLevel::Error => "ERROR",
+        })
+    }
+}
+
+/// When a sensor is graded WARN or ERROR.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HealthThresholds {
+    /// Fraction of the expected rate under which a sensor is WARN.
+    pub rate_warn: f64,
+    /// Fraction of the expected rate under which a sensor is ERROR.
+    pub rate_error: f64,
+    /// Jitter, in expected periods, above which a sensor is WARN.
+    pub jitter_warn: f64,
+    /// Expected periods without any event after which a sensor is ERROR.
+    pub stale_periods: f64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            rate_warn: 0.8,
+            rate_error: 0.5,
+            jitter_warn: 0.5,
+            stale_periods: 10.0,
+        }
+    }
+}
+
+/// Health of one sensor in a [`DiagnosticsReport`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SensorDiagnostics {
+    pub role_name: String,
+    /// Type name, e.g. `image`.
+    pub sensor: String,
+    pub actor_id: u32,
+    pub level: HealthLevel,
+    /// Why the sensor is not OK, empty if it is.
+    pub message: String,
+    /// Events per second over the last [`RATE_WINDOW`].
+    pub rate_hz: f64,
+    /// Rate the sensor ticks at; `None` for the event sensors.
+    pub expected_rate_hz: Option<f64>,
+    /// Standard deviation of the time between two events, in milliseconds.
+    pub jitter_ms: Option<f64>,
+    /// Mean encoding time since the previous report, in milliseconds.
+    pub encode_ms: Option<f64>,
+    /// Mean time to send a payload since the previous report, in milliseconds.
+    pub send_ms: Option<f64>,
+    /// Events received from the sensor.
+    pub received: u64,
+    /// Events dropped by the queue of the sensor.
+    pub dropped: u64,
+    /// Events that could not be encoded or sent.
+    pub failed: u64,
+    /// Seconds since the last event, `None` before the first one.
+    pub last_seen_s: Option<f64>,
+    /// Whether the sensor actor is still in the world.
+    pub present: bool,
+}
+
+/// JSON document published on the diagnostics topic.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    /// Simulation time of the report, in seconds.
+    pub timestamp: f64,
+    /// Worst level of the sensors.
+    pub level: HealthLevel,
+    pub sensors: Vec<SensorDiagnostics>,
+}
+
+impl DiagnosticsReport {
+    pub fn new(timestamp: f64, sensors: Vec<SensorDiagnostics>) -> Self {
+        Self {
+            timestamp,
+            level: sensors
+                .iter()
+                .map(|sensor| sensor.level)
+                .max()
+                .unwrap_or_default(),
+            sensors,
+        }
+    }
+}
+
+/// Rate in Hz at which `sensor` produces events when the world ticks every `delta`
+/// seconds, from its `sensor_tick` (or synthetic `sensor_rate`) attribute; `None` for
+/// the event sensors.
+///
+/// Rates are measured in wall-clock time, so they only match when the simulation runs
+/// in real time.
+pub fn expected_rate(sensor: &DiscoveredSensor, delta: f64) -> Option<f64> {
+    if matches!(
+        sensor.kind,
+        SensorKind::LaneInvasion | SensorKind::Collision | SensorKind::ObstacleDetection
+    ) {
+        return None;
+    }
+    let attribute = |name: &str| {
+        sensor
+            .attributes
+            .get(name)
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value > 0.0)
+    };
+    // Sensors tick with the world, on the first frame after their own period
+    let ticks = match (attribute("sensor_rate"), attribute("sensor_tick")) {
+        (Some(rate), _) => (1.0 / (rate * delta)).round(),
+        (None, Some(tick)) => (tick / delta - 1e-6).ceil(),
+        (None, None) => 1.0,
+    };
+    Some(1.0 / (ticks.max(1.0) * delta))
+}
+
+// Durations of a step of the pipeline since the previous report
+#[derive(Clone, Copy, Debug, Default)]
+struct Timings {
+    total: Duration,
+    count: u32,
+}
+
+impl Timings {
+    fn mean_ms(&self) -> Option<f64> {
+        (self.count > 0).then(|| 1e3 * self.total.as_secs_f64() / f64::from(self.count))
+    }
+}
+
+#[derive(Debug)]
+struct MonitorState {
+    started: Instant,
+    // Arrivals of the last RATE_WINDOW
+    arrivals: VecDeque<Instant>,
+    received: u64,
+    last_seen: Option<Instant>,
+    encode: Timings,
+    send: Timings,
+    failed: u64,
+    failed_at_report: u64,
+    dropped_at_report: u64,
+    // Level of the previous report, to log the changes
+    level: HealthLevel,
+}
+
+impl MonitorState {
+    fn forget_before(&mut self, now: Instant) {
+        let Some(start) = now.checked_sub(RATE_WINDOW) else {
+            return;
+        };
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|arrival| *arrival < start)
+        {
+            self.arrivals.pop_front();
+        }
+    }
+
+    fn jitter_ms(&self) -> Option<f64> {
+        let intervals: Vec<f64> = self
+            .arrivals
+            .iter()
+            .zip(self.arrivals.iter().skip(1))
+            .map(|(a, b)| b.duration_since(*a).as_secs_f64())
+            .collect();
+        if intervals.len() < 2 {
+            return None;
+        }
+        let n = intervals.len() as f64;
+        let mean = intervals.iter().sum::<f64>() / n;
+        let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n;
+        Some(1e3 * variance.sqrt())
+    }
+}
+
+/// Health of one bridged sensor, fed from its callback and its publishing worker.
+#[derive(Debug)]
+pub struct SensorMonitor {
+    role_name: String,
+    kind: SensorKind,
+    actor_id: u32,
+    expected_rate: Option<f64>,
+    thresholds: HealthThresholds,
+    state: Mutex<MonitorState>,
+}
+
+impl SensorMonitor {
+    pub fn new(
+        sensor: &DiscoveredSensor,
+        expected_rate: Option<f64>,
+        thresholds: HealthThresholds,
+    ) -> Self {
+        Self {
+            role_name: sensor.role_name.clone(),
+            kind: sensor.kind,
+            actor_id: sensor.actor_id,
+            expected_rate,
+            thresholds,
+            state: Mutex::new(MonitorState {
+                started: Instant::now(),
+                arrivals: VecDeque::new(),
+                received: 0,
+                last_seen: None,
+                encode: Timings::default(),
+                send: Timings::default(),
+                failed: 0,
+                failed_at_report: 0,
+                dropped_at_report: 0,
+                level: HealthLevel::Ok,
+            }),
+        }
+    }
+
+    pub fn actor_id(&self) -> u32 {
+        self.actor_id
+    }
+
+    pub fn expected_rate(&self) -> Option<f64> {
+        self.expected_rate
+    }
+
+    /// An event arrived from the sensor at `now`.
+    pub fn record_arrival(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.received += 1;
+        state.last_seen = Some(now);
+        state.arrivals.push_back(now);
+        state.forget_before(now);
+    }
+
+    pub fn record_encode(&self, elapsed: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.encode.total += elapsed;
+        state.encode.count += 1;
+    }
+
+    pub fn record_send(&self, elapsed: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.send.total += elapsed;
+        state.send.count += 1;
+    }
+
+    /// An event could not be encoded or sent.
+    pub fn record_failure(&self) {
+        self.state.lock().unwrap().failed += 1;
+    }
+
+    /// Health of the sensor at `now`, given the stats of its queue and whether its actor
+    /// is still in the world. Encoding and send times, drops and failures are counted
+    /// from the previous report.
+    pub fn diagnose(&self, now: Instant, queue: QueueStats, present: bool) -> SensorDiagnostics {
+        let mut state = self.state.lock().unwrap();
+        state.forget_before(now);
+
+        let span = now
+            .saturating_duration_since(state.started)
+            .min(RATE_WINDOW)
+            .as_secs_f64();
+        let rate_hz = if span > 0.0 {
+            state.arrivals.len() as f64 / span
+        } else {
+            0.0
+        };
+        let jitter_ms = state.jitter_ms();
+        let dropped = queue.dropped.saturating_sub(state.dropped_at_report);
+        let failed = state.failed - state.failed_at_report;
+
+        let mut level = HealthLevel::Ok;
+        let mut reasons = Vec::new();
+        let mut flag = |flagged: HealthLevel, reason: String| {
+            level = level.max(flagged);
+            reasons.push(reason);
+        };
+
+        if !present {
+            flag(
+                HealthLevel::Error,
+                "actor no longer in the world".to_string(),
+            );
+        }
+        if let Some(expected) = self.expected_rate {
+            let period = 1.0 / expected;
+            let silent = now
+                .saturating_duration_since(state.last_seen.unwrap_or(state.started))
+                .as_secs_f64();
+            if silent > self.thresholds.stale_periods * period {
+                flag(HealthLevel::Error, format!("no event for {silent:.1} s"));
+            } else if span >= (3.0 * period).min(RATE_WINDOW.as_secs_f64()) {
+                // Only once the window holds a few periods
+                let rate = format!("rate {rate_hz:.1} Hz, expected {expected:.1} Hz");
+                if rate_hz < self.thresholds.rate_error * expected {
+                    flag(HealthLevel::Error, rate);
+                } else if rate_hz < self.thresholds.rate_warn * expected {
+                    flag(HealthLevel::Warn, rate);
+                }
+            }
+            if let Some(jitter) =
+                jitter_ms.filter(|jitter| *jitter > 1e3 * self.thresholds.jitter_warn * period)
+            {
+                flag(HealthLevel::Warn, format!("jitter {jitter:.1} ms"));
+            }
+        }
+        if dropped > 0 {
+            flag(HealthLevel::Warn, format!("{dropped} events dropped"));
+        }
+        if failed > 0 {
+            flag(HealthLevel::Warn, format!("{failed} events failed"));
+        }
+        let message = reasons.join(", ");
+
+        if level != state.level {
+            match level {
+                HealthLevel::Ok => log::info!("{}: sensor OK again", self.role_name),
+                _ => log::warn!("{}: sensor {level}: {message}", self.role_name),
+            }
+        }
+
+        let diagnostics = SensorDiagnostics {
+            role_name: self.role_name.clone(),
+            sensor: self.kind.name().to_string(),
+            actor_id: self.actor_id,
+            level,
+            message,
+            rate_hz,
+            expected_rate_hz: self.expected_rate,
+            jitter_ms,
+            encode_ms: state.encode.mean_ms(),
+            send_ms: state.send.mean_ms(),
+            received: state.received,
+            dropped: queue.dropped,
+            failed: state.failed,
+            last_seen_s: state
+                .last_seen
+                .map(|last| now.saturating_duration_since(last).as_secs_f64()),
+            present,
+        };
+
+        state.encode = Timings::default();
+        state.send = Timings::default();
+        state.failed_at_report = state.failed;
+        state.dropped_at_report = queue.dropped;
+        state.level = level;
+        diagnostics
+    }
+}
+
+/// View that tells a [`SensorMonitor`] about every event before forwarding it.
+pub struct Monitored<'v, V> {
+    view: &'v V,
+    monitor: Arc<SensorMonitor>,
+}
+
+impl<'v, V: SensorSource> Monitored<'v, V> {
+    pub fn new(view: &'v V, monitor: Arc<SensorMonitor>) -> Self {
+        Self { view, monitor }
+    }
+}
+
+impl<V: SensorSource> SensorSource for Monitored<'_, V> {
+    type Data = V::Data;
+
+    fn listen<F>(&self, f: F)
+    where
+        F: FnMut(Self::Data) + Send + 'static,
+    {
+        let mut f = f;
+        let monitor = Arc::clone(&self.monitor);
+        self.view.listen(move |data: V::Data| {
+            monitor.record_arrival(Instant::now());
+            f(data);
+        });
+    }
+
+    fn change_key(data: &Self::Data) -> Option<u64> {
+        V::change_key(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn sensor(kind: SensorKind, attributes: &[(&str, &str)]) -> DiscoveredSensor {
+        DiscoveredSensor {
+            actor_id: 7,
+            type_id: kind.type_id().to_string(),
+            kind,
+            role_name: "front".to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_expected_rate() {
+        let every_tick = sensor(SensorKind::Image, &[("sensor_tick", "0.0")]);
+        assert_eq!(expected_rate(&every_tick, 0.05), Some(20.0));
+        let lidar = sensor(SensorKind::LidarMeasurement, &[("sensor_tick", "0.1")]);
+        assert_eq!(expected_rate(&lidar, 0.05), Some(10.0));
+        // A tick between two frames waits for the next one
+        let gnss = sensor(SensorKind::GnssMeasurement, &[("sensor_tick", "0.12")]);
+        assert_eq!(expected_rate(&gnss, 0.05), Some(1.0 / (3.0 * 0.05)));
+        let synthetic = sensor(SensorKind::ImuMeasurement, &[("sensor_rate", "100")]);
+        assert_eq!(expected_rate(&synthetic, 0.05), Some(20.0));
+        assert_eq!(
+            expected_rate(&sensor(SensorKind::Collision, &[]), 0.05),
+            None
+        );
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let lidar = sensor(SensorKind::LidarMeasurement, &[("sensor_tick", "0.1")]);
+        let monitor = SensorMonitor::new(&lidar, Some(10.0), HealthThresholds::default());
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        for ms in (100..=5000).step_by(100) {
+            monitor.record_arrival(at(ms));
+            monitor.record_encode(Duration::from_millis(2));
+        }
+        let queue = |dropped| QueueStats {
+            received: 50,
+            dropped,
+            depth: 0,
+            max_depth: 1,
+        };
+
+        let healthy = monitor.diagnose(at(5000), queue(0), true);
+        assert_eq!(healthy.level, HealthLevel::Ok, "{}", healthy.message);
+        assert!((healthy.rate_hz - 10.0).abs() < 0.1);
+        assert!(healthy.jitter_ms.unwrap() < 1.0);
+        assert_eq!(healthy.encode_ms, Some(2.0));
+        assert_eq!(healthy.received, 50);
+
+        // Drops are counted since the previous report
+        let dropping = monitor.diagnose(at(5050), queue(3), true);
+        assert_eq!(dropping.level, HealthLevel::Warn);
+        assert_eq!(dropping.message, "3 events dropped");
+        assert_eq!(dropping.encode_ms, None);
+        assert_eq!(
+            monitor.diagnose(at(5100), queue(3), true).level,
+            HealthLevel::Ok
+        );
+
+        let gone = monitor.diagnose(at(7000), queue(3), false);
+        assert_eq!(gone.level, HealthLevel::Error);
+        assert_eq!(
+            gone.message,
+            "actor no longer in the world, no event for 2.0 s"
+        );
+        assert_eq!(
+            DiagnosticsReport::new(0.0, vec![healthy, gone]).level,
+            HealthLevel::Error
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Discovery of the sensors attached to the ego vehicle, and their uProtocol resources.
+//!
+//! Every sensor actor whose parent is the ego vehicle is bridged: its type comes from
+//! its blueprint id (e.g. `sensor.camera.rgb`), and its resource ID from a mapping by
+//! role name, the historical ID of its type (first sensor of each type), or a range.
+
+use crate::transforms::{SensorFrame, Transform, TransformTree};
+use carla::client::{ActorBase, World};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Default range of the resource IDs of the sensors beyond the first of each type.
+pub const DEFAULT_RESOURCE_RANGE: ResourceRange = ResourceRange {
+    start: 0x8100,
+    end: 0x81FF,
+};
+
+/// Type of a bridged sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SensorKind {
+    LaneInvasion,
+    Collision,
+    ObstacleDetection,
+    Image,
+    RadarMeasurement,
+    LidarMeasurement,
+    ImuMeasurement,
+    GnssMeasurement,
+    SemanticLidarMeasurement,
+    DepthImage,
+    SemanticSegmentationImage,
+}
+
+impl SensorKind {
+    pub const ALL: [SensorKind; 11] = [
+        SensorKind::LaneInvasion,
+        SensorKind::Collision,
+        SensorKind::ObstacleDetection,
+        SensorKind::Image,
+        SensorKind::RadarMeasurement,
+        SensorKind::LidarMeasurement,
+        SensorKind::ImuMeasurement,
+        SensorKind::GnssMeasurement,
+        SensorKind::SemanticLidarMeasurement,
+        SensorKind::DepthImage,
+        SensorKind::SemanticSegmentationImage,
+    ];
+
+    /// Type of the sensor spawned from the blueprint `type_id`, if it is bridged.
+    pub fn from_type_id(type_id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.type_id() == type_id)
+    }
+
+    /// Blueprint id of the sensors of this type.
+    pub fn type_id(self) -> &'static str {
+        match self {
+            SensorKind::LaneInvasion => "sensor.other.lane_invasion",
+            SensorKind::Collision => "sensor.other.collision",
+            SensorKind::ObstacleDetection => "sensor.other.obstacle",
+            SensorKind::Image => "sensor.camera.rgb",
+            SensorKind::RadarMeasurement => "sensor.other.radar",
+            SensorKind::LidarMeasurement => "sensor.lidar.ray_cast",
+            SensorKind::ImuMeasurement => "sensor.other.imu",
+            SensorKind::GnssMeasurement => "sensor.other.gnss",
+            SensorKind::SemanticLidarMeasurement => "sensor.lidar.ray_cast_semantic",
+            SensorKind::DepthImage => "sensor.camera.depth",
+            SensorKind::SemanticSegmentationImage => "sensor.camera.semantic_segmentation",
+        }
+    }
+
+    /// Name of the type in the sensor options, e.g. `image` in `--sensor-queue image=...`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SensorKind::LaneInvasion => "lane_invasion",
+            SensorKind::Collision => "collision",
+            SensorKind::ObstacleDetection => "obstacle_detection",
+            SensorKind::Image => "image",
+            SensorKind::RadarMeasurement => "radar_measurement",
+            SensorKind::LidarMeasurement => "lidar_measurement",
+            SensorKind::ImuMeasurement => "imu_measurement",
+            SensorKind::GnssMeasurement => "gnss_measurement",
+            SensorKind::SemanticLidarMeasurement => "semantic_lidar_measurement",
+            SensorKind::DepthImage => "depth_image",
+            SensorKind::SemanticSegmentationImage => "semantic_segmentation_image",
+        }
+    }
+
+    /// Resource ID of the first sensor of this type (as published before discovery for
+    /// the first seven types).
+    pub fn default_resource_id(self) -> u16 {
+        match self {
+            SensorKind::LaneInvasion => 0x8010,
+            SensorKind::Collision => 0x8011,
+            SensorKind::ObstacleDetection => 0x8012,
+            SensorKind::Image => 0x8013,
+            SensorKind::RadarMeasurement => 0x8014,
+            SensorKind::LidarMeasurement => 0x8015,
+            SensorKind::ImuMeasurement => 0x8016,
+            SensorKind::GnssMeasurement => 0x8017,
+            SensorKind::SemanticLidarMeasurement => 0x8018,
+            SensorKind::DepthImage => 0x8019,
+            SensorKind::SemanticSegmentationImage => 0x801A,
+        }
+    }
+}
+
+impl fmt::Display for SensorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A sensor actor to bridge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredSensor {
+    pub actor_id: u32,
+    /// Blueprint id, e.g. `sensor.camera.rgb`.
+    pub type_id: String,
+    pub kind: SensorKind,
+    /// `role_name` attribute, or `<kind>_<actor id>` if it has none.
+    pub role_name: String,
+    /// Blueprint attributes (image size, field of view, range...).
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl DiscoveredSensor {
+    /// The sensor `actor`, if its type is bridged.
+    pub fn from_actor(actor: &impl ActorBase) -> Option<Self> {
+        let type_id = actor.type_id();
+        let kind = SensorKind::from_type_id(&type_id)?;
+        let attributes: BTreeMap<String, String> = actor
+            .attributes()
+            .iter()
+            .map(|attribute| (attribute.id().to_string(), attribute.value_string()))
+            .collect();
+        let role_name = attributes
+            .get("role_name")
+            .filter(|role_name| !role_name.is_empty())
+            .cloned()
+            .unwrap_or_else(|| format!("{kind}_{}", actor.id()));
+        Some(Self {
+            actor_id: actor.id(),
+            type_id,
+            kind,
+            role_name,
+            attributes,
+        })
+    }
+
+    /// Name of this sensor in the sensor options (e.g. `--sensor-queue front_camera=...`):
+    /// its role name, with `-` read as `_`.
+    pub fn config_name(&self) -> String {
+        self.role_name.replace('-', "_")
+    }
+}
+
+/// Sensor types CARLA provides that cannot be bridged, with the reason.
+///
+/// The RSS sensor reports `carla::sensor::data::RssResponse`, which the `carla` crate does
+/// not bind: its `SensorData` only converts to the measurement types of [`SensorKind`].
+pub const UNSUPPORTED_SENSOR_TYPES: [(&str, &str); 1] = [(
+    "sensor.other.rss",
+    "the carla crate does not expose its RssResponse data",
+)];
+
+/// Sensors attached to the actor `parent_id`, by actor id. Sensors of types that are not
+/// bridged are skipped, with a warning for the [`UNSUPPORTED_SENSOR_TYPES`].
+pub fn discover_sensors(world: &World, parent_id: u32) -> Vec<DiscoveredSensor> {
+    let mut sensors: Vec<DiscoveredSensor> = world
+        .actors()
+        .iter()
+        .filter(|actor| actor.parent_id() == parent_id && actor.type_id().starts_with("sensor."))
+        .filter_map(|actor| {
+            let sensor = DiscoveredSensor::from_actor(&actor);
+            let unsupported = UNSUPPORTED_SENSOR_TYPES
+                .iter()
+                .find(|(type_id, _)| *type_id == actor.type_id());
+            if let Some((type_id, reason)) = unsupported {
+                log::warn!("Ignoring sensor id={} ({type_id}): {reason}", actor.id());
+            } else if sensor.is_none() {
+                log::debug!(
+                    "Ignoring sensor id={} ({}): type not bridged",
+                    actor.id(),
+                    actor.type_id()
+                );
+            }
+            sensor
+        })
+        .collect();
+    sensors.sort_by_key(|sensor| sensor.actor_id);
+    sensors
+}
+
+/// Transforms of the `sensors` relative to the actor `parent_id`, from their poses in the
+/// world. Sensors no longer in the world are left out.
+pub fn sensor_transforms(
+    world: &World,
+    parent_id: u32,
+    sensors: &[DiscoveredSensor],
+) -> TransformTree {
+    let mut tree = TransformTree {
+        vehicle_actor_id: parent_id,
+        sensors: Vec::with_capacity(sensors.len()),
+    };
+    let Some(parent) = world.actor(parent_id) else {
+        log::warn!("Actor id={parent_id} left the world, no sensor transforms");
+        return tree;
+    };
+    let parent_pose = parent.transform().cast::<f64>();
+    for sensor in sensors {
+        let Some(actor) = world.actor(sensor.actor_id) else {
+            log::warn!("Sensor '{}' left the world, no transform", sensor.role_name);
+            continue;
+        };
+        let relative = parent_pose.inverse() * actor.transform().cast::<f64>();
+        tree.sensors.push(SensorFrame {
+            frame: sensor.role_name.clone(),
+            sensor: sensor.kind.name().to_string(),
+            actor_id: sensor.actor_id,
+            transform: Transform::from_isometry(&relative),
+        });
+    }
+    tree
+}
+
+/// Inclusive range of resource IDs, parsed from `0x8100-0x81ff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl FromStr for ResourceRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected START-END, got '{s}'"))?;
+        let range = Self {
+            start: parse_resource_id(start)?,
+            end: parse_resource_id(end)?,
+        };
+        if range.start > range.end {
+            return Err(format!("empty resource range '{s}'"));
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for ResourceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}-{:#06x}", self.start, self.end)
+    }
+}
+
+/// Parses a resource ID, in hexadecimal (`0x8013`) or decimal.
+pub fn parse_resource_id(s: &str) -> Result<u16, String> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("invalid resource ID '{s}': {e}"))
+}
+
+/// Assigns a distinct resource ID to every discovered sensor.
+#[derive(Clone, Debug)]
+pub struct ResourceAllocator {
+    by_role: BTreeMap<String, u16>,
+    range: ResourceRange,
+    used: BTreeSet<u16>,
+}
+
+impl ResourceAllocator {
+    /// `by_role` maps role names to resource IDs; the sensors without one get the
+    /// historical ID of their type if still free, else the first free ID of `range`.
+    pub fn new(by_role: BTreeMap<String, u16>, range: ResourceRange) -> Self {
+        // Mapped IDs are reserved up front, whatever the discovery order
+        let used = by_role.values().copied().collect();
+        Self {
+            by_role,
+            range,
+            used,
+        }
+    }
+
+    pub fn allocate(&mut self, sensor: &DiscoveredSensor) -> Result<u16, String> {
+        if let Some(id) = self.by_role.get(&sensor.role_name) {
+            return Ok(*id);
+        }
+        let default = sensor.kind.default_resource_id();
+        let id = std::iter::once(default)
+            .chain(self.range.start..=self.range.end)
+            .find(|id| !self.used.contains(id))
+            .ok_or_else(|| {
+                format!(
+                    "no resource ID left for sensor '{}' in {:#06x}-{:#06x}",
+                    sensor.role_name, self.range.start, self.range.end
+                )
+            })?;
+        self.used.insert(id);
+        Ok(id)
+    }
+}
+
+/// Description of one bridged sensor in the [`SensorManifest`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Type name, e.g. `image`.
+    pub sensor: String,
+    pub role_name: String,
+    pub type_id: String,
+    pub actor_id: u32,
+    pub resource_id: u16,
+    /// Topic of the sensor events.
+    pub uri: String,
+    /// uProtocol payload format, e.g. `UPAYLOAD_FORMAT_RAW`.
+    pub payload_format: String,
+    /// Payload encoding, e.g. `json`, `jpeg:90` or `packed:f16`.
+    pub encoding: String,
+    /// Zenoh key of the shared-memory publisher, if the sensor is also published through it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shm_key: Option<String>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// JSON document listing every bridged sensor, published on the manifest topic.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SensorManifest {
+    pub sensors: Vec<ManifestEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(actor_id: u32, kind: SensorKind, role_name: &str) -> DiscoveredSensor {
+        DiscoveredSensor {
+            actor_id,
+            type_id: String::new(),
+            kind,
+            role_name: role_name.to_string(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_kinds() {
+        assert_eq!(
+            SensorKind::from_type_id("sensor.camera.rgb"),
+            Some(SensorKind::Image)
+        );
+        assert_eq!(
+            SensorKind::from_type_id("sensor.lidar.ray_cast"),
+            Some(SensorKind::LidarMeasurement)
+        );
+        assert_eq!(
+            SensorKind::from_type_id("sensor.camera.semantic_segmentation"),
+            Some(SensorKind::SemanticSegmentationImage)
+        );
+        // No binding for the RSS responses in the carla crate
+        for (type_id, _) in UNSUPPORTED_SENSOR_TYPES {
+            assert_eq!(SensorKind::from_type_id(type_id), None);
+        }
+
+        let ids: BTreeSet<u16> = SensorKind::ALL
+            .iter()
+            .map(|kind| kind.default_resource_id())
+            .collect();
+        assert_eq!(ids.len(), SensorKind::ALL.len());
+    }
+
+    #[test]
+    fn test_allocate_resources() {
+        let range: ResourceRange = "0x8100-0x8101".parse().unwrap();
+        let by_role = BTreeMap::from([("rear_camera".to_string(), 0x8100)]);
+        let mut allocator = ResourceAllocator::new(by_role, range);
+
+        let front = sensor(1, SensorKind::Image, "front_camera");
+        let rear = sensor(2, SensorKind::Image, "rear_camera");
+        let left = sensor(3, SensorKind::Image, "left_camera");
+        let right = sensor(4, SensorKind::Image, "right_camera");
+        let lidar = sensor(5, SensorKind::LidarMeasurement, "lidar");
+
+        // The first camera keeps the historical ID, the mapped one its own
+        assert_eq!(allocator.allocate(&front), Ok(0x8013));
+        assert_eq!(allocator.allocate(&rear), Ok(0x8100));
+        assert_eq!(allocator.allocate(&left), Ok(0x8101));
+        assert_eq!(allocator.allocate(&lidar), Ok(0x8015));
+        assert!(allocator.allocate(&right).is_err());
+    }
+
+    #[test]
+    fn test_parse_resources() {
+        assert_eq!(parse_resource_id("0x8013"), Ok(0x8013));
+        assert_eq!(parse_resource_id("32787"), Ok(0x8013));
+        assert!(parse_resource_id("0x1ffff").is_err());
+        assert_eq!(
+            "0x8100-0x81ff".parse(),
+            Ok(ResourceRange {
+                start: 0x8100,
+                end: 0x81ff
+            })
+        );
+        assert!("0x8200-0x8100".parse::<ResourceRange>().is_err());
+        assert!("0x8100".parse::<ResourceRange>().is_err());
+    }
+}
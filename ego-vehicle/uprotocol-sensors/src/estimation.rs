@@ -0,0 +1,641 @@
+//! Estimation of the planar pose of the ego vehicle from its IMU, speed and GNSS.
+//!
+//! [`OdometryEstimator`] runs an extended Kalman filter over the state
+//! `[x, y, yaw, speed, yaw_rate, accel_bias, gyro_bias]`, in the CARLA world frame. The
+//! accelerometer drives the prediction of the speed; the gyroscope and compass of the
+//! IMU, the speed of the vehicle and the GNSS fixes correct it. A source that stops
+//! publishing is reported as dropped out, and the filter carries on with the others,
+//! its covariance growing meanwhile.
+//!
+//! The `ego_estimator` binary runs the estimator on the bridge topics and publishes
+//! [`FusedOdometry`]; [`evaluate_synthetic`] runs it offline on the streams of the
+//! synthetic world, against its ground truth.
+
+use crate::client::{SensorPayload, Stamp};
+use crate::discovery::SensorKind;
+use crate::sensors::GnssFix;
+use crate::synthetic::{
+    EARTH_RADIUS, Generator, GnssGenerator, ImuGenerator, Rng, SyntheticClock, SyntheticWorld,
+    default_rate,
+};
+use nalgebra::{SMatrix, SVector, Vector1, Vector2};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::ops::Range;
+use up_rust::UPayloadFormat;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Authority of the estimator, e.g. in `//EgoEstimator/0/2/8001`.
+pub const ESTIMATOR_AUTHORITY: &str = "EgoEstimator";
+/// Resource ID of the fused odometry topic.
+pub const RESOURCE_FUSED_ODOMETRY: u16 = 0x8001;
+
+// Indices of the state
+const X: usize = 0;
+const Y: usize = 1;
+const YAW: usize = 2;
+const SPEED: usize = 3;
+const YAW_RATE: usize = 4;
+const ACCEL_BIAS: usize = 5;
+const GYRO_BIAS: usize = 6;
+const STATES: usize = 7;
+
+type State = SVector<f64, STATES>;
+type Covariance = SMatrix<f64, STATES, STATES>;
+
+/// Three axes of an IMU measurement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub struct ImuAxes {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// IMU measurement, read from the JSON payloads of the bridge with the CARLA field names.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct ImuSample {
+    pub frame: u64,
+    pub timestamp: f64,
+    /// m/s², gravity included, `x` forward.
+    pub accelerometer: ImuAxes,
+    /// rad/s, `z` positive as the yaw increases.
+    pub gyroscope: ImuAxes,
+    /// Heading in radians, 0 towards north (-y).
+    pub compass: f64,
+}
+
+impl SensorPayload for ImuSample {
+    fn decode(payload: &[u8], _format: UPayloadFormat) -> Result<Self> {
+        Ok(serde_json::from_slice(payload)?)
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        Some(Stamp::new(self.frame, self.timestamp))
+    }
+}
+
+/// Measurement sources of the estimator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Imu,
+    Gnss,
+    Speed,
+}
+
+impl Source {
+    pub const ALL: [Source; 3] = [Source::Imu, Source::Gnss, Source::Speed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Imu => "imu",
+            Source::Gnss => "gnss",
+            Source::Speed => "speed",
+        }
+    }
+}
+
+/// Noise of the measurements and of the motion model, as standard deviations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EstimatorConfig {
+    /// Latitude and longitude of the origin of the world, in degrees; the first GNSS fix
+    /// when `None`, for a frame local to the drive.
+    pub geo_origin: Option<(f64, f64)>,
+    /// m/s².
+    pub accel_noise: f64,
+    /// rad/s.
+    pub gyro_noise: f64,
+    /// Radians.
+    pub compass_noise: f64,
+    /// Meters, horizontally.
+    pub gnss_noise: f64,
+    /// m/s.
+    pub speed_noise: f64,
+    /// Changes of the acceleration while the IMU is dropped out, in m/s² per √s.
+    pub jerk: f64,
+    /// Changes of the yaw rate, in rad/s per √s.
+    pub yaw_acceleration: f64,
+    /// Drift of the accelerometer bias, in m/s² per √s.
+    pub accel_bias_drift: f64,
+    /// Drift of the gyroscope bias, in rad/s per √s.
+    pub gyro_bias_drift: f64,
+    /// Seconds without a measurement after which a source is dropped out.
+    pub dropout: f64,
+    /// Mahalanobis distance beyond which a GNSS fix is rejected as an outlier.
+    pub gnss_gate: f64,
+}
+
+impl Default for EstimatorConfig {
+    fn default() -> Self {
+        Self {
+            geo_origin: None,
+            accel_noise: 0.1,
+            gyro_noise: 0.005,
+            compass_noise: 0.02,
+            gnss_noise: 1.0,
+            speed_noise: 0.1,
+            jerk: 1.0,
+            yaw_acceleration: 0.2,
+            accel_bias_drift: 0.01,
+            gyro_bias_drift: 0.001,
+            dropout: 0.5,
+            gnss_gate: 5.0,
+        }
+    }
+}
+
+/// Fused odometry of the ego vehicle, published as JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FusedOdometry {
+    /// Simulation time of the estimate, in seconds.
+    pub timestamp: f64,
+    /// Meters, in the CARLA world frame.
+    pub x: f64,
+    pub y: f64,
+    /// Degrees, as the CARLA yaw.
+    pub yaw: f64,
+    /// m/s.
+    pub speed: f64,
+    /// Degrees per second.
+    pub yaw_rate: f64,
+    /// Bias of the forward accelerometer axis, in m/s².
+    pub accel_bias: f64,
+    /// Bias of the vertical gyroscope axis, in rad/s.
+    pub gyro_bias: f64,
+    /// Covariance of `[x, y, yaw, speed, yaw_rate, accel_bias, gyro_bias]` in SI units
+    /// (angles in radians), row by row.
+    pub covariance: Vec<f64>,
+    /// Sources without a measurement for longer than the dropout time, e.g. `gnss`.
+    pub dropouts: Vec<String>,
+}
+
+// Angle in (-PI, PI]
+fn wrap_angle(angle: f64) -> f64 {
+    let wrapped = angle.rem_euclid(TAU);
+    if wrapped > PI { wrapped - TAU } else { wrapped }
+}
+
+// The filter itself, in SI units
+#[derive(Clone, Debug)]
+struct Ekf {
+    x: State,
+    p: Covariance,
+}
+
+impl Ekf {
+    // Moves the state `dt` seconds forward; `accel` is the measured forward acceleration,
+    // the speed staying constant without it
+    fn predict(&mut self, dt: f64, accel: Option<f64>, config: &EstimatorConfig) {
+        let (sin, cos) = self.x[YAW].sin_cos();
+        let speed = self.x[SPEED];
+
+        let mut f = Covariance::identity();
+        f[(X, YAW)] = -speed * sin * dt;
+        f[(X, SPEED)] = cos * dt;
+        f[(Y, YAW)] = speed * cos * dt;
+        f[(Y, SPEED)] = sin * dt;
+        f[(YAW, YAW_RATE)] = dt;
+
+        self.x[X] += speed * cos * dt;
+        self.x[Y] += speed * sin * dt;
+        self.x[YAW] = wrap_angle(self.x[YAW] + self.x[YAW_RATE] * dt);
+        let speed_noise = match accel {
+            Some(accel) => {
+                f[(SPEED, ACCEL_BIAS)] = -dt;
+                self.x[SPEED] += (accel - self.x[ACCEL_BIAS]) * dt;
+                config.accel_noise
+            }
+            None => config.jerk,
+        };
+
+        let mut q = Covariance::zeros();
+        q[(X, X)] = 0.01 * dt;
+        q[(Y, Y)] = 0.01 * dt;
+        q[(YAW, YAW)] = 1e-5 * dt;
+        q[(SPEED, SPEED)] = speed_noise.powi(2) * dt;
+        q[(YAW_RATE, YAW_RATE)] = config.yaw_acceleration.powi(2) * dt;
+        q[(ACCEL_BIAS, ACCEL_BIAS)] = config.accel_bias_drift.powi(2) * dt;
+        q[(GYRO_BIAS, GYRO_BIAS)] = config.gyro_bias_drift.powi(2) * dt;
+        self.p = f * self.p * f.transpose() + q;
+    }
+
+    // Corrects the state with a measurement of `innovation`, or rejects it beyond the
+    // Mahalanobis distance `gate`
+    fn update<const M: usize>(
+        &mut self,
+        innovation: SVector<f64, M>,
+        h: SMatrix<f64, M, STATES>,
+        r: SMatrix<f64, M, M>,
+        gate: Option<f64>,
+    ) -> bool {
+        let s = h * self.p * h.transpose() + r;
+        let Some(s_inverse) = s.try_inverse() else {
+            return false;
+        };
+        let distance = (innovation.transpose() * s_inverse * innovation)[0];
+        if gate.is_some_and(|gate| distance > gate * gate) {
+            return false;
+        }
+        let k = self.p * h.transpose() * s_inverse;
+        self.x += k * innovation;
+        self.x[YAW] = wrap_angle(self.x[YAW]);
+        // Joseph form, which keeps the covariance symmetric positive
+        let i_kh = Covariance::identity() - k * h;
+        self.p = i_kh * self.p * i_kh.transpose() + k * r * k.transpose();
+        true
+    }
+
+    fn update_scalar(&mut self, innovation: f64, h: SMatrix<f64, 1, STATES>, sigma: f64) {
+        self.update(
+            Vector1::new(innovation),
+            h,
+            SMatrix::<f64, 1, 1>::new(sigma * sigma),
+            None,
+        );
+    }
+}
+
+// Row of the measurement matrix reading the sum of the states `indices`
+fn reading(indices: &[usize]) -> SMatrix<f64, 1, STATES> {
+    let mut h = SMatrix::<f64, 1, STATES>::zeros();
+    for index in indices {
+        h[*index] = 1.0;
+    }
+    h
+}
+
+/// Estimates the odometry of the ego vehicle from its measurements, fed in the order
+/// they arrive.
+///
+/// The filter starts at the first GNSS fix, heading as the last compass reading. IMU
+/// samples and GNSS fixes carry their simulation time, and move the estimate forward to
+/// it; older ones are dropped. The speed has no time of its own and applies to the
+/// current estimate.
+#[derive(Clone, Debug)]
+pub struct OdometryEstimator {
+    config: EstimatorConfig,
+    ekf: Option<Ekf>,
+    origin: Option<(f64, f64)>,
+    // Simulation time of the estimate
+    time: Option<f64>,
+    // Last forward acceleration and yaw from the IMU, and speed
+    accel: Option<f64>,
+    compass_yaw: Option<f64>,
+    speed: Option<f64>,
+    // Simulation time of the last measurement of every source
+    last_seen: [Option<f64>; 3],
+    // GNSS fixes rejected in a row
+    rejected: u32,
+}
+
+impl OdometryEstimator {
+    pub fn new(config: EstimatorConfig) -> Self {
+        Self {
+            config,
+            ekf: None,
+            origin: config.geo_origin,
+            time: None,
+            accel: None,
+            compass_yaw: None,
+            speed: None,
+            last_seen: [None; 3],
+            rejected: 0,
+        }
+    }
+
+    pub fn on_imu(&mut self, sample: &ImuSample) {
+        let yaw = wrap_angle(sample.compass - FRAC_PI_2);
+        if self.advance(sample.timestamp) {
+            let config = self.config;
+            let ekf = self.ekf.as_mut().expect("advanced estimate");
+            ekf.update_scalar(
+                sample.gyroscope.z - (ekf.x[YAW_RATE] + ekf.x[GYRO_BIAS]),
+                reading(&[YAW_RATE, GYRO_BIAS]),
+                config.gyro_noise,
+            );
+            ekf.update_scalar(
+                wrap_angle(yaw - ekf.x[YAW]),
+                reading(&[YAW]),
+                config.compass_noise,
+            );
+        } else if self.ekf.is_some() {
+            return;
+        }
+        self.accel = Some(sample.accelerometer.x);
+        self.compass_yaw = Some(yaw);
+        self.seen(Source::Imu, sample.timestamp);
+    }
+
+    pub fn on_gnss(&mut self, fix: &GnssFix) {
+        let (latitude, longitude) = *self.origin.get_or_insert((fix.latitude, fix.longitude));
+        // North is -y, as in the synthetic world
+        let x =
+            (fix.longitude - longitude).to_radians() * EARTH_RADIUS * latitude.to_radians().cos();
+        let y = -(fix.latitude - latitude).to_radians() * EARTH_RADIUS;
+
+        if self.ekf.is_none() {
+            self.start(fix.timestamp, x, y);
+            self.seen(Source::Gnss, fix.timestamp);
+            return;
+        }
+        if !self.advance(fix.timestamp) {
+            return;
+        }
+
+        let mut h = SMatrix::<f64, 2, STATES>::zeros();
+        h[(0, X)] = 1.0;
+        h[(1, Y)] = 1.0;
+        let r = SMatrix::<f64, 2, 2>::identity() * self.config.gnss_noise.powi(2);
+        // A run of rejected fixes means the estimate drifted away, not the receiver
+        let gate = (self.rejected < 3).then_some(self.config.gnss_gate);
+        let ekf = self.ekf.as_mut().expect("advanced estimate");
+        let innovation = Vector2::new(x - ekf.x[X], y - ekf.x[Y]);
+        if ekf.update(innovation, h, r, gate) {
+            self.rejected = 0;
+        } else {
+            self.rejected += 1;
+            log::debug!("Rejected GNSS fix at {} s", fix.timestamp);
+        }
+        self.seen(Source::Gnss, fix.timestamp);
+    }
+
+    /// Speed of the vehicle in m/s.
+    pub fn on_speed(&mut self, speed: f64) {
+        self.speed = Some(speed);
+        if let (Some(ekf), Some(time)) = (self.ekf.as_mut(), self.time) {
+            ekf.update_scalar(
+                speed - ekf.x[SPEED],
+                reading(&[SPEED]),
+                self.config.speed_noise,
+            );
+            self.seen(Source::Speed, time);
+        }
+    }
+
+    /// Sources without a measurement for longer than the dropout time.
+    pub fn dropouts(&self) -> Vec<Source> {
+        Source::ALL
+            .into_iter()
+            .filter(|source| {
+                self.last_seen[*source as usize]
+                    .zip(self.time)
+                    .is_none_or(|(seen, time)| time - seen > self.config.dropout)
+            })
+            .collect()
+    }
+
+    /// The current estimate, once the filter has started.
+    pub fn odometry(&self) -> Option<FusedOdometry> {
+        let ekf = self.ekf.as_ref()?;
+        Some(FusedOdometry {
+            timestamp: self.time?,
+            x: ekf.x[X],
+            y: ekf.x[Y],
+            yaw: ekf.x[YAW].to_degrees(),
+            speed: ekf.x[SPEED],
+            yaw_rate: ekf.x[YAW_RATE].to_degrees(),
+            accel_bias: ekf.x[ACCEL_BIAS],
+            gyro_bias: ekf.x[GYRO_BIAS],
+            covariance: ekf.p.transpose().iter().copied().collect(),
+            dropouts: self
+                .dropouts()
+                .into_iter()
+                .map(|source| source.name().to_string())
+                .collect(),
+        })
+    }
+
+    fn seen(&mut self, source: Source, time: f64) {
+        self.last_seen[source as usize] = Some(time);
+    }
+
+    // Starts the filter at the position of a GNSS fix
+    fn start(&mut self, time: f64, x: f64, y: f64) {
+        let mut state = State::zeros();
+        state[X] = x;
+        state[Y] = y;
+        state[YAW] = self.compass_yaw.unwrap_or(0.0);
+        state[SPEED] = self.speed.unwrap_or(0.0);
+        let variances = [
+            self.config.gnss_noise.powi(2),
+            self.config.gnss_noise.powi(2),
+            if self.compass_yaw.is_some() {
+                self.config.compass_noise.powi(2)
+            } else {
+                PI * PI
+            },
+            if self.speed.is_some() {
+                self.config.speed_noise.powi(2)
+            } else {
+                100.0
+            },
+            0.1,
+            0.25,
+            1e-4,
+        ];
+        self.ekf = Some(Ekf {
+            x: state,
+            p: Covariance::from_diagonal(&State::from(variances)),
+        });
+        self.time = Some(time);
+        log::info!("Estimator started at ({x:.1}, {y:.1}) m, {time:.2} s");
+    }
+
+    // Moves the estimate forward to `time`; false before the start or for older times
+    fn advance(&mut self, time: f64) -> bool {
+        let (Some(ekf), Some(current)) = (self.ekf.as_mut(), self.time) else {
+            return false;
+        };
+        let dt = time - current;
+        if dt < 0.0 {
+            return false;
+        }
+        if dt > 0.0 {
+            // The last acceleration holds until the next sample, unless the IMU dropped out
+            let imu_fresh = self.last_seen[Source::Imu as usize]
+                .is_some_and(|seen| time - seen <= self.config.dropout);
+            let accel = self.accel.filter(|_| imu_fresh);
+            ekf.predict(dt, accel, &self.config);
+            self.time = Some(time);
+        }
+        true
+    }
+}
+
+/// Offline run of the estimator on the streams of a [`SyntheticWorld`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntheticRun {
+    /// Seconds of simulation.
+    pub duration: f64,
+    /// Seconds between two ticks of the world.
+    pub delta: f64,
+    pub seed: u64,
+    /// Simulation times without GNSS fixes.
+    pub gnss_outage: Option<Range<f64>>,
+}
+
+impl Default for SyntheticRun {
+    fn default() -> Self {
+        Self {
+            duration: 60.0,
+            delta: 0.02,
+            seed: 0,
+            gnss_outage: None,
+        }
+    }
+}
+
+/// Errors of an estimate against the ground truth.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EstimationErrors {
+    /// Meters.
+    pub position_rms: f64,
+    pub position_max: f64,
+    /// Degrees.
+    pub yaw_rms: f64,
+    /// m/s.
+    pub speed_rms: f64,
+    /// Estimates compared.
+    pub samples: usize,
+}
+
+// Seconds the filter is given to converge before its errors count
+const SETTLING_TIME: f64 = 5.0;
+
+/// Runs the estimator on the IMU, GNSS and speed of the synthetic world, at their
+/// default rates, and measures its errors after every IMU sample against the ground
+/// truth of the world, once settled.
+pub fn evaluate_synthetic(
+    world: &SyntheticWorld,
+    run: &SyntheticRun,
+    config: EstimatorConfig,
+) -> EstimationErrors {
+    let (latitude, longitude, _) = world.geo_origin;
+    let mut estimator = OdometryEstimator::new(EstimatorConfig {
+        geo_origin: Some((latitude, longitude)),
+        ..config
+    });
+    let mut imu = ImuGenerator {
+        rng: Rng::new(run.seed),
+        accelerometer_noise: 0.05,
+        gyroscope_noise: 0.002,
+    };
+    let mut gnss = GnssGenerator {
+        rng: Rng::new(run.seed ^ 1),
+        noise: 0.5,
+    };
+    let mut speed_noise = Rng::new(run.seed ^ 2);
+
+    let clock = SyntheticClock::new(run.delta);
+    let imu_ticks = clock.ticks(default_rate(SensorKind::ImuMeasurement));
+    let gnss_ticks = clock.ticks(default_rate(SensorKind::GnssMeasurement));
+    // As often as the bridge publishes the velocity, every tick
+    let speed_ticks = 1;
+
+    let mut squares = (0.0, 0.0, 0.0);
+    let mut errors = EstimationErrors::default();
+    for frame in 0..=(run.duration / run.delta) as u64 {
+        let timestamp = clock.timestamp(frame);
+        let outage = run
+            .gnss_outage
+            .as_ref()
+            .is_some_and(|outage| outage.contains(&timestamp));
+        let fix = (frame % gnss_ticks == 0 && !outage)
+            .then(|| gnss.generate(world, frame, timestamp))
+            .flatten();
+        if let Some(fix) = fix {
+            estimator.on_gnss(&fix);
+        }
+        if frame % speed_ticks == 0 {
+            estimator.on_speed(world.ego_at(timestamp).speed + speed_noise.normal(0.05));
+        }
+        if frame % imu_ticks != 0 {
+            continue;
+        }
+        let Some(sample) = imu.generate(world, frame, timestamp) else {
+            continue;
+        };
+        estimator.on_imu(&ImuSample {
+            frame: sample.frame,
+            timestamp: sample.timestamp,
+            accelerometer: ImuAxes {
+                x: sample.accelerometer.x.into(),
+                y: sample.accelerometer.y.into(),
+                z: sample.accelerometer.z.into(),
+            },
+            gyroscope: ImuAxes {
+                x: sample.gyroscope.x.into(),
+                y: sample.gyroscope.y.into(),
+                z: sample.gyroscope.z.into(),
+            },
+            compass: sample.compass.into(),
+        });
+
+        let Some(odometry) = estimator.odometry().filter(|_| timestamp >= SETTLING_TIME) else {
+            continue;
+        };
+        let truth = world.ego_at(timestamp);
+        let position = (odometry.x - truth.x).hypot(odometry.y - truth.y);
+        let yaw = wrap_angle((odometry.yaw - truth.yaw).to_radians()).to_degrees();
+        squares.0 += position * position;
+        squares.1 += yaw * yaw;
+        squares.2 += (odometry.speed - truth.speed).powi(2);
+        errors.position_max = errors.position_max.max(position);
+        errors.samples += 1;
+    }
+
+    if errors.samples > 0 {
+        let n = errors.samples as f64;
+        errors.position_rms = (squares.0 / n).sqrt();
+        errors.yaw_rms = (squares.1 / n).sqrt();
+        errors.speed_rms = (squares.2 / n).sqrt();
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_drive() {
+        let world = SyntheticWorld::default();
+        let errors =
+            evaluate_synthetic(&world, &SyntheticRun::default(), EstimatorConfig::default());
+        assert!(errors.samples > 2000);
+        assert!(errors.position_rms < 0.3, "{errors:?}");
+        assert!(errors.yaw_rms < 0.5, "{errors:?}");
+        assert!(errors.speed_rms < 0.05, "{errors:?}");
+    }
+
+    #[test]
+    fn test_gnss_outage() {
+        let world = SyntheticWorld::default();
+        let run = SyntheticRun {
+            gnss_outage: Some(20.0..30.0),
+            ..SyntheticRun::default()
+        };
+        let errors = evaluate_synthetic(&world, &run, EstimatorConfig::default());
+        // Dead reckoning on the IMU and the speed for 10 s
+        assert!(errors.position_max < 1.0, "{errors:?}");
+
+        let mut estimator = OdometryEstimator::new(EstimatorConfig::default());
+        assert!(estimator.odometry().is_none());
+        let fix = |timestamp| GnssFix {
+            frame: 0,
+            timestamp,
+            latitude: 49.0,
+            longitude: 8.0,
+            altitude: 0.0,
+        };
+        estimator.on_gnss(&fix(1.0));
+        estimator.on_gnss(&fix(2.0));
+        let odometry = estimator.odometry().unwrap();
+        assert_eq!(
+            (odometry.x, odometry.y, odometry.timestamp),
+            (0.0, 0.0, 2.0)
+        );
+        assert_eq!(odometry.dropouts, vec!["imu", "speed"]);
+    }
+}
@@ -1,12 +1,16 @@
-use crate::sensors::{Listen, SensorComms};
+use crate::chunking::ChunkedPublisher;
+use crate::diagnostics::{Monitored, SensorMonitor};
+use crate::discovery::{DiscoveredSensor, discover_sensors};
+use crate::sensors::{Filtered, PublishConfig, QueueConfig, SensorComms, SensorSource};
+use crate::shm::ShmPublisher;
 use carla::client::{ActorBase, Sensor, World};
 use log;
 use std::error::Error;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
-use up_rust::{UMessageBuilder, UPayloadFormat, UTransport, UUri};
+use up_rust::{UPayloadFormat, UTransport, UUri};
 
 type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
 
@@ -45,38 +49,112 @@ pub async fn wait_for_actor_id_by_role(
     found.ok_or_else(|| "Stopped before target actor appeared".into())
 }
 
+/// Polls the sensors attached to the actor `parent_id` until the same ones are found twice
+/// in a row, so that sensors spawned just after their vehicle are not missed.
+pub async fn wait_for_sensors(
+    carla_world: &World,
+    running: &AtomicBool,
+    parent_id: u32,
+    polling_ms: u64,
+) -> Result<Vec<DiscoveredSensor>> {
+    let poll = Duration::from_millis(polling_ms);
+    let mut previous: Option<Vec<u32>> = None;
+
+    while running.load(Ordering::SeqCst) {
+        let _ = carla_world.wait_for_tick();
+
+        let sensors = discover_sensors(carla_world, parent_id);
+        let ids: Vec<u32> = sensors.iter().map(|sensor| sensor.actor_id).collect();
+        if !ids.is_empty() && previous.as_ref() == Some(&ids) {
+            return Ok(sensors);
+        }
+        log::info!(
+            "Waiting for the sensors of actor id={parent_id} ({} found)...",
+            ids.len()
+        );
+        previous = Some(ids);
+
+        sleep(poll).await;
+    }
+
+    Err("Stopped before the sensors were discovered".into())
+}
+
 /// A factory that can build a typed view borrowing the `Sensor`.
 pub trait ViewFactory {
-    type View<'a>: Listen + Send + Sync + 'a
+    type View<'a>: SensorSource + Send + Sync + 'a
     where
         Self: 'a;
     fn make<'a>(&self, sensor: &'a Sensor) -> Self::View<'a>;
 }
 
-/// Generic setup: builds the typed view with a factory, encodes events, builds a UMessage,
-/// and sends via the provided `Arc<dyn UTransport>`.
+/// Where and how a sensor publishes its events.
+pub struct SensorSettings {
+    /// Role name of the sensor actor, also naming its queue in the logs.
+    pub role_name: String,
+    pub uuri: UUri,
+    pub transport: Arc<dyn UTransport>,
+    pub queue: QueueConfig,
+    pub publish: PublishConfig,
+    /// Payloads larger than this are split into chunks (see [`crate::chunking`]).
+    pub chunk_size: Option<usize>,
+    /// Publishes the payloads through Zenoh shared memory (see [`crate::shm`]), instead of
+    /// on `uuri` unless `shm_uprotocol` is set.
+    pub shm: Option<Arc<ShmPublisher>>,
+    /// Publishes the payloads on `uuri` as well as through `shm`.
+    pub shm_uprotocol: bool,
+    pub monitor: Arc<SensorMonitor>,
+}
+
+/// Generic setup: waits for the sensor actor with `settings.role_name`, then attaches to it
+/// (see [`attach_sensor_with_transport`]).
 ///
 /// Returns `(comms, actor_id, sensor_keepalive)`.
 pub async fn setup_sensor_with_transport<F, Encode>(
     carla_world: &World,
     running: &AtomicBool,
-    role_name: &str,
-    comms_name: &str,
     polling_ms: u64,
     factory: F,
-    uuri: UUri,
     encode: Encode,
     payload_format: UPayloadFormat,
-    transport: Arc<dyn UTransport>,
+    settings: SensorSettings,
 ) -> Result<(SensorComms, u32, Sensor)>
 where
     F: ViewFactory,
-    Encode: for<'a> Fn(<F::View<'a> as Listen>::Data) -> Result<Vec<u8>> + Send + Sync + 'static,
+    Encode:
+        for<'a> Fn(<F::View<'a> as SensorSource>::Data) -> Result<Vec<u8>> + Send + Sync + 'static,
 {
-    // 1) Wait for the actor
-    let actor_id = wait_for_actor_id_by_role(carla_world, running, role_name, polling_ms).await?;
+    let actor_id =
+        wait_for_actor_id_by_role(carla_world, running, &settings.role_name, polling_ms).await?;
+    let (comms, sensor) = attach_sensor_with_transport(
+        carla_world,
+        actor_id,
+        factory,
+        encode,
+        payload_format,
+        settings,
+    )?;
+    Ok((comms, actor_id, sensor))
+}
 
-    // 2) Fetch & convert to Sensor
+/// Attaches to the sensor actor `actor_id`: builds the typed view with a factory and
+/// publishes its events (see [`publish_source`]).
+///
+/// Returns `(comms, sensor_keepalive)`.
+pub fn attach_sensor_with_transport<F, Encode>(
+    carla_world: &World,
+    actor_id: u32,
+    factory: F,
+    encode: Encode,
+    payload_format: UPayloadFormat,
+    settings: SensorSettings,
+) -> Result<(SensorComms, Sensor)>
+where
+    F: ViewFactory,
+    Encode:
+        for<'a> Fn(<F::View<'a> as SensorSource>::Data) -> Result<Vec<u8>> + Send + Sync + 'static,
+{
+    // 1) Fetch & convert to Sensor
     let sensor_actor = carla_world
         .actor(actor_id)
         .ok_or_else(|| format!("Unable to locate sensor actor id: {actor_id}"))?;
@@ -85,55 +163,136 @@ where
         .try_into_sensor()
         .map_err(|_| "Unable to turn actor into a sensor")?;
 
-    // 3) Create comms (keep alive in caller)
-    let comms = SensorComms::new(comms_name);
+    // 2) Build typed view borrowing `sensor` (no clone!), and publish its events
+    let comms = {
+        let view = factory.make(&sensor);
+        publish_source(&view, encode, payload_format, settings)
+        // `view` (and its borrow of `sensor`) ends here
+    };
 
-    // 4) Capture stack for async handler
+    // 3) Return handles to keep things alive
+    Ok((comms, sensor))
+}
+
+/// Publishes the events of `source` as set by `settings`: encodes them, builds a
+/// UMessage, and sends via the provided `Arc<dyn UTransport>`. Events wait for the
+/// transport in a queue configured by `queue`, and are sent one at a time, in order.
+///
+/// Events not selected by `publish` (rate limit, decimation, on-change) are dropped as
+/// they arrive, before being queued or encoded.
+///
+/// Payloads larger than `chunk_size`, if any, are split into chunks (see [`crate::chunking`]).
+///
+/// With `shm`, payloads are published through Zenoh shared memory on its key instead, as
+/// whole payloads, and on `uuri` too only with `shm_uprotocol` (see [`crate::shm`]).
+///
+/// `monitor` is told about every event as it arrives, before any filtering, and about the
+/// time to encode and send it (see [`crate::diagnostics`]).
+///
+/// The returned comms must be kept alive, as well as `source` for sources that stop
+/// producing events when dropped.
+pub fn publish_source<S, Encode>(
+    source: &S,
+    encode: Encode,
+    payload_format: UPayloadFormat,
+    settings: SensorSettings,
+) -> SensorComms
+where
+    S: SensorSource,
+    Encode: Fn(S::Data) -> Result<Vec<u8>> + Send + Sync + 'static,
+{
+    let SensorSettings {
+        role_name,
+        uuri,
+        transport,
+        queue,
+        publish,
+        chunk_size,
+        shm,
+        shm_uprotocol,
+        monitor,
+    } = settings;
+
+    // 1) Create comms (keep alive in caller)
+    log::info!(
+        "Queueing {role_name} events [policy: {}, capacity: {}]",
+        queue.policy,
+        queue.capacity
+    );
+    if !publish.is_passthrough() {
+        log::info!(
+            "Filtering {role_name} events [max rate: {}, decimation: {}, on change: {}]",
+            publish
+                .max_rate_hz
+                .map_or("none".to_string(), |rate| format!("{rate} Hz")),
+            publish.decimation,
+            publish.on_change
+        );
+    }
+    if let Some(shm) = &shm {
+        let also = if shm_uprotocol {
+            ", and on uProtocol"
+        } else {
+            ""
+        };
+        log::info!(
+            "Publishing {role_name} payloads through shared memory on '{}'{also}",
+            shm.key()
+        );
+    }
+    let uprotocol = shm.is_none() || shm_uprotocol;
+    if let Some(chunk_size) = chunk_size {
+        log::info!("Chunking {role_name} payloads larger than {chunk_size} bytes");
+    }
+    let comms = SensorComms::new(&role_name, queue);
+
+    // 2) Capture stack for async handler
     let uuri_shared = uuri.clone();
     let encode = Arc::new(encode);
-    let transport = Arc::clone(&transport);
-    let role_name: String = role_name.to_owned();
+    let publisher = ChunkedPublisher::new(transport, chunk_size);
 
-    // 5) Build typed view borrowing `sensor` (no clone!)
-    {
-        let view = factory.make(&sensor);
+    // 3) Attach: encode -> shm.put and/or UMessageBuilder (one per chunk) -> transport.send
+    let monitored = Monitored::new(source, Arc::clone(&monitor));
+    let source = Filtered::new(&monitored, publish);
+    comms.listen_on_async(&source, move |evt| {
+        let uuri = uuri_shared.clone();
+        let encode = Arc::clone(&encode);
+        let publisher = publisher.clone();
+        let shm = shm.clone();
+        let role_name = role_name.clone();
+        let monitor = Arc::clone(&monitor);
 
-        // 6) Attach: encode -> UMessageBuilder -> transport.send
-        comms.listen_on_async(&view, move |evt| {
-            let uuri = uuri_shared.clone();
-            let encode = Arc::clone(&encode);
-            let transport = Arc::clone(&transport);
-            let role_name = role_name.clone();
-
-            async move {
-                let payload = match encode(evt) {
-                    Ok(b) => b,
-                    Err(e) => {
-                        log::error!("Event encoding failed for {role_name}: {e}");
-                        return;
-                    }
-                };
-
-                let umsg = match UMessageBuilder::publish(uuri)
-                    .build_with_payload(payload, payload_format)
-                {
-                    Ok(m) => m,
-                    Err(e) => {
-                        log::error!("UMessage build failed for {role_name}: {e}");
-                        return;
-                    }
-                };
-
-                if let Err(err) = transport.send(umsg).await {
-                    log::error!("Transport send failed for {role_name}: {:?}", err);
-                } else {
-                    log::info!("Transport send succeeded for {role_name}.");
+        async move {
+            let started = Instant::now();
+            let payload = match encode(evt) {
+                Ok(b) => b,
+                Err(e) => {
+                    monitor.record_failure();
+                    log::error!("Event encoding failed for {role_name}: {e}");
+                    return;
                 }
+            };
+            monitor.record_encode(started.elapsed());
+
+            let started = Instant::now();
+            let shared = match &shm {
+                Some(shm) => shm.put(&payload).await,
+                None => Ok(()),
+            };
+            let published = if uprotocol {
+                publisher.publish(&uuri, payload, payload_format).await
+            } else {
+                Ok(())
+            };
+            if let Err(err) = shared.and(published) {
+                monitor.record_failure();
+                log::error!("Publishing failed for {role_name}: {err}");
+            } else {
+                monitor.record_send(started.elapsed());
+                log::debug!("Transport send succeeded for {role_name}.");
             }
-        });
-        // `view` (and its borrow of `sensor`) ends here
-    }
+        }
+    });
 
-    // 7) Return handles to keep things alive
-    Ok((comms, actor_id, sensor))
+    comms
 }
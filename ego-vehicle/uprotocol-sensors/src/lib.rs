@@ -1,3 +1,13 @@
 pub mod args;
+pub mod chunking;
+pub mod client;
+pub mod codec;
+pub mod diagnostics;
+pub mod discovery;
+pub mod estimation;
 pub mod helpers;
 pub mod sensors;
+pub mod shm;
+pub mod synthetic;
+pub mod tracking;
+pub mod transforms;
@@ -15,41 +15,65 @@
 //
 
 use async_trait::async_trait;
-use carla::client::{ActorBase, Client};
+use carla::client::{ActorBase, Client, Sensor, World};
 use carla::sensor::data::{
-    CollisionEvent, Image as ImageEvent, ImuMeasurement as ImuMeasurementEvent, LaneInvasionEvent,
+    CollisionEvent, GnssMeasurement as GnssMeasurementEvent, Image as ImageEvent,
+    ImuMeasurement as ImuMeasurementEvent, LaneInvasionEvent,
     LidarMeasurement as LidarMeasurementEvent, ObstacleDetectionEvent,
     RadarMeasurement as RadarMeasurementEvent,
+    SemanticLidarMeasurement as SemanticLidarMeasurementEvent,
 };
 use carla_data_serde::{
     CollisionEventSerDe, ImageEventSerBorrowed, ImuMeasurementSerDe, LaneInvasionEventSerDe,
     LidarMeasurementSerBorrowed, ObstacleDetectionEventSerDe, RadarMeasurementSerBorrowed,
 };
 use clap::Parser;
-use ego_vehicle::args::Args;
-use ego_vehicle::helpers::setup_sensor_with_transport;
+use ego_vehicle::args::{Args, zenoh_config};
+use ego_vehicle::codec::{
+    ImageEncoder, ImageEncoding, PointCloud, PointCloudEncoding, PointDatatype,
+};
+use ego_vehicle::diagnostics::{DiagnosticsReport, HealthThresholds, SensorMonitor, expected_rate};
+use ego_vehicle::discovery::{
+    DiscoveredSensor, ManifestEntry, SensorKind, SensorManifest, sensor_transforms,
+};
+use ego_vehicle::helpers::{
+    SensorSettings, ViewFactory, attach_sensor_with_transport, publish_source,
+    wait_for_actor_id_by_role, wait_for_sensors,
+};
 use ego_vehicle::sensors::{
-    CollisionFactory, ImageFactory, ImuMeasurementFactory, LaneInvasionFactory,
-    LidarMeasurementFactory, ObstacleDetectionFactory, RadarMeasurementFactory,
+    CollisionFactory, GnssFix, GnssMeasurementFactory, ImageFactory, ImuMeasurementFactory,
+    LaneInvasionFactory, LidarMeasurementFactory, ObstacleDetectionFactory, PublishConfigs,
+    RadarMeasurementFactory, SemanticLidarMeasurementFactory, SensorComms, SensorSource,
+    encode_depth_image, encode_image, encode_segmentation_image, gnss_fix, lidar_point_cloud,
+    radar_point_cloud, semantic_lidar_point_cloud,
 };
+use ego_vehicle::shm::{ShmPool, ShmPublisher, enable_shared_memory, shm_pool};
+use ego_vehicle::synthetic::{
+    CollisionGenerator, Generator, GnssGenerator, ImuGenerator, LaneInvasionGenerator,
+    LidarGenerator, RadarGenerator, Rng, SYNTHETIC_KINDS, SyntheticClock, SyntheticCollision,
+    SyntheticImu, SyntheticLaneInvasion, SyntheticSource, SyntheticWorld, synthetic_sensor,
+    synthetic_transforms,
+};
+use ego_vehicle::transforms::{self, Transform, TransformServer, TransformTree};
 use log;
 use serde_json;
+use std::any::Any;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use up_rust::{
     LocalUriProvider, StaticUriProvider, UListener, UMessage, UMessageBuilder, UPayloadFormat,
-    UTransport, UUri,
+    UStatus, UTransport, UUri,
 };
 use up_transport_zenoh::UPTransportZenoh;
-use up_transport_zenoh::zenoh_config;
-use zenoh::{Config, key_expr::KeyExpr};
+use zenoh::{Config, Session, key_expr::KeyExpr};
 
 // General constants
 const CLIENT_TIME_MS: u64 = 5_000;
 const POLLING_EGO_MS: u64 = 1_000;
 const WAITING_PUB_MS: u64 = 1;
+const MANIFEST_PERIOD: Duration = Duration::from_secs(5);
 // Vehicle control constants
 const MIN_THROTTLE: f32 = 0.0;
 const MIN_STEERING: f32 = -1.0;
@@ -59,35 +83,15 @@ const MAX_THROTTLE: f32 = 1.0;
 const MAX_STEERING: f32 = 1.0;
 const MAX_BRAKING: f32 = 1.0;
 
-// uProtocol resource IDs
+// uProtocol resource IDs; 0x8003 to 0x8007 are the ego bridge's topics on the same
+// entity, so the topics of this bridge start at 0x8020
 const RESOURCE_VELOCITY_STATUS: u16 = 0x8001;
 const RESOURCE_CLOCK_STATUS: u16 = 0x8002;
-// uProtocol resource IDs for sensors
-const RESOURCE_LANE_INVASION_SENSOR: u16 = 0x8010;
-const RESOURCE_COLLISION_SENSOR: u16 = 0x8011;
-const RESOURCE_OBSTACLE_DETECTION_SENSOR: u16 = 0x8012;
-const RESOURCE_IMAGE_SENSOR: u16 = 0x8013;
-const RESOURCE_RADAR_SENSOR: u16 = 0x8014;
-const RESOURCE_LIDAR_SENSOR: u16 = 0x8015;
-const RESOURCE_IMU_SENSOR: u16 = 0x8016;
-
-// Helper function to create a Zenoh configuration
-pub(crate) fn get_zenoh_config() -> zenoh_config::Config {
-    let args = Args::parse();
-
-    let zenoh_string = if let Some(router) = &args.router {
-        format!(
-            "{{ mode: 'peer', connect: {{ endpoints: [ 'tcp/{}:7447' ] }} }}",
-            router
-        )
-    } else {
-        "{ mode: 'peer' }".to_string()
-    };
-
-    let zenoh_config = Config::from_json5(&zenoh_string).expect("Failed to load Zenoh config");
-
-    zenoh_config
-}
+const RESOURCE_SENSOR_MANIFEST: u16 = 0x8020;
+const RESOURCE_SENSOR_DIAGNOSTICS: u16 = 0x8021;
+const RESOURCE_TRANSFORM_TREE: u16 = 0x8022;
+// uProtocol resource IDs of the sensors: see `SensorKind::default_resource_id` and
+// `ResourceAllocator`
 
 // Listener for actuation command - implements the UListener trait for uProtocol
 struct ActuationListener {
@@ -135,10 +139,748 @@ impl UListener for EngageListener {
     }
 }
 
+// Encoders of the sensors with a choice of payload
+#[derive(Clone, Copy)]
+struct SensorEncoders {
+    image: ImageEncoder,
+    radar: PointCloudEncoding,
+    lidar: PointCloudEncoding,
+    segmentation: ImageEncoding,
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Attaches to `sensor` with the view of `factory` and the encoder `encode`
+fn attach<F, Encode>(
+    carla_world: &World,
+    sensor: &DiscoveredSensor,
+    settings: SensorSettings,
+    factory: F,
+    encode: Encode,
+    payload_format: UPayloadFormat,
+) -> Result<(SensorComms, Sensor), BoxError>
+where
+    F: ViewFactory,
+    Encode: for<'a> Fn(<F::View<'a> as SensorSource>::Data) -> Result<Vec<u8>, BoxError>
+        + Send
+        + Sync
+        + 'static,
+{
+    attach_sensor_with_transport(
+        carla_world,
+        sensor.actor_id,
+        factory,
+        encode,
+        payload_format,
+        settings,
+    )
+}
+
+// Attaches to `sensor` with the view and encoder of its type.
+// Returns `(comms, sensor_keepalive, payload_format, encoding)`.
+fn attach_sensor(
+    carla_world: &World,
+    sensor: &DiscoveredSensor,
+    settings: SensorSettings,
+    encoders: &SensorEncoders,
+) -> Result<(SensorComms, Sensor, UPayloadFormat, String), BoxError> {
+    const JSON: UPayloadFormat = UPayloadFormat::UPAYLOAD_FORMAT_JSON;
+
+    let ((comms, keepalive), payload_format, encoding) = match sensor.kind {
+        SensorKind::LaneInvasion => {
+            // Encoder: LaneInvasionEvent -> Vec<u8>
+            let encode = |evt: LaneInvasionEvent| {
+                let serde_evt: LaneInvasionEventSerDe = evt.into();
+                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                LaneInvasionFactory,
+                encode,
+                JSON,
+            )?;
+            (attached, JSON, "json".to_string())
+        }
+        SensorKind::Collision => {
+            // Encoder: CollisionEvent -> Vec<u8>
+            let encode = |evt: CollisionEvent| {
+                let serde_evt: CollisionEventSerDe = evt.into();
+                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                CollisionFactory,
+                encode,
+                JSON,
+            )?;
+            (attached, JSON, "json".to_string())
+        }
+        SensorKind::ObstacleDetection => {
+            // Encoder: ObstacleDetectionEvent -> Vec<u8>
+            let encode = |evt: ObstacleDetectionEvent| {
+                let serde_evt: ObstacleDetectionEventSerDe = evt.into();
+                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                ObstacleDetectionFactory,
+                encode,
+                JSON,
+            )?;
+            (attached, JSON, "json".to_string())
+        }
+        SensorKind::Image => {
+            // Encoder: ImageEvent -> Vec<u8>, as JSON (borrow-only) or as a binary image
+            let image_encoder = encoders.image;
+            let encode = move |evt: ImageEvent| {
+                if image_encoder.encoding != ImageEncoding::Json {
+                    return encode_image(&image_encoder, &evt);
+                }
+                // Borrow the event so the payload can serialize without copying the image buffer
+                let serde_evt: ImageEventSerBorrowed<'_> = (&evt).into();
+                serde_json::to_vec(&serde_evt).map_err(|e| -> BoxError { Box::new(e) })
+            };
+            let payload_format = image_encoder.encoding.payload_format();
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                ImageFactory,
+                encode,
+                payload_format,
+            )?;
+            (attached, payload_format, image_encoder.encoding.to_string())
+        }
+        SensorKind::RadarMeasurement => {
+            // Encoder: RadarMeasurementEvent -> Vec<u8>, as JSON or as packed points
+            let radar_encoding = encoders.radar;
+            let encode = move |evt: RadarMeasurementEvent| {
+                if let PointCloudEncoding::Packed(datatype) = radar_encoding {
+                    return radar_point_cloud(&evt).encode(datatype);
+                }
+                // Borrowed, zero-copy serializer
+                let serde_evt: RadarMeasurementSerBorrowed<'_> = (&evt).into();
+                serde_json::to_vec(&serde_evt).map_err(|e| -> BoxError { Box::new(e) })
+            };
+            let payload_format = radar_encoding.payload_format();
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                RadarMeasurementFactory,
+                encode,
+                payload_format,
+            )?;
+            (attached, payload_format, radar_encoding.to_string())
+        }
+        SensorKind::LidarMeasurement => {
+            // Encoder: LidarMeasurementEvent -> Vec<u8>, as JSON (borrow-only) or as packed points
+            let lidar_encoding = encoders.lidar;
+            let encode = move |evt: LidarMeasurementEvent| {
+                if let PointCloudEncoding::Packed(datatype) = lidar_encoding {
+                    return lidar_point_cloud(&evt).encode(datatype);
+                }
+                let serde_evt: LidarMeasurementSerBorrowed<'_> = (&evt).into();
+                serde_json::to_vec(&serde_evt).map_err(|e| -> BoxError { Box::new(e) })
+            };
+            let payload_format = lidar_encoding.payload_format();
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                LidarMeasurementFactory,
+                encode,
+                payload_format,
+            )?;
+            (attached, payload_format, lidar_encoding.to_string())
+        }
+        SensorKind::ImuMeasurement => {
+            // Encoder: ImuMeasurementEvent -> Vec<u8>
+            let encode = |evt: ImuMeasurementEvent| {
+                let serde_evt: ImuMeasurementSerDe = evt.into();
+                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                ImuMeasurementFactory,
+                encode,
+                JSON,
+            )?;
+            (attached, JSON, "json".to_string())
+        }
+        SensorKind::GnssMeasurement => {
+            // Encoder: GnssMeasurementEvent -> Vec<u8>
+            let encode = |evt: GnssMeasurementEvent| {
+                serde_json::to_vec(&gnss_fix(&evt)).map_err(|e| -> BoxError { Box::new(e) })
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                GnssMeasurementFactory,
+                encode,
+                JSON,
+            )?;
+            (attached, JSON, "json".to_string())
+        }
+        SensorKind::SemanticLidarMeasurement => {
+            // Encoder: SemanticLidarMeasurementEvent -> Vec<u8>, as packed f32 points (object
+            // ids do not fit in half precision)
+            let encoding = PointCloudEncoding::Packed(PointDatatype::F32);
+            let encode = |evt: SemanticLidarMeasurementEvent| {
+                semantic_lidar_point_cloud(&evt).encode(PointDatatype::F32)
+            };
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                SemanticLidarMeasurementFactory,
+                encode,
+                encoding.payload_format(),
+            )?;
+            (attached, encoding.payload_format(), encoding.to_string())
+        }
+        SensorKind::DepthImage => {
+            // Encoder: ImageEvent -> Vec<u8>, as the depth of every pixel
+            let encode = |evt: ImageEvent| encode_depth_image(&evt);
+            let payload_format = UPayloadFormat::UPAYLOAD_FORMAT_RAW;
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                ImageFactory,
+                encode,
+                payload_format,
+            )?;
+            (attached, payload_format, "depth".to_string())
+        }
+        SensorKind::SemanticSegmentationImage => {
+            // Encoder: ImageEvent -> Vec<u8>, as the semantic tag of every pixel
+            let segmentation_encoding = encoders.segmentation;
+            let encode =
+                move |evt: ImageEvent| encode_segmentation_image(&evt, segmentation_encoding);
+            let payload_format = segmentation_encoding.payload_format();
+            let attached = attach(
+                carla_world,
+                sensor,
+                settings,
+                ImageFactory,
+                encode,
+                payload_format,
+            )?;
+            (attached, payload_format, segmentation_encoding.to_string())
+        }
+    };
+
+    Ok((comms, keepalive, payload_format, encoding))
+}
+
+// Creates the uProtocol transport using Zenoh, configured by `config`, as the underlying
+// transport
+async fn uprotocol_transport(
+    uri_provider: &StaticUriProvider,
+    config: &Config,
+) -> Result<Arc<dyn UTransport>, UStatus> {
+    Ok(Arc::new(
+        UPTransportZenoh::builder(uri_provider.get_authority())
+            .expect("invalid authority name")
+            .with_config(config.clone())
+            .build()
+            .await?,
+    ))
+}
+
+// Registers the transform methods of the bridge of `authority`, answering from `tree`
+async fn serve_transforms(
+    transport: &Arc<dyn UTransport>,
+    authority: &str,
+    tree: &TransformTree,
+) -> Result<TransformServer, Box<dyn std::error::Error>> {
+    let uri_provider = Arc::new(StaticUriProvider::new(
+        authority,
+        transforms::TRANSFORM_SERVICE_ID,
+        transforms::TRANSFORM_SERVICE_VERSION,
+    ));
+    Ok(transforms::serve(Arc::clone(transport), uri_provider, tree.clone()).await?)
+}
+
+fn sensor_encoders(args: &Args) -> SensorEncoders {
+    let encoders = SensorEncoders {
+        image: args.image_encoder(),
+        radar: args.radar_encoding,
+        lidar: args.lidar_encoding,
+        segmentation: args.segmentation_encoding,
+    };
+    log::info!(
+        "Encoding images as {} [scale: {}], radar measurements as {}, lidar measurements as {}",
+        encoders.image.encoding,
+        encoders.image.scale,
+        encoders.radar,
+        encoders.lidar
+    );
+    encoders
+}
+
+// Session and pool publishing the high-bandwidth sensors through shared memory, if enabled
+async fn shared_memory(args: &Args, config: &Config) -> Option<(Session, Arc<ShmPool>)> {
+    if !args.shm {
+        return None;
+    }
+    let pool =
+        shm_pool(args.shm_pool_mb * 1024 * 1024).expect("Unable to create the shared-memory pool");
+    let mut shm_config = config.clone();
+    enable_shared_memory(&mut shm_config).expect("Unable to configure shared memory");
+    let shm_session = zenoh::open(shm_config).await.unwrap();
+    log::info!(
+        "Shared-memory pool of {} MiB for the camera and lidar sensors",
+        args.shm_pool_mb
+    );
+    Some((shm_session, pool))
+}
+
+// What `attach` returns: `(comms, keepalive, payload_format, encoding)`
+type Attached = (SensorComms, Box<dyn Any>, UPayloadFormat, String);
+
+// A sensor being bridged, with the handles to keep alive
+struct BridgedSensor {
+    comms: SensorComms,
+    monitor: Arc<SensorMonitor>,
+    _keepalive: Box<dyn Any>,
+}
+
+// Health of every bridged sensor at the simulation time `timestamp`; `present` tells
+// whether a sensor actor is still in the world
+fn diagnostics_report(
+    sensors: &[BridgedSensor],
+    timestamp: f64,
+    present: impl Fn(u32) -> bool,
+) -> DiagnosticsReport {
+    let now = Instant::now();
+    let sensors = sensors
+        .iter()
+        .map(|sensor| {
+            let monitor = &sensor.monitor;
+            monitor.diagnose(now, sensor.comms.stats(), present(monitor.actor_id()))
+        })
+        .collect();
+    DiagnosticsReport::new(timestamp, sensors)
+}
+
+// Clock or velocity status stamped with the simulation time, as the ego bridge publishes
+// its `json` signals, e.g. `{"velocity": 45.2, "frame": 250, "elapsed_seconds": 12.5}`
+fn status_payload(field: Option<(&str, f64)>, frame: u64, elapsed_seconds: f64) -> String {
+    let mut status = serde_json::json!({ "frame": frame, "elapsed_seconds": elapsed_seconds });
+    if let Some((name, value)) = field {
+        status[name] = serde_json::json!(value);
+    }
+    status.to_string()
+}
+
+// Sets up every sensor with `attach`, and describes it in the manifest.
+// Returns the manifest and the sensors to keep alive and monitor.
+async fn bridge_sensors<A>(
+    args: &Args,
+    sensors: &[DiscoveredSensor],
+    uri_provider: &StaticUriProvider,
+    transport: &Arc<dyn UTransport>,
+    publish: &PublishConfigs,
+    shm: Option<&(Session, Arc<ShmPool>)>,
+    mut attach: A,
+) -> Result<(SensorManifest, Vec<BridgedSensor>), Box<dyn std::error::Error>>
+where
+    A: FnMut(&DiscoveredSensor, SensorSettings) -> Result<Attached, BoxError>,
+{
+    let mut resources = args.resource_allocator();
+    let mut manifest = SensorManifest::default();
+    let mut bridged = Vec::with_capacity(sensors.len());
+
+    for sensor in sensors {
+        let resource_id = resources.allocate(sensor)?;
+        let uuri = uri_provider.get_resource_uri(resource_id);
+
+        let shm_publisher = match (shm, sensor.kind) {
+            (
+                Some((shm_session, pool)),
+                SensorKind::Image
+                | SensorKind::DepthImage
+                | SensorKind::SemanticSegmentationImage
+                | SensorKind::LidarMeasurement
+                | SensorKind::SemanticLidarMeasurement,
+            ) => {
+                let key = format!(
+                    "{}/{}/{}",
+                    args.shm_key_prefix, sensor.kind, sensor.role_name
+                );
+                let publisher = ShmPublisher::declare(shm_session, key, Arc::clone(pool))
+                    .await
+                    .expect("Unable to declare a shared-memory publisher");
+                Some(Arc::new(publisher))
+            }
+            _ => None,
+        };
+
+        log::info!(
+            "Bridging {} sensor '{}' (id={}, {}) on resource {resource_id:#06x}",
+            sensor.kind,
+            sensor.role_name,
+            sensor.actor_id,
+            sensor.type_id
+        );
+        let monitor = Arc::new(SensorMonitor::new(
+            sensor,
+            expected_rate(sensor, args.delta),
+            HealthThresholds::default(),
+        ));
+        let settings = SensorSettings {
+            role_name: sensor.role_name.clone(),
+            uuri: uuri.clone(),
+            transport: Arc::clone(transport),
+            queue: args.sensor_queue_config(sensor),
+            publish: publish.get_for(&sensor.config_name(), sensor.kind.name()),
+            chunk_size: args.chunk_size,
+            shm: shm_publisher.clone(),
+            shm_uprotocol: args.shm_uprotocol,
+            monitor: Arc::clone(&monitor),
+        };
+        let (comms, keepalive, payload_format, encoding) = attach(sensor, settings)
+            .map_err(|e| format!("Unable to set up sensor '{}': {e}", sensor.role_name))?;
+
+        manifest.sensors.push(ManifestEntry {
+            sensor: sensor.kind.name().to_string(),
+            role_name: sensor.role_name.clone(),
+            type_id: sensor.type_id.clone(),
+            actor_id: sensor.actor_id,
+            resource_id,
+            uri: uuri.to_uri(false),
+            payload_format: format!("{payload_format:?}"),
+            encoding,
+            shm_key: shm_publisher.map(|publisher| publisher.key().to_string()),
+            attributes: sensor.attributes.clone(),
+        });
+        bridged.push(BridgedSensor {
+            comms,
+            monitor,
+            _keepalive: keepalive,
+        });
+    }
+
+    Ok((manifest, bridged))
+}
+
+// Publishes the events of a synthetic source
+fn publish_synthetic<G, Encode>(
+    settings: SensorSettings,
+    source: SyntheticSource<G>,
+    encode: Encode,
+    payload_format: UPayloadFormat,
+    encoding: &str,
+) -> Attached
+where
+    G: Generator,
+    Encode: Fn(G::Data) -> Result<Vec<u8>, BoxError> + Send + Sync + 'static,
+{
+    let comms = publish_source(&source, encode, payload_format, settings);
+    let keepalive = Box::new(source) as Box<dyn Any>;
+    (comms, keepalive, payload_format, encoding.to_string())
+}
+
+// Starts the synthetic sensor `sensor`, with the generator of its type, and publishes its
+// events as a CARLA sensor of that type would be
+fn attach_synthetic(
+    sensor: &DiscoveredSensor,
+    settings: SensorSettings,
+    encoders: &SensorEncoders,
+    world: &Arc<SyntheticWorld>,
+    clock: SyntheticClock,
+    rate: f64,
+    seed: u64,
+) -> Result<Attached, BoxError> {
+    const JSON: UPayloadFormat = UPayloadFormat::UPAYLOAD_FORMAT_JSON;
+
+    let rng = Rng::new(seed ^ u64::from(sensor.actor_id).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let world = Arc::clone(world);
+    let name = sensor.role_name.as_str();
+
+    // Point clouds are always packed: JSON is the format of the CARLA events only
+    let packed = |encoding: PointCloudEncoding| match encoding {
+        PointCloudEncoding::Packed(datatype) => datatype,
+        PointCloudEncoding::Json => {
+            log::warn!("{name}: synthetic point clouds are published packed, not as JSON");
+            PointDatatype::F32
+        }
+    };
+
+    let attached = match sensor.kind {
+        SensorKind::LaneInvasion => {
+            let generator = LaneInvasionGenerator {
+                rng,
+                rate,
+                last_timestamp: None,
+            };
+            let source = SyntheticSource::new(name, generator, world, clock, 1);
+            let encode =
+                |evt: SyntheticLaneInvasion| serde_json::to_vec(&evt).map_err(|e| e.into());
+            publish_synthetic(settings, source, encode, JSON, "json")
+        }
+        SensorKind::Collision => {
+            let generator = CollisionGenerator {
+                rng,
+                rate,
+                last_timestamp: None,
+            };
+            let source = SyntheticSource::new(name, generator, world, clock, 1);
+            let encode = |evt: SyntheticCollision| serde_json::to_vec(&evt).map_err(|e| e.into());
+            publish_synthetic(settings, source, encode, JSON, "json")
+        }
+        SensorKind::ImuMeasurement => {
+            let generator = ImuGenerator {
+                rng,
+                accelerometer_noise: 0.05,
+                gyroscope_noise: 0.002,
+            };
+            let source = SyntheticSource::new(name, generator, world, clock, clock.ticks(rate));
+            let encode = |evt: SyntheticImu| serde_json::to_vec(&evt).map_err(|e| e.into());
+            publish_synthetic(settings, source, encode, JSON, "json")
+        }
+        SensorKind::GnssMeasurement => {
+            let generator = GnssGenerator { rng, noise: 0.5 };
+            let source = SyntheticSource::new(name, generator, world, clock, clock.ticks(rate));
+            let encode = |evt: GnssFix| serde_json::to_vec(&evt).map_err(|e| e.into());
+            publish_synthetic(settings, source, encode, JSON, "json")
+        }
+        SensorKind::LidarMeasurement => {
+            let datatype = packed(encoders.lidar);
+            let generator = LidarGenerator::new(rng);
+            let source = SyntheticSource::new(name, generator, world, clock, clock.ticks(rate));
+            let encode = move |cloud: PointCloud| cloud.encode(datatype);
+            let encoding = PointCloudEncoding::Packed(datatype);
+            let payload_format = encoding.payload_format();
+            publish_synthetic(
+                settings,
+                source,
+                encode,
+                payload_format,
+                &encoding.to_string(),
+            )
+        }
+        SensorKind::RadarMeasurement => {
+            let datatype = packed(encoders.radar);
+            let generator = RadarGenerator::new(rng);
+            let source = SyntheticSource::new(name, generator, world, clock, clock.ticks(rate));
+            let encode = move |cloud: PointCloud| cloud.encode(datatype);
+            let encoding = PointCloudEncoding::Packed(datatype);
+            let payload_format = encoding.payload_format();
+            publish_synthetic(
+                settings,
+                source,
+                encode,
+                payload_format,
+                &encoding.to_string(),
+            )
+        }
+        kind => return Err(format!("{kind} sensors cannot be simulated").into()),
+    };
+    Ok(attached)
+}
+
+// Sensor types and their `--ego-vehicle-sensor-*-role` options
+fn sensor_roles(args: &Args) -> [(SensorKind, &Option<String>); 11] {
+    [
+        (
+            SensorKind::LaneInvasion,
+            &args.ego_vehicle_sensor_lane_invasion_role,
+        ),
+        (
+            SensorKind::Collision,
+            &args.ego_vehicle_sensor_collision_role,
+        ),
+        (
+            SensorKind::ObstacleDetection,
+            &args.ego_vehicle_sensor_obstacle_detection_role,
+        ),
+        (SensorKind::Image, &args.ego_vehicle_sensor_image_role),
+        (
+            SensorKind::RadarMeasurement,
+            &args.ego_vehicle_sensor_radar_measurement_role,
+        ),
+        (
+            SensorKind::LidarMeasurement,
+            &args.ego_vehicle_sensor_lidar_measurement_role,
+        ),
+        (
+            SensorKind::ImuMeasurement,
+            &args.ego_vehicle_sensor_imu_measurement_role,
+        ),
+        (
+            SensorKind::GnssMeasurement,
+            &args.ego_vehicle_sensor_gnss_measurement_role,
+        ),
+        (
+            SensorKind::SemanticLidarMeasurement,
+            &args.ego_vehicle_sensor_semantic_lidar_measurement_role,
+        ),
+        (
+            SensorKind::DepthImage,
+            &args.ego_vehicle_sensor_depth_image_role,
+        ),
+        (
+            SensorKind::SemanticSegmentationImage,
+            &args.ego_vehicle_sensor_semantic_segmentation_image_role,
+        ),
+    ]
+}
+
+// Publishes synthetic sensors instead of the CARLA ones, with the clock, velocity,
+// manifest, diagnostics and transform topics, until Ctrl-C
+async fn run_synthetic(
+    args: &Args,
+    session_config: &Config,
+    running: &AtomicBool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let world = Arc::new(SyntheticWorld::default());
+    let clock = SyntheticClock::new(args.delta);
+    log::info!(
+        "Simulating the sensors in a scripted world ticking every {} s, without CARLA",
+        args.delta
+    );
+
+    UPTransportZenoh::try_init_log_from_env();
+    let uri_provider = StaticUriProvider::new(&args.authority, 0, 2);
+    let transport = uprotocol_transport(&uri_provider, session_config).await?;
+
+    // The sensors of the role options, or one of every simulated type
+    let mut requested: Vec<(SensorKind, Option<&str>)> = sensor_roles(args)
+        .into_iter()
+        .filter_map(|(kind, role_name)| Some((kind, Some(role_name.as_deref()?))))
+        .collect();
+    if requested.is_empty() {
+        requested = SYNTHETIC_KINDS.iter().map(|kind| (*kind, None)).collect();
+    }
+    let mut sensors = Vec::with_capacity(requested.len());
+    for (kind, role_name) in requested {
+        if !SYNTHETIC_KINDS.contains(&kind) {
+            log::warn!("{kind} sensors cannot be simulated, skipping them");
+            continue;
+        }
+        let actor_id = sensors.len() as u32 + 1;
+        let role_name = role_name.map_or_else(|| format!("{kind}_{actor_id}"), str::to_string);
+        let rate = args.synthetic_rate(kind, &role_name);
+        sensors.push(synthetic_sensor(kind, actor_id, &role_name, rate));
+    }
+
+    let publish = args
+        .publish_configs()
+        .map_err(|e| format!("Unable to load the sensor publish settings: {e}"))?;
+    let encoders = sensor_encoders(args);
+    let shm = shared_memory(args, session_config).await;
+
+    let (manifest, bridged) = bridge_sensors(
+        args,
+        &sensors,
+        &uri_provider,
+        &transport,
+        &publish,
+        shm.as_ref(),
+        |sensor, settings| {
+            let rate = args.synthetic_rate(sensor.kind, &sensor.role_name);
+            attach_synthetic(
+                sensor,
+                settings,
+                &encoders,
+                &world,
+                clock,
+                rate,
+                args.synthetic_seed,
+            )
+        },
+    )
+    .await?;
+
+    let clock_topic = uri_provider.get_resource_uri(RESOURCE_CLOCK_STATUS);
+    let velocity_topic = uri_provider.get_resource_uri(RESOURCE_VELOCITY_STATUS);
+    let manifest_topic = uri_provider.get_resource_uri(RESOURCE_SENSOR_MANIFEST);
+    let manifest_payload = serde_json::to_string(&manifest)?;
+    let mut last_manifest: Option<Instant> = None;
+    let tree = synthetic_transforms(&sensors);
+    let transform_topic = uri_provider.get_resource_uri(RESOURCE_TRANSFORM_TREE);
+    let transform_payload = serde_json::to_string(&tree)?;
+    let transform_server = serve_transforms(&transport, &args.authority, &tree).await?;
+    let diagnostics_topic = uri_provider.get_resource_uri(RESOURCE_SENSOR_DIAGNOSTICS);
+    let diagnostics_period = Duration::from_secs_f64(args.diagnostics_period);
+    let mut last_diagnostics: Option<Instant> = None;
+
+    // One iteration per tick of the synthetic world
+    while running.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_secs_f64(args.delta)).await;
+        let frame = clock.frame();
+        let elapsed = clock.timestamp(frame);
+
+        let clock_message = UMessageBuilder::publish(clock_topic.clone()).build_with_payload(
+            status_payload(None, frame, elapsed),
+            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+        )?;
+        transport.send(clock_message).await?;
+
+        let ego = world.ego_at(elapsed);
+        transform_server
+            .endpoint()
+            .set_vehicle_pose(elapsed, ego.transform());
+
+        let velocity = 3.6 * ego.speed;
+        let velocity_message = UMessageBuilder::publish(velocity_topic.clone())
+            .build_with_payload(
+                status_payload(Some(("velocity", velocity)), frame, elapsed),
+                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            )?;
+        transport.send(velocity_message).await?;
+
+        if last_manifest.is_none_or(|last| last.elapsed() >= MANIFEST_PERIOD) {
+            let manifest_message = UMessageBuilder::publish(manifest_topic.clone())
+                .build_with_payload(
+                    manifest_payload.clone(),
+                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                )?;
+            transport.send(manifest_message).await?;
+            let transform_message = UMessageBuilder::publish(transform_topic.clone())
+                .build_with_payload(
+                    transform_payload.clone(),
+                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                )?;
+            transport.send(transform_message).await?;
+            last_manifest = Some(Instant::now());
+        }
+
+        // Synthetic sensors never leave the world
+        if last_diagnostics.is_none_or(|last| last.elapsed() >= diagnostics_period) {
+            let report = diagnostics_report(&bridged, elapsed, |_| true);
+            let diagnostics_message = UMessageBuilder::publish(diagnostics_topic.clone())
+                .build_with_payload(
+                    serde_json::to_string(&report)?,
+                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                )?;
+            transport.send(diagnostics_message).await?;
+            last_diagnostics = Some(Instant::now());
+        }
+    }
+
+    log::info!("Exiting the synthetic loop. Bye!");
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // -- Parse command line arguments --
     let args = Args::parse();
+    let session_config = zenoh_config(args.router.as_deref())
+        .map_err(|e| format!("Unable to load the Zenoh configuration: {e}"))?;
 
     // Initiate logging
     pretty_env_logger::init();
@@ -153,6 +895,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     })
     .expect("Error setting Ctrl-C handler");
 
+    if args.synthetic {
+        return run_synthetic(&args, &session_config, &running).await;
+    }
+
     // -- CARLA configuration --
 
     // Connect to the Carla Server
@@ -216,16 +962,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create a uProtocol URI provider for this vehicle
     // This defines the identity of this node in the uProtocol network
-    let uri_provider = StaticUriProvider::new("EGOVehicle", 0, 2);
+    let uri_provider = StaticUriProvider::new(&args.authority, 0, 2);
 
     // Create the uProtocol transport using Zenoh as the underlying transport
-    let transport: Arc<dyn UTransport> = Arc::new(
-        UPTransportZenoh::builder(uri_provider.get_authority())
-            .expect("invalid authority name")
-            .with_config(get_zenoh_config())
-            .build()
-            .await?,
-    );
+    let transport = uprotocol_transport(&uri_provider, &session_config).await?;
 
     // Create shared data structures for uProtocol subscribers
     // These will store the latest values received from uProtocol messages
@@ -271,7 +1011,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let velocity_topic = uri_provider.get_resource_uri(RESOURCE_VELOCITY_STATUS);
 
     // Set up Zenoh session for traditional Zenoh subscribers
-    let zenoh_session = zenoh::open(get_zenoh_config()).await.unwrap();
+    let zenoh_session = zenoh::open(session_config.clone()).await.unwrap();
 
     // Define Zenoh topics to subscribe to
     let topic_throttle = KeyExpr::new("vehicle/status/throttle_status").unwrap();
@@ -359,308 +1099,84 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    // -- Set up Sensor for Lane Invasion -- (generic)
-    let (_lane_comms, _ego_vehicle_sensor_lane_invasion_id, _lane_sensor_keepalive) =
-        if let Some(ego_vehicle_sensor_lane_invasion_role) =
-            args.ego_vehicle_sensor_lane_invasion_role
-        {
-            let uuri = uri_provider.get_resource_uri(RESOURCE_LANE_INVASION_SENSOR);
+    // Which events of every sensor are published
+    let publish = args
+        .publish_configs()
+        .map_err(|e| format!("Unable to load the sensor publish settings: {e}"))?;
+    let encoders = sensor_encoders(&args);
 
-            // Encoder: LaneInvasionEvent -> Vec<u8>
-            let encode = |evt: LaneInvasionEvent| {
-                let serde_evt: LaneInvasionEventSerDe = evt.into();
-                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
-            };
-
-            let (_lane_comms, lane_actor_id, _lane_sensor_keepalive) = setup_sensor_with_transport(
-                &carla_world,
-                &running,
-                &ego_vehicle_sensor_lane_invasion_role,
-                "lane_invasion_sensor",
-                POLLING_EGO_MS,
-                LaneInvasionFactory,
-                uuri,
-                encode,
-                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-                Arc::clone(&transport),
-            )
-            .await
-            .expect("Unable to set up lane sensor with transport");
-
-            let _ego_vehicle_sensor_lane_invasion_id = Some(lane_actor_id);
-
-            (
-                Some(_lane_comms),
-                Some(_ego_vehicle_sensor_lane_invasion_id),
-                Some(_lane_sensor_keepalive),
-            )
-        } else {
-            (None, None, None)
-        };
-
-    // -- Set up Sensor for Collision -- (generic)
-    let (_collision_comms, _ego_vehicle_sensor_collision_id, _collision_sensor_keepalive) =
-        if let Some(ego_vehicle_sensor_collision_role) = args.ego_vehicle_sensor_collision_role {
-            let uuri = uri_provider.get_resource_uri(RESOURCE_COLLISION_SENSOR);
+    // Shared-memory pool of the high-bandwidth sensors, if enabled
+    let shm = shared_memory(&args, &session_config).await;
 
-            // Encoder: CollisionEvent -> Vec<u8>
-            let encode = |evt: CollisionEvent| {
-                let serde_evt: CollisionEventSerDe = evt.into();
-                serde_json::to_vec(&serde_evt).map_err(|e| e.into())
-            };
-
-            let (_collision_comms, collision_actor_id, _collision_sensor_keepalive) =
-                setup_sensor_with_transport(
-                    &carla_world,
-                    &running,
-                    &ego_vehicle_sensor_collision_role,
-                    "collision_sensor",
-                    POLLING_EGO_MS,
-                    CollisionFactory,
-                    uuri,
-                    encode,
-                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-                    Arc::clone(&transport),
-                )
-                .await
-                .expect("Unable to set up collision sensor with transport");
-
-            let _ego_vehicle_sensor_collision_id = Some(collision_actor_id);
-
-            (
-                Some(_collision_comms),
-                Some(_ego_vehicle_sensor_collision_id),
-                Some(_collision_sensor_keepalive),
-            )
-        } else {
-            (None, None, None)
-        };
-
-    // -- Set up Sensor for Obstacle Detection -- (generic)
-    let (
-        _obstacle_detection_comms,
-        _ego_vehicle_sensor_obstacle_detection_id,
-        _obstacle_detection_sensor_keepalive,
-    ) = if let Some(ego_vehicle_sensor_obstacle_detection_role) =
-        args.ego_vehicle_sensor_obstacle_detection_role
-    {
-        let uuri = uri_provider.get_resource_uri(RESOURCE_OBSTACLE_DETECTION_SENSOR);
-
-        // Encoder: ObstacleDetectionEvent -> Vec<u8>
-        let encode = |evt: ObstacleDetectionEvent| {
-            let serde_evt: ObstacleDetectionEventSerDe = evt.into();
-            serde_json::to_vec(&serde_evt).map_err(|e| e.into())
-        };
-
-        let (
-            _obstacle_detection_comms,
-            obstacle_detection_actor_id,
-            _obstacle_detection_sensor_keepalive,
-        ) = setup_sensor_with_transport(
+    // -- Find the sensors to bridge: attached to the ego vehicle, and by role name --
+    let mut sensors = if args.discover_sensors {
+        wait_for_sensors(
             &carla_world,
             &running,
-            &ego_vehicle_sensor_obstacle_detection_role,
-            "obstacle_detection_sensor",
+            ego_vehicle_id.unwrap(),
             POLLING_EGO_MS,
-            ObstacleDetectionFactory,
-            uuri,
-            encode,
-            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-            Arc::clone(&transport),
         )
         .await
-        .expect("Unable to set up obstacle detection sensor with transport");
-
-        let _ego_vehicle_sensor_obstacle_detection_id = Some(obstacle_detection_actor_id);
-
-        (
-            Some(_obstacle_detection_comms),
-            Some(_ego_vehicle_sensor_obstacle_detection_id),
-            Some(_obstacle_detection_sensor_keepalive),
-        )
+        .map_err(|e| format!("Unable to discover the sensors of the ego vehicle: {e}"))?
     } else {
-        (None, None, None)
+        Vec::new()
     };
 
-    // -- Set up Sensor for Image -- (generic)
-    let (_image_comms, _ego_vehicle_sensor_image_id, _image_sensor_keepalive) =
-        if let Some(ego_vehicle_sensor_image_role) = args.ego_vehicle_sensor_image_role {
-            let uuri = uri_provider.get_resource_uri(RESOURCE_IMAGE_SENSOR);
-
-            // Encoder: ImageEvent -> Vec<u8> (borrow-only)
-            let encode = |evt: ImageEvent| {
-                // Borrow the event so the payload can serialize without copying the image buffer
-                let serde_evt: ImageEventSerBorrowed<'_> = (&evt).into();
-                serde_json::to_vec(&serde_evt)
-                    .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
-            };
-
-            let (_image_comms, image_actor_id, _image_sensor_keepalive) =
-                setup_sensor_with_transport(
-                    &carla_world,
-                    &running,
-                    &ego_vehicle_sensor_image_role,
-                    "image_sensor",
-                    POLLING_EGO_MS,
-                    ImageFactory,
-                    uuri,
-                    encode,
-                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-                    Arc::clone(&transport),
-                )
-                .await
-                .expect("Unable to set up obstacle detection sensor with transport");
-
-            let _ego_vehicle_sensor_image_id = Some(image_actor_id);
-
-            (
-                Some(_image_comms),
-                Some(_ego_vehicle_sensor_image_id),
-                Some(_image_sensor_keepalive),
-            )
-        } else {
-            (None, None, None)
-        };
-
-    // -- Set up Sensor for RadarMeasurement -- (generic)
-    let (
-        _radar_measurement_comms,
-        _ego_vehicle_sensor_radar_measurement_id,
-        _radar_measurement_sensor_keepalive,
-    ) = if let Some(ego_vehicle_sensor_radar_measurement_role) =
-        args.ego_vehicle_sensor_radar_measurement_role
-    {
-        let uuri = uri_provider.get_resource_uri(RESOURCE_RADAR_SENSOR);
-
-        // Encoder: RadarMeasurementEvent -> Vec<u8>
-        let encode = |evt: RadarMeasurementEvent| {
-            // Borrowed, zero-copy serializer
-            let serde_evt: RadarMeasurementSerBorrowed<'_> = (&evt).into();
-            serde_json::to_vec(&serde_evt)
-                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
+    let sensor_roles = sensor_roles(&args);
+    for (kind, role_name) in sensor_roles {
+        let Some(role_name) = role_name else {
+            continue;
         };
-
-        let (
-            _radar_measurement_comms,
-            radar_measurement_actor_id,
-            _radar_measurement_sensor_keepalive,
-        ) = setup_sensor_with_transport(
-            &carla_world,
-            &running,
-            &ego_vehicle_sensor_radar_measurement_role,
-            "radar_measurement_sensor",
-            POLLING_EGO_MS,
-            RadarMeasurementFactory,
-            uuri,
-            encode,
-            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-            Arc::clone(&transport),
-        )
-        .await
-        .expect("Unable to set up obstacle detection sensor with transport");
-
-        let _ego_vehicle_sensor_radar_measurement_id = Some(radar_measurement_actor_id);
-
-        (
-            Some(_radar_measurement_comms),
-            Some(_ego_vehicle_sensor_radar_measurement_id),
-            Some(_radar_measurement_sensor_keepalive),
-        )
-    } else {
-        (None, None, None)
-    };
-
-    // -- Set up Sensor for LidarMeasurement -- (generic)
-    let (
-        _lidar_measurement_comms,
-        _ego_vehicle_sensor_lidar_measurement_id,
-        _lidar_measurement_sensor_keepalive,
-    ) = if let Some(ego_vehicle_sensor_lidar_measurement_role) =
-        args.ego_vehicle_sensor_lidar_measurement_role
-    {
-        let uuri = uri_provider.get_resource_uri(RESOURCE_LIDAR_SENSOR);
-
-        // Encoder: LidarMeasurementEvent -> Vec<u8> (borrow-only)
-        let encode = |evt: LidarMeasurementEvent|
-            -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>
-        {
-            let serde_evt: LidarMeasurementSerBorrowed<'_> = (&evt).into();
-            serde_json::to_vec(&serde_evt)
-                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })
-        };
-
-        let (
-            _lidar_measurement_comms,
-            lidar_measurement_actor_id,
-            _lidar_measurement_sensor_keepalive,
-        ) = setup_sensor_with_transport(
-            &carla_world,
-            &running,
-            &ego_vehicle_sensor_lidar_measurement_role,
-            "lidar_measurement_sensor",
-            POLLING_EGO_MS,
-            LidarMeasurementFactory,
-            uuri,
-            encode,
-            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-            Arc::clone(&transport),
-        )
-        .await
-        .expect("Unable to set up lidar measurement sensor with transport");
-
-        let _ego_vehicle_sensor_lidar_measurement_id = Some(lidar_measurement_actor_id);
-
-        (
-            Some(_lidar_measurement_comms),
-            Some(_ego_vehicle_sensor_lidar_measurement_id),
-            Some(_lidar_measurement_sensor_keepalive),
-        )
-    } else {
-        (None, None, None)
-    };
-
-    // -- Set up Sensor for ImuMeasurement -- (generic)
-    let (
-        _imu_measurement_comms,
-        _ego_vehicle_sensor_imu_measurement_id,
-        _imu_measurement_sensor_keepalive,
-    ) = if let Some(ego_vehicle_sensor_imu_measurement_role) =
-        args.ego_vehicle_sensor_imu_measurement_role
-    {
-        let uuri = uri_provider.get_resource_uri(RESOURCE_IMU_SENSOR);
-
-        // Encoder: ImuMeasurementEvent -> Vec<u8> (borrow-only)
-        let encode = |evt: ImuMeasurementEvent| {
-            let serde_evt: ImuMeasurementSerDe = evt.into();
-            serde_json::to_vec(&serde_evt).map_err(|e| e.into())
-        };
-
-        let (_imu_measurement_comms, imu_measurement_actor_id, _imu_measurement_sensor_keepalive) =
-            setup_sensor_with_transport(
-                &carla_world,
-                &running,
-                &ego_vehicle_sensor_imu_measurement_role,
-                "imu_measurement_sensor",
-                POLLING_EGO_MS,
-                ImuMeasurementFactory,
-                uuri,
-                encode,
-                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
-                Arc::clone(&transport),
-            )
+        let actor_id = wait_for_actor_id_by_role(&carla_world, &running, role_name, POLLING_EGO_MS)
             .await
-            .expect("Unable to set up imu measurement sensor with transport");
+            .map_err(|e| format!("Unable to find the sensor actor '{role_name}': {e}"))?;
+        if sensors.iter().any(|sensor| sensor.actor_id == actor_id) {
+            continue;
+        }
+        let sensor = carla_world
+            .actor(actor_id)
+            .and_then(|actor| DiscoveredSensor::from_actor(&actor))
+            .filter(|sensor| sensor.kind == kind)
+            .ok_or_else(|| format!("Actor with role_name='{role_name}' is not a {kind} sensor"))?;
+        sensors.push(sensor);
+    }
 
-        let _ego_vehicle_sensor_imu_measurement_id = Some(imu_measurement_actor_id);
-
-        (
-            Some(_imu_measurement_comms),
-            Some(_ego_vehicle_sensor_imu_measurement_id),
-            Some(_imu_measurement_sensor_keepalive),
-        )
-    } else {
-        (None, None, None)
-    };
+    // -- Set up every sensor, and describe it in the manifest --
+    let (manifest, bridged) = bridge_sensors(
+        &args,
+        &sensors,
+        &uri_provider,
+        &transport,
+        &publish,
+        shm.as_ref(),
+        |sensor, settings| {
+            let (comms, keepalive, payload_format, encoding) =
+                attach_sensor(&carla_world, sensor, settings, &encoders)?;
+            Ok((
+                comms,
+                Box::new(keepalive) as Box<dyn Any>,
+                payload_format,
+                encoding,
+            ))
+        },
+    )
+    .await?;
+
+    // The manifest is published periodically, for subscribers joining late
+    let manifest_topic = uri_provider.get_resource_uri(RESOURCE_SENSOR_MANIFEST);
+    let manifest_payload = serde_json::to_string(&manifest)?;
+    let mut last_manifest: Option<Instant> = None;
+
+    // And so are the sensor transforms, read once: the sensors are rigidly attached
+    let tree = sensor_transforms(&carla_world, ego_vehicle_id.unwrap(), &sensors);
+    let transform_topic = uri_provider.get_resource_uri(RESOURCE_TRANSFORM_TREE);
+    let transform_payload = serde_json::to_string(&tree)?;
+    let transform_server = serve_transforms(&transport, &args.authority, &tree).await?;
+
+    // The health of the sensors too
+    let diagnostics_topic = uri_provider.get_resource_uri(RESOURCE_SENSOR_DIAGNOSTICS);
+    let diagnostics_period = Duration::from_secs_f64(args.diagnostics_period);
+    let mut last_diagnostics: Option<Instant> = None;
 
     // Main loop
     let mut last_time: f64 = 0.0;
@@ -681,28 +1197,74 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         last_time = timestamp.platform_timestamp;
 
         // Publish clock status via uProtocol
-        let clock_payload = format!("{}", timestamp.elapsed_seconds);
+        let clock_payload = status_payload(None, timestamp.frame as u64, timestamp.elapsed_seconds);
         log::debug!("[to_uprotocol] clock_status : {}", clock_payload);
 
         let clock_message = UMessageBuilder::publish(clock_topic.clone())
-            .build_with_payload(clock_payload.clone(), UPayloadFormat::UPAYLOAD_FORMAT_TEXT)?;
+            .build_with_payload(clock_payload.clone(), UPayloadFormat::UPAYLOAD_FORMAT_JSON)?;
         transport.send(clock_message).await?;
 
+        // Publish the sensor manifest via uProtocol
+        if last_manifest.is_none_or(|last| last.elapsed() >= MANIFEST_PERIOD) {
+            log::debug!("[to_uprotocol] sensor_manifest : {}", manifest_payload);
+            let manifest_message = UMessageBuilder::publish(manifest_topic.clone())
+                .build_with_payload(
+                    manifest_payload.clone(),
+                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                )?;
+            transport.send(manifest_message).await?;
+
+            log::debug!("[to_uprotocol] transform_tree : {}", transform_payload);
+            let transform_message = UMessageBuilder::publish(transform_topic.clone())
+                .build_with_payload(
+                    transform_payload.clone(),
+                    UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+                )?;
+            transport.send(transform_message).await?;
+            last_manifest = Some(Instant::now());
+        }
+
+        // Publish the sensor diagnostics via uProtocol, noticing the sensors that left the world
+        if last_diagnostics.is_none_or(|last| last.elapsed() >= diagnostics_period) {
+            let report = diagnostics_report(&bridged, timestamp.elapsed_seconds, |actor_id| {
+                carla_world.actor(actor_id).is_some()
+            });
+            let diagnostics_payload = serde_json::to_string(&report)?;
+            log::debug!(
+                "[to_uprotocol] sensor_diagnostics : {}",
+                diagnostics_payload
+            );
+            let diagnostics_message = UMessageBuilder::publish(diagnostics_topic.clone())
+                .build_with_payload(diagnostics_payload, UPayloadFormat::UPAYLOAD_FORMAT_JSON)?;
+            transport.send(diagnostics_message).await?;
+            last_diagnostics = Some(Instant::now());
+        }
+
         tokio::time::sleep(Duration::from_millis(WAITING_PUB_MS)).await;
 
         // Control the Ego Vehicle
         if let Some(actor) = carla_world.actor(ego_vehicle_id.unwrap()) {
             if let Ok(ego_vehicle) = actor.into_kinds().try_into_vehicle() {
+                // Pose of the vehicle for the transform lookups to the world
+                transform_server.endpoint().set_vehicle_pose(
+                    timestamp.elapsed_seconds,
+                    Transform::from_isometry(&ego_vehicle.transform().cast::<f64>()),
+                );
+
                 // Calculate and publish velocity
                 let velocity = 3.6 * ego_vehicle.velocity().norm();
-                let velocity_payload = format!("{}", velocity);
+                let velocity_payload = status_payload(
+                    Some(("velocity", velocity as f64)),
+                    timestamp.frame as u64,
+                    timestamp.elapsed_seconds,
+                );
 
                 // Publish velocity via uProtocol
                 log::debug!("[to_uprotocol] velocity_status : {}", velocity_payload);
                 let velocity_message = UMessageBuilder::publish(velocity_topic.clone())
                     .build_with_payload(
                         velocity_payload.clone(),
-                        UPayloadFormat::UPAYLOAD_FORMAT_TEXT,
+                        UPayloadFormat::UPAYLOAD_FORMAT_JSON,
                     )?;
                 transport.send(velocity_message).await?;
 
@@ -1,17 +1,25 @@
 mod collision;
+mod gnss_measurement;
 mod image;
 mod imu_measurement;
 mod lane_invasion;
 mod lidar_measurement;
 mod obstacle_detection;
+mod publish_filter;
 mod radar_measurement;
+mod semantic_lidar_measurement;
 mod sensor_comms;
+mod sensor_queue;
 
 pub use collision::*;
+pub use gnss_measurement::*;
 pub use image::*;
 pub use imu_measurement::*;
 pub use lane_invasion::*;
 pub use lidar_measurement::*;
 pub use obstacle_detection::*;
+pub use publish_filter::*;
 pub use radar_measurement::*;
+pub use semantic_lidar_measurement::*;
 pub use sensor_comms::*;
+pub use sensor_queue::*;
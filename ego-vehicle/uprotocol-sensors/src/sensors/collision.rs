@@ -1,13 +1,13 @@
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
-use carla::client::Sensor as CarlaSensor;
+use crate::sensors::{SensorSource, change_key};
+use carla::client::{ActorBase, Sensor as CarlaSensor};
 use carla::sensor::SensorData;
 use carla::sensor::data::CollisionEvent;
 
 /// Typed view over a CARLA Sensor that emits `ColisionEvent`.
 pub struct Collision<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for Collision<'a> {
+impl<'a> SensorSource for Collision<'a> {
     type Data = CollisionEvent;
 
     fn listen<F>(&self, f: F)
@@ -24,6 +24,11 @@ impl<'a> Listen for Collision<'a> {
             }
         });
     }
+
+    // A collision is new when it involves another actor
+    fn change_key(evt: &Self::Data) -> Option<u64> {
+        Some(change_key(evt.other_actor().map(|actor| actor.id())))
+    }
 }
 
 pub struct CollisionFactory;
@@ -0,0 +1,61 @@
+use crate::helpers::ViewFactory;
+use crate::sensors::SensorSource;
+use carla::client::Sensor as CarlaSensor;
+use carla::sensor::data::GnssMeasurement as GnssMeasurementEvent;
+use carla::sensor::{SensorData, SensorDataBase};
+use serde::{Deserialize, Serialize};
+
+/// Typed view over a CARLA Sensor that emits `GnssMeasurement`.
+pub struct GnssMeasurement<'a>(pub &'a CarlaSensor);
+
+impl<'a> SensorSource for GnssMeasurement<'a> {
+    type Data = GnssMeasurementEvent;
+
+    fn listen<F>(&self, f: F)
+    where
+        F: FnMut(Self::Data) + Send + 'static,
+    {
+        // CARLA expects FnMut(SensorData), so adapt here:
+        let mut f = f;
+        self.0.listen(move |data: SensorData| {
+            if let Ok(evt) = data.try_into() {
+                f(evt);
+            } else {
+                log::warn!("Received non GnssMeasurement");
+            }
+        });
+    }
+}
+
+pub struct GnssMeasurementFactory;
+
+impl ViewFactory for GnssMeasurementFactory {
+    type View<'a> = GnssMeasurement<'a>;
+    fn make<'a>(&self, s: &'a CarlaSensor) -> Self::View<'a> {
+        GnssMeasurement(s)
+    }
+}
+
+/// Position of a GNSS measurement, published as JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GnssFix {
+    pub frame: u64,
+    pub timestamp: f64,
+    /// Degrees, positive north.
+    pub latitude: f64,
+    /// Degrees, positive east.
+    pub longitude: f64,
+    /// Meters above sea level.
+    pub altitude: f64,
+}
+
+/// The position of `measurement`.
+pub fn gnss_fix(measurement: &GnssMeasurementEvent) -> GnssFix {
+    GnssFix {
+        frame: measurement.frame() as u64,
+        timestamp: measurement.timestamp(),
+        latitude: measurement.latitude(),
+        longitude: measurement.longitude(),
+        altitude: measurement.altitude(),
+    }
+}
@@ -1,13 +1,15 @@
+use crate::codec::{ImageEncoder, ImageEncoding, ImageFrame, encode_depth, encode_labels};
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
+use crate::sensors::SensorSource;
 use carla::client::Sensor as CarlaSensor;
-use carla::sensor::SensorData;
 use carla::sensor::data::Image as ImageEvent;
+use carla::sensor::{SensorData, SensorDataBase};
+use std::error::Error;
 
 /// Typed view over a CARLA Sensor that emits `ImageEvent`.
 pub struct Image<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for Image<'a> {
+impl<'a> SensorSource for Image<'a> {
     type Data = ImageEvent;
 
     fn listen<F>(&self, f: F)
@@ -34,3 +36,40 @@ impl ViewFactory for ImageFactory {
         Image(s)
     }
 }
+
+/// Encodes `image` into a binary image payload (see [`ImageEncoder`]).
+pub fn encode_image(
+    encoder: &ImageEncoder,
+    image: &ImageEvent,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    with_frame(image, |frame| encoder.encode(frame))
+}
+
+/// Encodes the frame of a depth camera into the depth of every pixel in meters.
+pub fn encode_depth_image(image: &ImageEvent) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    with_frame(image, encode_depth)
+}
+
+/// Encodes the frame of a semantic segmentation camera into the semantic tag of every
+/// pixel, raw or as a PNG image.
+pub fn encode_segmentation_image(
+    image: &ImageEvent,
+    encoding: ImageEncoding,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    with_frame(image, |frame| encode_labels(frame, encoding))
+}
+
+fn with_frame<T>(image: &ImageEvent, encode: impl FnOnce(&ImageFrame<'_>) -> T) -> T {
+    let bgra: Vec<u8> = image
+        .as_slice()
+        .iter()
+        .flat_map(|color| [color.b, color.g, color.r, color.a])
+        .collect();
+    encode(&ImageFrame {
+        width: image.width() as u32,
+        height: image.height() as u32,
+        frame: image.frame() as u64,
+        timestamp: image.timestamp(),
+        bgra: &bgra,
+    })
+}
@@ -1,5 +1,5 @@
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
+use crate::sensors::SensorSource;
 use carla::client::Sensor as CarlaSensor;
 use carla::sensor::SensorData;
 use carla::sensor::data::ImuMeasurement as ImuMeasurementEvent;
@@ -7,7 +7,7 @@ use carla::sensor::data::ImuMeasurement as ImuMeasurementEvent;
 /// Typed view over a CARLA Sensor that emits `ImuMeasurement`.
 pub struct ImuMeasurement<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for ImuMeasurement<'a> {
+impl<'a> SensorSource for ImuMeasurement<'a> {
     type Data = ImuMeasurementEvent;
 
     fn listen<F>(&self, f: F)
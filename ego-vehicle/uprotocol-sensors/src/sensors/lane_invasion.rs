@@ -1,5 +1,5 @@
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
+use crate::sensors::{SensorSource, change_key};
 use carla::client::Sensor as CarlaSensor;
 use carla::sensor::SensorData;
 use carla::sensor::data::LaneInvasionEvent;
@@ -7,7 +7,7 @@ use carla::sensor::data::LaneInvasionEvent;
 /// Typed view over a CARLA Sensor that emits `LaneInvasionEvent`.
 pub struct LaneInvasion<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for LaneInvasion<'a> {
+impl<'a> SensorSource for LaneInvasion<'a> {
     type Data = LaneInvasionEvent;
 
     fn listen<F>(&self, f: F)
@@ -24,6 +24,16 @@ impl<'a> Listen for LaneInvasion<'a> {
             }
         });
     }
+
+    // An invasion is new when it crosses other kinds of lane markings
+    fn change_key(evt: &Self::Data) -> Option<u64> {
+        let markings: Vec<String> = evt
+            .crossed_lane_markings()
+            .iter()
+            .map(|marking| format!("{:?}", marking.type_()))
+            .collect();
+        Some(change_key(markings))
+    }
 }
 
 pub struct LaneInvasionFactory;
@@ -1,13 +1,14 @@
+use crate::codec::{LIDAR_FIELDS, PointCloud};
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
+use crate::sensors::SensorSource;
 use carla::client::Sensor as CarlaSensor;
-use carla::sensor::SensorData;
 use carla::sensor::data::LidarMeasurement as LidarMeasurementEvent;
+use carla::sensor::{SensorData, SensorDataBase};
 
 /// Typed view over a CARLA Sensor that emits `LidarMeasurementEvent`.
 pub struct LidarMeasurement<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for LidarMeasurement<'a> {
+impl<'a> SensorSource for LidarMeasurement<'a> {
     type Data = LidarMeasurementEvent;
 
     fn listen<F>(&self, f: F)
@@ -33,3 +34,17 @@ impl ViewFactory for LidarMeasurementFactory {
         LidarMeasurement(s)
     }
 }
+
+/// The points of `measurement`, with the [`LIDAR_FIELDS`].
+pub fn lidar_point_cloud(measurement: &LidarMeasurementEvent) -> PointCloud {
+    let mut cloud = PointCloud::new(
+        measurement.frame() as u64,
+        measurement.timestamp(),
+        &LIDAR_FIELDS,
+    );
+    for detection in measurement.as_slice() {
+        let point = &detection.point;
+        cloud.push(&[point.x, point.y, point.z, detection.intensity]);
+    }
+    cloud
+}
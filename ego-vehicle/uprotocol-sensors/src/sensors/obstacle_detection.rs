@@ -1,13 +1,13 @@
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
-use carla::client::Sensor as CarlaSensor;
+use crate::sensors::{SensorSource, change_key};
+use carla::client::{ActorBase, Sensor as CarlaSensor};
 use carla::sensor::SensorData;
 use carla::sensor::data::ObstacleDetectionEvent;
 
 /// Typed view over a CARLA Sensor that emits `ColisionEvent`.
 pub struct ObstacleDetection<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for ObstacleDetection<'a> {
+impl<'a> SensorSource for ObstacleDetection<'a> {
     type Data = ObstacleDetectionEvent;
 
     fn listen<F>(&self, f: F)
@@ -24,6 +24,11 @@ impl<'a> Listen for ObstacleDetection<'a> {
             }
         });
     }
+
+    // An obstacle is new when it is another actor, not when its distance changes
+    fn change_key(evt: &Self::Data) -> Option<u64> {
+        Some(change_key(evt.other_actor().id()))
+    }
 }
 
 pub struct ObstacleDetectionFactory;
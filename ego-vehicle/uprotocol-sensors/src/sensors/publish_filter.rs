@@ -0,0 +1,276 @@
+use crate::sensors::SensorSource;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Which events of a sensor are published.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublishConfig {
+    /// Maximum publish rate in Hz; events arriving sooner are dropped.
+    pub max_rate_hz: Option<f64>,
+    /// Only every Nth event is considered (1 keeps them all).
+    pub decimation: u32,
+    /// Only publishes events that differ from the last published one (event sensors).
+    pub on_change: bool,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        Self {
+            max_rate_hz: None,
+            decimation: 1,
+            on_change: false,
+        }
+    }
+}
+
+impl PublishConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(rate) = self
+            .max_rate_hz
+            .filter(|rate| !(rate.is_finite() && *rate > 0.0))
+        {
+            return Err(format!("max_rate_hz must be positive, got {rate}"));
+        }
+        if self.decimation == 0 {
+            return Err("decimation must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether every event is published.
+    pub fn is_passthrough(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Publish settings of every sensor, keyed by sensor name (e.g. `image`), as read from a
+/// YAML file:
+///
+/// ```yaml
+/// image:
+///   max_rate_hz: 10
+/// lidar_measurement:
+///   decimation: 2
+/// collision:
+///   on_change: true
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct PublishConfigs(BTreeMap<String, PublishConfig>);
+
+impl PublishConfigs {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        Self::from_yaml(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, String> {
+        let configs: Self = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+        for (sensor, config) in &configs.0 {
+            config.validate().map_err(|e| format!("{sensor}: {e}"))?;
+        }
+        Ok(configs)
+    }
+
+    /// Settings of `sensor`; every event is published if it has none.
+    pub fn get(&self, sensor: &str) -> PublishConfig {
+        self.0.get(sensor).copied().unwrap_or_default()
+    }
+
+    /// Settings of the sensor named `role_name`, or else of its type `sensor`.
+    pub fn get_for(&self, role_name: &str, sensor: &str) -> PublishConfig {
+        self.0
+            .get(role_name)
+            .or_else(|| self.0.get(sensor))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Settings of `sensor`, to which `update` applies its overrides.
+    pub fn update(&mut self, sensor: &str, update: impl FnOnce(&mut PublishConfig)) {
+        update(self.0.entry(sensor.to_string()).or_default());
+    }
+}
+
+/// Identity of an event for on-change publishing, from the values that define it.
+pub fn change_key(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Decides, event by event, which events of a sensor are published.
+///
+/// Decimation is applied first, then the rate limit, then the on-change check against
+/// the last published event.
+#[derive(Clone, Debug)]
+pub struct PublishFilter {
+    config: PublishConfig,
+    min_interval: Option<Duration>,
+    // Events still skipped by the decimation before the next candidate
+    skip: u32,
+    last_published: Option<Instant>,
+    last_key: Option<u64>,
+}
+
+impl PublishFilter {
+    pub fn new(config: PublishConfig) -> Self {
+        Self {
+            config,
+            min_interval: config
+                .max_rate_hz
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            skip: 0,
+            last_published: None,
+            last_key: None,
+        }
+    }
+
+    /// Whether the event received at `now` is published. `key` is its [`change_key`],
+    /// `None` for sensors without one, whose events always count as changed.
+    pub fn admit(&mut self, now: Instant, key: Option<u64>) -> bool {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return false;
+        }
+        self.skip = self.config.decimation.max(1) - 1;
+
+        let too_soon = self
+            .min_interval
+            .zip(self.last_published)
+            .is_some_and(|(interval, last)| now.saturating_duration_since(last) < interval);
+        if too_soon {
+            return false;
+        }
+
+        if self.config.on_change && key.is_some() && key == self.last_key {
+            return false;
+        }
+
+        self.last_published = Some(now);
+        self.last_key = key;
+        true
+    }
+}
+
+/// View that forwards only the events admitted by a [`PublishFilter`], so the others are
+/// neither queued nor encoded.
+pub struct Filtered<'v, V> {
+    view: &'v V,
+    config: PublishConfig,
+}
+
+impl<'v, V: SensorSource> Filtered<'v, V> {
+    pub fn new(view: &'v V, config: PublishConfig) -> Self {
+        Self { view, config }
+    }
+}
+
+impl<V: SensorSource> SensorSource for Filtered<'_, V> {
+    type Data = V::Data;
+
+    fn listen<F>(&self, f: F)
+    where
+        F: FnMut(Self::Data) + Send + 'static,
+    {
+        if self.config.is_passthrough() {
+            return self.view.listen(f);
+        }
+
+        let mut f = f;
+        let mut filter = PublishFilter::new(self.config);
+        self.view.listen(move |data: V::Data| {
+            let key = filter
+                .config
+                .on_change
+                .then(|| V::change_key(&data))
+                .flatten();
+            if filter.admit(Instant::now(), key) {
+                f(data);
+            }
+        });
+    }
+
+    fn change_key(data: &Self::Data) -> Option<u64> {
+        V::change_key(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admitted(config: PublishConfig, events: &[(u64, Option<u64>)]) -> Vec<usize> {
+        let start = Instant::now();
+        let mut filter = PublishFilter::new(config);
+        events
+            .iter()
+            .enumerate()
+            .filter(|(_, (ms, key))| filter.admit(start + Duration::from_millis(*ms), *key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_decimation_and_rate() {
+        let every_50ms: Vec<_> = (0..10).map(|i| (i * 50, None)).collect();
+
+        let decimated = PublishConfig {
+            decimation: 3,
+            ..Default::default()
+        };
+        assert_eq!(admitted(decimated, &every_50ms), vec![0, 3, 6, 9]);
+
+        let limited = PublishConfig {
+            max_rate_hz: Some(10.0),
+            ..Default::default()
+        };
+        assert_eq!(admitted(limited, &every_50ms), vec![0, 2, 4, 6, 8]);
+
+        // Decimation picks the candidates, the rate limit then applies to them
+        let both = PublishConfig {
+            max_rate_hz: Some(5.0),
+            decimation: 2,
+            ..Default::default()
+        };
+        assert_eq!(admitted(both, &every_50ms), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn test_on_change() {
+        let config = PublishConfig {
+            on_change: true,
+            ..Default::default()
+        };
+        let events = [
+            (0, Some(change_key(7u32))),
+            (10, Some(change_key(7u32))),
+            (20, Some(change_key(8u32))),
+            (30, Some(change_key(7u32))),
+            (40, None),
+            (50, None),
+        ];
+        assert_eq!(admitted(config, &events), vec![0, 2, 3, 4, 5]);
+        assert!(PublishConfig::default().is_passthrough());
+    }
+
+    #[test]
+    fn test_parse_configs() {
+        let configs =
+            PublishConfigs::from_yaml("image:\n  max_rate_hz: 10\ncollision:\n  on_change: true\n")
+                .unwrap();
+        assert_eq!(configs.get("image").max_rate_hz, Some(10.0));
+        assert_eq!(configs.get("image").decimation, 1);
+        assert!(configs.get("collision").on_change);
+        assert!(configs.get("lidar_measurement").is_passthrough());
+
+        assert!(PublishConfigs::from_yaml("image:\n  decimation: 0\n").is_err());
+        assert!(PublishConfigs::from_yaml("image:\n  rate: 10\n").is_err());
+    }
+}
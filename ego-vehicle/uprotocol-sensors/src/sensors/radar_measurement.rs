@@ -1,13 +1,14 @@
+use crate::codec::{PointCloud, RADAR_FIELDS};
 use crate::helpers::ViewFactory;
-use crate::sensors::Listen;
+use crate::sensors::SensorSource;
 use carla::client::Sensor as CarlaSensor;
-use carla::sensor::SensorData;
 use carla::sensor::data::RadarMeasurement as RadarMeasurementEvent;
+use carla::sensor::{SensorData, SensorDataBase};
 
 /// Typed view over a CARLA Sensor that emits `RadarMeasurementEvent`.
 pub struct RadarMeasurement<'a>(pub &'a CarlaSensor);
 
-impl<'a> Listen for RadarMeasurement<'a> {
+impl<'a> SensorSource for RadarMeasurement<'a> {
     type Data = RadarMeasurementEvent;
 
     fn listen<F>(&self, f: F)
@@ -34,3 +35,21 @@ impl ViewFactory for RadarMeasurementFactory {
         RadarMeasurement(s)
     }
 }
+
+/// The detections of `measurement`, with the [`RADAR_FIELDS`].
+pub fn radar_point_cloud(measurement: &RadarMeasurementEvent) -> PointCloud {
+    let mut cloud = PointCloud::new(
+        measurement.frame() as u64,
+        measurement.timestamp(),
+        &RADAR_FIELDS,
+    );
+    for detection in measurement.as_slice() {
+        cloud.push(&[
+            detection.velocity,
+            detection.azimuth,
+            detection.altitude,
+            detection.depth,
+        ]);
+    }
+    cloud
+}
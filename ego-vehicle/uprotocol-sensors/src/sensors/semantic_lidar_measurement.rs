@@ -0,0 +1,57 @@
+use crate::codec::{PointCloud, SEMANTIC_LIDAR_FIELDS};
+use crate::helpers::ViewFactory;
+use crate::sensors::SensorSource;
+use carla::client::Sensor as CarlaSensor;
+use carla::sensor::data::SemanticLidarMeasurement as SemanticLidarMeasurementEvent;
+use carla::sensor::{SensorData, SensorDataBase};
+
+/// Typed view over a CARLA Sensor that emits `SemanticLidarMeasurementEvent`.
+pub struct SemanticLidarMeasurement<'a>(pub &'a CarlaSensor);
+
+impl<'a> SensorSource for SemanticLidarMeasurement<'a> {
+    type Data = SemanticLidarMeasurementEvent;
+
+    fn listen<F>(&self, f: F)
+    where
+        F: FnMut(Self::Data) + Send + 'static,
+    {
+        let mut f = f;
+        self.0.listen(move |data: SensorData| {
+            if let Ok(evt) = data.try_into() {
+                f(evt);
+            } else {
+                log::warn!("Received non SemanticLidarMeasurementEvent");
+            }
+        });
+    }
+}
+
+pub struct SemanticLidarMeasurementFactory;
+
+impl ViewFactory for SemanticLidarMeasurementFactory {
+    type View<'a> = SemanticLidarMeasurement<'a>;
+    fn make<'a>(&self, s: &'a CarlaSensor) -> Self::View<'a> {
+        SemanticLidarMeasurement(s)
+    }
+}
+
+/// The points of `measurement`, with the [`SEMANTIC_LIDAR_FIELDS`].
+pub fn semantic_lidar_point_cloud(measurement: &SemanticLidarMeasurementEvent) -> PointCloud {
+    let mut cloud = PointCloud::new(
+        measurement.frame() as u64,
+        measurement.timestamp(),
+        &SEMANTIC_LIDAR_FIELDS,
+    );
+    for detection in measurement.as_slice() {
+        let point = &detection.point;
+        cloud.push(&[
+            point.x,
+            point.y,
+            point.z,
+            detection.cos_inc_angle,
+            detection.object_idx as f32,
+            detection.object_tag as f32,
+        ]);
+    }
+    cloud
+}
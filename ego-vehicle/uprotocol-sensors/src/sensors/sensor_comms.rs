@@ -1,88 +1,113 @@
+use crate::sensors::{QueueConfig, QueueStats, SensorQueue};
 use std::future::Future;
-use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::ThreadId;
 use std::thread::{self, JoinHandle};
 
-pub trait Listen {
+/// Where the events of one sensor come from: a typed view over a CARLA sensor (e.g.
+/// [`ImuMeasurement`]) or a synthetic generator (see [`crate::synthetic`]).
+///
+/// [`ImuMeasurement`]: crate::sensors::ImuMeasurement
+pub trait SensorSource {
     type Data: Send + 'static;
 
+    /// Calls `f` with every event, from whatever thread produces them, until the source
+    /// is dropped.
     fn listen<F>(&self, f: F)
     where
         F: FnMut(Self::Data) + Send + 'static;
+
+    /// Identity of an event for on-change publishing (see [`change_key`]); `None` when
+    /// every event is new, e.g. for streaming sensors.
+    ///
+    /// [`change_key`]: crate::sensors::change_key
+    fn change_key(_data: &Self::Data) -> Option<u64> {
+        None
+    }
 }
 
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Moves the events of one sensor from its CARLA callback to a dedicated worker thread.
+///
+/// Events go through a bounded [`SensorQueue`] whose policy decides what happens when the
+/// worker falls behind; the worker handles them one at a time, in order.
 pub struct SensorComms {
-    _name: String,
-    tx: Sender<Message>,
+    name: String,
+    queue: Arc<SensorQueue<Job>>,
     handle: Option<JoinHandle<()>>,
     worker_thread_id: ThreadId,
     rt: tokio::runtime::Handle,
 }
 
-enum Message {
-    Job(Box<dyn FnOnce() + Send>),
-    Shutdown,
-}
-
 impl SensorComms {
     /// Requires being called on a Tokio runtime (so we can grab Handle::current()).
-    pub fn new(name: impl Into<String>) -> Self {
+    pub fn new(name: impl Into<String>, queue: QueueConfig) -> Self {
         let rt = tokio::runtime::Handle::try_current()
             .expect("SensorComms::new must be called on a Tokio runtime");
-        Self::new_with_runtime(name, rt)
+        Self::new_with_runtime(name, queue, rt)
     }
 
-    pub fn new_with_runtime(name: impl Into<String>, rt: tokio::runtime::Handle) -> Self {
+    pub fn new_with_runtime(
+        name: impl Into<String>,
+        queue: QueueConfig,
+        rt: tokio::runtime::Handle,
+    ) -> Self {
         let name = name.into();
-        let (tx, rx) = mpsc::channel::<Message>();
+        let queue = Arc::new(SensorQueue::new(name.clone(), queue));
 
-        let worker_name = name.clone();
+        let worker_queue = Arc::clone(&queue);
         let handle = thread::Builder::new()
-            .name(format!("sensor-worker-{}", worker_name))
-            .spawn(move || worker_loop(rx))
+            .name(format!("sensor-worker-{}", name))
+            .spawn(move || worker_loop(&worker_queue))
             .expect("failed to spawn SensorComms worker");
 
         let worker_thread_id = handle.thread().id();
 
         Self {
-            _name: name,
-            tx,
+            name,
+            queue,
             handle: Some(handle),
             worker_thread_id,
             rt,
         }
     }
 
+    /// Received and dropped events, and the current depth of the queue.
+    pub fn stats(&self) -> QueueStats {
+        self.queue.stats()
+    }
+
     /// Synchronous handler variant (kept for convenience).
     pub fn listen_on<S, H>(&self, sensor: &S, handler: H)
     where
-        S: Listen,
+        S: SensorSource,
         H: FnMut(S::Data) + Send + 'static,
     {
-        let tx = self.tx.clone();
+        let queue = Arc::clone(&self.queue);
         let handler = Arc::new(Mutex::new(handler));
         sensor.listen({
             let handler = Arc::clone(&handler);
             move |data: S::Data| {
                 let handler = Arc::clone(&handler);
-                let _ = tx.send(Message::Job(Box::new(move || {
+                queue.push(Box::new(move || {
                     if let Ok(mut h) = handler.lock() {
                         h(data);
                     }
-                })));
+                }));
             }
         });
     }
 
-    /// Async handler variant. Your handler returns a Future; we spawn it on the stored Tokio runtime.
+    /// Async handler variant. Your handler returns a Future, which the worker runs to completion
+    /// on the stored Tokio runtime before taking the next event, so sends stay in order.
     pub fn listen_on_async<S, H, Fut>(&self, sensor: &S, handler: H)
     where
-        S: Listen,
+        S: SensorSource,
         H: Fn(S::Data) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let tx = self.tx.clone();
+        let queue = Arc::clone(&self.queue);
         let rt = self.rt.clone();
         let handler = Arc::new(handler);
 
@@ -93,11 +118,11 @@ impl SensorComms {
                 // Keep CARLA’s callback super light: just enqueue a job.
                 let handler = Arc::clone(&handler);
                 let rt = rt.clone();
-                let _ = tx.send(Message::Job(Box::new(move || {
-                    // Build the future and spawn it on Tokio.
+                queue.push(Box::new(move || {
+                    // Build the future and wait for it, so a slow transport backs up the queue.
                     let fut = (handler)(data);
-                    rt.spawn(async move { fut.await });
-                })));
+                    rt.block_on(fut);
+                }));
             }
         });
     }
@@ -105,7 +130,16 @@ impl SensorComms {
 
 impl Drop for SensorComms {
     fn drop(&mut self) {
-        let _ = self.tx.send(Message::Shutdown);
+        let stats = self.queue.stats();
+        log::info!(
+            "{}: {} events received, {} dropped, max queue depth {}",
+            self.name,
+            stats.received,
+            stats.dropped,
+            stats.max_depth
+        );
+
+        self.queue.close();
         if std::thread::current().id() != self.worker_thread_id {
             if let Some(handle) = self.handle.take() {
                 let _ = handle.join();
@@ -114,11 +148,8 @@ impl Drop for SensorComms {
     }
 }
 
-fn worker_loop(rx: Receiver<Message>) {
-    while let Ok(msg) = rx.recv() {
-        match msg {
-            Message::Job(job) => job(),
-            Message::Shutdown => break,
-        }
+fn worker_loop(queue: &SensorQueue<Job>) {
+    while let Some(job) = queue.pop() {
+        job();
     }
 }
@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// Default capacity of a sensor queue.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 8;
+
+// A warning is logged on the first drop, then every DROP_LOG_INTERVAL drops
+const DROP_LOG_INTERVAL: u64 = 100;
+
+/// What a sensor queue does with a new event when it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Drops the oldest queued event to make room.
+    #[default]
+    DropOldest,
+    /// Drops the new event.
+    DropNewest,
+    /// Only keeps the most recent event, whatever the capacity.
+    KeepLatest,
+    /// Blocks the CARLA callback until there is room (backpressure).
+    Block,
+}
+
+impl FromStr for QueuePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('-', "_").as_str() {
+            "drop_oldest" => Ok(QueuePolicy::DropOldest),
+            "drop_newest" => Ok(QueuePolicy::DropNewest),
+            "keep_latest" => Ok(QueuePolicy::KeepLatest),
+            "block" => Ok(QueuePolicy::Block),
+            _ => Err(format!(
+                "unknown queue policy '{s}' (drop_oldest, drop_newest, keep_latest or block)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for QueuePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            QueuePolicy::DropOldest => "drop_oldest",
+            QueuePolicy::DropNewest => "drop_newest",
+            QueuePolicy::KeepLatest => "keep_latest",
+            QueuePolicy::Block => "block",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Capacity and policy of a sensor queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub policy: QueuePolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_QUEUE_CAPACITY,
+            policy: QueuePolicy::default(),
+        }
+    }
+}
+
+impl FromStr for QueueConfig {
+    type Err = String;
+
+    /// Parses `<policy>[:<capacity>]`, e.g. `keep_latest` or `drop_oldest:2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (policy, capacity) = match s.split_once(':') {
+            Some((policy, capacity)) => (
+                policy,
+                capacity
+                    .parse::<usize>()
+                    .map_err(|e| format!("invalid queue capacity '{capacity}': {e}"))?,
+            ),
+            None => (s, DEFAULT_QUEUE_CAPACITY),
+        };
+        if capacity == 0 {
+            return Err("queue capacity must be at least 1".to_string());
+        }
+        Ok(Self {
+            capacity,
+            policy: policy.parse()?,
+        })
+    }
+}
+
+/// Counters of a sensor queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Events offered by the sensor.
+    pub received: u64,
+    /// Events dropped by the policy.
+    pub dropped: u64,
+    /// Events currently queued.
+    pub depth: usize,
+    /// Highest depth reached.
+    pub max_depth: usize,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+/// Bounded FIFO between a CARLA sensor callback and the worker publishing its events.
+pub struct SensorQueue<T> {
+    name: String,
+    config: QueueConfig,
+    state: Mutex<QueueState<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    max_depth: AtomicUsize,
+}
+
+impl<T> SensorQueue<T> {
+    pub fn new(name: impl Into<String>, config: QueueConfig) -> Self {
+        Self {
+            name: name.into(),
+            config: QueueConfig {
+                capacity: config.capacity.max(1),
+                ..config
+            },
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            received: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            max_depth: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn config(&self) -> QueueConfig {
+        self.config
+    }
+
+    /// Enqueues `item` according to the policy; returns whether it was queued.
+    pub fn push(&self, item: T) -> bool {
+        self.received.fetch_add(1, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+
+        let mut dropped = 0;
+        match self.config.policy {
+            QueuePolicy::DropOldest => {
+                while state.items.len() >= self.config.capacity {
+                    state.items.pop_front();
+                    dropped += 1;
+                }
+            }
+            QueuePolicy::DropNewest => {
+                if state.items.len() >= self.config.capacity {
+                    drop(state);
+                    self.count_drops(1);
+                    return false;
+                }
+            }
+            QueuePolicy::KeepLatest => {
+                dropped = state.items.len();
+                state.items.clear();
+            }
+            QueuePolicy::Block => {
+                while state.items.len() >= self.config.capacity && !state.closed {
+                    state = self.not_full.wait(state).unwrap();
+                }
+            }
+        }
+
+        let queued = !state.closed;
+        if queued {
+            state.items.push_back(item);
+            self.max_depth
+                .fetch_max(state.items.len(), Ordering::Relaxed);
+        }
+        drop(state);
+
+        self.count_drops(dropped as u64);
+        self.not_empty.notify_one();
+        queued
+    }
+
+    /// Waits for the next event; `None` once the queue is closed and drained.
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                drop(state);
+                self.not_full.notify_one();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Refuses new events, wakes blocked producers and lets the consumer drain the queue.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            received: self.received.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            depth: self.state.lock().unwrap().items.len(),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count_drops(&self, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let before = self.dropped.fetch_add(count, Ordering::Relaxed);
+        if before == 0 || before / DROP_LOG_INTERVAL != (before + count) / DROP_LOG_INTERVAL {
+            log::warn!(
+                "{}: {} events dropped so far (policy: {}, capacity: {})",
+                self.name,
+                before + count,
+                self.config.policy,
+                self.config.capacity
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn drain(queue: &SensorQueue<u32>) -> Vec<u32> {
+        queue.close();
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    fn filled(policy: QueuePolicy) -> SensorQueue<u32> {
+        let queue = SensorQueue::new(
+            "test",
+            QueueConfig {
+                capacity: 3,
+                policy,
+            },
+        );
+        for i in 0..5 {
+            queue.push(i);
+        }
+        queue
+    }
+
+    #[test]
+    fn test_drop_policies() {
+        let queue = filled(QueuePolicy::DropOldest);
+        assert_eq!(
+            queue.stats(),
+            QueueStats {
+                received: 5,
+                dropped: 2,
+                depth: 3,
+                max_depth: 3
+            }
+        );
+        assert_eq!(drain(&queue), vec![2, 3, 4]);
+
+        assert_eq!(drain(&filled(QueuePolicy::DropNewest)), vec![0, 1, 2]);
+
+        let queue = filled(QueuePolicy::KeepLatest);
+        assert_eq!(queue.stats().dropped, 4);
+        assert_eq!(drain(&queue), vec![4]);
+    }
+
+    #[test]
+    fn test_block_waits_for_room() {
+        let queue = Arc::new(SensorQueue::new(
+            "test",
+            QueueConfig {
+                capacity: 1,
+                policy: QueuePolicy::Block,
+            },
+        ));
+        queue.push(0);
+
+        let producer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.push(1))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+
+        assert_eq!(queue.pop(), Some(0));
+        assert!(producer.join().unwrap());
+        assert_eq!(drain(&queue), vec![1]);
+        assert_eq!(queue.stats().dropped, 0);
+
+        // Closing releases blocked producers without queuing
+        assert!(!queue.push(2));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(
+            "drop-oldest:2".parse(),
+            Ok(QueueConfig {
+                capacity: 2,
+                policy: QueuePolicy::DropOldest
+            })
+        );
+        assert_eq!(
+            "keep_latest".parse::<QueueConfig>().map(|c| c.policy),
+            Ok(QueuePolicy::KeepLatest)
+        );
+        assert!("block:0".parse::<QueueConfig>().is_err());
+        assert!("fifo".parse::<QueueConfig>().is_err());
+    }
+}
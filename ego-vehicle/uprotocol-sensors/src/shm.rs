@@ -0,0 +1,152 @@
+//! Publishing of high-bandwidth sensor payloads through Zenoh shared memory.
+//!
+//! Payloads are written once into a buffer of a shared-memory pool and published on a
+//! plain Zenoh key expression: subscribers on the same host map the buffer instead of
+//! receiving a copy over the network stack. Zenoh copies the buffer into a regular
+//! payload for subscribers without access to the pool (other hosts, or sessions with
+//! shared memory disabled), so remote subscribers keep working unchanged.
+
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use zenoh::Session;
+use zenoh::Wait;
+use zenoh::pubsub::Publisher;
+use zenoh::qos::CongestionControl;
+use zenoh::shm::{GarbageCollect, PosixShmProviderBackend, ShmProvider, ShmProviderBuilder};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Default size of the shared-memory pool, in MiB.
+pub const DEFAULT_SHM_POOL_MB: usize = 64;
+/// Default prefix of the Zenoh keys of the shared-memory sensors.
+pub const DEFAULT_SHM_KEY_PREFIX: &str = "ego_vehicle/sensors";
+
+// A warning is logged on the first fallback, then every FALLBACK_LOG_INTERVAL fallbacks
+const FALLBACK_LOG_INTERVAL: u64 = 100;
+
+/// Pool the shared-memory buffers are allocated from.
+pub type ShmPool = ShmProvider<PosixShmProviderBackend>;
+
+/// Creates a POSIX shared-memory pool of `size` bytes.
+pub fn shm_pool(size: usize) -> Result<Arc<ShmPool>> {
+    Ok(Arc::new(ShmProviderBuilder::default_backend(size).wait()?))
+}
+
+/// Enables shared-memory transport in a Zenoh session configuration.
+pub fn enable_shared_memory(config: &mut zenoh::Config) -> Result<()> {
+    config
+        .insert_json5("transport/shared_memory/enabled", "true")
+        .map_err(|e| format!("Unable to enable shared memory: {e}").into())
+}
+
+/// Publisher of one sensor, writing its payloads into shared-memory buffers.
+pub struct ShmPublisher {
+    // Publishers only hold a weak reference to their session
+    _session: Session,
+    pool: Arc<ShmPool>,
+    publisher: Publisher<'static>,
+    key: String,
+    fallbacks: AtomicU64,
+}
+
+impl ShmPublisher {
+    /// Declares a publisher on `key`. `session` must have shared memory enabled (see
+    /// [`enable_shared_memory`]).
+    pub async fn declare(session: &Session, key: String, pool: Arc<ShmPool>) -> Result<Self> {
+        let publisher = session
+            .declare_publisher(key.clone())
+            .congestion_control(CongestionControl::Drop)
+            .await?;
+        Ok(Self {
+            _session: session.clone(),
+            pool,
+            publisher,
+            key,
+            fallbacks: AtomicU64::new(0),
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Payloads published as regular payloads because the pool was exhausted.
+    pub fn fallbacks(&self) -> u64 {
+        self.fallbacks.load(Ordering::Relaxed)
+    }
+
+    /// Publishes `payload` from a shared-memory buffer, or as a regular payload when no
+    /// buffer can be allocated (e.g. the pool is full of buffers still held by slow
+    /// subscribers).
+    pub async fn put(&self, payload: &[u8]) -> Result<()> {
+        match self
+            .pool
+            .alloc(payload.len())
+            .with_policy::<GarbageCollect>()
+            .wait()
+        {
+            Ok(mut buffer) => {
+                buffer.copy_from_slice(payload);
+                self.publisher.put(buffer).await?;
+            }
+            Err(e) => {
+                self.count_fallback(payload.len(), &format!("{e:?}"));
+                self.publisher.put(payload.to_vec()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn count_fallback(&self, len: usize, reason: &str) {
+        let before = self.fallbacks.fetch_add(1, Ordering::Relaxed);
+        if before == 0 || before / FALLBACK_LOG_INTERVAL != (before + 1) / FALLBACK_LOG_INTERVAL {
+            log::warn!(
+                "{}: {} payloads published without shared memory so far (last: {len} bytes, {reason})",
+                self.key,
+                before + 1
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_put_falls_back_to_regular_payloads() {
+        let mut config = zenoh::Config::from_json5(
+            "{ mode: 'peer', scouting: { multicast: { enabled: false } } }",
+        )
+        .unwrap();
+        enable_shared_memory(&mut config).unwrap();
+        let session = zenoh::open(config).await.unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let _subscriber = session
+            .declare_subscriber("ego_vehicle/test/shm")
+            .callback(move |sample| {
+                let _ = tx.send(sample.payload().to_bytes().to_vec());
+            })
+            .await
+            .unwrap();
+        let publisher = ShmPublisher::declare(
+            &session,
+            "ego_vehicle/test/shm".to_string(),
+            shm_pool(4096).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        // Fits in the pool, then too large for it: both arrive whole
+        for payload in [vec![1u8; 1000], vec![2u8; 8192]] {
+            publisher.put(&payload).await.unwrap();
+            assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), payload);
+        }
+        assert_eq!(publisher.fallbacks(), 1);
+        assert_eq!(publisher.key(), "ego_vehicle/test/shm");
+    }
+}
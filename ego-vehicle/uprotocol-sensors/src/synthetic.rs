@@ -0,0 +1,814 @@
+//! Synthetic sensors, to run the sensor pipeline without a CARLA server.
+//!
+//! A [`SyntheticWorld`] scripts the ego vehicle driving laps of a circular track lined
+//! with poles and parked cars. Generators derive plausible measurements from it (IMU,
+//! GNSS, radar returns, lidar scans) or draw events at a configured rate (lane
+//! invasions, collisions), and a [`SyntheticSource`] calls them on the ticks of a
+//! [`SyntheticClock`], like CARLA sensors on the ticks of the world. Sources implement
+//! [`SensorSource`], so they are published exactly like CARLA sensors.
+//!
+//! Positions and directions follow CARLA: meters, x forward, y right, z up, yaw in
+//! degrees towards y.
+
+use crate::codec::{LIDAR_FIELDS, PointCloud, RADAR_FIELDS};
+use crate::discovery::{DiscoveredSensor, SensorKind};
+use crate::sensors::{GnssFix, SensorSource, change_key};
+use crate::transforms::{SensorFrame, Transform, TransformTree};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::f64::consts::{PI, TAU};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Radius of the WGS84 ellipsoid at the equator, in meters.
+pub const EARTH_RADIUS: f64 = 6_378_137.0;
+const GRAVITY: f64 = 9.81;
+// Height of the synthetic lidar above the ground, in meters
+const LIDAR_HEIGHT: f64 = 2.4;
+
+/// Sensor types that can be simulated.
+pub const SYNTHETIC_KINDS: [SensorKind; 6] = [
+    SensorKind::LaneInvasion,
+    SensorKind::Collision,
+    SensorKind::RadarMeasurement,
+    SensorKind::LidarMeasurement,
+    SensorKind::ImuMeasurement,
+    SensorKind::GnssMeasurement,
+];
+
+/// Default rate of a synthetic sensor of type `kind`, in Hz; for lane invasions and
+/// collisions, the average rate of the events.
+pub fn default_rate(kind: SensorKind) -> f64 {
+    match kind {
+        SensorKind::LaneInvasion => 0.1,
+        SensorKind::Collision => 0.02,
+        SensorKind::RadarMeasurement => 20.0,
+        SensorKind::ImuMeasurement => 50.0,
+        SensorKind::GnssMeasurement | SensorKind::LidarMeasurement => 10.0,
+        _ => 0.0,
+    }
+}
+
+/// Describes a synthetic sensor the way discovery describes a CARLA one, so it gets a
+/// resource ID and a manifest entry.
+pub fn synthetic_sensor(
+    kind: SensorKind,
+    actor_id: u32,
+    role_name: &str,
+    rate: f64,
+) -> DiscoveredSensor {
+    let rate_name = match kind {
+        SensorKind::LaneInvasion | SensorKind::Collision => "event_rate",
+        _ => "sensor_rate",
+    };
+    let attributes = BTreeMap::from([
+        ("role_name".to_string(), role_name.to_string()),
+        (rate_name.to_string(), rate.to_string()),
+        ("synthetic".to_string(), "true".to_string()),
+    ]);
+    DiscoveredSensor {
+        actor_id,
+        type_id: kind.type_id().to_string(),
+        kind,
+        role_name: role_name.to_string(),
+        attributes,
+    }
+}
+
+/// Transforms of synthetic sensors on the ego vehicle: the lidar on the roof, the other
+/// sensors at the origin of the vehicle, all looking forward.
+pub fn synthetic_transforms(sensors: &[DiscoveredSensor]) -> TransformTree {
+    TransformTree {
+        vehicle_actor_id: 0,
+        sensors: sensors
+            .iter()
+            .map(|sensor| SensorFrame {
+                frame: sensor.role_name.clone(),
+                sensor: sensor.kind.name().to_string(),
+                actor_id: sensor.actor_id,
+                transform: match sensor.kind {
+                    SensorKind::LidarMeasurement => Transform {
+                        z: LIDAR_HEIGHT,
+                        ..Transform::default()
+                    },
+                    _ => Transform::default(),
+                },
+            })
+            .collect(),
+    }
+}
+
+/// A pole, a parked car or any other static object, as a vertical cylinder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obstacle {
+    pub actor_id: u32,
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+    pub height: f64,
+}
+
+/// Pose and motion of the ego vehicle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EgoState {
+    pub x: f64,
+    pub y: f64,
+    /// Degrees.
+    pub yaw: f64,
+    /// m/s, along x.
+    pub speed: f64,
+    /// Degrees per second.
+    pub yaw_rate: f64,
+}
+
+impl EgoState {
+    /// Pose of the ego vehicle in the world, on the ground.
+    pub fn transform(&self) -> Transform {
+        Transform {
+            x: self.x,
+            y: self.y,
+            yaw: self.yaw,
+            ..Transform::default()
+        }
+    }
+}
+
+/// Scripted world: the ego vehicle drives laps of a circle around the origin, at a
+/// constant speed, turning right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntheticWorld {
+    /// Meters.
+    pub track_radius: f64,
+    /// m/s.
+    pub speed: f64,
+    pub obstacles: Vec<Obstacle>,
+    /// Geographic position of the origin: latitude, longitude (degrees) and altitude (m).
+    pub geo_origin: (f64, f64, f64),
+}
+
+impl Default for SyntheticWorld {
+    fn default() -> Self {
+        let track_radius = 40.0;
+        let on_circle = |i: usize, count: usize, radius: f64| {
+            let angle = TAU * i as f64 / count as f64;
+            (radius * angle.cos(), radius * angle.sin())
+        };
+        // Poles outside the track, parked cars on the inner lane
+        let poles = (0..12).map(|i| {
+            let (x, y) = on_circle(i, 12, track_radius + 6.0);
+            (x, y, 0.3, 4.0)
+        });
+        let cars = (0..4).map(|i| {
+            let (x, y) = on_circle(2 * i + 1, 8, track_radius - 3.5);
+            (x, y, 1.0, 1.5)
+        });
+        let obstacles = poles
+            .chain(cars)
+            .enumerate()
+            .map(|(i, (x, y, radius, height))| Obstacle {
+                actor_id: 1000 + i as u32,
+                x,
+                y,
+                radius,
+                height,
+            })
+            .collect();
+        Self {
+            track_radius,
+            speed: 8.0,
+            obstacles,
+            geo_origin: (49.0, 8.0, 0.0),
+        }
+    }
+}
+
+impl SyntheticWorld {
+    /// State of the ego vehicle `t` seconds after the start.
+    pub fn ego_at(&self, t: f64) -> EgoState {
+        let angular_speed = self.speed / self.track_radius;
+        let angle = angular_speed * t;
+        EgoState {
+            x: self.track_radius * angle.cos(),
+            y: self.track_radius * angle.sin(),
+            yaw: (angle + PI / 2.0).to_degrees(),
+            speed: self.speed,
+            yaw_rate: angular_speed.to_degrees(),
+        }
+    }
+
+    /// Position of `obstacle` in the frame of the ego vehicle.
+    fn relative(&self, ego: &EgoState, obstacle: &Obstacle) -> (f64, f64) {
+        let (dx, dy) = (obstacle.x - ego.x, obstacle.y - ego.y);
+        let (sin, cos) = ego.yaw.to_radians().sin_cos();
+        (dx * cos + dy * sin, -dx * sin + dy * cos)
+    }
+}
+
+/// Ticks of the synthetic world, every `delta` seconds from `start`.
+#[derive(Clone, Copy, Debug)]
+pub struct SyntheticClock {
+    pub start: Instant,
+    pub delta: f64,
+}
+
+impl SyntheticClock {
+    pub fn new(delta: f64) -> Self {
+        Self {
+            start: Instant::now(),
+            delta,
+        }
+    }
+
+    /// Simulation time of `frame`, in seconds.
+    pub fn timestamp(&self, frame: u64) -> f64 {
+        frame as f64 * self.delta
+    }
+
+    /// Last frame before now.
+    pub fn frame(&self) -> u64 {
+        (self.start.elapsed().as_secs_f64() / self.delta) as u64
+    }
+
+    /// Frames between two events of a sensor publishing at `rate` Hz, at least 1 (CARLA
+    /// sensors never tick faster than the world).
+    pub fn ticks(&self, rate: f64) -> u64 {
+        ((1.0 / (rate * self.delta)).round() as u64).max(1)
+    }
+
+    fn instant(&self, frame: u64) -> Instant {
+        self.start + Duration::from_secs_f64(self.timestamp(frame))
+    }
+}
+
+/// Produces the events of one synthetic sensor.
+pub trait Generator: Send + 'static {
+    type Data: Send + 'static;
+
+    /// Event of the tick `frame`, at `timestamp` seconds, if any.
+    fn generate(
+        &mut self,
+        world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<Self::Data>;
+
+    /// See [`SensorSource::change_key`].
+    fn change_key(_data: &Self::Data) -> Option<u64> {
+        None
+    }
+}
+
+/// Source calling a generator every `ticks` frames of the clock, on its own thread.
+pub struct SyntheticSource<G> {
+    name: String,
+    generator: Mutex<Option<G>>,
+    world: Arc<SyntheticWorld>,
+    clock: SyntheticClock,
+    ticks: u64,
+    stop: Arc<AtomicBool>,
+}
+
+impl<G: Generator> SyntheticSource<G> {
+    pub fn new(
+        name: impl Into<String>,
+        generator: G,
+        world: Arc<SyntheticWorld>,
+        clock: SyntheticClock,
+        ticks: u64,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            generator: Mutex::new(Some(generator)),
+            world,
+            clock,
+            ticks: ticks.max(1),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<G: Generator> SensorSource for SyntheticSource<G> {
+    type Data = G::Data;
+
+    fn listen<F>(&self, f: F)
+    where
+        F: FnMut(Self::Data) + Send + 'static,
+    {
+        let Some(mut generator) = self.generator.lock().unwrap().take() else {
+            log::warn!("{}: synthetic source already listened to", self.name);
+            return;
+        };
+        let (world, clock, ticks) = (Arc::clone(&self.world), self.clock, self.ticks);
+        let stop = Arc::clone(&self.stop);
+
+        let mut f = f;
+        thread::Builder::new()
+            .name(format!("synthetic-{}", self.name))
+            .spawn(move || {
+                let mut frame = (clock.frame() / ticks + 1) * ticks;
+                while !stop.load(Ordering::SeqCst) {
+                    thread::sleep(
+                        clock
+                            .instant(frame)
+                            .saturating_duration_since(Instant::now()),
+                    );
+                    if let Some(data) = generator.generate(&world, frame, clock.timestamp(frame)) {
+                        f(data);
+                    }
+                    // Like a world running late, skip to the last tick already due
+                    frame = (frame + ticks).max(clock.frame() / ticks * ticks);
+                }
+            })
+            .expect("failed to spawn synthetic sensor thread");
+    }
+
+    fn change_key(data: &Self::Data) -> Option<u64> {
+        G::change_key(data)
+    }
+}
+
+impl<G> Drop for SyntheticSource<G> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Small deterministic random generator (SplitMix64), so that runs with the same seed
+/// publish the same data.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Normal with mean 0 and standard deviation `sigma`.
+    pub fn normal(&mut self, sigma: f64) -> f64 {
+        let u = 1.0 - self.uniform();
+        sigma * (-2.0 * u.ln()).sqrt() * (TAU * self.uniform()).cos()
+    }
+
+    /// Whether an event of average rate `rate` Hz happens within `dt` seconds.
+    fn occurs(&mut self, rate: f64, dt: f64) -> bool {
+        self.uniform() < 1.0 - (-rate * dt).exp()
+    }
+}
+
+/// Vector of a synthetic event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// IMU measurement, published as JSON with the field names of CARLA.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SyntheticImu {
+    pub frame: u64,
+    pub timestamp: f64,
+    /// m/s², gravity included.
+    pub accelerometer: Vector3,
+    /// rad/s.
+    pub gyroscope: Vector3,
+    /// Heading in radians, 0 towards north (-y).
+    pub compass: f32,
+}
+
+/// Collision, published as JSON with the field names of CARLA.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SyntheticCollision {
+    pub frame: u64,
+    pub timestamp: f64,
+    pub other_actor_id: u32,
+    /// N·s.
+    pub normal_impulse: Vector3,
+}
+
+/// Lane invasion, published as JSON with the field names of CARLA.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SyntheticLaneInvasion {
+    pub frame: u64,
+    pub timestamp: f64,
+    /// Types of the crossed markings, e.g. `Broken`.
+    pub crossed_lane_markings: Vec<String>,
+}
+
+/// IMU with white noise on every axis.
+pub struct ImuGenerator {
+    pub rng: Rng,
+    pub accelerometer_noise: f64,
+    pub gyroscope_noise: f64,
+}
+
+impl Generator for ImuGenerator {
+    type Data = SyntheticImu;
+
+    fn generate(
+        &mut self,
+        world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<SyntheticImu> {
+        let ego = world.ego_at(timestamp);
+        // Centripetal acceleration, towards the center of the track on the right
+        let lateral = ego.speed * ego.yaw_rate.to_radians();
+        let mut noisy = |value: f64, sigma: f64| (value + self.rng.normal(sigma)) as f32;
+        Some(SyntheticImu {
+            frame,
+            timestamp,
+            accelerometer: Vector3 {
+                x: noisy(0.0, self.accelerometer_noise),
+                y: noisy(lateral, self.accelerometer_noise),
+                z: noisy(GRAVITY, self.accelerometer_noise),
+            },
+            gyroscope: Vector3 {
+                x: noisy(0.0, self.gyroscope_noise),
+                y: noisy(0.0, self.gyroscope_noise),
+                z: noisy(ego.yaw_rate.to_radians(), self.gyroscope_noise),
+            },
+            compass: (ego.yaw + 90.0).to_radians().rem_euclid(TAU) as f32,
+        })
+    }
+}
+
+/// GNSS receiver with a horizontal and vertical error of `noise` meters.
+pub struct GnssGenerator {
+    pub rng: Rng,
+    pub noise: f64,
+}
+
+impl Generator for GnssGenerator {
+    type Data = GnssFix;
+
+    fn generate(&mut self, world: &SyntheticWorld, frame: u64, timestamp: f64) -> Option<GnssFix> {
+        let ego = world.ego_at(timestamp);
+        let (latitude, longitude, altitude) = world.geo_origin;
+        let x = ego.x + self.rng.normal(self.noise);
+        let y = ego.y + self.rng.normal(self.noise);
+        // North is -y
+        Some(GnssFix {
+            frame,
+            timestamp,
+            latitude: latitude - (y / EARTH_RADIUS).to_degrees(),
+            longitude: longitude + (x / (EARTH_RADIUS * latitude.to_radians().cos())).to_degrees(),
+            altitude: altitude + self.rng.normal(self.noise),
+        })
+    }
+}
+
+/// Rotating lidar mounted `height` meters above the ground, hitting the ground and the
+/// obstacles, with the CARLA defaults.
+pub struct LidarGenerator {
+    pub rng: Rng,
+    pub channels: u32,
+    /// Points per channel and scan.
+    pub points_per_channel: u32,
+    /// Meters.
+    pub range: f64,
+    /// Degrees.
+    pub upper_fov: f64,
+    /// Degrees.
+    pub lower_fov: f64,
+    /// Meters.
+    pub height: f64,
+    /// Standard deviation of the measured distance, in meters.
+    pub noise: f64,
+}
+
+impl LidarGenerator {
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            rng,
+            channels: 32,
+            points_per_channel: 180,
+            range: 50.0,
+            upper_fov: 10.0,
+            lower_fov: -30.0,
+            height: LIDAR_HEIGHT,
+            noise: 0.02,
+        }
+    }
+
+    // Distance along the ray (elevation, azimuth) to the nearest hit within range
+    fn cast(
+        &self,
+        obstacles: &[(f64, f64, &Obstacle)],
+        elevation: f64,
+        azimuth: f64,
+    ) -> Option<f64> {
+        let (sin_e, cos_e) = elevation.sin_cos();
+        let (sin_a, cos_a) = azimuth.sin_cos();
+        let mut nearest = (sin_e < 0.0).then(|| self.height / -sin_e);
+
+        for &(x, y, obstacle) in obstacles {
+            // Horizontal distance along the ray to the cylinder
+            let along = x * cos_a + y * sin_a;
+            let across = -x * sin_a + y * cos_a;
+            if along <= 0.0 || across.abs() >= obstacle.radius {
+                continue;
+            }
+            let horizontal = along - (obstacle.radius.powi(2) - across.powi(2)).sqrt();
+            let distance = horizontal / cos_e;
+            let z = self.height + distance * sin_e;
+            if (0.0..=obstacle.height).contains(&z) && nearest.is_none_or(|d| distance < d) {
+                nearest = Some(distance);
+            }
+        }
+        nearest.filter(|distance| *distance <= self.range)
+    }
+}
+
+impl Generator for LidarGenerator {
+    type Data = PointCloud;
+
+    fn generate(
+        &mut self,
+        world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<PointCloud> {
+        let ego = world.ego_at(timestamp);
+        let obstacles: Vec<(f64, f64, &Obstacle)> = world
+            .obstacles
+            .iter()
+            .map(|obstacle| {
+                let (x, y) = world.relative(&ego, obstacle);
+                (x, y, obstacle)
+            })
+            .filter(|(x, y, _)| x.hypot(*y) <= self.range + 2.0)
+            .collect();
+
+        let mut cloud = PointCloud::new(frame, timestamp, &LIDAR_FIELDS);
+        for channel in 0..self.channels {
+            let elevation = if self.channels > 1 {
+                self.upper_fov
+                    - (self.upper_fov - self.lower_fov) * channel as f64
+                        / (self.channels - 1) as f64
+            } else {
+                self.upper_fov
+            }
+            .to_radians();
+            for step in 0..self.points_per_channel {
+                let azimuth = TAU * step as f64 / self.points_per_channel as f64;
+                let Some(distance) = self.cast(&obstacles, elevation, azimuth) else {
+                    continue;
+                };
+                let distance = distance + self.rng.normal(self.noise);
+                let (sin_e, cos_e) = elevation.sin_cos();
+                let (sin_a, cos_a) = azimuth.sin_cos();
+                // Intensity attenuated by the atmosphere, as in CARLA
+                let intensity = (-0.004 * distance).exp();
+                cloud.push(&[
+                    (distance * cos_e * cos_a) as f32,
+                    (distance * cos_e * sin_a) as f32,
+                    (distance * sin_e) as f32,
+                    intensity as f32,
+                ]);
+            }
+        }
+        Some(cloud)
+    }
+}
+
+/// Forward radar detecting the obstacles in its field of view, with a few returns each.
+pub struct RadarGenerator {
+    pub rng: Rng,
+    /// Degrees.
+    pub horizontal_fov: f64,
+    /// Meters.
+    pub range: f64,
+    pub returns_per_obstacle: u32,
+}
+
+impl RadarGenerator {
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            rng,
+            horizontal_fov: 30.0,
+            range: 100.0,
+            returns_per_obstacle: 3,
+        }
+    }
+}
+
+impl Generator for RadarGenerator {
+    type Data = PointCloud;
+
+    fn generate(
+        &mut self,
+        world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<PointCloud> {
+        let ego = world.ego_at(timestamp);
+        let mut cloud = PointCloud::new(frame, timestamp, &RADAR_FIELDS);
+        for obstacle in &world.obstacles {
+            let (x, y) = world.relative(&ego, obstacle);
+            let depth = x.hypot(y) - obstacle.radius;
+            let azimuth = y.atan2(x);
+            if depth > self.range || azimuth.abs() > (self.horizontal_fov / 2.0).to_radians() {
+                continue;
+            }
+            for _ in 0..self.returns_per_obstacle {
+                let azimuth = azimuth + self.rng.normal(0.005);
+                // Static obstacles approach at the speed of the ego vehicle (negative)
+                cloud.push(&[
+                    (-ego.speed * azimuth.cos() + self.rng.normal(0.1)) as f32,
+                    azimuth as f32,
+                    self.rng.normal(0.01) as f32,
+                    (depth + self.rng.normal(0.1)) as f32,
+                ]);
+            }
+        }
+        Some(cloud)
+    }
+}
+
+/// Lane invasions at an average rate of `rate` Hz.
+pub struct LaneInvasionGenerator {
+    pub rng: Rng,
+    pub rate: f64,
+    pub last_timestamp: Option<f64>,
+}
+
+impl Generator for LaneInvasionGenerator {
+    type Data = SyntheticLaneInvasion;
+
+    fn generate(
+        &mut self,
+        _world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<SyntheticLaneInvasion> {
+        let dt = timestamp - self.last_timestamp.replace(timestamp)?;
+        if !self.rng.occurs(self.rate, dt) {
+            return None;
+        }
+        let marking = if self.rng.uniform() < 0.8 {
+            "Broken"
+        } else {
+            "Solid"
+        };
+        Some(SyntheticLaneInvasion {
+            frame,
+            timestamp,
+            crossed_lane_markings: vec![marking.to_string()],
+        })
+    }
+
+    fn change_key(data: &SyntheticLaneInvasion) -> Option<u64> {
+        Some(change_key(&data.crossed_lane_markings))
+    }
+}
+
+/// Collisions with the obstacles at an average rate of `rate` Hz.
+pub struct CollisionGenerator {
+    pub rng: Rng,
+    pub rate: f64,
+    pub last_timestamp: Option<f64>,
+}
+
+impl Generator for CollisionGenerator {
+    type Data = SyntheticCollision;
+
+    fn generate(
+        &mut self,
+        world: &SyntheticWorld,
+        frame: u64,
+        timestamp: f64,
+    ) -> Option<SyntheticCollision> {
+        let dt = timestamp - self.last_timestamp.replace(timestamp)?;
+        if world.obstacles.is_empty() || !self.rng.occurs(self.rate, dt) {
+            return None;
+        }
+        let obstacle = &world.obstacles[self.rng.next_u64() as usize % world.obstacles.len()];
+        let impulse = 500.0 + 4500.0 * self.rng.uniform();
+        let angle = TAU * self.rng.uniform();
+        Some(SyntheticCollision {
+            frame,
+            timestamp,
+            other_actor_id: obstacle.actor_id,
+            normal_impulse: Vector3 {
+                x: (impulse * angle.cos()) as f32,
+                y: (impulse * angle.sin()) as f32,
+                z: 0.0,
+            },
+        })
+    }
+
+    fn change_key(data: &SyntheticCollision) -> Option<u64> {
+        Some(change_key(data.other_actor_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_and_clock() {
+        let world = SyntheticWorld::default();
+        let start = world.ego_at(0.0);
+        assert_eq!((start.x, start.y, start.yaw), (40.0, 0.0, 90.0));
+        // A quarter lap later, the vehicle drives towards -x
+        let quarter = world.ego_at(PI / 2.0 * 40.0 / 8.0);
+        assert!(quarter.x.abs() < 1e-9 && (quarter.y - 40.0).abs() < 1e-9);
+        assert!((quarter.yaw - 180.0).abs() < 1e-9);
+
+        let clock = SyntheticClock::new(0.05);
+        assert_eq!(clock.ticks(10.0), 2);
+        assert_eq!(clock.ticks(100.0), 1);
+        assert_eq!(clock.timestamp(40), 2.0);
+    }
+
+    #[test]
+    fn test_lidar_scan() {
+        let pole = Obstacle {
+            actor_id: 1,
+            x: 10.0,
+            y: 0.0,
+            radius: 0.5,
+            height: 4.0,
+        };
+        let world = SyntheticWorld {
+            track_radius: 1e6,
+            speed: 0.0,
+            obstacles: vec![pole],
+            geo_origin: (49.0, 8.0, 0.0),
+        };
+        let mut lidar = LidarGenerator::new(Rng::new(7));
+        lidar.noise = 0.0;
+        // The vehicle at (1e6, 0) faces +y: move the pole in front of it
+        let ego = world.ego_at(0.0);
+        let world = SyntheticWorld {
+            obstacles: vec![Obstacle {
+                x: ego.x,
+                y: ego.y + 10.0,
+                ..pole
+            }],
+            ..world
+        };
+
+        let cloud = lidar.generate(&world, 3, 0.3).unwrap();
+        assert_eq!((cloud.frame, cloud.fields.len()), (3, 4));
+        let points: Vec<&[f32]> = cloud.values.chunks(4).collect();
+        // Every point is on the ground or on the pole, 9.5 m ahead
+        assert!(
+            points.iter().all(|p| (p[2] + 2.4).abs() < 1e-3
+                || ((9.5..=10.0).contains(&p[0]) && p[1].abs() <= 0.5))
+        );
+        assert!(points.iter().any(|p| p[2] > -2.0));
+        assert!(points.iter().all(|p| p[0].hypot(p[1]).hypot(p[2]) <= 50.0));
+
+        // In the frame of the vehicle, the ground is at z = 0
+        let sensors = [synthetic_sensor(
+            SensorKind::LidarMeasurement,
+            1,
+            "lidar",
+            10.0,
+        )];
+        let vehicle = synthetic_transforms(&sensors)
+            .transform_cloud(&cloud, "lidar", crate::transforms::VEHICLE_FRAME, None)
+            .unwrap();
+        assert!(vehicle.values.chunks(4).any(|p| p[2].abs() < 1e-3));
+        assert!(vehicle.values.chunks(4).all(|p| p[2] > -1e-3));
+    }
+
+    #[test]
+    fn test_events_are_reproducible() {
+        let world = SyntheticWorld::default();
+        let run = |seed| {
+            let mut collisions = CollisionGenerator {
+                rng: Rng::new(seed),
+                rate: 1.0,
+                last_timestamp: None,
+            };
+            (0..200)
+                .filter_map(|frame| collisions.generate(&world, frame, frame as f64 * 0.1))
+                .map(|collision| (collision.frame, collision.other_actor_id))
+                .collect::<Vec<_>>()
+        };
+        let events = run(1);
+        // About one collision per second, over 20 s
+        assert!((10..=30).contains(&events.len()), "{}", events.len());
+        assert_eq!(events, run(1));
+        assert_ne!(events, run(2));
+    }
+}
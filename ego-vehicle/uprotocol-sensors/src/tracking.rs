@@ -0,0 +1,704 @@
+//! Tracking of the objects around the ego vehicle, from its lidar and radar.
+//!
+//! Every lidar scan and radar measurement is reduced to [`Detection`]s: the lidar points
+//! above the ground and the radar returns, clustered. A [`Tracker`] associates the
+//! detections with its tracks, by nearest neighbour or global nearest neighbour on their
+//! Mahalanobis distance, and filters every track with a constant-velocity Kalman filter
+//! in the frame of the ego vehicle, compensated for its turning. The radial velocity of
+//! the radar corrects the velocity of the tracks.
+//!
+//! The [`TrackList`] lists the confirmed tracks, relative to the ego vehicle, and the lead
+//! object: the nearest track ahead on the predicted path of the vehicle, for ACC and AEB.
+//! The `object_tracker` binary publishes it from the bridge topics.
+
+mod detection;
+
+pub use detection::*;
+
+use crate::codec::PointCloud;
+use nalgebra::{Matrix2, Matrix4, Rotation2, RowVector4, SMatrix, Vector2, Vector4};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Authority of the tracker, e.g. in `//EgoTracker/0/2/8001`.
+pub const TRACKER_AUTHORITY: &str = "EgoTracker";
+/// Resource ID of the track list topic.
+pub const RESOURCE_TRACK_LIST: u16 = 0x8001;
+
+// Weight of the last update in the confidence of a track, and in its size
+const CONFIDENCE_GAIN: f64 = 0.3;
+const SIZE_GAIN: f64 = 0.3;
+// Meters beyond the range of every sensor where tracks are deleted
+const RANGE_MARGIN: f64 = 5.0;
+
+/// Association of the detections with the tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Association {
+    /// Each track takes the nearest free detection, nearest pairs first.
+    NearestNeighbour,
+    /// The assignment minimising the sum of the distances, unassigned tracks and
+    /// detections counting as the gate.
+    GlobalNearestNeighbour,
+}
+
+/// Horizontal field of view of a sensor, centered on the x axis of the vehicle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldOfView {
+    /// Degrees.
+    pub horizontal: f64,
+    /// Meters.
+    pub range: f64,
+}
+
+impl FieldOfView {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x.hypot(y) <= self.range && y.atan2(x).abs().to_degrees() <= self.horizontal / 2.0
+    }
+}
+
+/// Settings of the tracker; noises are standard deviations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackerConfig {
+    pub detection: DetectionConfig,
+    pub association: Association,
+    /// Mahalanobis distance beyond which a detection cannot be associated with a track.
+    pub gate: f64,
+    /// Meters.
+    pub lidar_noise: f64,
+    pub radar_noise: f64,
+    /// m/s.
+    pub radial_velocity_noise: f64,
+    /// Accelerations of the objects, in m/s².
+    pub acceleration_noise: f64,
+    /// Hits after which a track is confirmed and published.
+    pub confirm_hits: u32,
+    /// Consecutive misses after which a confirmed track is deleted; tentative tracks are
+    /// deleted at their first miss.
+    pub max_misses: u32,
+    /// A track is missed by a sensor only within its field of view.
+    pub lidar_fov: FieldOfView,
+    pub radar_fov: FieldOfView,
+    /// Meters; a lead object is within half of it from the path of the vehicle.
+    pub lane_width: f64,
+}
+
+impl Default for TrackerConfig {
+    /// Fields of view of the synthetic sensors.
+    fn default() -> Self {
+        Self {
+            detection: DetectionConfig::default(),
+            association: Association::GlobalNearestNeighbour,
+            gate: 3.5,
+            lidar_noise: 0.3,
+            radar_noise: 0.5,
+            radial_velocity_noise: 0.2,
+            acceleration_noise: 2.0,
+            confirm_hits: 3,
+            max_misses: 5,
+            lidar_fov: FieldOfView {
+                horizontal: 360.0,
+                range: 50.0,
+            },
+            radar_fov: FieldOfView {
+                horizontal: 30.0,
+                range: 100.0,
+            },
+            lane_width: 3.5,
+        }
+    }
+}
+
+/// Motion of the ego vehicle, which the tracks are relative to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EgoMotion {
+    /// m/s.
+    pub speed: f64,
+    /// rad/s, positive towards y.
+    pub yaw_rate: f64,
+}
+
+/// Tracked object, relative to the ego vehicle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackedObject {
+    pub id: u32,
+    /// Meters, x forward, y right, from the origin of the vehicle.
+    pub x: f64,
+    pub y: f64,
+    /// Velocity relative to the vehicle, in m/s: `-speed` along x for static objects.
+    pub vx: f64,
+    pub vy: f64,
+    /// Once seen by the lidar.
+    pub size: Option<ObjectSize>,
+    /// Seconds since the first detection.
+    pub age: f64,
+    /// Between 0 and 1: the detection rate of the track, smoothed.
+    pub confidence: f64,
+    /// Sensors which detected the track.
+    pub sources: Vec<DetectionSource>,
+}
+
+/// Tracks after a sensor update, published as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackList {
+    /// CARLA frame and simulation time of the last update.
+    pub frame: u64,
+    pub timestamp: f64,
+    pub tracks: Vec<TrackedObject>,
+    /// ID of the lead object, among the tracks.
+    pub lead: Option<u32>,
+}
+
+impl TrackList {
+    pub fn lead_object(&self) -> Option<&TrackedObject> {
+        let lead = self.lead?;
+        self.tracks.iter().find(|track| track.id == lead)
+    }
+}
+
+// Track filtered over [x, y, vx, vy]
+#[derive(Clone, Debug)]
+struct Track {
+    id: u32,
+    state: Vector4<f64>,
+    covariance: Matrix4<f64>,
+    created: f64,
+    hits: u32,
+    misses: u32,
+    confirmed: bool,
+    confidence: f64,
+    size: Option<ObjectSize>,
+    sources: Vec<DetectionSource>,
+}
+
+impl Track {
+    // Mahalanobis distance, squared, of a detection at (x, y) measured with `noise`
+    fn distance(&self, detection: &Detection, noise: f64) -> f64 {
+        let innovation = Vector2::new(detection.x - self.state[0], detection.y - self.state[1]);
+        let s = self.covariance.fixed_view::<2, 2>(0, 0) + Matrix2::identity() * noise * noise;
+        s.try_inverse().map_or(f64::INFINITY, |s| {
+            (innovation.transpose() * s * innovation)[0]
+        })
+    }
+
+    fn update<const M: usize>(
+        &mut self,
+        innovation: SMatrix<f64, M, 1>,
+        h: SMatrix<f64, M, 4>,
+        r: SMatrix<f64, M, M>,
+    ) {
+        let s = h * self.covariance * h.transpose() + r;
+        let Some(s_inverse) = s.try_inverse() else {
+            return;
+        };
+        let k = self.covariance * h.transpose() * s_inverse;
+        self.state += k * innovation;
+        let i_kh = Matrix4::identity() - k * h;
+        self.covariance = i_kh * self.covariance * i_kh.transpose() + k * r * k.transpose();
+    }
+
+    fn update_radial_velocity(&mut self, detection: &Detection, velocity: f64, noise: f64) {
+        let (dx, dy) = (
+            self.state[0] - detection.origin.0,
+            self.state[1] - detection.origin.1,
+        );
+        let range = dx.hypot(dy);
+        if range < 1.0 {
+            return;
+        }
+        let (ux, uy) = (dx / range, dy / range);
+        let (vx, vy) = (self.state[2], self.state[3]);
+        let predicted = vx * ux + vy * uy;
+        // Derivative of the radial velocity with the position: its tangential part
+        let h = RowVector4::new(
+            (vx - predicted * ux) / range,
+            (vy - predicted * uy) / range,
+            ux,
+            uy,
+        );
+        self.update(
+            SMatrix::<f64, 1, 1>::new(velocity - predicted),
+            h,
+            SMatrix::<f64, 1, 1>::new(noise * noise),
+        );
+    }
+}
+
+/// Tracks the objects detected by the lidar and the radar, fed in the order they arrive.
+#[derive(Clone, Debug)]
+pub struct Tracker {
+    config: TrackerConfig,
+    tracks: Vec<Track>,
+    next_id: u32,
+    frame: u64,
+    time: Option<f64>,
+    ego: EgoMotion,
+}
+
+impl Tracker {
+    pub fn new(config: TrackerConfig) -> Self {
+        Self {
+            config,
+            tracks: Vec::new(),
+            next_id: 1,
+            frame: 0,
+            time: None,
+            ego: EgoMotion::default(),
+        }
+    }
+
+    /// Sets the motion of the ego vehicle, until the next call; tracks are predicted
+    /// as if the vehicle drove straight ahead at constant speed otherwise.
+    pub fn set_ego_motion(&mut self, ego: EgoMotion) {
+        self.ego = ego;
+    }
+
+    /// Sets the mount of the sensor `source`, e.g. once read from the transform tree.
+    pub fn set_mount(&mut self, source: DetectionSource, mount: SensorMount) {
+        match source {
+            DetectionSource::Lidar => self.config.detection.lidar_mount = mount,
+            DetectionSource::Radar => self.config.detection.radar_mount = mount,
+        }
+    }
+
+    pub fn on_lidar(&mut self, cloud: &PointCloud) -> Result<()> {
+        let detections = lidar_detections(cloud, &self.config.detection)?;
+        self.update(
+            cloud.frame,
+            cloud.timestamp,
+            DetectionSource::Lidar,
+            &detections,
+        );
+        Ok(())
+    }
+
+    pub fn on_radar(&mut self, cloud: &PointCloud) -> Result<()> {
+        let detections = radar_detections(cloud, &self.config.detection)?;
+        self.update(
+            cloud.frame,
+            cloud.timestamp,
+            DetectionSource::Radar,
+            &detections,
+        );
+        Ok(())
+    }
+
+    /// Updates the tracks with the detections of `source` at `timestamp` seconds:
+    /// predicts them, associates the detections, corrects the associated tracks, starts
+    /// new tracks for the others and deletes lost tracks.
+    pub fn update(
+        &mut self,
+        frame: u64,
+        timestamp: f64,
+        source: DetectionSource,
+        detections: &[Detection],
+    ) {
+        // Measurements late by a few milliseconds apply to the current tracks
+        let dt = self.time.map_or(0.0, |time| (timestamp - time).max(0.0));
+        self.predict(dt);
+        self.frame = frame;
+        self.time = Some(self.time.map_or(timestamp, |time| time.max(timestamp)));
+
+        let (noise, fov) = match source {
+            DetectionSource::Lidar => (self.config.lidar_noise, self.config.lidar_fov),
+            DetectionSource::Radar => (self.config.radar_noise, self.config.radar_fov),
+        };
+        let distances: Vec<Vec<f64>> = self
+            .tracks
+            .iter()
+            .map(|track| {
+                detections
+                    .iter()
+                    .map(|detection| track.distance(detection, noise))
+                    .collect()
+            })
+            .collect();
+        let gate = self.config.gate * self.config.gate;
+        let assignment = match self.config.association {
+            Association::NearestNeighbour => nearest_neighbour(&distances, gate),
+            Association::GlobalNearestNeighbour => global_nearest_neighbour(&distances, gate),
+        };
+
+        let mut associated = vec![false; detections.len()];
+        for (track, detection) in self.tracks.iter_mut().zip(assignment) {
+            let Some(detection) = detection.map(|index| {
+                associated[index] = true;
+                &detections[index]
+            }) else {
+                if fov.contains(track.state[0], track.state[1]) {
+                    track.misses += 1;
+                    track.confidence *= 1.0 - CONFIDENCE_GAIN;
+                }
+                continue;
+            };
+
+            let mut h = SMatrix::<f64, 2, 4>::zeros();
+            h[(0, 0)] = 1.0;
+            h[(1, 1)] = 1.0;
+            track.update(
+                Vector2::new(detection.x - track.state[0], detection.y - track.state[1]),
+                h,
+                Matrix2::identity() * noise * noise,
+            );
+            if let Some(velocity) = detection.radial_velocity {
+                track.update_radial_velocity(
+                    detection,
+                    velocity,
+                    self.config.radial_velocity_noise,
+                );
+            }
+            if let Some(size) = detection.size {
+                track.size = Some(track.size.map_or(size, |smoothed| ObjectSize {
+                    length: smoothed.length + SIZE_GAIN * (size.length - smoothed.length),
+                    width: smoothed.width + SIZE_GAIN * (size.width - smoothed.width),
+                    height: smoothed.height + SIZE_GAIN * (size.height - smoothed.height),
+                }));
+            }
+            if !track.sources.contains(&source) {
+                track.sources.push(source);
+            }
+            track.hits += 1;
+            track.misses = 0;
+            track.confidence += CONFIDENCE_GAIN * (1.0 - track.confidence);
+            track.confirmed |= track.hits >= self.config.confirm_hits;
+        }
+
+        let max_misses = self.config.max_misses;
+        let max_range = self.config.lidar_fov.range.max(self.config.radar_fov.range) + RANGE_MARGIN;
+        self.tracks.retain(|track| {
+            let lost = if track.confirmed {
+                track.misses > max_misses
+            } else {
+                track.misses > 0
+            };
+            if lost {
+                log::debug!("Deleted track {} after {} hits", track.id, track.hits);
+            }
+            !lost && track.state[0].hypot(track.state[1]) <= max_range
+        });
+
+        for (detection, _) in detections
+            .iter()
+            .zip(associated)
+            .filter(|(_, associated)| !associated)
+        {
+            self.start(detection, noise, source);
+        }
+    }
+
+    /// The confirmed tracks and the lead object.
+    pub fn track_list(&self) -> TrackList {
+        let time = self.time.unwrap_or_default();
+        let tracks: Vec<TrackedObject> = self
+            .tracks
+            .iter()
+            .filter(|track| track.confirmed)
+            .map(|track| TrackedObject {
+                id: track.id,
+                x: track.state[0],
+                y: track.state[1],
+                vx: track.state[2],
+                vy: track.state[3],
+                size: track.size,
+                age: time - track.created,
+                confidence: track.confidence,
+                sources: track.sources.clone(),
+            })
+            .collect();
+        let lead = lead_object(&tracks, self.ego, self.config.lane_width).map(|track| track.id);
+        TrackList {
+            frame: self.frame,
+            timestamp: time,
+            tracks,
+            lead,
+        }
+    }
+
+    // Moves the tracks dt seconds forward, and into the frame of the vehicle then
+    fn predict(&mut self, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        // The vehicle turning by `angle` turns the world by `-angle`; its own velocity
+        // stays along x, so only the velocity of the objects over ground turns
+        let rotation = Rotation2::new(-self.ego.yaw_rate * dt).into_inner();
+        let ego_velocity = Vector2::new(self.ego.speed, 0.0);
+        let mut f = Matrix4::zeros();
+        f.fixed_view_mut::<2, 2>(0, 0).copy_from(&rotation);
+        f.fixed_view_mut::<2, 2>(0, 2).copy_from(&(rotation * dt));
+        f.fixed_view_mut::<2, 2>(2, 2).copy_from(&rotation);
+
+        // Constant-velocity model driven by white accelerations
+        let q = self.config.acceleration_noise.powi(2);
+        let mut noise = Matrix4::zeros();
+        for axis in 0..2 {
+            noise[(axis, axis)] = q * dt.powi(4) / 4.0;
+            noise[(axis, axis + 2)] = q * dt.powi(3) / 2.0;
+            noise[(axis + 2, axis)] = q * dt.powi(3) / 2.0;
+            noise[(axis + 2, axis + 2)] = q * dt * dt;
+        }
+
+        for track in &mut self.tracks {
+            let mut state = f * track.state;
+            let velocity = rotation * ego_velocity - ego_velocity;
+            state[2] += velocity[0];
+            state[3] += velocity[1];
+            track.state = state;
+            track.covariance = f * track.covariance * f.transpose() + noise;
+        }
+    }
+
+    // Starts a tentative track at an unassociated detection, static until measured
+    // otherwise
+    fn start(&mut self, detection: &Detection, noise: f64, source: DetectionSource) {
+        let mut track = Track {
+            id: self.next_id,
+            state: Vector4::new(detection.x, detection.y, -self.ego.speed, 0.0),
+            covariance: Matrix4::from_diagonal(&Vector4::new(
+                noise * noise,
+                noise * noise,
+                16.0,
+                16.0,
+            )),
+            created: self.time.unwrap_or_default(),
+            hits: 1,
+            misses: 0,
+            confirmed: self.config.confirm_hits <= 1,
+            confidence: CONFIDENCE_GAIN,
+            size: detection.size,
+            sources: vec![source],
+        };
+        if let Some(velocity) = detection.radial_velocity {
+            track.update_radial_velocity(detection, velocity, self.config.radial_velocity_noise);
+        }
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        self.tracks.push(track);
+    }
+}
+
+/// Nearest track ahead within half a lane of the path of the vehicle, predicted from its
+/// speed and yaw rate as a circle.
+pub fn lead_object(
+    tracks: &[TrackedObject],
+    ego: EgoMotion,
+    lane_width: f64,
+) -> Option<&TrackedObject> {
+    let curvature = if ego.speed > 1.0 {
+        ego.yaw_rate / ego.speed
+    } else {
+        0.0
+    };
+    tracks
+        .iter()
+        .filter(|track| {
+            let path = curvature * track.x * track.x / 2.0;
+            track.x > 0.0 && (track.y - path).abs() <= lane_width / 2.0
+        })
+        .min_by(|a, b| a.x.total_cmp(&b.x))
+}
+
+/// Detection of each track, among those within `gate` of it, taking the nearest pairs
+/// first.
+fn nearest_neighbour(distances: &[Vec<f64>], gate: f64) -> Vec<Option<usize>> {
+    let mut pairs: Vec<(f64, usize, usize)> = distances
+        .iter()
+        .enumerate()
+        .flat_map(|(track, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(detection, distance)| (*distance, track, detection))
+        })
+        .filter(|(distance, _, _)| *distance <= gate)
+        .collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut assignment = vec![None; distances.len()];
+    let mut taken = vec![false; distances.first().map_or(0, Vec::len)];
+    for (_, track, detection) in pairs {
+        if assignment[track].is_none() && !taken[detection] {
+            assignment[track] = Some(detection);
+            taken[detection] = true;
+        }
+    }
+    assignment
+}
+
+/// Detection of each track minimising the sum of the distances, with the Hungarian
+/// algorithm; leaving a track or a detection unassigned costs `gate`.
+fn global_nearest_neighbour(distances: &[Vec<f64>], gate: f64) -> Vec<Option<usize>> {
+    let tracks = distances.len();
+    let detections = distances.first().map_or(0, Vec::len);
+    // Square problem: tracks then a dummy per detection, detections then a dummy per track
+    let n = tracks + detections;
+    let forbidden = 2.0 * gate * n as f64 + 1.0;
+    let cost = |row: usize, column: usize| match (row < tracks, column < detections) {
+        (true, true) => {
+            let distance = distances[row][column];
+            if distance <= gate {
+                distance
+            } else {
+                forbidden
+            }
+        }
+        (true, false) => {
+            if column - detections == row {
+                gate
+            } else {
+                forbidden
+            }
+        }
+        (false, true) => {
+            if row - tracks == column {
+                gate
+            } else {
+                forbidden
+            }
+        }
+        (false, false) => 0.0,
+    };
+
+    // Potentials of the rows and columns, 1-based with the column 0 as a sentinel
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut row_of = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut column = 0;
+        let mut min_value = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[column] = true;
+            let current = row_of[column];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(current - 1, j - 1) - u[current] - v[j];
+                if reduced < min_value[j] {
+                    min_value[j] = reduced;
+                    way[j] = column;
+                }
+                if min_value[j] < delta {
+                    delta = min_value[j];
+                    next = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_value[j] -= delta;
+                }
+            }
+            column = next;
+            if row_of[column] == 0 {
+                break;
+            }
+        }
+        while column != 0 {
+            let previous = way[column];
+            row_of[column] = row_of[previous];
+            column = previous;
+        }
+    }
+
+    let mut assignment = vec![None; tracks];
+    for column in 1..=detections {
+        let row = row_of[column] - 1;
+        if row < tracks && distances[row][column - 1] <= gate {
+            assignment[row] = Some(column - 1);
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{
+        Generator, LidarGenerator, Obstacle, RadarGenerator, Rng, SyntheticWorld,
+    };
+
+    #[test]
+    fn test_association() {
+        // Nearest neighbour pairs the track 0 with the detection 0, leaving the track 1
+        // without any; the global assignment pairs both
+        let distances = vec![vec![1.0, 2.0], vec![1.5, 9.0]];
+        assert_eq!(nearest_neighbour(&distances, 4.0), vec![Some(0), None]);
+        assert_eq!(
+            global_nearest_neighbour(&distances, 4.0),
+            vec![Some(1), Some(0)]
+        );
+        assert_eq!(
+            global_nearest_neighbour(&[vec![5.0], vec![0.5]], 4.0),
+            vec![None, Some(0)]
+        );
+        assert_eq!(
+            global_nearest_neighbour(&[vec![], vec![]], 4.0),
+            vec![None, None]
+        );
+        assert!(global_nearest_neighbour(&[], 4.0).is_empty());
+    }
+
+    #[test]
+    fn test_synthetic_lead_object() {
+        // A stopped car on the track, 20 m ahead of the start
+        let mut world = SyntheticWorld::default();
+        let angle = 20.0 / world.track_radius;
+        world.obstacles.push(Obstacle {
+            actor_id: 2000,
+            x: world.track_radius * angle.cos(),
+            y: world.track_radius * angle.sin(),
+            radius: 1.0,
+            height: 1.5,
+        });
+
+        let mut tracker = Tracker::new(TrackerConfig::default());
+        let mut lidar = LidarGenerator::new(Rng::new(1));
+        let mut radar = RadarGenerator::new(Rng::new(2));
+        for frame in 0..=25 {
+            let timestamp = 0.05 * frame as f64;
+            let ego = world.ego_at(timestamp);
+            tracker.set_ego_motion(EgoMotion {
+                speed: ego.speed,
+                yaw_rate: ego.yaw_rate.to_radians(),
+            });
+            if frame % 2 == 0 {
+                let cloud = lidar.generate(&world, frame, timestamp).unwrap();
+                tracker.on_lidar(&cloud).unwrap();
+            }
+            let cloud = radar.generate(&world, frame, timestamp).unwrap();
+            tracker.on_radar(&cloud).unwrap();
+        }
+
+        let tracks = tracker.track_list();
+        assert_eq!(tracks.frame, 25);
+        let lead = tracks.lead_object().expect("lead object");
+        // 10 m driven of the 20 m, the car centered 1 m behind its visible surface
+        let distance = lead.x.hypot(lead.y);
+        assert!((distance - 10.0).abs() < 1.2, "{lead:?}");
+        assert!(
+            (lead.vx + 8.0).abs() < 0.5 && lead.vy.abs() < 1.0,
+            "{lead:?}"
+        );
+        assert_eq!(
+            lead.sources,
+            vec![DetectionSource::Lidar, DetectionSource::Radar]
+        );
+        assert!(lead.size.is_some() && lead.confidence > 0.9);
+        assert!(tracks.tracks.len() > 3);
+        // The parked cars are in the next lane
+        assert!(
+            tracks
+                .tracks
+                .iter()
+                .filter(|track| track.id != lead.id && track.x > 0.0 && track.x < lead.x)
+                .all(|track| track.y.abs() > 1.75)
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Objects detected in one lidar scan or radar measurement.
+
+use crate::codec::PointCloud;
+use crate::transforms::Transform;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Sensor of a detection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionSource {
+    Lidar,
+    Radar,
+}
+
+/// Position of a sensor on the ego vehicle, in meters; its axes are assumed aligned with
+/// the vehicle ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SensorMount {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<&Transform> for SensorMount {
+    /// Position of a sensor from its transform in the frame of the vehicle; its rotation
+    /// is ignored.
+    fn from(transform: &Transform) -> Self {
+        Self {
+            x: transform.x,
+            y: transform.y,
+            z: transform.z,
+        }
+    }
+}
+
+/// Extent of an object along the axes of the vehicle, in meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObjectSize {
+    pub length: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Object detected by a sensor, in the frame of the ego vehicle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+    pub source: DetectionSource,
+    /// Meters, x forward, y right.
+    pub x: f64,
+    pub y: f64,
+    /// Extent of the points, for lidar detections.
+    pub size: Option<ObjectSize>,
+    /// Speed away from the sensor in m/s, for radar detections.
+    pub radial_velocity: Option<f64>,
+    /// Position of the sensor, which the radial velocity is measured from.
+    pub origin: (f64, f64),
+    /// Points of the cluster.
+    pub points: usize,
+}
+
+/// Settings of the ground removal and the clustering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DetectionConfig {
+    pub lidar_mount: SensorMount,
+    pub radar_mount: SensorMount,
+    /// Half the length and width of the ego vehicle, whose own points are ignored.
+    pub ego_extent: (f64, f64),
+    /// Side of the cells of the ground grid, in meters.
+    pub ground_cell: f64,
+    /// Points less than this above the lowest point of their cell are ground, in meters.
+    pub ground_tolerance: f64,
+    /// Cells whose lowest point is higher than this above the vehicle have no ground.
+    pub max_ground_height: f64,
+    /// Points higher than this above the vehicle are ignored, e.g. bridges and trees.
+    pub max_height: f64,
+    /// Meters between neighbouring points of a lidar cluster.
+    pub lidar_cluster_distance: f64,
+    pub min_lidar_points: usize,
+    /// Meters between neighbouring detections of a radar cluster.
+    pub radar_cluster_distance: f64,
+}
+
+impl Default for DetectionConfig {
+    /// Mounts of the synthetic sensors.
+    fn default() -> Self {
+        Self {
+            lidar_mount: SensorMount {
+                x: 0.0,
+                y: 0.0,
+                z: 2.4,
+            },
+            radar_mount: SensorMount::default(),
+            ego_extent: (2.5, 1.1),
+            ground_cell: 1.0,
+            ground_tolerance: 0.25,
+            max_ground_height: 0.5,
+            max_height: 3.0,
+            lidar_cluster_distance: 0.7,
+            min_lidar_points: 3,
+            radar_cluster_distance: 2.0,
+        }
+    }
+}
+
+// Cell of a grid of side `size` containing (x, y)
+fn cell(x: f64, y: f64, size: f64) -> (i64, i64) {
+    ((x / size).floor() as i64, (y / size).floor() as i64)
+}
+
+/// Points above the ground: the points close to the lowest point of their cell of the
+/// ground grid are removed, unless that cell is all above the ground.
+pub fn remove_ground(points: &[[f64; 3]], config: &DetectionConfig) -> Vec<[f64; 3]> {
+    let mut lowest: HashMap<(i64, i64), f64> = HashMap::new();
+    for [x, y, z] in points {
+        let entry = lowest.entry(cell(*x, *y, config.ground_cell)).or_insert(*z);
+        *entry = entry.min(*z);
+    }
+    points
+        .iter()
+        .filter(|[x, y, z]| {
+            let ground = lowest[&cell(*x, *y, config.ground_cell)];
+            *z <= config.max_height
+                && (ground > config.max_ground_height || *z - ground > config.ground_tolerance)
+        })
+        .copied()
+        .collect()
+}
+
+/// Clusters of points closer than `distance` to one another on the ground plane, as
+/// indices into `points`, with at least `min_points` each.
+pub fn euclidean_clusters(
+    points: &[[f64; 3]],
+    distance: f64,
+    min_points: usize,
+) -> Vec<Vec<usize>> {
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, [x, y, _]) in points.iter().enumerate() {
+        grid.entry(cell(*x, *y, distance)).or_default().push(i);
+    }
+
+    let mut clustered = vec![false; points.len()];
+    let mut clusters = Vec::new();
+    for seed in 0..points.len() {
+        if clustered[seed] {
+            continue;
+        }
+        clustered[seed] = true;
+        let mut cluster = vec![seed];
+        let mut next = 0;
+        while let Some(&i) = cluster.get(next) {
+            next += 1;
+            let [x, y, _] = points[i];
+            let (column, row) = cell(x, y, distance);
+            for neighbour in (column - 1..=column + 1)
+                .flat_map(|c| (row - 1..=row + 1).map(move |r| (c, r)))
+                .filter_map(|key| grid.get(&key))
+                .flatten()
+            {
+                let [nx, ny, _] = points[*neighbour];
+                if !clustered[*neighbour] && (nx - x).hypot(ny - y) <= distance {
+                    clustered[*neighbour] = true;
+                    cluster.push(*neighbour);
+                }
+            }
+        }
+        if cluster.len() >= min_points {
+            clusters.push(cluster);
+        }
+    }
+    clusters
+}
+
+// Values of the fields `names`, point by point
+fn fields<const N: usize>(cloud: &PointCloud, names: [&str; N]) -> Result<Vec<[f64; N]>> {
+    let mut indices = [0; N];
+    for (index, name) in indices.iter_mut().zip(names) {
+        *index = cloud
+            .fields
+            .iter()
+            .position(|field| field == name)
+            .ok_or_else(|| format!("point cloud without a {name} field"))?;
+    }
+    Ok(cloud
+        .points()
+        .map(|point| indices.map(|index| point[index].into()))
+        .collect())
+}
+
+/// Objects of a lidar scan: the points above the ground, clustered.
+pub fn lidar_detections(cloud: &PointCloud, config: &DetectionConfig) -> Result<Vec<Detection>> {
+    let mount = config.lidar_mount;
+    let (half_length, half_width) = config.ego_extent;
+    let points: Vec<[f64; 3]> = fields(cloud, ["x", "y", "z"])?
+        .into_iter()
+        .map(|[x, y, z]| [x + mount.x, y + mount.y, z + mount.z])
+        .filter(|[x, y, _]| x.abs() > half_length || y.abs() > half_width)
+        .collect();
+    let points = remove_ground(&points, config);
+
+    let clusters = euclidean_clusters(
+        &points,
+        config.lidar_cluster_distance,
+        config.min_lidar_points,
+    );
+    Ok(clusters
+        .into_iter()
+        .map(|cluster| {
+            let mut min = [f64::INFINITY; 3];
+            let mut max = [f64::NEG_INFINITY; 3];
+            for point in cluster.iter().map(|i| points[*i]) {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(point[axis]);
+                    max[axis] = max[axis].max(point[axis]);
+                }
+            }
+            Detection {
+                source: DetectionSource::Lidar,
+                x: (min[0] + max[0]) / 2.0,
+                y: (min[1] + max[1]) / 2.0,
+                size: Some(ObjectSize {
+                    length: max[0] - min[0],
+                    width: max[1] - min[1],
+                    height: max[2] - min[2].min(0.0),
+                }),
+                radial_velocity: None,
+                origin: (mount.x, mount.y),
+                points: cluster.len(),
+            }
+        })
+        .collect())
+}
+
+/// Objects of a radar measurement: its detections, clustered.
+pub fn radar_detections(cloud: &PointCloud, config: &DetectionConfig) -> Result<Vec<Detection>> {
+    let mount = config.radar_mount;
+    let returns = fields(cloud, ["velocity", "azimuth", "altitude", "depth"])?;
+    let points: Vec<[f64; 3]> = returns
+        .iter()
+        .map(|[_, azimuth, altitude, depth]| {
+            let horizontal = depth * altitude.cos();
+            [
+                mount.x + horizontal * azimuth.cos(),
+                mount.y + horizontal * azimuth.sin(),
+                mount.z + depth * altitude.sin(),
+            ]
+        })
+        .collect();
+
+    let clusters = euclidean_clusters(&points, config.radar_cluster_distance, 1);
+    Ok(clusters
+        .into_iter()
+        .map(|cluster| {
+            let n = cluster.len() as f64;
+            let (mut x, mut y, mut velocity) = (0.0, 0.0, 0.0);
+            for i in &cluster {
+                x += points[*i][0];
+                y += points[*i][1];
+                velocity += returns[*i][0];
+            }
+            Detection {
+                source: DetectionSource::Radar,
+                x: x / n,
+                y: y / n,
+                size: None,
+                radial_velocity: Some(velocity / n),
+                origin: (mount.x, mount.y),
+                points: cluster.len(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{LIDAR_FIELDS, RADAR_FIELDS};
+
+    #[test]
+    fn test_lidar_detections() {
+        let mut cloud = PointCloud::new(1, 0.1, &LIDAR_FIELDS);
+        // Flat ground 2.4 m below the lidar, and a 1.5 m high box 10 m ahead
+        for i in 0..40 {
+            for j in 0..20 {
+                cloud.push(&[i as f32 - 20.0, j as f32 - 10.0, -2.4, 1.0]);
+            }
+        }
+        for i in 0..5 {
+            for k in 0..4 {
+                cloud.push(&[10.0, 0.4 * i as f32 - 0.8, 0.4 * k as f32 - 2.0, 1.0]);
+            }
+        }
+
+        let detections = lidar_detections(&cloud, &DetectionConfig::default()).unwrap();
+        assert_eq!(detections.len(), 1, "{detections:?}");
+        let detection = detections[0];
+        assert_eq!((detection.x, detection.y), (10.0, 0.0));
+        assert_eq!(detection.points, 20);
+        let size = detection.size.unwrap();
+        assert!((size.width - 1.6).abs() < 1e-6 && (size.height - 1.6).abs() < 1e-6);
+
+        let radar = PointCloud::new(1, 0.1, &RADAR_FIELDS);
+        assert!(lidar_detections(&radar, &DetectionConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_radar_detections() {
+        let mut cloud = PointCloud::new(1, 0.1, &RADAR_FIELDS);
+        cloud.push(&[-8.0, 0.0, 0.0, 20.0]);
+        cloud.push(&[-7.0, 0.02, 0.0, 20.5]);
+        cloud.push(&[1.0, -0.3, 0.0, 40.0]);
+
+        let mut detections = radar_detections(&cloud, &DetectionConfig::default()).unwrap();
+        detections.sort_by(|a, b| a.x.total_cmp(&b.x));
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].points, 2);
+        assert_eq!(detections[0].radial_velocity, Some(-7.5));
+        assert!((detections[1].y - 40.0 * (-0.3f64).sin()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,443 @@
+//! Coordinate frames of the sensors, the ego vehicle and the world.
+//!
+//! Sensor payloads are in the frame of their sensor. The bridge reads the transform of
+//! every sensor actor relative to the ego vehicle once, when it bridges it, and publishes
+//! them as a static [`TransformTree`]; it also answers transform lookups over RPC, the
+//! world frame included, from the current pose of the vehicle. Consumers convert points
+//! between frames with [`TransformTree::lookup`].
+//!
+//! Frames are named `vehicle`, `world`, or after the role name of a sensor. Transforms
+//! follow CARLA: meters, x forward, y right, z up, and roll, pitch and yaw in degrees,
+//! converted to isometries as the `carla` crate does.
+
+use crate::codec::PointCloud;
+use async_trait::async_trait;
+use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use up_rust::communication::{
+    InMemoryRpcServer, RequestHandler, RpcServer, ServiceInvocationError, UPayload,
+};
+use up_rust::{LocalUriProvider, UAttributes, UPayloadFormat, UTransport};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Frame of the ego vehicle, the parent of every sensor frame.
+pub const VEHICLE_FRAME: &str = "vehicle";
+/// Frame of the CARLA world.
+pub const WORLD_FRAME: &str = "world";
+
+/// uEntity ID of the transform service, on the authority of the bridge. The ego bridge
+/// serves its simulation control methods, with the same method IDs, as entity 0.
+pub const TRANSFORM_SERVICE_ID: u32 = 0x0001;
+/// Major version of the transform service.
+pub const TRANSFORM_SERVICE_VERSION: u8 = 2;
+
+// uProtocol resource IDs of the transform methods
+pub const RESOURCE_LOOKUP_TRANSFORM: u16 = 0x0001;
+pub const RESOURCE_GET_TRANSFORM_TREE: u16 = 0x0002;
+
+/// Pose of a frame in its parent frame, as CARLA describes it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    /// Meters.
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Degrees.
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+impl Transform {
+    /// Isometry taking points of the frame to its parent frame.
+    pub fn to_isometry(&self) -> Isometry3<f64> {
+        Isometry3::from_parts(
+            Translation3::new(self.x, self.y, self.z),
+            UnitQuaternion::from_euler_angles(
+                self.roll.to_radians(),
+                self.pitch.to_radians(),
+                self.yaw.to_radians(),
+            ),
+        )
+    }
+
+    pub fn from_isometry(isometry: &Isometry3<f64>) -> Self {
+        let (roll, pitch, yaw) = isometry.rotation.euler_angles();
+        let translation = isometry.translation.vector;
+        Self {
+            x: translation.x,
+            y: translation.y,
+            z: translation.z,
+            roll: roll.to_degrees(),
+            pitch: pitch.to_degrees(),
+            yaw: yaw.to_degrees(),
+        }
+    }
+}
+
+/// Frame of a bridged sensor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SensorFrame {
+    /// Role name of the sensor.
+    pub frame: String,
+    /// Type name, e.g. `lidar_measurement`.
+    pub sensor: String,
+    pub actor_id: u32,
+    /// Pose of the sensor in the frame of the vehicle.
+    pub transform: Transform,
+}
+
+/// Static transforms of the bridged sensors, published as JSON on the transform tree
+/// topic.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransformTree {
+    pub vehicle_actor_id: u32,
+    pub sensors: Vec<SensorFrame>,
+}
+
+impl TransformTree {
+    pub fn sensor(&self, frame: &str) -> Option<&SensorFrame> {
+        self.sensors.iter().find(|sensor| sensor.frame == frame)
+    }
+
+    /// Isometry taking points of the frame `from` to the frame `to`. `vehicle_pose`, the
+    /// pose of the vehicle in the world, is required to or from the world frame only.
+    pub fn lookup(
+        &self,
+        from: &str,
+        to: &str,
+        vehicle_pose: Option<&Transform>,
+    ) -> Result<Isometry3<f64>> {
+        let to_vehicle = |frame: &str| -> Result<Isometry3<f64>> {
+            match frame {
+                VEHICLE_FRAME => Ok(Isometry3::identity()),
+                WORLD_FRAME => {
+                    let pose =
+                        vehicle_pose.ok_or("the world frame needs the pose of the vehicle")?;
+                    Ok(pose.to_isometry().inverse())
+                }
+                _ => self
+                    .sensor(frame)
+                    .map(|sensor| sensor.transform.to_isometry())
+                    .ok_or_else(|| format!("unknown frame '{frame}'").into()),
+            }
+        };
+        Ok(to_vehicle(to)?.inverse() * to_vehicle(from)?)
+    }
+
+    pub fn transform_point(
+        &self,
+        point: &Point3<f64>,
+        from: &str,
+        to: &str,
+        vehicle_pose: Option<&Transform>,
+    ) -> Result<Point3<f64>> {
+        Ok(self.lookup(from, to, vehicle_pose)? * point)
+    }
+
+    /// `cloud` with its `x`, `y` and `z` fields in the frame `to`, its other fields
+    /// unchanged.
+    pub fn transform_cloud(
+        &self,
+        cloud: &PointCloud,
+        from: &str,
+        to: &str,
+        vehicle_pose: Option<&Transform>,
+    ) -> Result<PointCloud> {
+        let isometry = self.lookup(from, to, vehicle_pose)?.cast::<f32>();
+        let index = |name: &str| {
+            cloud
+                .fields
+                .iter()
+                .position(|field| field == name)
+                .ok_or_else(|| format!("point cloud without a {name} field"))
+        };
+        let (x, y, z) = (index("x")?, index("y")?, index("z")?);
+
+        let mut transformed = cloud.clone();
+        let stride = cloud.fields.len();
+        for point in transformed.values.chunks_exact_mut(stride) {
+            let moved = isometry * Point3::new(point[x], point[y], point[z]);
+            (point[x], point[y], point[z]) = (moved.x, moved.y, moved.z);
+        }
+        Ok(transformed)
+    }
+}
+
+/// Request of the lookup method: `{"from": "roof_lidar", "to": "vehicle"}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransformRequest {
+    pub from: String,
+    pub to: String,
+}
+
+/// Response of the lookup method.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransformLookup {
+    pub from: String,
+    pub to: String,
+    /// Pose of the frame `from` in the frame `to`.
+    pub transform: Transform,
+    /// Simulation time of the pose of the vehicle, for lookups involving the world.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<f64>,
+}
+
+/// RPC handler of the transform methods.
+pub struct TransformEndpoint {
+    tree: TransformTree,
+    // Simulation time and pose of the vehicle in the world, once known
+    vehicle_pose: Mutex<Option<(f64, Transform)>>,
+}
+
+impl TransformEndpoint {
+    pub fn new(tree: TransformTree) -> Self {
+        Self {
+            tree,
+            vehicle_pose: Mutex::new(None),
+        }
+    }
+
+    /// Sets the pose of the vehicle in the world at `timestamp` seconds.
+    pub fn set_vehicle_pose(&self, timestamp: f64, pose: Transform) {
+        *self.vehicle_pose.lock().unwrap() = Some((timestamp, pose));
+    }
+
+    /// JSON response of the method `resource_id` to a request `payload`.
+    pub fn answer(
+        &self,
+        resource_id: u16,
+        payload: Option<&[u8]>,
+    ) -> std::result::Result<Vec<u8>, String> {
+        match resource_id {
+            RESOURCE_GET_TRANSFORM_TREE => {
+                serde_json::to_vec(&self.tree).map_err(|e| e.to_string())
+            }
+            RESOURCE_LOOKUP_TRANSFORM => {
+                let request: TransformRequest = serde_json::from_slice(payload.unwrap_or_default())
+                    .map_err(|e| format!("invalid lookup request: {e}"))?;
+                let vehicle_pose = *self.vehicle_pose.lock().unwrap();
+                let isometry = self
+                    .tree
+                    .lookup(
+                        &request.from,
+                        &request.to,
+                        vehicle_pose.as_ref().map(|(_, pose)| pose),
+                    )
+                    .map_err(|e| e.to_string())?;
+                let world = request.from == WORLD_FRAME || request.to == WORLD_FRAME;
+                let response = TransformLookup {
+                    transform: Transform::from_isometry(&isometry),
+                    timestamp: vehicle_pose
+                        .filter(|_| world)
+                        .map(|(timestamp, _)| timestamp),
+                    from: request.from,
+                    to: request.to,
+                };
+                serde_json::to_vec(&response).map_err(|e| e.to_string())
+            }
+            _ => Err(format!("unknown method {resource_id:#06x}")),
+        }
+    }
+}
+
+#[async_trait]
+impl RequestHandler for TransformEndpoint {
+    async fn handle_request(
+        &self,
+        resource_id: u16,
+        _message_attributes: &UAttributes,
+        request_payload: Option<UPayload>,
+    ) -> std::result::Result<Option<UPayload>, ServiceInvocationError> {
+        let payload = request_payload.map(|p| p.payload());
+        let response = self
+            .answer(resource_id, payload.as_deref())
+            .map_err(ServiceInvocationError::InvalidArgument)?;
+        Ok(Some(UPayload::new(
+            response,
+            UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+        )))
+    }
+}
+
+/// Transform methods registered on a transport; unregistered when dropped.
+pub struct TransformServer {
+    endpoint: Arc<TransformEndpoint>,
+    _server: InMemoryRpcServer,
+}
+
+impl TransformServer {
+    pub fn endpoint(&self) -> &TransformEndpoint {
+        &self.endpoint
+    }
+}
+
+/// Registers the transform methods of the entity given by `uri_provider`, answering from
+/// `tree`.
+pub async fn serve(
+    transport: Arc<dyn UTransport>,
+    uri_provider: Arc<dyn LocalUriProvider>,
+    tree: TransformTree,
+) -> Result<TransformServer> {
+    let endpoint = Arc::new(TransformEndpoint::new(tree));
+    let server = InMemoryRpcServer::new(transport, uri_provider.clone());
+    for resource_id in [RESOURCE_LOOKUP_TRANSFORM, RESOURCE_GET_TRANSFORM_TREE] {
+        server
+            .register_endpoint(None, resource_id, endpoint.clone())
+            .await?;
+        log::info!(
+            "Registered transform endpoint {}",
+            uri_provider.get_resource_uri(resource_id).to_uri(true)
+        );
+    }
+    Ok(TransformServer {
+        endpoint,
+        _server: server,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::LIDAR_FIELDS;
+
+    fn tree() -> TransformTree {
+        let sensor = |frame: &str, transform| SensorFrame {
+            frame: frame.to_string(),
+            sensor: String::new(),
+            actor_id: 0,
+            transform,
+        };
+        TransformTree {
+            vehicle_actor_id: 1,
+            sensors: vec![
+                sensor(
+                    "roof_lidar",
+                    Transform {
+                        z: 2.4,
+                        ..Transform::default()
+                    },
+                ),
+                sensor(
+                    "front_radar",
+                    Transform {
+                        x: 2.0,
+                        z: 1.0,
+                        yaw: 90.0,
+                        ..Transform::default()
+                    },
+                ),
+            ],
+        }
+    }
+
+    fn assert_near(point: Point3<f64>, expected: [f64; 3]) {
+        assert!(
+            (point - Point3::from(expected)).norm() < 1e-9,
+            "{point} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let tree = tree();
+        let point = Point3::new(1.0, 0.0, 0.0);
+        // The radar looks right
+        let vehicle = tree
+            .transform_point(&point, "front_radar", VEHICLE_FRAME, None)
+            .unwrap();
+        assert_near(vehicle, [2.0, 1.0, 1.0]);
+        let lidar = tree
+            .transform_point(&point, "front_radar", "roof_lidar", None)
+            .unwrap();
+        assert_near(lidar, [2.0, 1.0, -1.4]);
+        let back = tree
+            .transform_point(&lidar, "roof_lidar", "front_radar", None)
+            .unwrap();
+        assert_near(back, [1.0, 0.0, 0.0]);
+
+        // Vehicle at (10, 0) heading along y
+        let pose = Transform {
+            x: 10.0,
+            yaw: 90.0,
+            ..Transform::default()
+        };
+        let world = tree
+            .transform_point(&point, VEHICLE_FRAME, WORLD_FRAME, Some(&pose))
+            .unwrap();
+        assert_near(world, [10.0, 1.0, 0.0]);
+        assert!(tree.lookup(VEHICLE_FRAME, WORLD_FRAME, None).is_err());
+        assert!(tree.lookup("rear_camera", VEHICLE_FRAME, None).is_err());
+
+        let isometry = Transform {
+            x: 1.0,
+            y: -2.0,
+            z: 0.5,
+            roll: 3.0,
+            pitch: -8.0,
+            yaw: 120.0,
+        }
+        .to_isometry();
+        let round_trip = Transform::from_isometry(&isometry).to_isometry();
+        assert!((round_trip.to_homogeneous() - isometry.to_homogeneous()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_transform_cloud() {
+        let mut cloud = PointCloud::new(3, 0.3, &LIDAR_FIELDS);
+        cloud.push(&[5.0, 1.0, -2.4, 0.5]);
+        let vehicle = tree()
+            .transform_cloud(&cloud, "roof_lidar", VEHICLE_FRAME, None)
+            .unwrap();
+        assert_eq!(vehicle.values, vec![5.0, 1.0, 0.0, 0.5]);
+        assert_eq!((vehicle.frame, vehicle.timestamp), (3, 0.3));
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let endpoint = TransformEndpoint::new(tree());
+        let answer = |resource_id, payload: &str| {
+            endpoint
+                .answer(resource_id, Some(payload.as_bytes()))
+                .map(|response| serde_json::from_slice::<TransformLookup>(&response).unwrap())
+        };
+
+        let lookup = answer(
+            RESOURCE_LOOKUP_TRANSFORM,
+            r#"{"from": "roof_lidar", "to": "vehicle"}"#,
+        )
+        .unwrap();
+        assert_eq!(lookup.transform.z, 2.4);
+        assert_eq!(lookup.timestamp, None);
+        assert!(
+            answer(
+                RESOURCE_LOOKUP_TRANSFORM,
+                r#"{"from": "vehicle", "to": "world"}"#
+            )
+            .is_err()
+        );
+
+        endpoint.set_vehicle_pose(
+            12.5,
+            Transform {
+                x: 10.0,
+                ..Transform::default()
+            },
+        );
+        let lookup = answer(
+            RESOURCE_LOOKUP_TRANSFORM,
+            r#"{"from": "roof_lidar", "to": "world"}"#,
+        )
+        .unwrap();
+        assert_eq!((lookup.transform.x, lookup.timestamp), (10.0, Some(12.5)));
+
+        let tree: TransformTree =
+            serde_json::from_slice(&endpoint.answer(RESOURCE_GET_TRANSFORM_TREE, None).unwrap())
+                .unwrap();
+        assert_eq!(tree.sensors.len(), 2);
+        assert!(endpoint.answer(0x0042, None).is_err());
+    }
+}
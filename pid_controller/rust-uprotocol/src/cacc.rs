@@ -0,0 +1,489 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+// Low-pass factor of the acceleration estimated from speed samples
+const ACCELERATION_FILTER: f64 = 0.3;
+// Low-pass factor of the gap rate estimated from gap samples
+const GAP_RATE_FILTER: f64 = 0.5;
+// Leader accelerations (m/s²) below this are too small to rate string stability
+const MIN_PEAK_ACCELERATION: f64 = 0.1;
+
+/// State every vehicle broadcasts on its V2V topic, in SI units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct V2vState {
+    /// Simulation time (s) the state refers to.
+    pub time: f64,
+    /// Distance travelled (m) since the controller started.
+    pub position: f64,
+    /// Position (m) in the CARLA world frame, shared by all vehicles, as estimated by the
+    /// odometry estimator of the vehicle; `None`, and left out of the payload, while no
+    /// estimate has been received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_position: Option<(f64, f64)>,
+    /// Speed (m/s).
+    pub speed: f64,
+    /// Acceleration (m/s²) the vehicle is heading for: its CACC command while
+    /// following, its measured acceleration otherwise.
+    pub acceleration: f64,
+}
+
+/// Estimates position and acceleration of the own vehicle from its speed samples, and
+/// keeps its latest world position.
+#[derive(Debug, Default)]
+pub struct Odometry {
+    state: Option<V2vState>,
+    world_position: Option<(f64, f64)>,
+}
+
+impl Odometry {
+    /// Records the world position (m) of the vehicle, from its odometry estimator.
+    pub fn set_world_position(&mut self, x: f64, y: f64) {
+        self.world_position = Some((x, y));
+    }
+
+    /// Integrates the `speed` (m/s) reported at simulation time `now` (s).
+    pub fn update(&mut self, now: f64, speed: f64) -> V2vState {
+        let state = match self.state {
+            Some(previous) if now > previous.time => {
+                let dt = now - previous.time;
+                let measured = (speed - previous.speed) / dt;
+                V2vState {
+                    time: now,
+                    position: previous.position + 0.5 * (speed + previous.speed) * dt,
+                    world_position: self.world_position,
+                    speed,
+                    acceleration: previous.acceleration
+                        + ACCELERATION_FILTER * (measured - previous.acceleration),
+                }
+            }
+            // No new clock yet: keep the estimate
+            Some(previous) => V2vState {
+                world_position: self.world_position,
+                speed,
+                ..previous
+            },
+            None => V2vState {
+                time: now,
+                world_position: self.world_position,
+                speed,
+                ..Default::default()
+            },
+        };
+
+        self.state = Some(state);
+        state
+    }
+}
+
+/// Gains and limits of the cooperative adaptive cruise control.
+#[derive(Clone, Debug)]
+pub struct CaccConfig {
+    /// Time gap (s) to the leader while its V2V state is received.
+    pub time_gap: f64,
+    /// Time gap (s) when only the gap measurement is available.
+    pub degraded_time_gap: f64,
+    /// Gap (m) kept at standstill.
+    pub standstill_gap: f64,
+    /// Gain (1/s²) on the gap error.
+    pub kp: f64,
+    /// Gain (1/s) on the speed difference to the leader.
+    pub kd: f64,
+    /// Share of the leader acceleration fed forward.
+    pub feedforward: f64,
+    /// Gaps (m) above this are not followed: the vehicle joins below it.
+    pub max_range: f64,
+    /// Age (s) after which a gap measurement is dropped.
+    pub gap_timeout: f64,
+    /// Age (s) after which the leader's V2V state is dropped.
+    pub leader_timeout: f64,
+    pub max_acceleration: f64,
+    pub max_deceleration: f64,
+    /// Acceleration (m/s²) of the vehicle at full throttle.
+    pub full_throttle: f64,
+    /// Deceleration (m/s²) of the vehicle at full brake.
+    pub full_brake: f64,
+}
+
+impl Default for CaccConfig {
+    fn default() -> Self {
+        Self {
+            time_gap: 0.6,
+            degraded_time_gap: 1.4,
+            standstill_gap: 5.0,
+            kp: 0.2,
+            kd: 0.7,
+            feedforward: 1.0,
+            max_range: 60.0,
+            gap_timeout: 0.5,
+            leader_timeout: 0.5,
+            max_acceleration: 2.0,
+            max_deceleration: 6.0,
+            full_throttle: 3.5,
+            full_brake: 9.0,
+        }
+    }
+}
+
+/// Whether, and how, the vehicle follows a leader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlatoonMode {
+    /// No vehicle in range: plain cruise control.
+    #[default]
+    Cruise,
+    /// Gap measurement and leader V2V state: short time gap with feed-forward.
+    Following,
+    /// Gap measurement only: adaptive cruise control with a longer time gap.
+    Degraded,
+}
+
+/// Join/leave counts and string-stability figures of a run.
+///
+/// A platoon is string stable when disturbances shrink along the string, i.e. when
+/// the peak acceleration of the follower stays below the leader's.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlatoonMetrics {
+    pub joins: usize,
+    pub leaves: usize,
+    pub leader_peak_acceleration: f64,
+    pub peak_acceleration: f64,
+    gap_error_squares: f64,
+    samples: usize,
+}
+
+impl PlatoonMetrics {
+    /// Follower over leader peak acceleration; below 1 when string stable.
+    pub fn amplification(&self) -> Option<f64> {
+        (self.leader_peak_acceleration > MIN_PEAK_ACCELERATION)
+            .then(|| self.peak_acceleration / self.leader_peak_acceleration)
+    }
+
+    /// Root mean square of the gap error (m) while following.
+    pub fn rms_gap_error(&self) -> Option<f64> {
+        (self.samples > 0).then(|| (self.gap_error_squares / self.samples as f64).sqrt())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct GapMeasurement {
+    time: f64,
+    distance: f64,
+    /// Rate of change (m/s), i.e. leader speed minus own speed.
+    rate: f64,
+}
+
+/// Cooperative adaptive cruise control: keeps a speed-dependent gap to the vehicle
+/// ahead, measured by a range sensor, and feeds the leader's broadcast acceleration
+/// forward so that the platoon reacts as one.
+///
+/// Broadcasting the commanded rather than the measured acceleration is what lets
+/// followers anticipate their actuator lag and keeps the platoon string stable.
+#[derive(Debug)]
+pub struct CaccController {
+    config: CaccConfig,
+    gap: Option<GapMeasurement>,
+    leader: Option<V2vState>,
+    mode: PlatoonMode,
+    gap_error: Option<f64>,
+    metrics: PlatoonMetrics,
+}
+
+impl CaccController {
+    pub fn new(config: CaccConfig) -> Self {
+        CaccController {
+            config,
+            gap: None,
+            leader: None,
+            mode: PlatoonMode::Cruise,
+            gap_error: None,
+            metrics: PlatoonMetrics::default(),
+        }
+    }
+
+    /// Records the gap (m) to the vehicle ahead, measured at simulation time `now` (s).
+    pub fn on_gap(&mut self, now: f64, distance: f64) {
+        let rate = match self.gap {
+            Some(previous)
+                if now > previous.time && now - previous.time <= self.config.gap_timeout =>
+            {
+                let measured = (distance - previous.distance) / (now - previous.time);
+                previous.rate + GAP_RATE_FILTER * (measured - previous.rate)
+            }
+            Some(previous) if now == previous.time => previous.rate,
+            _ => 0.0,
+        };
+
+        self.gap = Some(GapMeasurement {
+            time: now,
+            distance,
+            rate,
+        });
+    }
+
+    pub fn on_leader(&mut self, state: V2vState) {
+        self.leader = Some(state);
+    }
+
+    pub fn mode(&self) -> PlatoonMode {
+        self.mode
+    }
+
+    /// Gap error (m) of the last update, if following.
+    pub fn gap_error(&self) -> Option<f64> {
+        self.gap_error
+    }
+
+    pub fn metrics(&self) -> &PlatoonMetrics {
+        &self.metrics
+    }
+
+    /// Desired acceleration (m/s²) for the own vehicle in state `ego` (with its measured
+    /// acceleration), or `None` when there is no vehicle to follow.
+    pub fn update(&mut self, ego: &V2vState) -> Option<f64> {
+        let now = ego.time;
+        let config = &self.config;
+
+        let gap = self
+            .gap
+            .filter(|gap| now - gap.time <= config.gap_timeout && gap.distance <= config.max_range);
+        let leader = self
+            .leader
+            .filter(|leader| now - leader.time <= config.leader_timeout);
+
+        let mode = match (gap, leader) {
+            (None, _) => PlatoonMode::Cruise,
+            (Some(_), Some(_)) => PlatoonMode::Following,
+            (Some(_), None) => PlatoonMode::Degraded,
+        };
+        self.set_mode(now, mode);
+
+        let Some(gap) = gap else {
+            self.gap_error = None;
+            return None;
+        };
+
+        let config = &self.config;
+        let (time_gap, feedforward, relative_speed) = match leader {
+            Some(leader) => (
+                config.time_gap,
+                config.feedforward * leader.acceleration,
+                leader.speed - ego.speed,
+            ),
+            None => (config.degraded_time_gap, 0.0, gap.rate),
+        };
+
+        let error = gap.distance - (config.standstill_gap + time_gap * ego.speed);
+        let acceleration = (feedforward + config.kp * error + config.kd * relative_speed)
+            .clamp(-config.max_deceleration, config.max_acceleration);
+
+        if let Some(leader) = leader {
+            let metrics = &mut self.metrics;
+            metrics.leader_peak_acceleration = metrics
+                .leader_peak_acceleration
+                .max(leader.acceleration.abs());
+            metrics.peak_acceleration = metrics.peak_acceleration.max(ego.acceleration.abs());
+            metrics.gap_error_squares += error * error;
+            metrics.samples += 1;
+        }
+        self.gap_error = Some(error);
+
+        Some(acceleration)
+    }
+
+    /// Actuation command (positive throttles, negative brakes) for an `acceleration`.
+    pub fn actuation(&self, acceleration: f64) -> f64 {
+        if acceleration >= 0.0 {
+            (acceleration / self.config.full_throttle).min(1.0)
+        } else {
+            (acceleration / self.config.full_brake).max(-1.0)
+        }
+    }
+
+    /// Forgets measurements and the current mode, keeping the metrics.
+    pub fn reset(&mut self) {
+        self.gap = None;
+        self.leader = None;
+        self.mode = PlatoonMode::Cruise;
+        self.gap_error = None;
+    }
+
+    fn set_mode(&mut self, now: f64, mode: PlatoonMode) {
+        if mode == self.mode {
+            return;
+        }
+
+        match (self.mode, mode) {
+            (PlatoonMode::Cruise, _) => self.metrics.joins += 1,
+            (_, PlatoonMode::Cruise) => self.metrics.leaves += 1,
+            _ => {}
+        }
+        info!("[cacc] {:?} -> {:?} at {:.2} s", self.mode, mode, now);
+        self.mode = mode;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.05;
+    // Lag (s) between desired and actual acceleration of the simulated vehicles
+    const ACTUATOR_LAG: f64 = 0.3;
+
+    /// A vehicle whose acceleration follows the command with a first-order lag.
+    #[derive(Clone, Copy, Default)]
+    struct Plant {
+        position: f64,
+        speed: f64,
+        acceleration: f64,
+    }
+
+    impl Plant {
+        fn step(&mut self, command: f64) {
+            self.acceleration += (command - self.acceleration) * DT / ACTUATOR_LAG;
+            self.speed = (self.speed + self.acceleration * DT).max(0.0);
+            self.position += self.speed * DT;
+        }
+    }
+
+    /// Drives a leader through `leader_command` (time to acceleration) with
+    /// `followers` CACC vehicles behind it, starting at 20 m/s on their target gaps.
+    fn platoon(
+        followers: usize,
+        broadcast: bool,
+        leader_command: impl Fn(f64) -> f64,
+    ) -> Vec<CaccController> {
+        let config = CaccConfig::default();
+        let gap = config.standstill_gap + config.time_gap * 20.0;
+        let mut plants: Vec<Plant> = (0..=followers)
+            .map(|i| Plant {
+                position: -(i as f64) * gap,
+                speed: 20.0,
+                acceleration: 0.0,
+            })
+            .collect();
+        let mut odometries: Vec<Odometry> = (0..=followers).map(|_| Odometry::default()).collect();
+        let mut controllers: Vec<CaccController> = (0..followers)
+            .map(|_| CaccController::new(config.clone()))
+            .collect();
+        let mut commands = vec![0.0; followers + 1];
+
+        for step in 0..1200 {
+            let now = step as f64 * DT;
+            let states: Vec<V2vState> = plants
+                .iter()
+                .zip(odometries.iter_mut())
+                .map(|(plant, odometry)| odometry.update(now, plant.speed))
+                .collect();
+
+            commands[0] = leader_command(now);
+            plants[0].step(commands[0]);
+            for (i, controller) in controllers.iter_mut().enumerate() {
+                controller.on_gap(now, plants[i].position - plants[i + 1].position);
+                if broadcast {
+                    // Every vehicle broadcasts its command
+                    controller.on_leader(V2vState {
+                        acceleration: commands[i],
+                        ..states[i]
+                    });
+                }
+                commands[i + 1] = controller.update(&states[i + 1]).unwrap_or(0.0);
+                plants[i + 1].step(commands[i + 1]);
+            }
+        }
+
+        controllers
+    }
+
+    #[test]
+    fn test_platoon_is_string_stable() {
+        // The leader brakes, then speeds up again
+        let controllers = platoon(3, true, |t| match t {
+            t if (5.0..8.0).contains(&t) => -2.0,
+            t if (15.0..18.0).contains(&t) => 2.0,
+            _ => 0.0,
+        });
+
+        let amplifications: Vec<f64> = controllers
+            .iter()
+            .map(|c| c.metrics().amplification().unwrap())
+            .collect();
+        assert!(
+            amplifications.iter().all(|a| *a <= 1.0),
+            "{amplifications:?}"
+        );
+
+        for controller in &controllers {
+            assert_eq!(controller.mode(), PlatoonMode::Following);
+            assert_eq!(controller.metrics().joins, 1);
+            assert!(controller.gap_error().unwrap().abs() < 0.5);
+            assert!(controller.metrics().rms_gap_error().unwrap() < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_without_v2v_keeps_longer_gap() {
+        let controllers = platoon(1, false, |_| 0.0);
+        let controller = &controllers[0];
+
+        assert_eq!(controller.mode(), PlatoonMode::Degraded);
+        // Fell back from the CACC gap (17 m) towards the ACC gap (33 m at 20 m/s)
+        assert!(controller.gap_error().unwrap().abs() < 1.0);
+        assert_eq!(controller.metrics().amplification(), None);
+    }
+
+    #[test]
+    fn test_join_and_leave() {
+        let mut controller = CaccController::new(CaccConfig::default());
+        let mut odometry = Odometry::default();
+
+        assert_eq!(controller.update(&odometry.update(0.0, 20.0)), None);
+        assert_eq!(odometry.update(0.0, 20.0).world_position, None);
+        odometry.set_world_position(-12.5, 40.0);
+        assert_eq!(
+            odometry.update(0.05, 20.0).world_position,
+            Some((-12.5, 40.0))
+        );
+
+        // States without a world position keep the payload of controllers without it
+        let state: V2vState = serde_json::from_str(
+            r#"{"time": 0.1, "position": 2.0, "speed": 20.0, "acceleration": 0.0}"#,
+        )
+        .unwrap();
+        assert_eq!((state.position, state.world_position), (2.0, None));
+        let payload = serde_json::to_value(odometry.update(0.1, 20.0)).unwrap();
+        assert_eq!(payload["position"], 2.0);
+        assert_eq!(payload["world_position"], serde_json::json!([-12.5, 40.0]));
+
+        controller.on_gap(0.1, 30.0);
+        assert!(controller.update(&odometry.update(0.1, 20.0)).is_some());
+        assert_eq!(controller.mode(), PlatoonMode::Degraded);
+
+        // The vehicle ahead is out of range
+        controller.on_gap(0.2, 80.0);
+        assert_eq!(controller.update(&odometry.update(0.2, 20.0)), None);
+        assert_eq!(controller.mode(), PlatoonMode::Cruise);
+        assert_eq!(
+            (controller.metrics().joins, controller.metrics().leaves),
+            (1, 1)
+        );
+
+        assert!((controller.actuation(1.75) - 0.5).abs() < 1e-9);
+        assert_eq!(controller.actuation(-20.0), -1.0);
+    }
+}
@@ -20,10 +20,13 @@ use up_transport_zenoh::{UPTransportZenoh, zenoh_config};
 use up_rust::{LocalUriProvider, StaticUriProvider};
 use zenoh::{Config};
 
+use cacc::{CaccConfig, CaccController};
 use pid_controller::PIDController;
-use uprotocol_handler::UProtocolHandler;
+use uprotocol_handler::{Authorities, UProtocolHandler};
 
+mod cacc;
 mod pid_controller;
+mod stamp;
 mod uprotocol_handler;
 
 #[derive(Parser, Debug)]
@@ -39,6 +42,24 @@ struct Args {
     delta: f64,
     #[clap(long, default_value = None)]
     router: Option<String>,
+    /// uProtocol authority of this controller
+    #[clap(long, default_value = "CruiseControl")]
+    authority: String,
+    /// uProtocol authority of the controlled vehicle
+    #[clap(long, default_value = "EGOVehicle")]
+    vehicle_authority: String,
+    /// uProtocol authority of the HMI setting target speed and engage
+    #[clap(long, default_value = "AAOS")]
+    hmi_authority: String,
+    /// Authority of the controller of the vehicle ahead; enables platooning (CACC)
+    #[clap(long, default_value = None)]
+    leader: Option<String>,
+    /// uProtocol authority of the object tracker measuring the gap to the vehicle ahead
+    #[clap(long, default_value = "EgoTracker")]
+    tracker_authority: String,
+    /// uProtocol authority of the odometry estimator giving the world position
+    #[clap(long, default_value = "EgoEstimator")]
+    estimator_authority: String,
 }
 
 // Helper function to create a Zenoh configuration
@@ -71,9 +92,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let pid = PIDController::new(kp, ki, kd);
 
+    let args = Args::parse();
+    let authorities = Authorities {
+        controller: args.authority,
+        vehicle: args.vehicle_authority,
+        hmi: args.hmi_authority,
+        tracker: args.tracker_authority,
+        estimator: args.estimator_authority,
+        leader: args.leader,
+    };
+
+    // Follow the vehicle ahead when a leader is given
+    let cacc = authorities.leader.as_ref().map(|leader| {
+        println!("CACC => following {}", leader);
+        CaccController::new(CaccConfig::default())
+    });
+
     // Create a uProtocol URI provider for the PID controller
     // This defines the identity of this node in the uProtocol network
-    let uri_provider = StaticUriProvider::new("CruiseControl", 0, 2);
+    let uri_provider = StaticUriProvider::new(&authorities.controller, 0, 2);
     
     // Initialize uProtocol transport with Zenoh
     let transport = UPTransportZenoh::builder(uri_provider.get_authority())
@@ -82,7 +119,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build()
         .await?;
 
-    let handler = UProtocolHandler::new(pid, transport)?;
+    let handler = UProtocolHandler::new(pid, cacc, transport, &authorities)?;
 
     handler.start().await?;
 
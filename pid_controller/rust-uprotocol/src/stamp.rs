@@ -0,0 +1,123 @@
+//
+// Copyright (c) 2025 The X-Verse <https://github.com/The-Xverse>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// Frames kept while waiting for their missing signals
+const MAX_PENDING_FRAMES: usize = 32;
+
+/// Simulation time the ego bridge stamps its samples with: the CARLA frame and the
+/// simulation clock, e.g. `{"velocity": 45.2, "frame": 250, "elapsed_seconds": 12.5}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub struct SampleStamp {
+    pub frame: u64,
+    pub elapsed_seconds: f64,
+}
+
+/// Values of every aligned signal for one frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignedFrame<K> {
+    pub stamp: SampleStamp,
+    pub values: BTreeMap<K, f64>,
+}
+
+/// Pairs signals received separately (e.g. speed and clock) by their frame instead of
+/// by arrival order.
+///
+/// A frame is released once every signal has a value for it; frames older than a
+/// released one, and the oldest beyond `MAX_PENDING_FRAMES`, are dropped incomplete.
+#[derive(Debug)]
+pub struct FrameAligner<K> {
+    signals: Vec<K>,
+    pending: BTreeMap<u64, AlignedFrame<K>>,
+    last_released: Option<u64>,
+}
+
+impl<K: Ord + Clone> FrameAligner<K> {
+    pub fn new(signals: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            signals: signals.into_iter().collect(),
+            pending: BTreeMap::new(),
+            last_released: None,
+        }
+    }
+
+    /// Adds a received value; returns its frame once all signals have arrived for it.
+    pub fn push(&mut self, signal: K, stamp: SampleStamp, value: f64) -> Option<AlignedFrame<K>> {
+        if !self.signals.contains(&signal) || self.last_released.is_some_and(|f| stamp.frame <= f) {
+            return None;
+        }
+
+        let frame = self
+            .pending
+            .entry(stamp.frame)
+            .or_insert_with(|| AlignedFrame {
+                stamp,
+                values: BTreeMap::new(),
+            });
+        frame.values.insert(signal, value);
+
+        if frame.values.len() == self.signals.len() {
+            let complete = self.pending.remove(&stamp.frame);
+            self.pending = self.pending.split_off(&stamp.frame);
+            self.last_released = Some(stamp.frame);
+            return complete;
+        }
+
+        while self.pending.len() > MAX_PENDING_FRAMES {
+            self.pending.pop_first();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(frame: u64) -> SampleStamp {
+        SampleStamp {
+            frame,
+            elapsed_seconds: frame as f64 * 0.05,
+        }
+    }
+
+    #[test]
+    fn test_signals_are_aligned_by_frame() {
+        let mut aligner = FrameAligner::new(["clock", "speed"]);
+
+        // Speed of frame 2 overtakes the clock of frame 1
+        assert_eq!(aligner.push("speed", stamp(1), 10.0), None);
+        assert_eq!(aligner.push("speed", stamp(2), 11.0), None);
+        let frame = aligner.push("clock", stamp(1), 0.05).unwrap();
+        assert_eq!(frame.stamp, stamp(1));
+        assert_eq!(frame.values["speed"], 10.0);
+
+        // Frame 3 completes while frame 2 lacks its clock: frame 2 is dropped
+        assert_eq!(aligner.push("clock", stamp(3), 0.15), None);
+        let frame = aligner.push("speed", stamp(3), 12.0).unwrap();
+        assert_eq!(frame.values["speed"], 12.0);
+        assert_eq!(aligner.push("clock", stamp(2), 0.1), None);
+        assert!(aligner.pending.is_empty());
+
+        // Stamps are read from the bridge payloads
+        let stamp: SampleStamp =
+            serde_json::from_str(r#"{"velocity": 45.2, "frame": 250, "elapsed_seconds": 12.5}"#)
+                .unwrap();
+        assert_eq!((stamp.frame, stamp.elapsed_seconds), (250, 12.5));
+    }
+}
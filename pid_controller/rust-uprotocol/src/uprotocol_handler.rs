@@ -14,6 +14,7 @@
 // limitations under the License.
 //
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -23,16 +24,35 @@ use log::{info, debug, error};
 use up_rust::{UUri, UListener, UMessage, UMessageBuilder, UTransport, UPayloadFormat};
 use up_transport_zenoh::UPTransportZenoh;
 
+use crate::cacc::{CaccController, Odometry, V2vState};
 use crate::pid_controller::PIDController;
+use crate::stamp::{FrameAligner, SampleStamp};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct VelocityStatus {
     velocity: f64,
+    // Simulation frame and time of the sample, sent by the ego bridge with the `json`
+    // encoding
+    #[serde(default)]
+    frame: Option<u64>,
+    #[serde(default)]
+    elapsed_seconds: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ClockStatus {
+    // `elapsed_seconds` and `frame` as stamped by the ego bridge
+    #[serde(alias = "elapsed_seconds")]
     time: f64,
+    #[serde(default)]
+    frame: Option<u64>,
+}
+
+// Signals paired by frame before a control step
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Signal {
+    Clock,
+    Speed,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,6 +65,54 @@ struct EngageStatus {
     engaged: u8,
 }
 
+// Distance (m) from the origin of a vehicle to its front bumper
+const FRONT_OVERHANG: f64 = 2.5;
+
+// Track list of the object tracker; only what the gap needs
+#[derive(Debug, Deserialize)]
+struct TrackList {
+    timestamp: f64,
+    tracks: Vec<TrackedObject>,
+    lead: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackedObject {
+    id: u32,
+    // Meters ahead of the origin of the vehicle
+    x: f64,
+    #[serde(default)]
+    size: Option<ObjectSize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectSize {
+    length: f64,
+}
+
+// Fused odometry of the odometry estimator; only the position
+#[derive(Debug, Deserialize)]
+struct FusedOdometry {
+    x: f64,
+    y: f64,
+}
+
+/// uProtocol authorities of the entities the controller talks to.
+pub struct Authorities {
+    /// The controller itself: actuation and V2V state are published as this entity.
+    pub controller: String,
+    /// The ego vehicle bridge: speed and clock.
+    pub vehicle: String,
+    /// The HMI: target speed and engage.
+    pub hmi: String,
+    /// The object tracker of the vehicle: the lead object, whose distance is the gap.
+    pub tracker: String,
+    /// The odometry estimator of the vehicle: the world position broadcast over V2V.
+    pub estimator: String,
+    /// The controller of the vehicle ahead, when platooning.
+    pub leader: Option<String>,
+}
+
 pub struct UProtocolHandler {
     controller: Arc<Mutex<PIDController>>,
     transport: Arc<UPTransportZenoh>,
@@ -55,6 +123,14 @@ pub struct UProtocolHandler {
     engage_uri: UUri,
     target_speed_uri: UUri,
     actuation_uri: UUri,
+    v2v_uri: UUri,
+    lead_object_uri: UUri,
+    odometry_uri: UUri,
+    leader_uri: Option<UUri>,
+
+    // Platooning
+    cacc: Option<Arc<Mutex<CaccController>>>,
+    odometry: Arc<Mutex<Odometry>>,
     
     // State variables
     current_velocity: Arc<Mutex<f64>>,
@@ -71,7 +147,9 @@ pub struct UProtocolHandler {
 impl UProtocolHandler {
     pub fn new(
         controller: PIDController,
+        cacc: Option<CaccController>,
         transport: UPTransportZenoh,
+        authorities: &Authorities,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut results = HashMap::new();
         results.insert("desired_velocity".to_string(), Vec::new());
@@ -80,11 +158,18 @@ impl UProtocolHandler {
         results.insert("acceleration".to_string(), Vec::new());
 
         // Create URIs for different services
-        let velocity_uri = UUri::try_from_parts("EGOVehicle", 0, 2, 0x8001)?;
-        let clock_uri = UUri::try_from_parts("EGOVehicle", 0, 2, 0x8002)?;
-        let engage_uri = UUri::try_from_parts("AAOS", 0, 2, 0x8002)?;
-        let target_speed_uri = UUri::try_from_parts("AAOS", 0, 2, 0x8001)?;
-        let actuation_uri = UUri::try_from_parts("CruiseControl", 0, 2, 0x8001)?;
+        let velocity_uri = UUri::try_from_parts(&authorities.vehicle, 0, 2, 0x8001)?;
+        let clock_uri = UUri::try_from_parts(&authorities.vehicle, 0, 2, 0x8002)?;
+        let lead_object_uri = UUri::try_from_parts(&authorities.tracker, 0, 2, 0x8001)?;
+        let odometry_uri = UUri::try_from_parts(&authorities.estimator, 0, 2, 0x8001)?;
+        let engage_uri = UUri::try_from_parts(&authorities.hmi, 0, 2, 0x8002)?;
+        let target_speed_uri = UUri::try_from_parts(&authorities.hmi, 0, 2, 0x8001)?;
+        let actuation_uri = UUri::try_from_parts(&authorities.controller, 0, 2, 0x8001)?;
+        let v2v_uri = UUri::try_from_parts(&authorities.controller, 0, 2, 0x8002)?;
+        let leader_uri = match &authorities.leader {
+            Some(leader) => Some(UUri::try_from_parts(leader, 0, 2, 0x8002)?),
+            None => None,
+        };
 
         Ok(UProtocolHandler {
             controller: Arc::new(Mutex::new(controller)),
@@ -94,6 +179,12 @@ impl UProtocolHandler {
             engage_uri,
             target_speed_uri,
             actuation_uri,
+            v2v_uri,
+            lead_object_uri,
+            odometry_uri,
+            leader_uri,
+            cacc: cacc.map(|cacc| Arc::new(Mutex::new(cacc))),
+            odometry: Arc::new(Mutex::new(Odometry::default())),
             current_velocity: Arc::new(Mutex::new(0.0)),
             desired_velocity: Arc::new(Mutex::new(0.0)),
             current_time: Arc::new(Mutex::new(0.0)),
@@ -107,28 +198,39 @@ impl UProtocolHandler {
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
         info!("Starting UProtocolHandler subscribers...");
 
-        // Register listeners for each subscription
-        self.setup_clock_subscriber().await?;
-        self.setup_velocity_subscriber().await?;
+        // Register listeners for each subscription; the clock completes the frames of
+        // the velocity listener
+        let velocity_listener = self.setup_velocity_subscriber().await?;
+        self.setup_clock_subscriber(velocity_listener).await?;
         self.setup_target_subscriber().await?;
         self.setup_engage_subscriber().await?;
+        self.setup_position_subscriber().await?;
+
+        if let Some(cacc) = &self.cacc {
+            self.setup_platoon_subscribers(cacc).await?;
+        }
 
         Ok(())
     }
     
-    async fn setup_clock_subscriber(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn setup_clock_subscriber(
+        &self,
+        velocity_listener: Arc<VelocityListener>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let current_time_arc = Arc::clone(&self.current_time);
         let transport = Arc::clone(&self.transport);
         let clock_uri = self.clock_uri.clone();
         
-        let listener = ClockListener::new(current_time_arc);
+        let listener = ClockListener::new(current_time_arc, velocity_listener);
         transport.register_listener(&clock_uri, None, Arc::new(listener)).await?;
         
         info!("Timestamp subscriber registered");
         Ok(())
     }
     
-    async fn setup_velocity_subscriber(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn setup_velocity_subscriber(
+        &self,
+    ) -> Result<Arc<VelocityListener>, Box<dyn std::error::Error>> {
         let current_velocity = Arc::clone(&self.current_velocity);
         let transport = Arc::clone(&self.transport);
         let velocity_uri = self.velocity_uri.clone();
@@ -142,8 +244,11 @@ impl UProtocolHandler {
         let results = Arc::clone(&self.results);
         let actuation_uri = self.actuation_uri.clone();
         let transport_for_publish = Arc::clone(&self.transport);
+        let cacc = self.cacc.clone();
+        let odometry = Arc::clone(&self.odometry);
+        let v2v_uri = self.v2v_uri.clone();
         
-        let listener = VelocityListener::new(
+        let listener = Arc::new(VelocityListener::new(
             current_velocity,
             desired_velocity,
             current_time,
@@ -153,12 +258,15 @@ impl UProtocolHandler {
             results,
             actuation_uri,
             transport_for_publish,
-        );
+            cacc,
+            odometry,
+            v2v_uri,
+        ));
         
-        transport.register_listener(&velocity_uri, None, Arc::new(listener)).await?;
+        transport.register_listener(&velocity_uri, None, listener.clone()).await?;
         
         info!("Velocity subscriber registered");
-        Ok(())
+        Ok(listener)
     }
 
     async fn setup_target_subscriber(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -179,15 +287,46 @@ impl UProtocolHandler {
         let controller = Arc::clone(&self.controller);
         let transport = Arc::clone(&self.transport);
         let engage_uri = self.engage_uri.clone();
+        let cacc = self.cacc.clone();
         
-        let listener = EngageListener::new(is_engaged, pid_active, controller);
+        let listener = EngageListener::new(is_engaged, pid_active, controller, cacc);
         transport.register_listener(&engage_uri, None, Arc::new(listener)).await?;
         
         info!("Engage subscriber registered");
         Ok(())
     }
 
-    // Static method for PID computation and publishing
+    async fn setup_position_subscriber(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let transport = Arc::clone(&self.transport);
+
+        let listener = PositionListener::new(Arc::clone(&self.odometry));
+        transport.register_listener(&self.odometry_uri, None, Arc::new(listener)).await?;
+
+        info!("Position subscriber registered on {}", String::from(&self.odometry_uri));
+        Ok(())
+    }
+
+    async fn setup_platoon_subscribers(
+        &self,
+        cacc: &Arc<Mutex<CaccController>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let transport = Arc::clone(&self.transport);
+
+        let listener = LeadObjectListener::new(Arc::clone(cacc));
+        transport.register_listener(&self.lead_object_uri, None, Arc::new(listener)).await?;
+        info!("Lead object subscriber registered on {}", String::from(&self.lead_object_uri));
+
+        if let Some(leader_uri) = &self.leader_uri {
+            let listener = LeaderListener::new(Arc::clone(cacc));
+            transport.register_listener(leader_uri, None, Arc::new(listener)).await?;
+            info!("Leader V2V subscriber registered on {}", String::from(leader_uri));
+        }
+
+        Ok(())
+    }
+
+    // Static method for PID computation and publishing; returns the CACC acceleration
+    // when it drives the vehicle
     async fn publish_acc(
         desired_velocity: &Arc<Mutex<f64>>,
         current_velocity: &Arc<Mutex<f64>>,
@@ -198,7 +337,9 @@ impl UProtocolHandler {
         transport: &Arc<UPTransportZenoh>,
         actuation_uri: UUri,
         results: &Arc<Mutex<HashMap<String, Vec<f64>>>>,
-    ) {
+        cacc: Option<&Arc<Mutex<CaccController>>>,
+        ego: V2vState,
+    ) -> Option<f64> {
         // Check if PID is active
         let is_active = {
             let active = pid_active.lock().unwrap();
@@ -206,7 +347,7 @@ impl UProtocolHandler {
         };
         
         if !is_active {
-            return;
+            return None;
         }
 
         let (desired_vel, current_vel, curr_time) = {
@@ -223,13 +364,32 @@ impl UProtocolHandler {
                 Ok(acc) => acc,
                 Err(e) => {
                     error!("PID computation failed: {}", e);
-                    return;
+                    return None;
                 }
             }
         };
 
+        // Follow the vehicle ahead, never faster than the cruise control would drive
+        let mut actuation = acceleration;
+        let mut cacc_acceleration = None;
+        if let Some(cacc) = cacc {
+            let mut cacc = cacc.lock().unwrap();
+            if let Some(desired) = cacc.update(&ego) {
+                if cacc.actuation(desired) < actuation {
+                    actuation = cacc.actuation(desired);
+                    cacc_acceleration = Some(desired);
+                }
+
+                let mut results_guard = results.lock().unwrap();
+                results_guard.entry("cacc_time".to_string()).or_default().push(curr_time);
+                results_guard.entry("cacc_acceleration".to_string()).or_default().push(desired);
+                results_guard.entry("cacc_gap_error".to_string()).or_default()
+                    .push(cacc.gap_error().unwrap_or_default());
+            }
+        }
+
         // Create and publish uProtocol message
-        let actuation_cmd_payload = format!("{}", acceleration);
+        let actuation_cmd_payload = format!("{}", actuation);
         let message = UMessageBuilder::publish(actuation_uri)
             .build_with_payload(actuation_cmd_payload.clone(), UPayloadFormat::UPAYLOAD_FORMAT_TEXT)
             .unwrap();
@@ -260,6 +420,29 @@ impl UProtocolHandler {
         if delta_time > 0.0 {
             debug!("Delta time: {} seconds", delta_time);
         }
+
+        cacc_acceleration
+    }
+
+    // Broadcasts the own vehicle state for the vehicles behind; the acceleration is the
+    // CACC command while it drives the vehicle
+    async fn publish_v2v(transport: &Arc<UPTransportZenoh>, v2v_uri: UUri, state: V2vState) {
+        let payload = match serde_json::to_vec(&state) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to encode V2V state: {}", e);
+                return;
+            }
+        };
+        let message = UMessageBuilder::publish(v2v_uri)
+            .build_with_payload(payload, UPayloadFormat::UPAYLOAD_FORMAT_JSON)
+            .unwrap();
+
+        if let Err(e) = transport.send(message).await {
+            error!("Failed to publish V2V state: {}", e);
+        } else {
+            debug!("Publishing V2V state: {:?}", state);
+        }
     }
 
     // Activation method
@@ -378,6 +561,24 @@ impl UProtocolHandler {
         } else {
             info!("No data points available");
         }
+
+        if let Some(cacc) = &self.cacc {
+            let cacc = cacc.lock().unwrap();
+            let metrics = cacc.metrics();
+            info!("Platoon joins: {}, leaves: {}", metrics.joins, metrics.leaves);
+            if let Some(rms) = metrics.rms_gap_error() {
+                info!("Gap error RMS: {:.4} m", rms);
+            }
+            if let Some(amplification) = metrics.amplification() {
+                info!(
+                    "Peak acceleration - Leader: {:.4}, Own: {:.4}, Amplification: {:.4} ({})",
+                    metrics.leader_peak_acceleration,
+                    metrics.peak_acceleration,
+                    amplification,
+                    if amplification <= 1.0 { "string stable" } else { "string unstable" }
+                );
+            }
+        }
     }
 
     // Additional helper method to get current PID status
@@ -402,11 +603,12 @@ impl UProtocolHandler {
 // Listener implementations
 struct ClockListener {
     current_time: Arc<Mutex<f64>>,
+    velocity_listener: Arc<VelocityListener>,
 }
 
 impl ClockListener {
-    fn new(current_time: Arc<Mutex<f64>>) -> Self {
-        Self { current_time }
+    fn new(current_time: Arc<Mutex<f64>>, velocity_listener: Arc<VelocityListener>) -> Self {
+        Self { current_time, velocity_listener }
     }
 }
 
@@ -416,14 +618,14 @@ impl UListener for ClockListener {
         if let Some(payload) = message.payload {
             let bytes = &payload[..];
             
-            // Try to parse as text first (new format)
-            let time_value = if let Ok(payload_str) = std::str::from_utf8(&bytes) {
+            // Try to parse as text first (legacy format)
+            let (time_value, frame) = if let Ok(payload_str) = std::str::from_utf8(&bytes) {
                 match payload_str.trim().parse::<f64>() {
-                    Ok(time) => time,
+                    Ok(time) => (time, None),
                     Err(_) => {
-                        // Fall back to JSON format for backward compatibility
+                        // Fall back to JSON format, stamped with the frame by the ego bridge
                         if let Ok(clock_status) = serde_json::from_slice::<ClockStatus>(&bytes) {
-                            clock_status.time
+                            (clock_status.time, clock_status.frame)
                         } else {
                             error!("[ERROR] Timestamp processing failed as JSON");
                             return;
@@ -440,6 +642,11 @@ impl UListener for ClockListener {
                 *clock = time_value;
             }
             debug!("Received current clock '{:.4}' seconds", time_value);
+
+            if let Some(frame) = frame {
+                let stamp = SampleStamp { frame, elapsed_seconds: time_value };
+                self.velocity_listener.on_clock(stamp).await;
+            }
         }
     }
 }
@@ -454,6 +661,12 @@ struct VelocityListener {
     results: Arc<Mutex<HashMap<String, Vec<f64>>>>,
     actuation_uri: UUri,
     transport: Arc<UPTransportZenoh>,
+    cacc: Option<Arc<Mutex<CaccController>>>,
+    odometry: Arc<Mutex<Odometry>>,
+    v2v_uri: UUri,
+    // Stamped speeds wait for the clock of their frame, once the clock is stamped too
+    aligner: Mutex<FrameAligner<Signal>>,
+    clock_stamped: AtomicBool,
 }
 
 impl VelocityListener {
@@ -467,6 +680,9 @@ impl VelocityListener {
         results: Arc<Mutex<HashMap<String, Vec<f64>>>>,
         actuation_uri: UUri,
         transport: Arc<UPTransportZenoh>,
+        cacc: Option<Arc<Mutex<CaccController>>>,
+        odometry: Arc<Mutex<Odometry>>,
+        v2v_uri: UUri,
     ) -> Self {
         Self {
             current_velocity,
@@ -478,8 +694,62 @@ impl VelocityListener {
             results,
             actuation_uri,
             transport,
+            cacc,
+            odometry,
+            v2v_uri,
+            aligner: Mutex::new(FrameAligner::new([Signal::Clock, Signal::Speed])),
+            clock_stamped: AtomicBool::new(false),
+        }
+    }
+
+    // Completes the frame of a stamped clock
+    async fn on_clock(&self, stamp: SampleStamp) {
+        self.clock_stamped.store(true, Ordering::Relaxed);
+        let aligned = self.aligner.lock().unwrap().push(Signal::Clock, stamp, stamp.elapsed_seconds);
+        if let Some(frame) = aligned {
+            self.step(frame.values[&Signal::Speed], Some(frame.stamp.elapsed_seconds)).await;
         }
     }
+
+    // Runs the control step for a velocity sample at simulation time `sample_time`, or at
+    // the latest clock received
+    async fn step(&self, velocity_value: f64, sample_time: Option<f64>) {
+        {
+            let mut vel = self.current_velocity.lock().unwrap();
+            *vel = velocity_value;
+        }
+        // A stamped sample gives the exact time of the velocity, instead of the
+        // latest clock received
+        if let Some(time) = sample_time {
+            *self.current_time.lock().unwrap() = time;
+        }
+
+        // Velocity is in km/h, the V2V state in SI units
+        let mut ego = {
+            let time = *self.current_time.lock().unwrap();
+            self.odometry.lock().unwrap().update(time, velocity_value / 3.6)
+        };
+        
+        // Trigger PID computation
+        let cacc_acceleration = UProtocolHandler::publish_acc(
+            &self.desired_velocity,
+            &self.current_velocity,
+            &self.current_time,
+            &self.previous_time,
+            &self.pid_active,
+            &self.controller,
+            &self.transport,
+            self.actuation_uri.clone(),
+            &self.results,
+            self.cacc.as_ref(),
+            ego,
+        ).await;
+
+        if let Some(acceleration) = cacc_acceleration {
+            ego.acceleration = acceleration;
+        }
+        UProtocolHandler::publish_v2v(&self.transport, self.v2v_uri.clone(), ego).await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -488,14 +758,14 @@ impl UListener for VelocityListener {
         if let Some(payload) = message.payload {
             let bytes = &payload[..];
             
-            // Try to parse as text first (new format)
-            let velocity_value = if let Ok(payload_str) = std::str::from_utf8(&bytes) {
+            // Try to parse as text first (legacy format)
+            let (velocity_value, frame, sample_time) = if let Ok(payload_str) = std::str::from_utf8(&bytes) {
                 match payload_str.trim().parse::<f64>() {
-                    Ok(velocity) => velocity,
+                    Ok(velocity) => (velocity, None, None),
                     Err(_) => {
-                        // Fall back to JSON format for backward compatibility
+                        // Fall back to JSON format, which may carry the simulation time
                         if let Ok(velocity_status) = serde_json::from_slice::<VelocityStatus>(&bytes) {
-                            velocity_status.velocity
+                            (velocity_status.velocity, velocity_status.frame, velocity_status.elapsed_seconds)
                         } else {
                             error!("Failed to parse velocity payload");
                             return;
@@ -507,24 +777,21 @@ impl UListener for VelocityListener {
                 return;
             };
             
-            {
-                let mut vel = self.current_velocity.lock().unwrap();
-                *vel = velocity_value;
-            }
             debug!("Received current velocity '{:.2}'", velocity_value);
-            
-            // Trigger PID computation
-            UProtocolHandler::publish_acc(
-                &self.desired_velocity,
-                &self.current_velocity,
-                &self.current_time,
-                &self.previous_time,
-                &self.pid_active,
-                &self.controller,
-                &self.transport,
-                self.actuation_uri.clone(),
-                &self.results,
-            ).await;
+
+            // With a stamped clock, the step runs once the clock of the same frame is in,
+            // whichever arrives first
+            if let (Some(frame), Some(elapsed_seconds)) = (frame, sample_time) {
+                if self.clock_stamped.load(Ordering::Relaxed) {
+                    let stamp = SampleStamp { frame, elapsed_seconds };
+                    let aligned = self.aligner.lock().unwrap().push(Signal::Speed, stamp, velocity_value);
+                    if let Some(frame) = aligned {
+                        self.step(frame.values[&Signal::Speed], Some(frame.stamp.elapsed_seconds)).await;
+                    }
+                    return;
+                }
+            }
+            self.step(velocity_value, sample_time).await;
         }
     }
 }
@@ -573,6 +840,7 @@ struct EngageListener {
     is_engaged: Arc<Mutex<u8>>,
     pid_active: Arc<Mutex<bool>>,
     controller: Arc<Mutex<PIDController>>,
+    cacc: Option<Arc<Mutex<CaccController>>>,
 }
 
 impl EngageListener {
@@ -580,11 +848,13 @@ impl EngageListener {
         is_engaged: Arc<Mutex<u8>>,
         pid_active: Arc<Mutex<bool>>,
         controller: Arc<Mutex<PIDController>>,
+        cacc: Option<Arc<Mutex<CaccController>>>,
     ) -> Self {
         Self {
             is_engaged,
             pid_active,
             controller,
+            cacc,
         }
     }
 }
@@ -634,6 +904,99 @@ impl UListener for EngageListener {
                 UProtocolHandler::activate_pid(&self.pid_active, &self.controller);
             } else if !enable && was_active {
                 UProtocolHandler::deactivate_pid(&self.pid_active, &self.controller);
+                if let Some(cacc) = &self.cacc {
+                    cacc.lock().unwrap().reset();
+                }
+            }
+        }
+    }
+}
+
+struct LeadObjectListener {
+    cacc: Arc<Mutex<CaccController>>,
+}
+
+impl LeadObjectListener {
+    fn new(cacc: Arc<Mutex<CaccController>>) -> Self {
+        Self { cacc }
+    }
+}
+
+#[async_trait::async_trait]
+impl UListener for LeadObjectListener {
+    async fn on_receive(&self, message: UMessage) {
+        if let Some(payload) = message.payload {
+            let tracks = match serde_json::from_slice::<TrackList>(&payload[..]) {
+                Ok(tracks) => tracks,
+                Err(e) => {
+                    error!("Failed to parse track list: {}", e);
+                    return;
+                }
+            };
+
+            // No lead object: the gap ages out and the vehicle leaves the platoon
+            let Some(lead) = tracks
+                .lead
+                .and_then(|lead| tracks.tracks.iter().find(|track| track.id == lead))
+            else {
+                return;
+            };
+
+            // From the front bumper to the rear of the lead object, as seen by the radar
+            // and the lidar of the tracker
+            let length = lead.size.as_ref().map_or(0.0, |size| size.length);
+            let distance = lead.x - 0.5 * length - FRONT_OVERHANG;
+            self.cacc.lock().unwrap().on_gap(tracks.timestamp, distance);
+            debug!("Received gap '{:.2}' m to object {}", distance, lead.id);
+        }
+    }
+}
+
+struct PositionListener {
+    odometry: Arc<Mutex<Odometry>>,
+}
+
+impl PositionListener {
+    fn new(odometry: Arc<Mutex<Odometry>>) -> Self {
+        Self { odometry }
+    }
+}
+
+#[async_trait::async_trait]
+impl UListener for PositionListener {
+    async fn on_receive(&self, message: UMessage) {
+        if let Some(payload) = message.payload {
+            match serde_json::from_slice::<FusedOdometry>(&payload[..]) {
+                Ok(odometry) => {
+                    self.odometry.lock().unwrap().set_world_position(odometry.x, odometry.y);
+                    debug!("Received position ({:.1}, {:.1})", odometry.x, odometry.y);
+                }
+                Err(e) => error!("Failed to parse fused odometry: {}", e),
+            }
+        }
+    }
+}
+
+struct LeaderListener {
+    cacc: Arc<Mutex<CaccController>>,
+}
+
+impl LeaderListener {
+    fn new(cacc: Arc<Mutex<CaccController>>) -> Self {
+        Self { cacc }
+    }
+}
+
+#[async_trait::async_trait]
+impl UListener for LeaderListener {
+    async fn on_receive(&self, message: UMessage) {
+        if let Some(payload) = message.payload {
+            match serde_json::from_slice::<V2vState>(&payload[..]) {
+                Ok(state) => {
+                    self.cacc.lock().unwrap().on_leader(state);
+                    debug!("Received leader state: {:?}", state);
+                }
+                Err(e) => error!("Failed to parse leader V2V state: {}", e),
             }
         }
     }
@@ -12,14 +12,20 @@ Additionally, the example periodically publishes status messages containing curr
 information, including the current speed. The status messages are published to a configurable
 uProtocol topic.
 
+Once the ego vehicle publishes its speed, the status is built from the simulation instead: a trip
+computer integrates an EV energy model over the speed and the applied throttle and brake, and
+its state of charge, range, trip distance, average speed and consumption are published as well.
+
 The example supports two different transports: Zenoh and MQTT 5. The transport can be
 selected via command line arguments.
  */
 
+mod trip;
+
 use std::{
     str::FromStr,
     sync::{Arc, RwLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use backon::{BackoffBuilder, ExponentialBuilder, Retryable};
@@ -27,8 +33,10 @@ use bytes::Buf;
 use clap::{Parser, command};
 use log::{debug, error, info};
 use serde_json::{Value, json};
+use trip::{EnergyModel, TripComputer};
 use up_rust::{
-    LocalUriProvider, StaticUriProvider, UAttributes, UCode, UPayloadFormat, UPriority, UUri,
+    LocalUriProvider, StaticUriProvider, UAttributes, UCode, UListener, UMessage, UPayloadFormat,
+    UPriority, UUri,
     communication::{
         CallOptions, InMemoryRpcServer, Publisher, RequestHandler, RpcServer,
         ServiceInvocationError, SimplePublisher, UPayload,
@@ -39,6 +47,7 @@ use up_transport_zenoh::UPTransportZenoh;
 
 const MAX_TARGET_SPEED_KMH: f32 = 180.0;
 const RESOURCE_ID_SET_TARGET_SPEED: u16 = 0x0001;
+const RESOURCE_ID_TRIP_STATUS: u16 = 0x8001;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -58,6 +67,18 @@ struct Cli {
     /// A value of 1000 ms (1 second) is recommended to simulate a realistic update rate.
     #[arg(long, value_name = "INTERVAL", env = "STATUS_PUBLISH_INTERVAL_MS", default_value_t = 1000)]
     status_publish_interval_ms: u64,
+    /// The uProtocol topic on which the ego vehicle publishes its speed in km/h.
+    #[arg(long, value_name = "URI", env = "SPEED_TOPIC", default_value = "//EGOVehicle/0/2/8001", value_parser = UUri::from_str)]
+    speed_topic: UUri,
+    /// The uProtocol topic on which the ego vehicle publishes its applied throttle (0.0 to 1.0).
+    #[arg(long, value_name = "URI", env = "THROTTLE_TOPIC", default_value = "//EGOVehicle/0/2/8006", value_parser = UUri::from_str)]
+    throttle_topic: UUri,
+    /// The uProtocol topic on which the ego vehicle publishes its applied brake (0.0 to 1.0).
+    #[arg(long, value_name = "URI", env = "BRAKE_TOPIC", default_value = "//EGOVehicle/0/2/8007", value_parser = UUri::from_str)]
+    brake_topic: UUri,
+    /// The state of charge of the battery in percent at the start of the trip.
+    #[arg(long, value_name = "PERCENT", env = "INITIAL_SOC", default_value_t = 80.0)]
+    initial_soc: f64,
 
     #[command(subcommand)]
     transport: Transports,
@@ -126,6 +147,8 @@ struct OperationalState {
     target_speed: u8,
     current_speed: u8,
     engine_temp: f32,
+    trip: TripComputer,
+    started: Instant,
 }
 
 impl Default for OperationalState {
@@ -136,6 +159,8 @@ impl Default for OperationalState {
             target_speed: 100u8,
             current_speed: 90,
             engine_temp: 70.0,
+            trip: TripComputer::new(EnergyModel::default(), 80.0),
+            started: Instant::now(),
         }
     }
 }
@@ -152,6 +177,24 @@ impl OperationalState {
     }
 
     fn get_status(&self) -> Value {
+        if self.trip.is_running() {
+            return json!({
+                "AmbientTemperature": 22,
+                "Battery": self.trip.state_of_charge().round(),
+                "CruiseControl": false,
+                "Economy": "Normal",
+                "Engine Temperature": self.engine_temp,
+                "Gear": self.trip.gear(),
+                "RPM": self.trip.motor_rpm().round(),
+                "Range": self.trip.range_km().round(),
+                "ShareLocation": false,
+                "Speed": self.trip.speed_kmh().round(),
+                "SpeedUnit": "km/h",
+                "TemperatureUnit": 0,
+                "TypeOfVehicle": 0
+            });
+        }
+
         json!({
             "AmbientTemperature": 22,
             "Battery": 80,
@@ -216,6 +259,78 @@ impl RequestHandler for SetTargetSpeed {
     }
 }
 
+fn parse_value(payload: &[u8]) -> Option<f64> {
+    std::str::from_utf8(payload).ok()?.trim().parse::<f64>().ok()
+}
+
+// Feeds the ego vehicle speed to the trip computer, at the simulation time if the payload
+// carries it (`{"velocity": 45.2, "elapsed_seconds": 12.5}`), at the arrival time otherwise.
+struct SpeedListener(Arc<RwLock<OperationalState>>);
+
+#[async_trait::async_trait]
+impl UListener for SpeedListener {
+    async fn on_receive(&self, msg: UMessage) {
+        let Some(payload) = msg.payload else {
+            return;
+        };
+        let (speed_kmh, time) = match parse_value(&payload) {
+            Some(speed_kmh) => (speed_kmh, None),
+            None => {
+                let status = serde_json::from_slice::<Value>(&payload).unwrap_or_default();
+                let Some(speed_kmh) = status.get("velocity").and_then(Value::as_f64) else {
+                    error!("Failed to parse ego vehicle speed: {}", status);
+                    return;
+                };
+                (speed_kmh, status.get("elapsed_seconds").and_then(Value::as_f64))
+            }
+        };
+
+        let mut state = self.0.write().unwrap();
+        let time = time.unwrap_or_else(|| state.started.elapsed().as_secs_f64());
+        state.trip.on_speed(time, speed_kmh);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AppliedControl {
+    Throttle,
+    Brake,
+}
+
+impl AppliedControl {
+    // Field of the control in stamped payloads, e.g. `{"throttle": 0.35, "frame": 250, ...}`
+    fn field(self) -> &'static str {
+        match self {
+            AppliedControl::Throttle => "throttle",
+            AppliedControl::Brake => "brake",
+        }
+    }
+}
+
+struct ControlListener(Arc<RwLock<OperationalState>>, AppliedControl);
+
+#[async_trait::async_trait]
+impl UListener for ControlListener {
+    async fn on_receive(&self, msg: UMessage) {
+        let Some(payload) = msg.payload else {
+            return;
+        };
+        let value = parse_value(&payload).or_else(|| {
+            let status = serde_json::from_slice::<Value>(&payload).ok()?;
+            status.get(self.1.field()).and_then(Value::as_f64)
+        });
+        let Some(value) = value else {
+            error!("Failed to parse applied control");
+            return;
+        };
+        let mut state = self.0.write().unwrap();
+        match self.1 {
+            AppliedControl::Throttle => state.trip.set_throttle(value),
+            AppliedControl::Brake => state.trip.set_brake(value),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn core::error::Error>> {
     env_logger::init();
@@ -224,11 +339,44 @@ async fn main() -> Result<(), Box<dyn core::error::Error>> {
     let status_topic = command.topic.to_uri(true);
     let status_event_ttl = command.status_ttl_ms;
     let status_publish_interval_ms = command.status_publish_interval_ms;
+    let speed_topic = command.speed_topic.clone();
+    let control_topics = [
+        (command.throttle_topic.clone(), AppliedControl::Throttle),
+        (command.brake_topic.clone(), AppliedControl::Brake),
+    ];
+    let initial_soc = command.initial_soc;
     let uri_provider = Arc::new(StaticUriProvider::try_from(&command.topic)?);
 
     let transport = get_transport(command).await?;
     let publisher = SimplePublisher::new(transport.clone(), uri_provider.clone());
-    let operational_state = Arc::new(RwLock::new(OperationalState::default()));
+    let operational_state = Arc::new(RwLock::new(OperationalState {
+        trip: TripComputer::new(EnergyModel::default(), initial_soc),
+        ..Default::default()
+    }));
+
+    // The trip computer follows the simulated ego vehicle
+    transport
+        .register_listener(
+            &speed_topic,
+            None,
+            Arc::new(SpeedListener(operational_state.clone())),
+        )
+        .await?;
+    for (topic, control) in control_topics {
+        transport
+            .register_listener(
+                &topic,
+                None,
+                Arc::new(ControlListener(operational_state.clone(), control)),
+            )
+            .await?;
+    }
+    info!(
+        "trip computer is running [speed topic: {}, trip topic: {}]",
+        speed_topic.to_uri(true),
+        uri_provider.get_resource_uri(RESOURCE_ID_TRIP_STATUS).to_uri(true),
+    );
+
     let request_handler = Arc::new(SetTargetSpeed(operational_state.clone()));
     let rpc_server = InMemoryRpcServer::new(transport.clone(), uri_provider.clone());
     rpc_server
@@ -278,6 +426,27 @@ async fn main() -> Result<(), Box<dyn core::error::Error>> {
                 status_topic, current_status_str
             );
         }
+
+        let trip_summary = {
+            let state = operational_state.read().unwrap();
+            state.trip.is_running().then(|| state.trip.summary())
+        };
+        if let Some(trip_summary) = trip_summary {
+            let payload = UPayload::new(
+                serde_json::to_vec(&trip_summary).unwrap(),
+                UPayloadFormat::UPAYLOAD_FORMAT_JSON,
+            );
+            if let Err(e) = publisher
+                .publish(
+                    RESOURCE_ID_TRIP_STATUS,
+                    CallOptions::for_publish(Some(status_event_ttl), None, Some(UPriority::UPRIORITY_CS1)),
+                    Some(payload),
+                )
+                .await
+            {
+                error!("Failed to publish trip status message: {}", e);
+            }
+        }
         tokio::time::sleep(std::time::Duration::from_millis(status_publish_interval_ms)).await;
     }
 }
@@ -0,0 +1,299 @@
+/*!
+Trip computer of the cluster, fed by the speed and the applied controls of the simulated
+ego vehicle.
+
+A simple longitudinal EV energy model turns the speed profile into battery power:
+traction power (inertia, rolling resistance and aerodynamic drag) through the drivetrain
+efficiency, regeneration while braking or coasting, and a constant auxiliary load.
+ */
+
+use serde_json::{Value, json};
+
+/// Speed samples further apart than this are treated as a pause, not integrated.
+const MAX_SAMPLE_GAP_S: f64 = 1.0;
+/// Below this speed the vehicle is considered stopped.
+const STANDSTILL_SPEED_MS: f64 = 0.1;
+/// Trip distance from which the range is estimated from the trip consumption.
+const MIN_TRIP_DISTANCE_M: f64 = 1000.0;
+
+/// Parameters of the EV energy model, in SI units.
+#[derive(Debug, Clone)]
+pub struct EnergyModel {
+    pub mass_kg: f64,
+    pub rolling_resistance: f64,
+    /// Drag coefficient times frontal area.
+    pub drag_area_m2: f64,
+    pub air_density: f64,
+    /// Battery to wheel efficiency while driving.
+    pub drivetrain_efficiency: f64,
+    /// Wheel to battery efficiency while regenerating.
+    pub regen_efficiency: f64,
+    pub max_regen_power_w: f64,
+    /// Heating, lights, infotainment, ...
+    pub auxiliary_power_w: f64,
+    pub battery_capacity_wh: f64,
+    /// Consumption assumed for the range estimate until the trip is long enough.
+    pub reference_consumption_wh_per_km: f64,
+    pub wheel_radius_m: f64,
+    pub gear_ratio: f64,
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        Self {
+            mass_kg: 1800.0,
+            rolling_resistance: 0.011,
+            drag_area_m2: 0.65,
+            air_density: 1.2,
+            drivetrain_efficiency: 0.9,
+            regen_efficiency: 0.7,
+            max_regen_power_w: 60_000.0,
+            auxiliary_power_w: 800.0,
+            battery_capacity_wh: 75_000.0,
+            reference_consumption_wh_per_km: 170.0,
+            wheel_radius_m: 0.33,
+            gear_ratio: 9.0,
+        }
+    }
+}
+
+impl EnergyModel {
+    /// Power in W needed at the wheels to drive at `speed` (m/s) while accelerating at
+    /// `acceleration` (m/s²); negative while the vehicle decelerates faster than it coasts.
+    pub fn wheel_power(&self, speed: f64, acceleration: f64) -> f64 {
+        let rolling = if speed > STANDSTILL_SPEED_MS {
+            self.rolling_resistance * self.mass_kg * 9.81
+        } else {
+            0.0
+        };
+        let drag = 0.5 * self.air_density * self.drag_area_m2 * speed * speed;
+        (self.mass_kg * acceleration + rolling + drag) * speed
+    }
+
+    /// Power in W drawn from the battery (negative while charging). Negative wheel power
+    /// is only recovered while `regenerating`, otherwise it is lost in the friction brakes.
+    pub fn battery_power(&self, wheel_power: f64, regenerating: bool) -> f64 {
+        let traction = if wheel_power >= 0.0 {
+            wheel_power / self.drivetrain_efficiency
+        } else if regenerating {
+            -(-wheel_power * self.regen_efficiency).min(self.max_regen_power_w)
+        } else {
+            0.0
+        };
+        traction + self.auxiliary_power_w
+    }
+
+    pub fn motor_rpm(&self, speed: f64) -> f64 {
+        speed / self.wheel_radius_m * self.gear_ratio * 60.0 / (2.0 * std::f64::consts::PI)
+    }
+}
+
+/// Integrates the energy model over the received speed samples and keeps the trip figures.
+#[derive(Debug, Clone)]
+pub struct TripComputer {
+    model: EnergyModel,
+    battery_energy_wh: f64,
+    trip_energy_wh: f64,
+    trip_distance_m: f64,
+    driving_time_s: f64,
+    last_sample: Option<(f64, f64)>,
+    speed_ms: f64,
+    throttle: f64,
+    brake: f64,
+}
+
+impl TripComputer {
+    /// Starts a trip with the battery at `state_of_charge` percent.
+    pub fn new(model: EnergyModel, state_of_charge: f64) -> Self {
+        let battery_energy_wh =
+            model.battery_capacity_wh * state_of_charge.clamp(0.0, 100.0) / 100.0;
+        Self {
+            model,
+            battery_energy_wh,
+            trip_energy_wh: 0.0,
+            trip_distance_m: 0.0,
+            driving_time_s: 0.0,
+            last_sample: None,
+            speed_ms: 0.0,
+            throttle: 0.0,
+            brake: 0.0,
+        }
+    }
+
+    /// Whether a speed sample has been received yet.
+    pub fn is_running(&self) -> bool {
+        self.last_sample.is_some()
+    }
+
+    /// Applied throttle (0.0 to 1.0).
+    pub fn set_throttle(&mut self, throttle: f64) {
+        self.throttle = throttle.clamp(0.0, 1.0);
+    }
+
+    /// Applied brake (0.0 to 1.0).
+    pub fn set_brake(&mut self, brake: f64) {
+        self.brake = brake.clamp(0.0, 1.0);
+    }
+
+    /// Integrates the trip up to `time_s` (any monotonic clock in seconds), at which the
+    /// vehicle drives at `speed_kmh`.
+    pub fn on_speed(&mut self, time_s: f64, speed_kmh: f64) {
+        let speed = speed_kmh.max(0.0) / 3.6;
+
+        if let Some((last_time, last_speed)) = self.last_sample {
+            let dt = time_s - last_time;
+            if dt > 0.0 && dt <= MAX_SAMPLE_GAP_S {
+                let mean_speed = 0.5 * (speed + last_speed);
+                let acceleration = (speed - last_speed) / dt;
+
+                // Regeneration on the brake pedal, and when lifting off the throttle
+                let regenerating = self.brake > 0.0 || self.throttle <= 0.0;
+                let wheel_power = self.model.wheel_power(mean_speed, acceleration);
+                let energy_wh = self.model.battery_power(wheel_power, regenerating) * dt / 3600.0;
+
+                self.battery_energy_wh =
+                    (self.battery_energy_wh - energy_wh).clamp(0.0, self.model.battery_capacity_wh);
+                self.trip_energy_wh += energy_wh;
+                self.trip_distance_m += mean_speed * dt;
+                if mean_speed > STANDSTILL_SPEED_MS {
+                    self.driving_time_s += dt;
+                }
+            }
+        }
+
+        self.last_sample = Some((time_s, speed));
+        self.speed_ms = speed;
+    }
+
+    pub fn speed_kmh(&self) -> f64 {
+        self.speed_ms * 3.6
+    }
+
+    /// State of charge in percent.
+    pub fn state_of_charge(&self) -> f64 {
+        100.0 * self.battery_energy_wh / self.model.battery_capacity_wh
+    }
+
+    pub fn trip_distance_km(&self) -> f64 {
+        self.trip_distance_m / 1000.0
+    }
+
+    /// Average speed while moving, in km/h.
+    pub fn average_speed_kmh(&self) -> f64 {
+        if self.driving_time_s > 0.0 {
+            self.trip_distance_m / self.driving_time_s * 3.6
+        } else {
+            0.0
+        }
+    }
+
+    /// Net trip consumption in Wh/km, `None` until the vehicle has moved.
+    pub fn consumption_wh_per_km(&self) -> Option<f64> {
+        (self.trip_distance_m > 0.0).then(|| self.trip_energy_wh / self.trip_distance_km())
+    }
+
+    /// Range in km with the remaining energy, at the trip consumption once the trip is long
+    /// enough to be representative, at the reference consumption before.
+    pub fn range_km(&self) -> f64 {
+        let reference = self.model.reference_consumption_wh_per_km;
+        let consumption = match self.consumption_wh_per_km() {
+            // Regeneration-heavy stretches must not promise an unbounded range
+            Some(consumption) if self.trip_distance_m >= MIN_TRIP_DISTANCE_M => {
+                consumption.max(0.25 * reference)
+            }
+            _ => reference,
+        };
+        self.battery_energy_wh / consumption
+    }
+
+    pub fn motor_rpm(&self) -> f64 {
+        self.model.motor_rpm(self.speed_ms)
+    }
+
+    /// Gear shown by the cluster: parked at standstill without throttle, in drive otherwise.
+    pub fn gear(&self) -> &'static str {
+        if self.speed_ms <= STANDSTILL_SPEED_MS && self.throttle <= 0.0 {
+            "P"
+        } else {
+            "D"
+        }
+    }
+
+    /// Trip figures as published on the trip topic.
+    pub fn summary(&self) -> Value {
+        json!({
+            "StateOfCharge": round(self.state_of_charge(), 1),
+            "Range": round(self.range_km(), 1),
+            "TripDistance": round(self.trip_distance_km(), 2),
+            "AverageSpeed": round(self.average_speed_kmh(), 1),
+            "Consumption": self.consumption_wh_per_km().map(|c| round(c, 1)),
+            "ConsumptionUnit": "Wh/km"
+        })
+    }
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Drives `seconds` at 20 Hz, with the speed given by `profile(t)`
+    fn drive(trip: &mut TripComputer, start: f64, seconds: f64, profile: impl Fn(f64) -> f64) {
+        let steps = (seconds * 20.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / 20.0;
+            trip.on_speed(start + t, profile(t));
+        }
+    }
+
+    #[test]
+    fn test_steady_cruise_consumption() {
+        let mut trip = TripComputer::new(EnergyModel::default(), 80.0);
+        trip.set_throttle(0.3);
+        drive(&mut trip, 0.0, 600.0, |_| 100.0);
+
+        assert!((trip.trip_distance_km() - 16.67).abs() < 0.01);
+        assert!((trip.average_speed_kmh() - 100.0).abs() < 0.1);
+        // Roughly 150 to 180 Wh/km at 100 km/h for a mid-size EV
+        let consumption = trip.consumption_wh_per_km().unwrap();
+        assert!(consumption > 150.0 && consumption < 180.0, "{consumption}");
+        assert!(trip.state_of_charge() < 80.0);
+        assert!(
+            (trip.range_km() - 75_000.0 * trip.state_of_charge() / 100.0 / consumption).abs() < 0.1
+        );
+        assert_eq!(trip.gear(), "D");
+    }
+
+    #[test]
+    fn test_braking_regenerates() {
+        let decelerate = |t: f64| (100.0 - 10.0 * t).max(0.0);
+
+        let mut regen = TripComputer::new(EnergyModel::default(), 50.0);
+        regen.set_brake(0.4);
+        drive(&mut regen, 0.0, 10.0, decelerate);
+        assert!(regen.state_of_charge() > 50.0);
+
+        // Decelerating with the throttle applied (e.g. uphill) recovers nothing
+        let mut friction = TripComputer::new(EnergyModel::default(), 50.0);
+        friction.set_throttle(0.2);
+        drive(&mut friction, 0.0, 10.0, decelerate);
+        assert!(friction.state_of_charge() < 50.0);
+
+        assert_eq!(regen.speed_kmh(), 0.0);
+        regen.set_brake(0.0);
+        assert_eq!(regen.gear(), "P");
+    }
+
+    #[test]
+    fn test_pauses_are_not_integrated() {
+        let mut trip = TripComputer::new(EnergyModel::default(), 80.0);
+        drive(&mut trip, 0.0, 10.0, |_| 36.0);
+        drive(&mut trip, 100.0, 10.0, |_| 36.0);
+        assert!((trip.trip_distance_km() - 0.2).abs() < 1e-9);
+        assert!((trip.average_speed_kmh() - 36.0).abs() < 1e-9);
+    }
+}